@@ -5,15 +5,26 @@ use rand_pcg::Pcg64Mcg;
 
 use criterion::Criterion;
 
-use taxi::doormax::DoorMax;
-use taxi::factoredrmax::FactoredRMax;
+use taxi::actions::Actions;
+use taxi::doormax::{DoorMax, EffectModel};
+use taxi::factoredrmax::{Dbn, FactoredRMax, Structure};
+use taxi::oomdp::taxi::TaxiObjects;
 use taxi::qlearner::QLearner;
 use taxi::rmax::RMax;
 use taxi::runner::{run_training_session, Probe};
+use taxi::start::Curriculum;
 use taxi::state::State;
+use taxi::state_indexer::StateIndexer;
 use taxi::world::{Costs, World};
 
-criterion_group!(trainers, qlearner, rmax, factored_rmax, doormax);
+criterion_group!(
+    trainers,
+    qlearner,
+    rmax,
+    factored_rmax,
+    doormax,
+    world_lookups
+);
 criterion_main!(trainers);
 
 struct SessionData {
@@ -40,12 +51,12 @@ impl Default for SessionData {
         // let world = World::build_from_str(world_str, Costs::default()).unwrap();
 
         // let probes = vec![
-        //     Probe::new(State::build(&world, (2, 2), Some('Y'), 'R').unwrap(), 10),
-        //     Probe::new(State::build(&world, (2, 2), Some('Y'), 'G').unwrap(), 14),
-        //     Probe::new(State::build(&world, (2, 2), Some('Y'), 'B').unwrap(), 13),
-        //     Probe::new(State::build(&world, (2, 2), Some('R'), 'B').unwrap(), 13),
-        //     Probe::new(State::build(&world, (2, 2), Some('Y'), 'R').unwrap(), 6),
-        //     Probe::new(State::build(&world, (2, 2), Some('B'), 'G').unwrap(), 13),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("Y"), "R").unwrap(), 10),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("Y"), "G").unwrap(), 14),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("Y"), "B").unwrap(), 13),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("R"), "B").unwrap(), 13),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("Y"), "R").unwrap(), 6),
+        //     Probe::new(State::build_named(&world, (2, 2), Some("B"), "G").unwrap(), 13),
         // ];
 
         let world_str = "\
@@ -61,12 +72,30 @@ impl Default for SessionData {
         let world = World::build_from_str(world_str, Costs::default()).unwrap();
 
         let probes = vec![
-            Probe::new(State::build(&world, (1, 1), Some('Y'), 'R').unwrap(), 4),
-            Probe::new(State::build(&world, (1, 1), Some('Y'), 'G').unwrap(), 6),
-            Probe::new(State::build(&world, (1, 1), Some('Y'), 'B').unwrap(), 3),
-            Probe::new(State::build(&world, (1, 1), Some('R'), 'B').unwrap(), 5),
-            Probe::new(State::build(&world, (1, 1), Some('G'), 'R').unwrap(), 6),
-            Probe::new(State::build(&world, (1, 1), Some('B'), 'G').unwrap(), 4),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("Y"), "R").unwrap(),
+                4,
+            ),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("Y"), "G").unwrap(),
+                6,
+            ),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("Y"), "B").unwrap(),
+                3,
+            ),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("R"), "B").unwrap(),
+                5,
+            ),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("G"), "R").unwrap(),
+                6,
+            ),
+            Probe::new(
+                State::build_named(&world, (1, 1), Some("B"), "G").unwrap(),
+                4,
+            ),
         ];
 
         SessionData { world, probes }
@@ -82,7 +111,15 @@ fn qlearner(c: &mut Criterion) {
             let mut qlearner = QLearner::new(&data.world, 0.1, 0.3, 0.6);
             let rng = &mut source_rng.clone();
 
-            run_training_session(&data.world, &data.probes, 1, 100, &mut qlearner, rng)
+            run_training_session(
+                &data.world,
+                &Curriculum::new(),
+                &data.probes,
+                1,
+                100,
+                &mut qlearner,
+                rng,
+            )
         })
     });
 }
@@ -96,7 +133,15 @@ fn rmax(c: &mut Criterion) {
             let mut rmax = RMax::new(&data.world, 0.3, 1.0, 1.0e-6);
             let rng = &mut source_rng.clone();
 
-            run_training_session(&data.world, &data.probes, 1, 10, &mut rmax, rng)
+            run_training_session(
+                &data.world,
+                &Curriculum::new(),
+                &data.probes,
+                1,
+                10,
+                &mut rmax,
+                rng,
+            )
         })
     });
 }
@@ -107,10 +152,19 @@ fn factored_rmax(c: &mut Criterion) {
 
     c.bench_function("factored_rmax", move |b| {
         b.iter(|| {
-            let mut factored_rmax = FactoredRMax::new(&data.world, 0.3, 1.0, 1.0e-6);
+            let mut factored_rmax =
+                FactoredRMax::new(&data.world, Structure::Fixed(Dbn::taxi()), 0.3, 1.0, 1.0e-6);
             let rng = &mut source_rng.clone();
 
-            run_training_session(&data.world, &data.probes, 1, 10, &mut factored_rmax, rng)
+            run_training_session(
+                &data.world,
+                &Curriculum::new(),
+                &data.probes,
+                1,
+                10,
+                &mut factored_rmax,
+                rng,
+            )
         })
     });
 }
@@ -121,10 +175,78 @@ fn doormax(c: &mut Criterion) {
 
     c.bench_function("doormax", move |b| {
         b.iter(|| {
-            let mut doormax = DoorMax::new(&data.world, 0.95, true, 1.0, 1.0e-6);
+            let mut doormax = DoorMax::new(
+                &data.world,
+                TaxiObjects::new(),
+                EffectModel::Deterministic { max_effects: 2 },
+                0.95,
+                true,
+                1.0,
+                1.0e-6,
+            );
             let rng = &mut source_rng.clone();
 
-            run_training_session(&data.world, &data.probes, 1, 10, &mut doormax, rng)
+            run_training_session(
+                &data.world,
+                &Curriculum::new(),
+                &data.probes,
+                1,
+                10,
+                &mut doormax,
+                rng,
+            )
+        })
+    });
+}
+
+// Indexes every state of a world with blocked cells and terrain, then takes
+// every action from it, which is what the model based solvers do each time
+// they plan.  Each step asks the world for open cells, blocked neighbours,
+// action affects and movement costs.
+fn world_lookups(c: &mut Criterion) {
+    let world_str = "\
+                     ┌───────────┐\n\
+                     │R . . # . G│\n\
+                     │           │\n\
+                     │. # . . . .│\n\
+                     │           │\n\
+                     │. . . # # .│\n\
+                     │           │\n\
+                     │. # . . . .│\n\
+                     │           │\n\
+                     │Y . . # . B│\n\
+                     └───────────┘\n\
+                     ";
+
+    let mut world = World::build_from_str(world_str, Costs::default()).unwrap();
+    world
+        .apply_terrain_layer(
+            ". . 2 . . .\n\
+             . . 2 . 3 .\n\
+             . . . . 3 .\n\
+             . . 2 . . .\n\
+             . . 2 . . .\n",
+        )
+        .unwrap();
+    let state_indexer = StateIndexer::new(&world);
+
+    c.bench_function("world_lookups", move |b| {
+        b.iter(|| {
+            let mut total = 0.0;
+
+            for state_index in 0..state_indexer.num_states() {
+                let state = state_indexer.get_state(&world, state_index).unwrap();
+
+                for action_index in 0..Actions::NUM_ELEMENTS {
+                    let action = Actions::from_index(action_index).unwrap();
+                    let (reward, next_state) = state.apply_action(&world, action);
+
+                    total += reward;
+                    total += state_indexer.get_index(&world, &next_state).unwrap() as f64;
+                }
+            }
+
+            total
         })
     });
 }
@@ -0,0 +1,219 @@
+use rand_pcg::Pcg64Mcg;
+
+use taxi::doormax::{DoorMax, EffectModel};
+use taxi::factoredrmax::{FactoredRMax, Structure};
+use taxi::oomdp::taxi::TaxiObjects;
+use taxi::runner::{run_accuracy_session, AccuracyPoint, Probe, Runner};
+use taxi::schemanet::SchemaNet;
+use taxi::world::World;
+
+use crate::configuration::{Configuration, ReportConfig};
+use crate::{generate_session_world, write_output, AppError};
+
+// Trains each solver with a model for one session, and reports how well the
+// model predicts the world after every trial.
+pub fn run_accuracy(
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    seed: u128,
+    output: Option<&String>,
+) -> Result<(), AppError> {
+    let mut table = String::from(
+        "solver,trial,steps,transitions_correct,transitions_wrong,transitions_unknown,\
+         rewards_correct,rewards_wrong,rewards_unknown\n",
+    );
+
+    for factored_rmax_config in &config.factored_r_max {
+        measure_accuracy(
+            |world| {
+                FactoredRMax::new(
+                    world,
+                    Structure::from(factored_rmax_config),
+                    factored_rmax_config.gamma,
+                    factored_rmax_config.known_count,
+                    factored_rmax_config.error_delta,
+                )
+            },
+            factored_rmax_config,
+            world,
+            probes,
+            config,
+            seed,
+            &mut table,
+        )?;
+    }
+
+    for doormax_config in &config.door_max {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+        measure_accuracy(
+            |world| {
+                DoorMax::new(
+                    world,
+                    taxi_objects.clone(),
+                    EffectModel::from(doormax_config),
+                    doormax_config.gamma,
+                    doormax_config.use_reward_learner,
+                    doormax_config.known_count,
+                    doormax_config.error_delta,
+                )
+            },
+            doormax_config,
+            world,
+            probes,
+            config,
+            seed,
+            &mut table,
+        )?;
+    }
+
+    for schemanet_config in &config.schema_net {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&schemanet_config.terms).map_err(AppError::Terms)?;
+        measure_accuracy(
+            |world| {
+                SchemaNet::new(
+                    world,
+                    taxi_objects.clone(),
+                    schemanet_config.gamma,
+                    schemanet_config.error_delta,
+                )
+            },
+            schemanet_config,
+            world,
+            probes,
+            config,
+            seed,
+            &mut table,
+        )?;
+    }
+
+    if let Some(filename) = output {
+        write_output(filename, &table)?;
+    }
+
+    Ok(())
+}
+
+fn measure_accuracy<B, Rnr>(
+    builder: B,
+    report_config: &dyn ReportConfig,
+    world: &World,
+    probes: &[Probe],
+    config: &Configuration,
+    seed: u128,
+    table: &mut String,
+) -> Result<(), AppError>
+where
+    B: Fn(&World) -> Rnr,
+    Rnr: Runner,
+{
+    let label = report_config.label();
+
+    let mut rng = Pcg64Mcg::new(seed);
+
+    let session_world = generate_session_world(config, world.costs, &mut rng)?;
+    let (world, probes) = match session_world {
+        Some((ref world, ref probes)) => (world, &probes[..]),
+        None => (world, probes),
+    };
+
+    let starts = config.build_starts(world).map_err(AppError::Start)?;
+
+    let mut solver = builder(world);
+
+    let (training_step_count, points) = run_accuracy_session(
+        world,
+        &starts,
+        probes,
+        config.max_trials,
+        config.max_trial_steps,
+        &mut solver,
+        &mut rng,
+    )
+    .map_err(AppError::Runner)?;
+
+    for point in &points {
+        println!("{} - {}", label, describe_point(point));
+
+        let transitions = point.accuracy.transitions;
+        let rewards = point.accuracy.rewards;
+        *table += &format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&label),
+            point.trial,
+            point.steps,
+            transitions.correct,
+            transitions.wrong,
+            transitions.unknown,
+            rewards.correct,
+            rewards.wrong,
+            rewards.unknown,
+        );
+    }
+
+    match training_step_count {
+        Some(num_steps) => println!(
+            "{} - Finished seed [{}, {}] in {} steps.",
+            label,
+            seed.rotate_right(64) as i64,
+            seed as i64,
+            num_steps,
+        ),
+        None => println!(
+            "{} - Failed seed [{},{}] with maximums {} trials of {} steps.",
+            label,
+            seed.rotate_right(64) as i64,
+            seed as i64,
+            config.max_trials,
+            config.max_trial_steps,
+        ),
+    }
+
+    Ok(())
+}
+
+fn describe_point(point: &AccuracyPoint) -> String {
+    let transitions = point.accuracy.transitions;
+    let rewards = point.accuracy.rewards;
+
+    format!(
+        "trial {} after {} steps: transitions {:.1}% correct, {} wrong, {} unknown; \
+         rewards {:.1}% correct, {} wrong, {} unknown.",
+        point.trial,
+        point.steps,
+        100.0 * transitions.accuracy(),
+        transitions.wrong,
+        transitions.unknown,
+        100.0 * rewards.accuracy(),
+        rewards.wrong,
+        rewards.unknown,
+    )
+}
+
+// A CSV field holding text, quoted if it has a comma, quote or line break,
+// as profile names may.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        String::from(text)
+    }
+}
+
+#[cfg(test)]
+mod test_accuracy {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields_when_needed() {
+        assert_eq!(csv_field("DoorMax"), "DoorMax");
+        assert_eq!(csv_field("QLearner(fast)"), "QLearner(fast)");
+        assert_eq!(csv_field("QLearner(a, b)"), "\"QLearner(a, b)\"");
+        assert_eq!(
+            csv_field("QLearner(\"quoted\")"),
+            "\"QLearner(\"\"quoted\"\")\""
+        );
+    }
+}
@@ -0,0 +1,586 @@
+use std::fmt;
+
+use crate::configuration::{SolverChoice, WorldFormatChoice};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    // Everything the configuration asks for, as when no command is given.
+    All,
+    Train,
+    Stats,
+    Sweep,
+    Continuing,
+    Accuracy,
+    Transfer,
+    Rerun,
+    Replay,
+    ValidateConfig,
+    ShowWorld,
+}
+
+const COMMANDS: [Command; 10] = [
+    Command::Train,
+    Command::Stats,
+    Command::Sweep,
+    Command::Continuing,
+    Command::Accuracy,
+    Command::Transfer,
+    Command::Rerun,
+    Command::Replay,
+    Command::ValidateConfig,
+    Command::ShowWorld,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flag {
+    Sessions,
+    Solver,
+    Seed,
+    Output,
+    Format,
+}
+
+const FLAGS: [Flag; 5] = [
+    Flag::Sessions,
+    Flag::Solver,
+    Flag::Seed,
+    Flag::Output,
+    Flag::Format,
+];
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        COMMANDS
+            .iter()
+            .cloned()
+            .find(|command| command.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Command::All => "",
+            Command::Train => "train",
+            Command::Stats => "stats",
+            Command::Sweep => "sweep",
+            Command::Continuing => "continuing",
+            Command::Accuracy => "accuracy",
+            Command::Transfer => "transfer",
+            Command::Rerun => "rerun",
+            Command::Replay => "replay",
+            Command::ValidateConfig => "validate-config",
+            Command::ShowWorld => "show-world",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Command::All => "Run everything the configuration asks for.",
+            Command::Train => "Train each configured solver for one session and report the result.",
+            Command::Stats => "Gather step statistics for each configured solver.",
+            Command::Sweep => "Run the [sweep] section of the configuration.",
+            Command::Continuing => "Serve requests without end and report requests per 1000 steps.",
+            Command::Accuracy => {
+                "Train the solvers with models once, measuring them after each trial."
+            }
+            Command::Transfer => "Train on the world, then measure the solvers on [transfer].",
+            Command::Rerun => "Rerun single sessions from their seeds.",
+            Command::Replay => "Train the [replay] solver, then step through an attempt.",
+            Command::ValidateConfig => "Check the configuration, world and probes without running.",
+            Command::ShowWorld => "Print the configured world and the distances across it.",
+        }
+    }
+
+    fn accepts(self, flag: Flag) -> bool {
+        match self {
+            Command::All => flag != Flag::Output && flag != Flag::Format,
+            Command::Train => flag == Flag::Solver || flag == Flag::Seed,
+            Command::Stats | Command::Sweep => flag != Flag::Format,
+            Command::Continuing => flag == Flag::Sessions || flag == Flag::Seed,
+            Command::Accuracy => flag == Flag::Solver || flag == Flag::Seed || flag == Flag::Output,
+            Command::Transfer => flag != Flag::Format,
+            Command::Rerun => flag == Flag::Solver || flag == Flag::Seed,
+            Command::Replay => flag == Flag::Solver,
+            // A generated world depends on the seed.
+            Command::ShowWorld => flag == Flag::Seed || flag == Flag::Format,
+            Command::ValidateConfig => false,
+        }
+    }
+}
+
+impl Flag {
+    fn from_name(name: &str) -> Option<Flag> {
+        FLAGS.iter().cloned().find(|flag| flag.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Flag::Sessions => "--sessions",
+            Flag::Solver => "--solver",
+            Flag::Seed => "--seed",
+            Flag::Output => "--output",
+            Flag::Format => "--format",
+        }
+    }
+
+    fn usage(self, command: Command) -> &'static str {
+        match self {
+            Flag::Sessions => "--sessions <N>     Number of sessions, overriding 'sessions'.",
+            Flag::Solver => "--solver <NAME>    Only use this solver, e.g. q_learner or DoorMax.",
+            Flag::Seed if command == Command::Rerun => {
+                "--seed <HIGH,LOW>  Seed to rerun, may be repeated. Overrides 'rerun_seeds'."
+            }
+            Flag::Seed => "--seed <HIGH,LOW>  Root seed, overriding 'root_seed'.",
+            Flag::Output => "--output <PATH>    Also write the results table to PATH.",
+            Flag::Format => {
+                "--format <FORMAT>  Write the world as box_drawing, ascii, toml or json."
+            }
+        }
+    }
+}
+
+pub struct Options {
+    pub command: Command,
+    pub config_path: String,
+    pub sessions: Option<usize>,
+    pub solver: Option<SolverChoice>,
+    pub seeds: Vec<(i64, i64)>,
+    pub output: Option<String>,
+    pub format: Option<WorldFormatChoice>,
+}
+
+pub enum Arguments {
+    Help(Command),
+    Run(Options),
+}
+
+// Accepts "taxi <command> <config> [options]", as well as the older
+// "taxi <config> [options]" which runs everything the configuration asks for.
+pub fn parse<I>(args: I) -> Result<Arguments, Error>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        None => return Err(Error::MissingConfiguration(Command::All)),
+        Some("help") | Some("--help") | Some("-h") => {
+            args.next();
+            let command = match args.next() {
+                Some(name) => {
+                    Command::from_name(&name).ok_or(Error::UnknownCommand(name.clone()))?
+                }
+                None => Command::All,
+            };
+            return Ok(Arguments::Help(command));
+        }
+        Some(name) => match Command::from_name(name) {
+            Some(command) => {
+                args.next();
+                command
+            }
+            None => Command::All,
+        },
+    };
+
+    let mut options = Options {
+        command,
+        config_path: String::new(),
+        sessions: None,
+        solver: None,
+        seeds: Vec::new(),
+        output: None,
+        format: None,
+    };
+
+    let mut config_path = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(Arguments::Help(command));
+        }
+
+        if !arg.starts_with('-') {
+            if config_path.is_some() {
+                return Err(Error::UnexpectedArgument(arg));
+            }
+            config_path = Some(arg);
+            continue;
+        }
+
+        // Both "--flag value" and "--flag=value" are accepted.
+        let (name, inline_value) = match arg.find('=') {
+            Some(index) => (&arg[..index], Some(String::from(&arg[index + 1..]))),
+            None => (arg.as_str(), None),
+        };
+
+        let flag = Flag::from_name(name).ok_or_else(|| Error::UnknownOption(String::from(name)))?;
+
+        if !command.accepts(flag) {
+            return Err(Error::OptionNotAccepted {
+                option: flag.name(),
+                command,
+            });
+        }
+
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or(Error::MissingValue(flag.name()))?;
+
+        match flag {
+            Flag::Sessions => {
+                let sessions = value.parse().map_err(|_| Error::InvalidValue {
+                    option: flag.name(),
+                    value: value.clone(),
+                    expected: "a non-negative number",
+                })?;
+                options.sessions = Some(sessions);
+            }
+            Flag::Solver => {
+                let solver =
+                    SolverChoice::from_name(&value).ok_or_else(|| Error::InvalidValue {
+                        option: flag.name(),
+                        value: value.clone(),
+                        expected: "one of random_solver, q_learner, r_max, factored_r_max, \
+                               max_q, door_max or schema_net",
+                    })?;
+                options.solver = Some(solver);
+            }
+            Flag::Seed => {
+                let seed = parse_seed(&value).ok_or_else(|| Error::InvalidValue {
+                    option: flag.name(),
+                    value: value.clone(),
+                    expected: "two signed 64 bit integers, as in \"--seed 1234,-5678\"",
+                })?;
+
+                if command != Command::Rerun && !options.seeds.is_empty() {
+                    return Err(Error::RepeatedSeed(command));
+                }
+                options.seeds.push(seed);
+            }
+            Flag::Output => options.output = Some(value),
+            Flag::Format => {
+                let format = WorldFormatChoice::from_name(&value)
+                    .filter(|&format| format != WorldFormatChoice::Auto)
+                    .ok_or_else(|| Error::InvalidValue {
+                        option: flag.name(),
+                        value: value.clone(),
+                        expected: "one of box_drawing, ascii, toml or json",
+                    })?;
+                options.format = Some(format);
+            }
+        }
+    }
+
+    options.config_path = config_path.ok_or(Error::MissingConfiguration(command))?;
+
+    Ok(Arguments::Run(options))
+}
+
+// Seeds are printed as "[high, low]", so accept that form as well as "high,low".
+fn parse_seed(value: &str) -> Option<(i64, i64)> {
+    let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+
+    let mut parts = value.split(',').map(str::trim);
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(high), Some(low), None) => Some((high.parse().ok()?, low.parse().ok()?)),
+        _ => None,
+    }
+}
+
+pub fn usage(command: Command) -> String {
+    let mut result = String::new();
+
+    if command == Command::All {
+        result += "Usage: taxi [COMMAND] <CONFIG> [OPTIONS]\n\n";
+        result += Command::All.description();
+        result += "\n\nCommands:\n";
+
+        for command in COMMANDS.iter() {
+            result += &format!("  {:<17}{}\n", command.name(), command.description());
+        }
+    } else {
+        result += &format!("Usage: taxi {} <CONFIG> [OPTIONS]\n\n", command.name());
+        result += command.description();
+        result.push('\n');
+    }
+
+    result += "\nOptions:\n";
+    for flag in FLAGS.iter().filter(|flag| command.accepts(**flag)) {
+        result += &format!("  {}\n", flag.usage(command));
+    }
+    result += "  -h, --help         Print this help.\n";
+
+    if command == Command::All {
+        result += "\nRun 'taxi help <COMMAND>' for the options of a command.\n";
+    }
+
+    result
+}
+
+pub enum Error {
+    MissingConfiguration(Command),
+    UnknownCommand(String),
+    UnknownOption(String),
+    UnexpectedArgument(String),
+    OptionNotAccepted {
+        option: &'static str,
+        command: Command,
+    },
+    MissingValue(&'static str),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    RepeatedSeed(Command),
+}
+
+impl Error {
+    // The command whose usage is worth showing alongside the error.
+    pub fn command(&self) -> Command {
+        match *self {
+            Error::MissingConfiguration(command)
+            | Error::OptionNotAccepted { command, .. }
+            | Error::RepeatedSeed(command) => command,
+            _ => Command::All,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::MissingConfiguration(_) => write!(f, "Configuration file not specified."),
+            Error::UnknownCommand(ref name) => write!(f, "Unknown command '{}'.", name),
+            Error::UnknownOption(ref name) => write!(f, "Unknown option '{}'.", name),
+            Error::UnexpectedArgument(ref arg) => write!(
+                f,
+                "Unexpected argument '{}', only one configuration file may be given.",
+                arg
+            ),
+            Error::OptionNotAccepted {
+                option,
+                ref command,
+            } => match *command {
+                Command::All => write!(
+                    f,
+                    "Option '{}' needs a command, such as 'stats' or 'sweep'.",
+                    option
+                ),
+                _ => write!(
+                    f,
+                    "Option '{}' does not apply to '{}'.",
+                    option,
+                    command.name()
+                ),
+            },
+            Error::MissingValue(option) => write!(f, "Option '{}' needs a value.", option),
+            Error::InvalidValue {
+                option,
+                ref value,
+                expected,
+            } => write!(
+                f,
+                "Invalid value '{}' for '{}', expected {}.",
+                value, option, expected
+            ),
+            Error::RepeatedSeed(_) => {
+                write!(f, "Option '--seed' may only be repeated for 'rerun'.")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cli {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Arguments, Error> {
+        parse(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    fn parse_options(args: &[&str]) -> Options {
+        match parse_args(args) {
+            Ok(Arguments::Run(options)) => options,
+            Ok(Arguments::Help(command)) => panic!("Expected options, got help for {:?}", command),
+            Err(error) => panic!("Expected options, got '{}'", error),
+        }
+    }
+
+    fn parse_error(args: &[&str]) -> Error {
+        match parse_args(args) {
+            Ok(_) => panic!("Expected {:?} to fail", args),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn parses_command_and_options() {
+        let options = parse_options(&[
+            "stats",
+            "standard.cfg",
+            "--sessions",
+            "5",
+            "--solver=door_max",
+            "--seed",
+            "[12, -34]",
+            "--output",
+            "results.txt",
+        ]);
+
+        assert_eq!(options.command, Command::Stats);
+        assert_eq!(options.config_path, "standard.cfg");
+        assert_eq!(options.sessions, Some(5));
+        assert_eq!(options.solver, Some(SolverChoice::DoorMax));
+        assert_eq!(options.seeds, vec![(12, -34)]);
+        assert_eq!(options.output.as_deref(), Some("results.txt"));
+        assert_eq!(options.format, None);
+    }
+
+    #[test]
+    fn config_alone_runs_everything() {
+        let options = parse_options(&["tiny.cfg"]);
+
+        assert_eq!(options.command, Command::All);
+        assert_eq!(options.config_path, "tiny.cfg");
+    }
+
+    #[test]
+    fn rerun_accepts_repeated_seeds() {
+        let options = parse_options(&["rerun", "tiny.cfg", "--seed", "1,2", "--seed", "3,4"]);
+        assert_eq!(options.seeds, vec![(1, 2), (3, 4)]);
+
+        let error = parse_error(&["stats", "tiny.cfg", "--seed", "1,2", "--seed", "3,4"]);
+        assert!(matches!(error, Error::RepeatedSeed(Command::Stats)));
+    }
+
+    #[test]
+    fn help_names_command() {
+        assert!(matches!(
+            parse_args(&["help", "sweep"]),
+            Ok(Arguments::Help(Command::Sweep))
+        ));
+        assert!(matches!(
+            parse_args(&["transfer", "--help"]),
+            Ok(Arguments::Help(Command::Transfer))
+        ));
+        assert!(matches!(
+            parse_args(&["-h"]),
+            Ok(Arguments::Help(Command::All))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_command() {
+        let error = parse_error(&["help", "frobnicate"]);
+        assert!(matches!(error, Error::UnknownCommand(ref name) if name == "frobnicate"));
+        assert_eq!(error.to_string(), "Unknown command 'frobnicate'.");
+
+        // Anything else that is not a command is taken as the configuration.
+        let error = parse_error(&["frobnicate", "tiny.cfg"]);
+        assert!(matches!(error, Error::UnexpectedArgument(ref arg) if arg == "tiny.cfg"));
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        let error = parse_error(&["stats", "tiny.cfg", "--output"]);
+        assert!(matches!(error, Error::MissingValue("--output")));
+        assert_eq!(error.to_string(), "Option '--output' needs a value.");
+
+        let error = parse_error(&["stats"]);
+        assert!(matches!(error, Error::MissingConfiguration(Command::Stats)));
+        assert_eq!(error.command(), Command::Stats);
+
+        assert!(matches!(
+            parse_error(&[]),
+            Error::MissingConfiguration(Command::All)
+        ));
+    }
+
+    #[test]
+    fn rejects_options_command_does_not_take() {
+        let error = parse_error(&["train", "tiny.cfg", "--output", "results.txt"]);
+        assert!(matches!(
+            error,
+            Error::OptionNotAccepted {
+                option: "--output",
+                command: Command::Train,
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Option '--output' does not apply to 'train'."
+        );
+
+        let error = parse_error(&["tiny.cfg", "--format", "ascii"]);
+        assert_eq!(
+            error.to_string(),
+            "Option '--format' needs a command, such as 'stats' or 'sweep'."
+        );
+
+        let error = parse_error(&["stats", "tiny.cfg", "--verbose"]);
+        assert!(matches!(error, Error::UnknownOption(ref name) if name == "--verbose"));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let error = parse_error(&["stats", "tiny.cfg", "--sessions", "-1"]);
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                option: "--sessions",
+                ..
+            }
+        ));
+
+        let error = parse_error(&["stats", "tiny.cfg", "--seed", "12"]);
+        assert!(
+            matches!(error, Error::InvalidValue { option: "--seed", ref value, .. } if value == "12")
+        );
+
+        let error = parse_error(&["show-world", "tiny.cfg", "--format", "auto"]);
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                option: "--format",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_seeds() {
+        assert_eq!(parse_seed("1234,-5678"), Some((1234, -5678)));
+        assert_eq!(parse_seed("[1234, -5678]"), Some((1234, -5678)));
+        assert_eq!(parse_seed(" 0 , 0 "), Some((0, 0)));
+
+        assert_eq!(parse_seed(""), None);
+        assert_eq!(parse_seed("1234"), None);
+        assert_eq!(parse_seed("1,2,3"), None);
+        assert_eq!(parse_seed("1,two"), None);
+        assert_eq!(parse_seed("1.5,2"), None);
+        assert_eq!(parse_seed("9223372036854775808,0"), None);
+    }
+
+    #[test]
+    fn usage_lists_commands_and_accepted_options() {
+        let all = usage(Command::All);
+        assert!(all.starts_with("Usage: taxi [COMMAND] <CONFIG> [OPTIONS]"));
+        for command in COMMANDS.iter() {
+            assert!(all.contains(command.name()));
+        }
+        assert!(all.contains("--sessions"));
+        assert!(!all.contains("--output"));
+
+        let train = usage(Command::Train);
+        assert!(train.starts_with("Usage: taxi train <CONFIG> [OPTIONS]"));
+        assert!(train.contains("--solver"));
+        assert!(train.contains("--seed <HIGH,LOW>  Root seed"));
+        assert!(!train.contains("--output"));
+        assert!(!train.contains("Commands:"));
+
+        let rerun = usage(Command::Rerun);
+        assert!(rerun.contains("Seed to rerun, may be repeated."));
+    }
+}
@@ -1,12 +1,27 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
 
 use toml;
 
-use taxi::world::Costs;
+use taxi::actions::Actions;
+use taxi::doormax::EffectModel;
+use taxi::factoredrmax::{Dbn, Factor, Parents, Structure};
+use taxi::oomdp::taxi::TaxiObjects;
+use taxi::position::Position;
+use taxi::schedule::{Decay, Schedule, Timescale};
+use taxi::start;
+use taxi::start::{Curriculum, StartDistribution};
+use taxi::world;
+use taxi::world::generator::Generator;
+use taxi::world::{Costs, Format, World, WorldDescription};
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum SolverChoice {
     Random,
     QLearner,
@@ -14,6 +29,41 @@ pub enum SolverChoice {
     FactoredRMax,
     MaxQ,
     DoorMax,
+    SchemaNet,
+}
+
+impl SolverChoice {
+    pub const ALL: [SolverChoice; 7] = [
+        SolverChoice::Random,
+        SolverChoice::QLearner,
+        SolverChoice::RMax,
+        SolverChoice::FactoredRMax,
+        SolverChoice::MaxQ,
+        SolverChoice::DoorMax,
+        SolverChoice::SchemaNet,
+    ];
+
+    // Accepts either the variant name ("QLearner") or the table name
+    // ("q_learner"), ignoring case.
+    pub fn from_name(name: &str) -> Option<SolverChoice> {
+        SolverChoice::ALL.iter().cloned().find(|solver| {
+            name.eq_ignore_ascii_case(solver.config_key())
+                || name.eq_ignore_ascii_case(&format!("{:?}", solver))
+        })
+    }
+
+    // The name of the table that configures this solver.
+    pub fn config_key(self) -> &'static str {
+        match self {
+            SolverChoice::Random => "random_solver",
+            SolverChoice::QLearner => "q_learner",
+            SolverChoice::RMax => "r_max",
+            SolverChoice::FactoredRMax => "factored_r_max",
+            SolverChoice::MaxQ => "max_q",
+            SolverChoice::DoorMax => "door_max",
+            SolverChoice::SchemaNet => "schema_net",
+        }
+    }
 }
 
 impl fmt::Display for SolverChoice {
@@ -25,6 +75,7 @@ impl fmt::Display for SolverChoice {
             SolverChoice::FactoredRMax => write!(f, "FactoredRMax"),
             SolverChoice::MaxQ => write!(f, "MaxQ"),
             SolverChoice::DoorMax => write!(f, "DoorMax"),
+            SolverChoice::SchemaNet => write!(f, "SchemaNet"),
         }
     }
 }
@@ -32,25 +83,167 @@ impl fmt::Display for SolverChoice {
 pub trait ReportConfig {
     fn solver_choice(&self) -> SolverChoice;
 
+    // Set when a solver has several profiles, e.g. one of many [[q_learner]].
+    fn name(&self) -> Option<&str>;
+
     fn report(&self) -> bool {
         false
     }
+
+    fn label(&self) -> String {
+        match self.name() {
+            Some(name) => format!("{:?}({})", self.solver_choice(), name),
+            None => format!("{:?}", self.solver_choice()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimescaleConfig {
+    #[default]
+    Step,
+    Trial,
+}
+
+impl From<TimescaleConfig> for Timescale {
+    fn from(timescale: TimescaleConfig) -> Self {
+        match timescale {
+            TimescaleConfig::Step => Timescale::Step,
+            TimescaleConfig::Trial => Timescale::Trial,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DecayConfig {
+    Constant {
+        value: f64,
+    },
+    Linear {
+        start: f64,
+        end: f64,
+        duration: usize,
+        #[serde(default)]
+        per: TimescaleConfig,
+    },
+    Exponential {
+        start: f64,
+        rate: f64,
+        #[serde(default)]
+        minimum: f64,
+        #[serde(default)]
+        per: TimescaleConfig,
+    },
+    VisitCount {
+        #[serde(default = "default_visit_scale")]
+        scale: f64,
+        #[serde(default)]
+        minimum: f64,
+    },
+    StepWise {
+        start: f64,
+        factor: f64,
+        interval: usize,
+        #[serde(default)]
+        minimum: f64,
+        #[serde(default)]
+        per: TimescaleConfig,
+    },
+}
+
+fn default_visit_scale() -> f64 {
+    1.0
+}
+
+// Either a plain number, e.g. "alpha = 0.1", or a table such as
+// "alpha = { kind = "linear", start = 1.0, end = 0.1, duration = 500, per = "trial" }".
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(untagged)]
+pub enum ScheduleConfig {
+    Constant(f64),
+    Decay(DecayConfig),
+}
+
+impl From<ScheduleConfig> for Schedule {
+    fn from(config: ScheduleConfig) -> Self {
+        match config {
+            ScheduleConfig::Constant(value) => Schedule::constant(value),
+            ScheduleConfig::Decay(DecayConfig::Constant { value }) => Schedule::constant(value),
+            ScheduleConfig::Decay(DecayConfig::Linear {
+                start,
+                end,
+                duration,
+                per,
+            }) => Schedule::new(
+                Decay::Linear {
+                    start,
+                    end,
+                    duration,
+                },
+                per.into(),
+            ),
+            ScheduleConfig::Decay(DecayConfig::Exponential {
+                start,
+                rate,
+                minimum,
+                per,
+            }) => Schedule::new(
+                Decay::Exponential {
+                    start,
+                    rate,
+                    minimum,
+                },
+                per.into(),
+            ),
+            ScheduleConfig::Decay(DecayConfig::VisitCount { scale, minimum }) => Schedule::new(
+                Decay::VisitCount { scale, minimum },
+                TimescaleConfig::Step.into(),
+            ),
+            ScheduleConfig::Decay(DecayConfig::StepWise {
+                start,
+                factor,
+                interval,
+                minimum,
+                per,
+            }) => Schedule::new(
+                Decay::StepWise {
+                    start,
+                    factor,
+                    interval,
+                    minimum,
+                },
+                per.into(),
+            ),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
-pub struct RandomSolverConfig {}
+pub struct RandomSolverConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+}
 
 impl ReportConfig for RandomSolverConfig {
     fn solver_choice(&self) -> SolverChoice {
         SolverChoice::Random
     }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct QLearnerConfig {
-    pub alpha: f64,
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub alpha: ScheduleConfig,
     pub gamma: f64,
-    pub epsilon: f64,
+    pub epsilon: ScheduleConfig,
 
     pub report: bool,
 }
@@ -60,6 +253,10 @@ impl ReportConfig for QLearnerConfig {
         SolverChoice::QLearner
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     fn report(&self) -> bool {
         self.report
     }
@@ -67,6 +264,9 @@ impl ReportConfig for QLearnerConfig {
 
 #[derive(Deserialize, Debug)]
 pub struct RMaxConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+
     pub gamma: f64,
     pub known_count: f64,
     pub error_delta: f64,
@@ -79,6 +279,10 @@ impl ReportConfig for RMaxConfig {
         SolverChoice::RMax
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     fn report(&self) -> bool {
         self.report
     }
@@ -86,18 +290,132 @@ impl ReportConfig for RMaxConfig {
 
 #[derive(Deserialize, Debug)]
 pub struct FactoredRMaxConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+
     pub gamma: f64,
     pub known_count: f64,
     pub error_delta: f64,
 
+    // Learn which factors each factor and the reward depend on, instead of
+    // using the taxi's, from parent sets of at most max_parents factors.
+    // Sets are ruled out once they differ from a larger set by more than
+    // structure_tolerance, as a chance or as a reward.
+    #[serde(default)]
+    pub learn_structure: bool,
+    #[serde(default = "default_max_parents")]
+    pub max_parents: usize,
+    #[serde(default = "default_structure_tolerance")]
+    pub structure_tolerance: f64,
+
+    // The parents to use in place of the taxi's, for the actions and
+    // factors given.
+    #[serde(default)]
+    pub parents: Option<DbnConfig>,
+
     pub report: bool,
 }
 
+fn default_max_parents() -> usize {
+    3
+}
+
+fn default_structure_tolerance() -> f64 {
+    0.1
+}
+
+impl From<&FactoredRMaxConfig> for Structure {
+    fn from(config: &FactoredRMaxConfig) -> Self {
+        if config.learn_structure {
+            Structure::Learnt {
+                max_parents: config.max_parents,
+                tolerance: config.structure_tolerance,
+            }
+        } else {
+            match config.parents {
+                Some(ref parents) => Structure::Fixed(parents.build()),
+                None => Structure::Fixed(Dbn::taxi()),
+            }
+        }
+    }
+}
+
+// The parents of each factor's next value and of the reward, by action, as
+// [factored_r_max.parents.east] with x = ["x", "y"].
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct DbnConfig {
+    pub north: ActionParentsConfig,
+    pub south: ActionParentsConfig,
+    pub east: ActionParentsConfig,
+    pub west: ActionParentsConfig,
+    pub pick_up: ActionParentsConfig,
+    pub drop_off: ActionParentsConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ActionParentsConfig {
+    pub x: Option<Vec<Factor>>,
+    pub y: Option<Vec<Factor>>,
+    pub passenger: Option<Vec<Factor>>,
+    pub destination: Option<Vec<Factor>>,
+    pub reward: Option<Vec<Factor>>,
+}
+
+impl DbnConfig {
+    // Each action with its key.
+    fn actions(&self) -> [(Actions, &str, &ActionParentsConfig); Actions::NUM_ELEMENTS] {
+        [
+            (Actions::North, "north", &self.north),
+            (Actions::South, "south", &self.south),
+            (Actions::East, "east", &self.east),
+            (Actions::West, "west", &self.west),
+            (Actions::PickUp, "pick_up", &self.pick_up),
+            (Actions::DropOff, "drop_off", &self.drop_off),
+        ]
+    }
+
+    // The taxi's parents, with those given replaced.
+    pub fn build(&self) -> Dbn {
+        let mut dbn = Dbn::taxi();
+
+        for &(action, _, action_parents) in self.actions().iter() {
+            for &(factor, parents) in action_parents.factors().iter() {
+                if let Some(parents) = parents {
+                    dbn.set_transition_parents(action, factor, Parents::new(parents));
+                }
+            }
+
+            if let Some(ref parents) = action_parents.reward {
+                dbn.set_reward_parents(action, Parents::new(parents));
+            }
+        }
+
+        dbn
+    }
+}
+
+impl ActionParentsConfig {
+    fn factors(&self) -> [(Factor, &Option<Vec<Factor>>); Factor::NUM_ELEMENTS] {
+        [
+            (Factor::X, &self.x),
+            (Factor::Y, &self.y),
+            (Factor::Passenger, &self.passenger),
+            (Factor::Destination, &self.destination),
+        ]
+    }
+}
+
 impl ReportConfig for FactoredRMaxConfig {
     fn solver_choice(&self) -> SolverChoice {
         SolverChoice::FactoredRMax
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     fn report(&self) -> bool {
         self.report
     }
@@ -105,9 +423,12 @@ impl ReportConfig for FactoredRMaxConfig {
 
 #[derive(Deserialize, Debug)]
 pub struct MaxQConfig {
-    pub alpha: f64,
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub alpha: ScheduleConfig,
     pub gamma: f64,
-    pub epsilon: f64,
+    pub epsilon: ScheduleConfig,
     pub report: bool,
     pub show_learning: bool,
 }
@@ -117,6 +438,10 @@ impl ReportConfig for MaxQConfig {
         SolverChoice::MaxQ
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     fn report(&self) -> bool {
         self.report || self.show_learning
     }
@@ -124,12 +449,53 @@ impl ReportConfig for MaxQConfig {
 
 #[derive(Deserialize, Debug)]
 pub struct DoorMaxConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+
     pub gamma: f64,
     pub use_reward_learner: bool,
     pub known_count: f64,
     pub error_delta: f64,
 
+    // Predicates to add to the taxi terms, by name.
+    #[serde(default)]
+    pub terms: Vec<String>,
+
+    // The most effects of each effect type per attribute and action.
+    #[serde(default = "default_max_effects")]
+    pub max_effects: usize,
+
+    // Learn the chance of each effect, for worlds where actions do not
+    // always do the same thing.  An effect is known once its condition has
+    // been seen known_count times.
+    #[serde(default)]
+    pub stochastic: bool,
+
     pub report: bool,
+
+    // A file to write the explanation of what was learnt to, as JSON, when
+    // reporting.  Each session reported writes it over the last.
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+fn default_max_effects() -> usize {
+    2
+}
+
+impl From<&DoorMaxConfig> for EffectModel {
+    fn from(config: &DoorMaxConfig) -> Self {
+        if config.stochastic {
+            EffectModel::Stochastic {
+                max_effects: config.max_effects,
+                known_count: config.known_count,
+            }
+        } else {
+            EffectModel::Deterministic {
+                max_effects: config.max_effects,
+            }
+        }
+    }
 }
 
 impl ReportConfig for DoorMaxConfig {
@@ -137,6 +503,39 @@ impl ReportConfig for DoorMaxConfig {
         SolverChoice::DoorMax
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn report(&self) -> bool {
+        self.report
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SchemaNetConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub gamma: f64,
+    pub error_delta: f64,
+
+    // Predicates to add to the taxi terms, by name.
+    #[serde(default)]
+    pub terms: Vec<String>,
+
+    pub report: bool,
+}
+
+impl ReportConfig for SchemaNetConfig {
+    fn solver_choice(&self) -> SolverChoice {
+        SolverChoice::SchemaNet
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     fn report(&self) -> bool {
         self.report
     }
@@ -145,142 +544,2027 @@ impl ReportConfig for DoorMaxConfig {
 #[derive(Deserialize, Debug)]
 pub struct Probe {
     pub taxi_pos: (i32, i32),
-    pub passenger_loc: Option<char>,
-    pub destination_loc: char,
+    pub passenger_loc: Option<String>,
+    pub destination_loc: String,
     pub max_steps: usize,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Replay {
     pub solver: SolverChoice,
+    // Which of the solver's profiles to replay, the first if not set.
+    #[serde(default)]
+    pub profile: Option<String>,
     pub taxi_pos: (i32, i32),
-    pub passenger_loc: Option<char>,
-    pub destination_loc: char,
+    pub passenger_loc: Option<String>,
+    pub destination_loc: String,
     pub max_steps: usize,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchChoice {
+    #[default]
+    Grid,
+    Random,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum SweepRange {
+    // An explicit list, e.g. "gamma = { values = [0.3, 0.6, 0.9] }".  Values
+    // may be any TOML value the solver accepts, including schedule tables.
+    Values {
+        values: Vec<toml::Value>,
+    },
+
+    // count evenly spaced values from start to end inclusive, spaced
+    // geometrically if log is set.
+    Range {
+        start: f64,
+        end: f64,
+        count: usize,
+        #[serde(default)]
+        log: bool,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SweepConfig {
+    pub solver: SolverChoice,
+    // Which of the solver's profiles to start from, the first if not set.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub search: SearchChoice,
+    // Number of random combinations to draw when search = "random".
+    #[serde(default)]
+    pub samples: usize,
+    pub output: Option<String>,
+    pub parameters: BTreeMap<String, SweepRange>,
+}
+
+impl SweepConfig {
+    pub fn label(&self) -> String {
+        match self.profile {
+            Some(ref profile) => format!("{:?}({})", self.solver, profile),
+            None => format!("{:?}", self.solver),
+        }
+    }
+}
+
+// Either the world drawn as text, or a [world] table listing its size, the
+// walls inside it and the fixed positions.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum WorldConfig {
+    Text(String),
+    Structured(WorldDescription),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorldFormatChoice {
+    #[default]
+    Auto,
+    BoxDrawing,
+    Ascii,
+    Toml,
+    Json,
+}
+
+impl WorldFormatChoice {
+    pub const ALL: [WorldFormatChoice; 5] = [
+        WorldFormatChoice::Auto,
+        WorldFormatChoice::BoxDrawing,
+        WorldFormatChoice::Ascii,
+        WorldFormatChoice::Toml,
+        WorldFormatChoice::Json,
+    ];
+
+    pub fn from_name(name: &str) -> Option<WorldFormatChoice> {
+        WorldFormatChoice::ALL
+            .iter()
+            .cloned()
+            .find(|choice| choice.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorldFormatChoice::Auto => "auto",
+            WorldFormatChoice::BoxDrawing => "box_drawing",
+            WorldFormatChoice::Ascii => "ascii",
+            WorldFormatChoice::Toml => "toml",
+            WorldFormatChoice::Json => "json",
+        }
+    }
+}
+
+impl From<WorldFormatChoice> for Format {
+    fn from(choice: WorldFormatChoice) -> Self {
+        match choice {
+            WorldFormatChoice::Auto => Format::Auto,
+            WorldFormatChoice::BoxDrawing => Format::BoxDrawing,
+            WorldFormatChoice::Ascii => Format::Ascii,
+            WorldFormatChoice::Toml => Format::Toml,
+            WorldFormatChoice::Json => Format::Json,
+        }
+    }
+}
+
+// Replaces the world with a randomly generated one, along with probes that
+// each allow exactly the optimal number of steps.
+#[derive(Deserialize, Debug)]
+pub struct GeneratorConfig {
+    pub width: i32,
+    pub height: i32,
+    pub wall_density: f64,
+    pub fixed_positions: usize,
+    #[serde(default = "default_generated_probes")]
+    pub probes: usize,
+    // The root seed is used if not set.
+    pub seed: Option<(i64, i64)>,
+    // Generate a new world for every session, from the session's seed.
+    #[serde(default)]
+    pub per_session: bool,
+}
+
+fn default_generated_probes() -> usize {
+    6
+}
+
 #[derive(Deserialize, Debug)]
 pub struct CostsConfig {
     pub movement: f64,
     pub miss_pickup: f64,
     pub miss_dropoff: f64,
     pub empty_dropoff: f64,
+    // A row of numbers for each row of cells, scaling movement for moves
+    // from that cell, with '.' leaving it as it is.
+    pub terrain: Option<String>,
+}
+
+// R-learning for the continuing task, see [continuing].
+#[derive(Deserialize, Debug)]
+pub struct RLearnerConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub alpha: ScheduleConfig,
+    // Learning rate of the average reward.
+    pub beta: ScheduleConfig,
+    pub epsilon: ScheduleConfig,
+}
+
+impl RLearnerConfig {
+    pub fn label(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("RLearner({})", name),
+            None => String::from("RLearner"),
+        }
+    }
+}
+
+// One unending trial per session, where every delivery is followed by a new
+// request where the taxi is.  Every [q_learner] profile is run alongside the
+// R-learners for comparison.
+#[derive(Deserialize, Debug)]
+pub struct ContinuingConfig {
+    pub steps: usize,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub r_learner: Vec<RLearnerConfig>,
+}
+
+// A second world for the solvers that can move what they learn to another
+// world.  They are trained on the configured world, then moved to this one
+// without further learning.
+#[derive(Deserialize, Debug)]
+pub struct TransferConfig {
+    pub world: WorldConfig,
+    #[serde(default)]
+    pub world_format: WorldFormatChoice,
+    #[serde(default)]
+    pub location_names: BTreeMap<char, String>,
+    pub probes: Vec<Probe>,
+    // Attempts from random states of the new world before learning there.
+    #[serde(default = "default_transfer_attempts")]
+    pub attempts: usize,
+}
+
+fn default_transfer_attempts() -> usize {
+    100
+}
+
+impl TransferConfig {
+    // Uses the configured costs, but not the terrain, which is laid out for
+    // the first world.
+    pub fn build_world(&self, costs: Costs) -> Result<World, world::Error> {
+        let mut world = match self.world {
+            WorldConfig::Text(ref source) => World::build(source, self.world_format.into(), costs)?,
+            WorldConfig::Structured(ref description) => {
+                World::build_from_description(description, costs)?
+            }
+        };
+
+        for (&glyph, name) in &self.location_names {
+            world.name_location(glyph, name)?;
+        }
+
+        Ok(world)
+    }
+}
+
+// Where trials start.  A location left out of a non-empty passenger or
+// destination table is never used, an empty table weights every location
+// equally.
+#[derive(Deserialize, Debug)]
+pub struct StartConfig {
+    // The first trial this distribution is used for, later stages replace
+    // earlier ones.
+    #[serde(default)]
+    pub from_trial: usize,
+    // Anywhere in the world if not set.
+    pub taxi: Option<(i32, i32)>,
+    #[serde(default)]
+    pub passenger: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub destination: BTreeMap<String, f64>,
+    // Lets the passenger start at the destination, which ends the trial
+    // before the first step.
+    #[serde(default)]
+    pub allow_same: bool,
+}
+
+impl StartConfig {
+    pub fn build(&self, world: &World) -> Result<StartDistribution, start::Error> {
+        let passenger: Vec<(&str, f64)> = self
+            .passenger
+            .iter()
+            .map(|(name, &w)| (name.as_str(), w))
+            .collect();
+        let destination: Vec<(&str, f64)> = self
+            .destination
+            .iter()
+            .map(|(name, &w)| (name.as_str(), w))
+            .collect();
+
+        StartDistribution::new(
+            world,
+            self.taxi.map(|(x, y)| Position::new(x, y)),
+            &passenger,
+            &destination,
+            self.allow_same,
+        )
+    }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(default)]
 pub struct Configuration {
-    pub world: String,
+    pub world: WorldConfig,
+    pub world_format: WorldFormatChoice,
+    pub generator: Option<GeneratorConfig>,
     pub costs: CostsConfig,
+    // Longer names for the locations, keyed by the glyph drawn on the map.
+    pub location_names: BTreeMap<char, String>,
     // TOML only allows for signed 64 bit integers, not unsigned.
     pub root_seed: Option<(i64, i64)>,
     pub rerun_seeds: Vec<(i64, i64)>,
     pub probes: Vec<Probe>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub start: Vec<StartConfig>,
     pub max_trials: usize,
     pub max_trial_steps: usize,
     pub sessions: usize,
-    pub random_solver: Option<RandomSolverConfig>,
-    pub q_learner: Option<QLearnerConfig>,
-    pub r_max: Option<RMaxConfig>,
-    pub factored_r_max: Option<FactoredRMaxConfig>,
-    pub max_q: Option<MaxQConfig>,
-    pub door_max: Option<DoorMaxConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub random_solver: Vec<RandomSolverConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub q_learner: Vec<QLearnerConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub r_max: Vec<RMaxConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub factored_r_max: Vec<FactoredRMaxConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub max_q: Vec<MaxQConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub door_max: Vec<DoorMaxConfig>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub schema_net: Vec<SchemaNetConfig>,
     pub replay: Option<Replay>,
+    pub sweep: Option<SweepConfig>,
+    pub continuing: Option<ContinuingConfig>,
+    pub transfer: Option<TransferConfig>,
+
+    // The raw tables, used to re-read solver configurations with
+    // overridden parameters.
+    #[serde(skip)]
+    pub source: toml::value::Table,
 }
 
 impl Configuration {
     pub fn from_file(filename: &str) -> Result<Configuration, Error> {
-        let mut config_file = File::open(filename).map_err(|_| Error::OpenFailure {
-            filename: String::from(filename),
-        })?;
+        let source = load_table(Path::new(filename), &mut Vec::new())?;
 
-        let mut config_string = String::new();
-        config_file
-            .read_to_string(&mut config_string)
-            .map_err(|_| Error::ReadFailure {
-                filename: String::from(filename),
-            })?;
+        Configuration::from_table(filename, source)
+    }
 
-        toml::from_str(&config_string).map_err(|error| Error::ParseFailure {
-            filename: String::from(filename),
-            error,
-        })
+    // Reads a configuration from its merged tables, filename only being used
+    // to report errors.
+    fn from_table(filename: &str, source: toml::value::Table) -> Result<Configuration, Error> {
+        let mut config: Configuration =
+            toml::Value::Table(source.clone())
+                .try_into()
+                .map_err(|error| Error::ParseFailure {
+                    filename: String::from(filename),
+                    error,
+                })?;
+
+        config.source = source;
+
+        Ok(config)
     }
-}
 
-pub enum Error {
-    OpenFailure {
-        filename: String,
-    },
-    ReadFailure {
-        filename: String,
-    },
-    ParseFailure {
-        filename: String,
-        error: toml::de::Error,
-    },
-}
+    pub fn is_solver_configured(&self, solver: SolverChoice) -> bool {
+        match solver {
+            SolverChoice::Random => !self.random_solver.is_empty(),
+            SolverChoice::QLearner => !self.q_learner.is_empty(),
+            SolverChoice::RMax => !self.r_max.is_empty(),
+            SolverChoice::FactoredRMax => !self.factored_r_max.is_empty(),
+            SolverChoice::MaxQ => !self.max_q.is_empty(),
+            SolverChoice::DoorMax => !self.door_max.is_empty(),
+            SolverChoice::SchemaNet => !self.schema_net.is_empty(),
+        }
+    }
 
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Error::OpenFailure { ref filename } => {
-                write!(f, "Configuration - Failed to open file '{}'", filename)
-            }
-            Error::ReadFailure { ref filename } => {
-                write!(f, "Configuration - Failed to read file '{}'", filename)
+    // The raw table of one of a solver's profiles, the first if no profile
+    // is named.
+    pub fn solver_table(
+        &self,
+        solver: SolverChoice,
+        profile: Option<&str>,
+    ) -> Option<&toml::value::Table> {
+        let tables: Vec<&toml::value::Table> = match self.source.get(solver.config_key())? {
+            toml::Value::Table(table) => vec![table],
+            toml::Value::Array(values) => values.iter().filter_map(toml::Value::as_table).collect(),
+            _ => Vec::new(),
+        };
+
+        tables.into_iter().find(|table| match profile {
+            Some(profile) => table.get("name").and_then(toml::Value::as_str) == Some(profile),
+            None => true,
+        })
+    }
+
+    // Drops every solver configuration except the given one.
+    pub fn retain_solver(&mut self, solver: SolverChoice) {
+        for other in SolverChoice::ALL.iter().filter(|&&other| other != solver) {
+            match *other {
+                SolverChoice::Random => self.random_solver.clear(),
+                SolverChoice::QLearner => self.q_learner.clear(),
+                SolverChoice::RMax => self.r_max.clear(),
+                SolverChoice::FactoredRMax => self.factored_r_max.clear(),
+                SolverChoice::MaxQ => self.max_q.clear(),
+                SolverChoice::DoorMax => self.door_max.clear(),
+                SolverChoice::SchemaNet => self.schema_net.clear(),
             }
-            Error::ParseFailure {
-                ref filename,
-                ref error,
-            } => write!(
-                f,
-                "Configuration - Failed to parse config file '{}' - {}",
-                filename, error
-            ),
         }
     }
 }
 
-impl Default for Configuration {
-    fn default() -> Configuration {
-        let world_str = "\
-                         ┌───┬─────┐\n\
-                         │R .│. . G│\n\
-                         │   │     │\n\
-                         │. .│. . .│\n\
-                         │         │\n\
-                         │. . . . .│\n\
-                         │         │\n\
-                         │.│. .│. .│\n\
-                         │ │   │   │\n\
-                         │Y│. .│B .│\n\
-                         └─┴───┴───┘\n\
-                         ";
+// One of a solver's profiles, the first if no profile is named.
+pub fn find_profile<'a, T: ReportConfig>(
+    profiles: &'a [T],
+    profile: Option<&str>,
+) -> Option<&'a T> {
+    profiles.iter().find(|config| match profile {
+        Some(profile) => config.name() == Some(profile),
+        None => true,
+    })
+}
 
-        let costs = CostsConfig {
-            movement: Costs::default().movement,
-            miss_pickup: Costs::default().miss_pickup,
-            miss_dropoff: Costs::default().miss_dropoff,
-            empty_dropoff: Costs::default().empty_dropoff,
+// Reads a file along with everything it includes.  Included files are merged
+// in the order given, then the including file is merged over them.
+fn load_table(path: &Path, including: &mut Vec<PathBuf>) -> Result<toml::value::Table, Error> {
+    let filename = path.display().to_string();
+
+    let mut config_file = File::open(path).map_err(|_| Error::OpenFailure {
+        filename: filename.clone(),
+    })?;
+
+    // Compare canonical paths so that "a/../b.toml" and "b.toml" are the same file.
+    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if including.contains(&canonical_path) {
+        return Err(Error::IncludeCycle { filename });
+    }
+
+    let mut config_string = String::new();
+    config_file
+        .read_to_string(&mut config_string)
+        .map_err(|_| Error::ReadFailure {
+            filename: filename.clone(),
+        })?;
+
+    let mut table: toml::value::Table =
+        toml::from_str(&config_string).map_err(|error| Error::ParseFailure {
+            filename: filename.clone(),
+            error,
+        })?;
+
+    let includes = match table.remove("include") {
+        Some(toml::Value::Array(includes)) => includes,
+        Some(_) => return Err(Error::InvalidInclude { filename }),
+        None => Vec::new(),
+    };
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    including.push(canonical_path);
+
+    let mut merged = toml::value::Table::new();
+    for include in includes {
+        let include = include.as_str().ok_or_else(|| Error::InvalidInclude {
+            filename: filename.clone(),
+        })?;
+
+        merge_tables(
+            &mut merged,
+            load_table(&directory.join(include), including)?,
+        );
+    }
+
+    including.pop();
+
+    merge_tables(&mut merged, table);
+
+    Ok(merged)
+}
+
+// Tables are merged key by key, any other value, including arrays, replaces
+// the one before it.
+fn merge_tables(base: &mut toml::value::Table, overlay: toml::value::Table) {
+    for (key, value) in overlay {
+        let value = match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+                continue;
+            }
+            (_, value) => value,
+        };
+
+        base.insert(key, value);
+    }
+}
+
+// Accepts either a single table, e.g. [q_learner], or an array of profiles,
+// e.g. [[q_learner]].
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    match toml::Value::deserialize(deserializer)? {
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|value| value.try_into().map_err(D::Error::custom))
+            .collect(),
+        value => value
+            .try_into()
+            .map(|config| vec![config])
+            .map_err(D::Error::custom),
+    }
+}
+
+pub struct ValidationError {
+    // The TOML key path of the offending value, e.g. "probes[2].passenger_loc".
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.path, self.message)
+    }
+}
+
+impl fmt::Debug for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Configuration {
+    pub fn build_world(&self, costs: Costs) -> Result<World, world::Error> {
+        let mut world = self.build_world_layout(costs)?;
+
+        if let Some(ref terrain) = self.costs.terrain {
+            world.apply_terrain_layer(terrain)?;
+        }
+
+        for (&glyph, name) in &self.location_names {
+            world.name_location(glyph, name)?;
+        }
+
+        Ok(world)
+    }
+
+    fn build_world_layout(&self, costs: Costs) -> Result<World, world::Error> {
+        match self.world {
+            WorldConfig::Text(ref source) => World::build(source, self.world_format.into(), costs),
+            WorldConfig::Structured(ref description) => {
+                World::build_from_description(description, costs)
+            }
+        }
+    }
+
+    pub fn build_starts(&self, world: &World) -> Result<Curriculum, start::Error> {
+        let mut curriculum = Curriculum::new();
+
+        for start_config in &self.start {
+            curriculum.add_stage(start_config.from_trial, start_config.build(world)?);
+        }
+
+        Ok(curriculum)
+    }
+
+    // Checks everything that can be checked without running a solver,
+    // reporting every problem found rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        validate_costs(&self.costs, &mut errors);
+
+        if self.max_trials == 0 {
+            add_error(&mut errors, "max_trials", "must be at least 1.");
+        }
+        if self.max_trial_steps == 0 {
+            add_error(&mut errors, "max_trial_steps", "must be at least 1.");
+        }
+
+        let costs = Costs::new(
+            self.costs.movement,
+            self.costs.miss_pickup,
+            self.costs.miss_dropoff,
+            self.costs.empty_dropoff,
+        );
+
+        // A generated world is only known once it has been generated, so the
+        // probes, starts and replay can not be checked against it here.
+        if let Some(ref generator) = self.generator {
+            if let Err(error) = Generator::new(
+                generator.width,
+                generator.height,
+                generator.wall_density,
+                generator.fixed_positions,
+            ) {
+                add_error(&mut errors, "generator", &format!("{}", error));
+            }
+
+            if generator.probes == 0 {
+                add_error(&mut errors, "generator.probes", "must be at least 1.");
+            }
+
+            if self.costs.terrain.is_some() {
+                add_error(
+                    &mut errors,
+                    "costs.terrain",
+                    "does not apply to a generated world.",
+                );
+            }
+
+            if !self.location_names.is_empty() {
+                add_error(
+                    &mut errors,
+                    "location_names",
+                    "does not apply to a generated world.",
+                );
+            }
+        } else {
+            if let WorldConfig::Structured(_) = self.world {
+                if self.world_format != WorldFormatChoice::Auto
+                    && self.world_format != WorldFormatChoice::Toml
+                {
+                    add_error(
+                        &mut errors,
+                        "world_format",
+                        &format!(
+                            "{} does not apply to a [world] table.",
+                            self.world_format.name()
+                        ),
+                    );
+                }
+            }
+
+            match self.build_world_layout(costs) {
+                Ok(mut world) => {
+                    if let Some(ref terrain) = self.costs.terrain {
+                        if let Err(error) = world.apply_terrain_layer(terrain) {
+                            add_error(&mut errors, "costs.terrain", &format!("{}", error));
+                        }
+                    }
+
+                    for (&glyph, name) in &self.location_names {
+                        if let Err(error) = world.name_location(glyph, name) {
+                            add_error(
+                                &mut errors,
+                                &format!("location_names.{}", glyph),
+                                &format!("{}", error),
+                            );
+                        }
+                    }
+
+                    if world.num_fixed_positions() < 2 {
+                        add_error(
+                            &mut errors,
+                            "world",
+                            &format!(
+                                "has {} locations, at least 2 are needed.",
+                                world.num_fixed_positions()
+                            ),
+                        );
+                    }
+
+                    for (index, factored_rmax_config) in self.factored_r_max.iter().enumerate() {
+                        if let Some(ref parents) = factored_rmax_config.parents {
+                            if let Err(dbn_errors) = parents.build().validate(&world) {
+                                let path = profile_path(&self.factored_r_max, index);
+                                for error in dbn_errors {
+                                    add_error(
+                                        &mut errors,
+                                        &format!("{}.parents", path),
+                                        &format!("{}", error),
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    for (index, probe) in self.probes.iter().enumerate() {
+                        validate_state(
+                            &world,
+                            &format!("probes[{}]", index),
+                            probe.taxi_pos,
+                            probe.passenger_loc.as_deref(),
+                            &probe.destination_loc,
+                            &mut errors,
+                        );
+                    }
+
+                    for (index, start_config) in self.start.iter().enumerate() {
+                        if let Err(error) = start_config.build(&world) {
+                            add_error(
+                                &mut errors,
+                                &format!("start[{}]", index),
+                                &format!("{}", error),
+                            );
+                        }
+                    }
+
+                    if let Some(ref replay) = self.replay {
+                        validate_state(
+                            &world,
+                            "replay",
+                            replay.taxi_pos,
+                            replay.passenger_loc.as_deref(),
+                            &replay.destination_loc,
+                            &mut errors,
+                        );
+                    }
+                }
+                Err(error) => add_error(&mut errors, "world", &format!("{}", error)),
+            }
+        }
+
+        if let Some(ref replay) = self.replay {
+            if !self.is_solver_configured(replay.solver) {
+                add_error(
+                    &mut errors,
+                    "replay.solver",
+                    &format!(
+                        "{:?} has no [{}] section.",
+                        replay.solver,
+                        replay.solver.config_key()
+                    ),
+                );
+            } else if let Some(ref profile) = replay.profile {
+                if self.solver_table(replay.solver, Some(profile)).is_none() {
+                    add_error(
+                        &mut errors,
+                        "replay.profile",
+                        &format!(
+                            "no [{}] profile is named '{}'.",
+                            replay.solver.config_key(),
+                            profile
+                        ),
+                    );
+                }
+            }
+        }
+
+        validate_profiles(&self.random_solver, &mut errors, |_, _, _| ());
+
+        validate_profiles(&self.q_learner, &mut errors, |path, config, errors| {
+            validate_schedule(&format!("{}.alpha", path), &config.alpha, true, errors);
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+            validate_schedule(&format!("{}.epsilon", path), &config.epsilon, false, errors);
+        });
+
+        validate_profiles(&self.r_max, &mut errors, |path, config, errors| {
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+            validate_known_count(path, config.known_count, config.error_delta, errors);
+        });
+
+        validate_profiles(&self.factored_r_max, &mut errors, |path, config, errors| {
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+            validate_known_count(path, config.known_count, config.error_delta, errors);
+
+            if config.max_parents < 1 || config.max_parents > Factor::NUM_ELEMENTS {
+                add_error(
+                    errors,
+                    &format!("{}.max_parents", path),
+                    &format!(
+                        "is {}, but must be between 1 and {}.",
+                        config.max_parents,
+                        Factor::NUM_ELEMENTS
+                    ),
+                );
+            }
+
+            if config.structure_tolerance <= 0.0 {
+                add_error(
+                    errors,
+                    &format!("{}.structure_tolerance", path),
+                    &format!("is {}, but must be positive.", config.structure_tolerance),
+                );
+            }
+
+            if let Some(ref parents) = config.parents {
+                if config.learn_structure {
+                    add_error(
+                        errors,
+                        &format!("{}.parents", path),
+                        "does not apply when learn_structure is set.",
+                    );
+                }
+
+                validate_dbn(&format!("{}.parents", path), parents, errors);
+            }
+        });
+
+        validate_profiles(&self.max_q, &mut errors, |path, config, errors| {
+            validate_schedule(&format!("{}.alpha", path), &config.alpha, true, errors);
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+            validate_schedule(&format!("{}.epsilon", path), &config.epsilon, false, errors);
+        });
+
+        validate_profiles(&self.door_max, &mut errors, |path, config, errors| {
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+            validate_known_count(path, config.known_count, config.error_delta, errors);
+
+            if let Err(error) = TaxiObjects::with_predicates(&config.terms) {
+                add_error(errors, &format!("{}.terms", path), &format!("{}", error));
+            }
+
+            if config.max_effects < 1 {
+                add_error(
+                    errors,
+                    &format!("{}.max_effects", path),
+                    "must be at least 1.",
+                );
+            }
+
+            if config.explanation.is_some() && !config.report {
+                add_error(
+                    errors,
+                    &format!("{}.explanation", path),
+                    "is only written when report is set.",
+                );
+            }
+        });
+
+        validate_profiles(&self.schema_net, &mut errors, |path, config, errors| {
+            validate_unit_interval(&format!("{}.gamma", path), config.gamma, errors);
+
+            if config.error_delta <= 0.0 {
+                add_error(
+                    errors,
+                    &format!("{}.error_delta", path),
+                    &format!("is {}, but must be positive.", config.error_delta),
+                );
+            }
+
+            if let Err(error) = TaxiObjects::with_predicates(&config.terms) {
+                add_error(errors, &format!("{}.terms", path), &format!("{}", error));
+            }
+        });
+
+        if let Some(ref sweep) = self.sweep {
+            self.validate_sweep(sweep, &mut errors);
+        }
+
+        if let Some(ref continuing) = self.continuing {
+            self.validate_continuing(continuing, &mut errors);
+        }
+
+        if let Some(ref transfer) = self.transfer {
+            self.validate_transfer(transfer, costs, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_continuing(
+        &self,
+        continuing: &ContinuingConfig,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if continuing.steps == 0 {
+            add_error(errors, "continuing.steps", "must be at least 1.");
+        }
+
+        if continuing.r_learner.is_empty() && self.q_learner.is_empty() {
+            add_error(
+                errors,
+                "continuing",
+                "has nothing to run, add a [continuing.r_learner] or a [q_learner].",
+            );
+        }
+
+        for (index, config) in continuing.r_learner.iter().enumerate() {
+            let path = if continuing.r_learner.len() == 1 {
+                String::from("continuing.r_learner")
+            } else {
+                let path = format!("continuing.r_learner[{}]", index);
+
+                let duplicate_name = config.name.is_some()
+                    && continuing.r_learner[..index]
+                        .iter()
+                        .any(|other| other.name == config.name);
+
+                if config.name.is_none() || duplicate_name {
+                    add_error(
+                        errors,
+                        &format!("{}.name", path),
+                        "must be set and unique to tell apart the profiles.",
+                    );
+                }
+
+                path
+            };
+
+            validate_schedule(&format!("{}.alpha", path), &config.alpha, true, errors);
+            validate_schedule(&format!("{}.beta", path), &config.beta, true, errors);
+            validate_schedule(&format!("{}.epsilon", path), &config.epsilon, false, errors);
+        }
+    }
+
+    fn validate_transfer(
+        &self,
+        transfer: &TransferConfig,
+        costs: Costs,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if self.door_max.is_empty() && self.schema_net.is_empty() {
+            add_error(
+                errors,
+                "transfer",
+                "has nothing to run, add a [door_max] or a [schema_net].",
+            );
+        }
+
+        if transfer.probes.is_empty() {
+            add_error(errors, "transfer.probes", "must have at least 1 probe.");
+        }
+
+        match transfer.build_world(costs) {
+            Ok(world) => {
+                for (index, probe) in transfer.probes.iter().enumerate() {
+                    validate_state(
+                        &world,
+                        &format!("transfer.probes[{}]", index),
+                        probe.taxi_pos,
+                        probe.passenger_loc.as_deref(),
+                        &probe.destination_loc,
+                        errors,
+                    );
+                }
+            }
+            Err(error) => add_error(errors, "transfer.world", &format!("{}", error)),
+        }
+    }
+
+    fn validate_sweep(&self, sweep: &SweepConfig, errors: &mut Vec<ValidationError>) {
+        if let SearchChoice::Random = sweep.search {
+            if sweep.samples == 0 {
+                add_error(
+                    errors,
+                    "sweep.samples",
+                    "must be at least 1 for a random search.",
+                );
+            }
+        }
+
+        let base_table = match self.solver_table(sweep.solver, sweep.profile.as_deref()) {
+            Some(table) => table,
+            None => {
+                match sweep.profile {
+                    Some(ref profile) if self.is_solver_configured(sweep.solver) => add_error(
+                        errors,
+                        "sweep.profile",
+                        &format!(
+                            "no [{}] profile is named '{}'.",
+                            sweep.solver.config_key(),
+                            profile
+                        ),
+                    ),
+                    _ => add_error(
+                        errors,
+                        "sweep.solver",
+                        &format!(
+                            "{:?} has no [{}] section.",
+                            sweep.solver,
+                            sweep.solver.config_key()
+                        ),
+                    ),
+                }
+                return;
+            }
+        };
+
+        for (parameter, range) in &sweep.parameters {
+            let path = format!("sweep.parameters.{}", parameter);
+
+            if !base_table.contains_key(parameter) {
+                add_error(
+                    errors,
+                    &path,
+                    &format!("is not set in [{}].", sweep.solver.config_key()),
+                );
+            }
+
+            match *range {
+                SweepRange::Values { ref values } => {
+                    if values.is_empty() {
+                        add_error(errors, &format!("{}.values", path), "must not be empty.");
+                    }
+                }
+                SweepRange::Range {
+                    start,
+                    end,
+                    count,
+                    log,
+                } => {
+                    if count == 0 {
+                        add_error(errors, &format!("{}.count", path), "must be at least 1.");
+                    }
+                    if log && (start <= 0.0 || end <= 0.0) {
+                        add_error(
+                            errors,
+                            &path,
+                            "start and end must be positive for a log range.",
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The key path of a profile, "q_learner" for a single profile or
+// "q_learner[1]" for one of several.
+fn profile_path<T: ReportConfig>(profiles: &[T], index: usize) -> String {
+    let key = profiles[index].solver_choice().config_key();
+
+    if profiles.len() == 1 {
+        String::from(key)
+    } else {
+        format!("{}[{}]", key, index)
+    }
+}
+
+// Checks each profile at its key path, where several profiles must have
+// distinct names.
+fn validate_profiles<T, F>(profiles: &[T], errors: &mut Vec<ValidationError>, validate: F)
+where
+    T: ReportConfig,
+    F: Fn(&str, &T, &mut Vec<ValidationError>),
+{
+    for (index, config) in profiles.iter().enumerate() {
+        let key = config.solver_choice().config_key();
+        let path = profile_path(profiles, index);
+
+        if profiles.len() == 1 {
+            validate(&path, config, errors);
+            continue;
+        }
+
+        match config.name() {
+            Some(name) => {
+                if profiles[..index]
+                    .iter()
+                    .any(|other| other.name() == Some(name))
+                {
+                    add_error(
+                        errors,
+                        &format!("{}.name", path),
+                        &format!("'{}' is used by more than one profile.", name),
+                    );
+                }
+            }
+            None => add_error(
+                errors,
+                &format!("{}.name", path),
+                &format!(
+                    "is needed to tell apart the {} [[{}]] profiles.",
+                    profiles.len(),
+                    key
+                ),
+            ),
+        }
+
+        validate(&path, config, errors);
+    }
+}
+
+// A factor named twice among its parents is most likely a typo for another.
+fn validate_dbn(path: &str, parents: &DbnConfig, errors: &mut Vec<ValidationError>) {
+    for &(_, action_key, action_parents) in parents.actions().iter() {
+        let mut declared: Vec<(String, &Vec<Factor>)> = action_parents
+            .factors()
+            .iter()
+            .filter_map(|&(factor, factor_parents)| {
+                factor_parents
+                    .as_ref()
+                    .map(|factor_parents| (factor.to_string(), factor_parents))
+            })
+            .collect();
+        if let Some(ref reward_parents) = action_parents.reward {
+            declared.push((String::from("reward"), reward_parents));
+        }
+
+        for (key, factor_parents) in declared {
+            for (index, factor) in factor_parents.iter().enumerate() {
+                if factor_parents[..index].contains(factor) {
+                    add_error(
+                        errors,
+                        &format!("{}.{}.{}", path, action_key, key),
+                        &format!("names {} more than once.", factor),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn add_error(errors: &mut Vec<ValidationError>, path: &str, message: &str) {
+    errors.push(ValidationError {
+        path: String::from(path),
+        message: String::from(message),
+    });
+}
+
+// Rewards are costs, the best a step can do is 0.
+fn validate_costs(costs: &CostsConfig, errors: &mut Vec<ValidationError>) {
+    if costs.movement >= 0.0 {
+        add_error(
+            errors,
+            "costs.movement",
+            &format!("is {}, but must be negative.", costs.movement),
+        );
+    }
+
+    let penalties = [
+        ("costs.miss_pickup", costs.miss_pickup),
+        ("costs.miss_dropoff", costs.miss_dropoff),
+        ("costs.empty_dropoff", costs.empty_dropoff),
+    ];
+
+    for &(path, cost) in &penalties {
+        if cost > 0.0 {
+            add_error(
+                errors,
+                path,
+                &format!("is {}, but must not be positive.", cost),
+            );
+        }
+    }
+}
+
+fn validate_state(
+    world: &World,
+    path: &str,
+    taxi_pos: (i32, i32),
+    passenger_loc: Option<&str>,
+    destination_loc: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    if taxi_pos.0 < 0 || taxi_pos.0 >= world.width || taxi_pos.1 < 0 || taxi_pos.1 >= world.height {
+        add_error(
+            errors,
+            &format!("{}.taxi_pos", path),
+            &format!(
+                "[{}, {}] is outside the {}x{} world.",
+                taxi_pos.0, taxi_pos.1, world.width, world.height
+            ),
+        );
+    }
+
+    if let Some(passenger_loc) = passenger_loc {
+        if world.find_location(passenger_loc).is_none() {
+            add_error(
+                errors,
+                &format!("{}.passenger_loc", path),
+                &format!("'{}' is not a location in the world.", passenger_loc),
+            );
+        }
+    }
+
+    if world.find_location(destination_loc).is_none() {
+        add_error(
+            errors,
+            &format!("{}.destination_loc", path),
+            &format!("'{}' is not a location in the world.", destination_loc),
+        );
+    }
+}
+
+fn validate_unit_interval(path: &str, value: f64, errors: &mut Vec<ValidationError>) {
+    if !(0.0..=1.0).contains(&value) {
+        add_error(
+            errors,
+            path,
+            &format!("is {}, but must be between 0 and 1.", value),
+        );
+    }
+}
+
+fn validate_learning_rate(path: &str, value: f64, errors: &mut Vec<ValidationError>) {
+    if value <= 0.0 || value > 1.0 {
+        add_error(
+            errors,
+            path,
+            &format!("is {}, but must be above 0 and at most 1.", value),
+        );
+    }
+}
+
+// A learning rate must start above 0 but may decay to 0, every other
+// schedule stays within [0, 1].
+fn validate_schedule(
+    path: &str,
+    schedule: &ScheduleConfig,
+    is_learning_rate: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    let validate_initial = if is_learning_rate {
+        validate_learning_rate
+    } else {
+        validate_unit_interval
+    };
+
+    let decay = match *schedule {
+        ScheduleConfig::Constant(value) => {
+            validate_initial(path, value, errors);
+            return;
+        }
+        ScheduleConfig::Decay(decay) => decay,
+    };
+
+    let field = |name: &str| format!("{}.{}", path, name);
+
+    match decay {
+        DecayConfig::Constant { value } => validate_initial(&field("value"), value, errors),
+        DecayConfig::Linear { start, end, .. } => {
+            validate_initial(&field("start"), start, errors);
+            validate_unit_interval(&field("end"), end, errors);
+        }
+        DecayConfig::Exponential {
+            start,
+            rate,
+            minimum,
+            ..
+        } => {
+            validate_initial(&field("start"), start, errors);
+            validate_learning_rate(&field("rate"), rate, errors);
+            validate_unit_interval(&field("minimum"), minimum, errors);
+        }
+        DecayConfig::VisitCount { scale, minimum } => {
+            validate_initial(&field("scale"), scale, errors);
+            validate_unit_interval(&field("minimum"), minimum, errors);
+        }
+        DecayConfig::StepWise {
+            start,
+            factor,
+            minimum,
+            ..
+        } => {
+            validate_initial(&field("start"), start, errors);
+            validate_learning_rate(&field("factor"), factor, errors);
+            validate_unit_interval(&field("minimum"), minimum, errors);
+        }
+    }
+}
+
+fn validate_known_count(
+    solver: &str,
+    known_count: f64,
+    error_delta: f64,
+    errors: &mut Vec<ValidationError>,
+) {
+    if known_count < 1.0 {
+        add_error(
+            errors,
+            &format!("{}.known_count", solver),
+            &format!("is {}, but must be at least 1.", known_count),
+        );
+    }
+
+    if error_delta <= 0.0 {
+        add_error(
+            errors,
+            &format!("{}.error_delta", solver),
+            &format!("is {}, but must be positive.", error_delta),
+        );
+    }
+}
+
+pub enum Error {
+    OpenFailure {
+        filename: String,
+    },
+    ReadFailure {
+        filename: String,
+    },
+    ParseFailure {
+        filename: String,
+        error: toml::de::Error,
+    },
+    InvalidInclude {
+        filename: String,
+    },
+    IncludeCycle {
+        filename: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::OpenFailure { ref filename } => {
+                write!(f, "Configuration - Failed to open file '{}'", filename)
+            }
+            Error::ReadFailure { ref filename } => {
+                write!(f, "Configuration - Failed to read file '{}'", filename)
+            }
+            Error::ParseFailure {
+                ref filename,
+                ref error,
+            } => write!(
+                f,
+                "Configuration - Failed to parse config file '{}' - {}",
+                filename, error
+            ),
+            Error::InvalidInclude { ref filename } => write!(
+                f,
+                "Configuration - 'include' in '{}' must be an array of file names",
+                filename
+            ),
+            Error::IncludeCycle { ref filename } => {
+                write!(
+                    f,
+                    "Configuration - '{}' is included again by a file it includes",
+                    filename
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Default for Configuration {
+    fn default() -> Configuration {
+        let world_str = "\
+                         ┌───┬─────┐\n\
+                         │R .│. . G│\n\
+                         │   │     │\n\
+                         │. .│. . .│\n\
+                         │         │\n\
+                         │. . . . .│\n\
+                         │         │\n\
+                         │.│. .│. .│\n\
+                         │ │   │   │\n\
+                         │Y│. .│B .│\n\
+                         └─┴───┴───┘\n\
+                         ";
+
+        let costs = CostsConfig {
+            movement: Costs::default().movement,
+            miss_pickup: Costs::default().miss_pickup,
+            miss_dropoff: Costs::default().miss_dropoff,
+            empty_dropoff: Costs::default().empty_dropoff,
+            terrain: None,
         };
 
         Configuration {
-            world: String::from(world_str),
+            world: WorldConfig::Text(String::from(world_str)),
+            world_format: WorldFormatChoice::Auto,
+            generator: None,
             costs,
+            location_names: BTreeMap::new(),
             root_seed: None,
             rerun_seeds: Vec::new(),
             probes: Vec::new(),
+            start: Vec::new(),
             max_trials: 1,
             max_trial_steps: 100,
             sessions: 0,
-            random_solver: None,
-            q_learner: None,
-            r_max: None,
-            factored_r_max: None,
-            max_q: None,
-            door_max: None,
+            random_solver: Vec::new(),
+            q_learner: Vec::new(),
+            r_max: Vec::new(),
+            factored_r_max: Vec::new(),
+            max_q: Vec::new(),
+            door_max: Vec::new(),
+            schema_net: Vec::new(),
             replay: None,
+            sweep: None,
+            continuing: None,
+            transfer: None,
+            source: toml::value::Table::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_configuration {
+    use super::*;
+
+    fn build_config(source: &str) -> Configuration {
+        Configuration::from_table("test.cfg", toml::from_str(source).unwrap()).unwrap()
+    }
+
+    fn error_paths(config: &Configuration) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.path).collect(),
         }
     }
+
+    const WORLD: &str = r#"
+world = """
+┌───┐
+│R .│
+│   │
+│.│G│
+└─┴─┘
+"""
+max_trials = 10
+max_trial_steps = 100
+sessions = 1
+
+[costs]
+movement = -1
+miss_pickup = -10
+miss_dropoff = -10
+empty_dropoff = -10
+"#;
+
+    fn build_errors(extra: &str) -> Vec<String> {
+        error_paths(&build_config(&format!("{}{}", WORLD, extra)))
+    }
+
+    fn build_world() -> World {
+        build_config(WORLD).build_world(Costs::default()).unwrap()
+    }
+
+    fn schedule_paths(schedule: &str, is_learning_rate: bool) -> Vec<String> {
+        let table: toml::value::Table = toml::from_str(&format!("alpha = {}", schedule)).unwrap();
+        let schedule: ScheduleConfig = table["alpha"].clone().try_into().unwrap();
+
+        let mut errors = Vec::new();
+        validate_schedule("alpha", &schedule, is_learning_rate, &mut errors);
+        errors.into_iter().map(|error| error.path).collect()
+    }
+
+    // A directory of configuration files, removed once the test is done.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!(
+                "taxi-configuration-{}-{}",
+                std::process::id(),
+                name
+            ));
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn write(&self, filename: &str, contents: &str) -> String {
+            let path = self.0.join(filename);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, contents).unwrap();
+            path.display().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn parse_table(source: &str) -> toml::value::Table {
+        toml::from_str(source).unwrap()
+    }
+
+    const Q_LEARNER: &str = r#"
+[q_learner]
+alpha = 0.1
+gamma = 0.3
+epsilon = 0.1
+report = false
+"#;
+
+    #[test]
+    fn accepts_valid_configuration() {
+        assert!(build_errors("").is_empty());
+    }
+
+    #[test]
+    fn rejects_zero_trials() {
+        let source = WORLD
+            .replace("max_trials = 10", "max_trials = 0")
+            .replace("max_trial_steps = 100", "max_trial_steps = 0");
+
+        assert_eq!(
+            error_paths(&build_config(&source)),
+            vec!["max_trials", "max_trial_steps"]
+        );
+    }
+
+    #[test]
+    fn rejects_unbuildable_world() {
+        let source = WORLD.replace("│R .│", "│R .");
+        assert_eq!(error_paths(&build_config(&source)), vec!["world"]);
+
+        let source = WORLD.replace("│R .│", "│. .│");
+        let errors = build_config(&source).validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .to_string()
+            .starts_with("world - Parse failure: Found 1 fixed positions"));
+    }
+
+    #[test]
+    fn rejects_generator_settings() {
+        let errors = build_errors(
+            r#"
+            [generator]
+            width = 0
+            height = 3
+            wall_density = 0.1
+            fixed_positions = 2
+            probes = 0
+            "#,
+        );
+        assert_eq!(errors, vec!["generator", "generator.probes"]);
+
+        let source = format!(
+            "{}{}",
+            WORLD.replace("[costs]", "[location_names]\nR = \"Red\"\n\n[costs]"),
+            r#"
+            terrain = "2 ."
+            [generator]
+            width = 3
+            height = 3
+            wall_density = 0.1
+            fixed_positions = 2
+            "#,
+        );
+        assert_eq!(
+            error_paths(&build_config(&source)),
+            vec!["costs.terrain", "location_names"]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_locations() {
+        let source = WORLD.replace("[costs]", "[location_names]\nB = \"Blue\"\n\n[costs]");
+        assert_eq!(
+            error_paths(&build_config(&source)),
+            vec!["location_names.B"]
+        );
+
+        let errors = build_errors(
+            r#"
+            [[probes]]
+            taxi_pos = [0, 0]
+            passenger_loc = "R"
+            destination_loc = "G"
+            max_steps = 10
+
+            [[probes]]
+            taxi_pos = [2, 0]
+            passenger_loc = "B"
+            destination_loc = "Y"
+            max_steps = 10
+
+            [[start]]
+            passenger = { B = 1.0 }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "probes[1].taxi_pos",
+                "probes[1].passenger_loc",
+                "probes[1].destination_loc",
+                "start[0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_replay_without_solver() {
+        let replay = r#"
+            [replay]
+            solver = "QLearner"
+            profile = "fast"
+            taxi_pos = [0, 0]
+            passenger_loc = "R"
+            destination_loc = "X"
+            max_steps = 10
+            "#;
+
+        assert_eq!(
+            build_errors(replay),
+            vec!["replay.destination_loc", "replay.solver"]
+        );
+
+        let q_learner = r#"
+            [q_learner]
+            alpha = 0.1
+            gamma = 0.3
+            epsilon = 0.1
+            report = false
+            "#;
+        assert_eq!(
+            build_errors(&format!("{}{}", q_learner, replay)),
+            vec!["replay.destination_loc", "replay.profile"]
+        );
+    }
+
+    #[test]
+    fn rejects_solver_settings() {
+        let errors = build_errors(
+            r#"
+            [q_learner]
+            alpha = 0.0
+            gamma = 1.5
+            epsilon = -0.1
+            report = false
+
+            [r_max]
+            gamma = 0.3
+            known_count = 0.5
+            error_delta = 0.0
+            report = false
+
+            [door_max]
+            gamma = 0.3
+            use_reward_learner = true
+            known_count = 1
+            error_delta = 1.0e-6
+            terms = ["no-such-term"]
+            max_effects = 0
+            report = false
+            explanation = "doormax.json"
+
+            [schema_net]
+            gamma = -0.3
+            error_delta = -1.0
+            report = false
+            "#,
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                "q_learner.alpha",
+                "q_learner.gamma",
+                "q_learner.epsilon",
+                "r_max.known_count",
+                "r_max.error_delta",
+                "door_max.terms",
+                "door_max.max_effects",
+                "door_max.explanation",
+                "schema_net.gamma",
+                "schema_net.error_delta",
+            ]
+        );
+    }
+
+    const FACTORED_R_MAX: &str = r#"
+[factored_r_max]
+gamma = 0.3
+known_count = 1
+error_delta = 1.0e-6
+report = false
+"#;
+
+    #[test]
+    fn builds_declared_parents() {
+        let source = format!(
+            "{}{}{}",
+            WORLD,
+            FACTORED_R_MAX,
+            r#"
+[factored_r_max.parents.north]
+x = ["x", "y"]
+reward = []
+[factored_r_max.parents.pick_up]
+passenger = ["destination", "passenger"]
+"#
+        );
+        let config = build_config(&source);
+        assert!(error_paths(&config).is_empty());
+
+        let mut expected = Dbn::taxi();
+        expected.set_transition_parents(
+            Actions::North,
+            Factor::X,
+            Parents::new(&[Factor::X, Factor::Y]),
+        );
+        expected.set_reward_parents(Actions::North, Parents::default());
+        expected.set_transition_parents(
+            Actions::PickUp,
+            Factor::Passenger,
+            Parents::new(&[Factor::Passenger, Factor::Destination]),
+        );
+        assert_eq!(
+            Structure::from(&config.factored_r_max[0]),
+            Structure::Fixed(expected)
+        );
+
+        // Without parents, the taxi's are used.
+        let config = build_config(&format!("{}{}", WORLD, FACTORED_R_MAX));
+        assert_eq!(
+            Structure::from(&config.factored_r_max[0]),
+            Structure::Fixed(Dbn::taxi())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_parents() {
+        let source = format!(
+            "{}{}{}",
+            WORLD, FACTORED_R_MAX, "[factored_r_max.parents.east]\nx = [\"x\", \"fuel\"]\n"
+        );
+        let error = Configuration::from_table("test.cfg", toml::from_str(&source).unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown variant `fuel`"), "{}", error);
+
+        assert_eq!(
+            build_errors(&format!(
+                "{}{}",
+                FACTORED_R_MAX, "[factored_r_max.parents.east]\nreward = [\"x\", \"x\"]\n"
+            )),
+            vec!["factored_r_max.parents.east.reward"]
+        );
+    }
+
+    #[test]
+    fn rejects_schedules_outside_range() {
+        assert!(schedule_paths("0.5", true).is_empty());
+        assert!(schedule_paths("0.0", false).is_empty());
+        assert_eq!(schedule_paths("0.0", true), vec!["alpha"]);
+        assert_eq!(schedule_paths("1.5", false), vec!["alpha"]);
+
+        assert_eq!(
+            schedule_paths(r#"{ kind = "constant", value = 0.0 }"#, true),
+            vec!["alpha.value"]
+        );
+        assert_eq!(
+            schedule_paths(
+                r#"{ kind = "linear", start = 1.5, end = -0.1, duration = 10 }"#,
+                true
+            ),
+            vec!["alpha.start", "alpha.end"]
+        );
+        assert!(schedule_paths(
+            r#"{ kind = "linear", start = 1.0, end = 0.0, duration = 10 }"#,
+            true
+        )
+        .is_empty());
+        assert_eq!(
+            schedule_paths(
+                r#"{ kind = "exponential", start = 0.0, rate = 1.5, minimum = 2.0 }"#,
+                true
+            ),
+            vec!["alpha.start", "alpha.rate", "alpha.minimum"]
+        );
+        assert_eq!(
+            schedule_paths(
+                r#"{ kind = "visit_count", scale = 1.5, minimum = -1.0 }"#,
+                false
+            ),
+            vec!["alpha.scale", "alpha.minimum"]
+        );
+        assert_eq!(
+            schedule_paths(
+                r#"{ kind = "step_wise", start = 0.0, factor = 0.0, interval = 10, minimum = 1.5 }"#,
+                true
+            ),
+            vec!["alpha.start", "alpha.factor", "alpha.minimum"]
+        );
+    }
+
+    #[test]
+    fn rejects_positive_costs() {
+        let costs = |movement, miss_pickup, miss_dropoff, empty_dropoff| {
+            let costs = CostsConfig {
+                movement,
+                miss_pickup,
+                miss_dropoff,
+                empty_dropoff,
+                terrain: None,
+            };
+
+            let mut errors = Vec::new();
+            validate_costs(&costs, &mut errors);
+            errors
+                .into_iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert!(costs(-1.0, -10.0, 0.0, -10.0).is_empty());
+        assert_eq!(
+            costs(0.0, 1.0, 2.0, 3.0),
+            vec![
+                "costs.movement - is 0, but must be negative.",
+                "costs.miss_pickup - is 1, but must not be positive.",
+                "costs.miss_dropoff - is 2, but must not be positive.",
+                "costs.empty_dropoff - is 3, but must not be positive.",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_states_outside_world() {
+        let world = build_world();
+        let state_paths = |taxi_pos, passenger_loc, destination_loc| {
+            let mut errors = Vec::new();
+            validate_state(
+                &world,
+                "probes[0]",
+                taxi_pos,
+                passenger_loc,
+                destination_loc,
+                &mut errors,
+            );
+            errors
+                .into_iter()
+                .map(|error| error.path)
+                .collect::<Vec<String>>()
+        };
+
+        assert!(state_paths((1, 1), Some("R"), "G").is_empty());
+        assert!(state_paths((0, 0), None, "R").is_empty());
+
+        for &taxi_pos in &[(-1, 0), (0, -1), (2, 0), (0, 3)] {
+            assert_eq!(state_paths(taxi_pos, None, "R"), vec!["probes[0].taxi_pos"]);
+        }
+
+        assert_eq!(
+            state_paths((0, 0), Some("B"), "Y"),
+            vec!["probes[0].passenger_loc", "probes[0].destination_loc"]
+        );
+    }
+
+    #[test]
+    fn including_file_is_merged_over_includes() {
+        let dir = TempDir::new("include");
+        dir.write("common/world.cfg", WORLD);
+        dir.write(
+            "common/solver.cfg",
+            &format!("include = [\"world.cfg\"]\nsessions = 5\n{}", Q_LEARNER),
+        );
+        dir.write("fast.cfg", "sessions = 7\n[q_learner]\ngamma = 0.6\n");
+        let filename = dir.write(
+            "main.cfg",
+            "include = [\"common/solver.cfg\", \"fast.cfg\"]\n\
+             [q_learner]\n\
+             alpha = 0.2\n",
+        );
+
+        let config = Configuration::from_file(&filename).unwrap();
+
+        // Includes are found beside the file naming them, later ones override
+        // earlier ones, and the including file overrides them all.
+        assert_eq!(config.max_trials, 10);
+        assert_eq!(config.sessions, 7);
+        assert_eq!(config.q_learner.len(), 1);
+        assert!(
+            matches!(config.q_learner[0].alpha, ScheduleConfig::Constant(alpha) if alpha == 0.2)
+        );
+        assert_eq!(config.q_learner[0].gamma, 0.6);
+        assert!(
+            matches!(config.q_learner[0].epsilon, ScheduleConfig::Constant(epsilon) if epsilon == 0.1)
+        );
+        assert!(!config.source.contains_key("include"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_include_cycle() {
+        let dir = TempDir::new("cycle");
+        dir.write("sub/a.cfg", "include = [\"../b.cfg\"]\n");
+        dir.write("b.cfg", "include = [\"sub/a.cfg\"]\n");
+        let filename = dir.write("main.cfg", "include = [\"sub/a.cfg\"]\n");
+
+        match Configuration::from_file(&filename) {
+            Err(Error::IncludeCycle { ref filename }) => assert!(filename.ends_with("a.cfg")),
+            other => panic!("Expected an include cycle, got {:?}", other.err()),
+        }
+
+        let filename = dir.write("self.cfg", "include = [\"self.cfg\"]\n");
+        assert!(matches!(
+            Configuration::from_file(&filename),
+            Err(Error::IncludeCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_include() {
+        let dir = TempDir::new("invalid");
+        dir.write("world.cfg", WORLD);
+
+        let filename = dir.write("single.cfg", "include = \"world.cfg\"\n");
+        assert!(matches!(
+            Configuration::from_file(&filename),
+            Err(Error::InvalidInclude { .. })
+        ));
+
+        let filename = dir.write("number.cfg", "include = [\"world.cfg\", 3]\n");
+        assert!(matches!(
+            Configuration::from_file(&filename),
+            Err(Error::InvalidInclude { .. })
+        ));
+
+        let filename = dir.write("missing.cfg", "include = [\"nowhere.cfg\"]\n");
+        match Configuration::from_file(&filename) {
+            Err(Error::OpenFailure { ref filename }) => assert!(filename.ends_with("nowhere.cfg")),
+            other => panic!("Expected an open failure, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn merges_tables_key_by_key() {
+        let mut base = parse_table(
+            r#"
+            sessions = 1
+            probes = [1, 2, 3]
+            [q_learner]
+            alpha = 0.1
+            gamma = 0.3
+            [q_learner.epsilon]
+            kind = "linear"
+            start = 1.0
+            "#,
+        );
+        let overlay = parse_table(
+            r#"
+            probes = [4]
+            [q_learner]
+            gamma = 0.6
+            [q_learner.epsilon]
+            start = 0.5
+            end = 0.1
+            "#,
+        );
+
+        merge_tables(&mut base, overlay);
+
+        let expected = parse_table(
+            r#"
+            sessions = 1
+            probes = [4]
+            [q_learner]
+            alpha = 0.1
+            gamma = 0.6
+            [q_learner.epsilon]
+            kind = "linear"
+            start = 0.5
+            end = 0.1
+            "#,
+        );
+        assert_eq!(base, expected);
+
+        // A table replaces any other value, and the other way round.
+        let mut base = parse_table("world = \"text\"\n[costs]\nmovement = -1\n");
+        merge_tables(&mut base, parse_table("costs = 3\n[world]\nwidth = 2\n"));
+        assert_eq!(base, parse_table("costs = 3\n[world]\nwidth = 2\n"));
+    }
+
+    #[test]
+    fn reads_one_or_many_profiles() {
+        let config = build_config(&format!("{}{}", WORLD, Q_LEARNER));
+        assert_eq!(config.q_learner.len(), 1);
+        assert!(config.q_learner[0].name.is_none());
+        assert!(config.r_max.is_empty());
+
+        let config = build_config(&format!(
+            "{}{}",
+            WORLD,
+            r#"
+            [[q_learner]]
+            name = "slow"
+            alpha = 0.1
+            gamma = 0.3
+            epsilon = 0.1
+            report = false
+
+            [[q_learner]]
+            name = "fast"
+            alpha = 0.5
+            gamma = 0.3
+            epsilon = 0.1
+            report = false
+            "#
+        ));
+        let names: Vec<Option<&str>> = config.q_learner.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec![Some("slow"), Some("fast")]);
+        assert!(config.validate().is_ok());
+
+        let source = format!("q_learner = 3\n{}", WORLD);
+        assert!(Configuration::from_table("test.cfg", parse_table(&source)).is_err());
+    }
+
+    #[test]
+    fn profiles_need_distinct_names() {
+        let profile = |name: &str, gamma: f64| {
+            format!(
+                "[[q_learner]]\n{}alpha = 0.1\ngamma = {}\nepsilon = 0.1\nreport = false\n",
+                name, gamma
+            )
+        };
+
+        // A single profile needs no name, and is reported without an index.
+        assert!(build_errors(&profile("", 0.3)).is_empty());
+        assert_eq!(build_errors(&profile("", 2.0)), vec!["q_learner.gamma"]);
+
+        let errors = build_errors(&format!("{}{}", profile("", 0.3), profile("", 2.0)));
+        assert_eq!(
+            errors,
+            vec![
+                "q_learner[0].name",
+                "q_learner[1].name",
+                "q_learner[1].gamma"
+            ]
+        );
+
+        let errors = build_errors(&format!(
+            "{}{}{}",
+            profile("name = \"a\"\n", 0.3),
+            profile("name = \"b\"\n", 0.3),
+            profile("name = \"a\"\n", 0.3)
+        ));
+        assert_eq!(errors, vec!["q_learner[2].name"]);
+    }
+
+    #[test]
+    fn random_sweep_needs_samples() {
+        let source = format!(
+            "{}{}",
+            WORLD,
+            r#"
+            [q_learner]
+            alpha = 0.1
+            gamma = 0.3
+            epsilon = 0.1
+            report = false
+
+            [sweep]
+            solver = "QLearner"
+            search = "random"
+            [sweep.parameters]
+            alpha = { values = [0.1, 0.2] }
+            "#
+        );
+
+        assert_eq!(error_paths(&build_config(&source)), vec!["sweep.samples"]);
+
+        let source = source.replace("search = \"random\"", "search = \"random\"\nsamples = 3");
+        assert!(error_paths(&build_config(&source)).is_empty());
+    }
 }
@@ -0,0 +1,147 @@
+use std::time;
+
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+use rayon::prelude::*;
+
+use taxi::distribution::MeasureDistribution;
+use taxi::qlearner::QLearner;
+use taxi::rlearner::RLearner;
+use taxi::runner::{run_continuing_session, ContinuingRunner};
+use taxi::schedule::Schedule;
+use taxi::world::World;
+
+use crate::configuration::{Configuration, ContinuingConfig, ReportConfig};
+use crate::{generate_session_world, AppError};
+
+#[derive(Default)]
+struct ContinuingStats {
+    requests_per_thousand: MeasureDistribution,
+    average_reward: MeasureDistribution,
+    duration: time::Duration,
+}
+
+pub fn run_continuing(
+    continuing: &ContinuingConfig,
+    config: &Configuration,
+    world: &World,
+    root_seed: u128,
+) -> Result<(), AppError> {
+    let mut results = Vec::new();
+
+    for rlearner_config in &continuing.r_learner {
+        let stats = measure_continuing(
+            |world| {
+                RLearner::new(
+                    world,
+                    Schedule::from(rlearner_config.alpha),
+                    Schedule::from(rlearner_config.beta),
+                    Schedule::from(rlearner_config.epsilon),
+                )
+            },
+            continuing,
+            config,
+            world,
+            root_seed,
+        )?;
+
+        results.push((rlearner_config.label(), stats));
+    }
+
+    for qlearner_config in &config.q_learner {
+        let stats = measure_continuing(
+            |world| {
+                QLearner::new(
+                    world,
+                    Schedule::from(qlearner_config.alpha),
+                    qlearner_config.gamma,
+                    Schedule::from(qlearner_config.epsilon),
+                )
+            },
+            continuing,
+            config,
+            world,
+            root_seed,
+        )?;
+
+        results.push((qlearner_config.label(), stats));
+    }
+
+    println!();
+    for (label, stats) in results {
+        let (requests, requests_stddev) = stats.requests_per_thousand.get_distribution();
+        let (reward, _) = stats.average_reward.get_distribution();
+
+        let elapsed_time =
+            stats.duration.as_secs() as f64 + f64::from(stats.duration.subsec_nanos()) * 1e-9;
+
+        println!(
+            "{} - served {:.1} requests per 1000 steps with stddev of {:.2} and average \
+             reward {:.3} over {} sessions of {} steps in {:.3} secs. Using seed [{}, {}]",
+            label,
+            requests,
+            requests_stddev,
+            reward,
+            stats.requests_per_thousand.get_count() as usize,
+            continuing.steps,
+            elapsed_time,
+            root_seed.rotate_right(64) as i64,
+            root_seed as i64,
+        );
+    }
+
+    Ok(())
+}
+
+fn measure_continuing<B, Rnr>(
+    builder: B,
+    continuing: &ContinuingConfig,
+    config: &Configuration,
+    world: &World,
+    root_seed: u128,
+) -> Result<ContinuingStats, AppError>
+where
+    B: Fn(&World) -> Rnr + Sync,
+    Rnr: ContinuingRunner,
+{
+    let mut seed_generator = Pcg64Mcg::new(root_seed);
+    let seeds: Vec<u128> = (0..config.sessions).map(|_| seed_generator.gen()).collect();
+
+    seeds
+        .par_iter()
+        .map(|seed| -> Result<ContinuingStats, AppError> {
+            let mut rng = Pcg64Mcg::new(*seed);
+
+            let session_world = generate_session_world(config, world.costs, &mut rng)?;
+            let world = match session_world {
+                Some((ref world, _)) => world,
+                None => world,
+            };
+
+            let start_time = time::Instant::now();
+
+            let mut solver = builder(world);
+            let result = run_continuing_session(world, continuing.steps, &mut solver, &mut rng)
+                .map_err(AppError::Runner)?;
+
+            let mut stats = ContinuingStats::default();
+            stats
+                .requests_per_thousand
+                .add_value(result.requests_per_thousand_steps());
+            stats.average_reward.add_value(result.average_reward());
+            stats.duration = start_time.elapsed();
+
+            Ok(stats)
+        })
+        .try_reduce(ContinuingStats::default, |mut stats_a, stats_b| {
+            stats_a
+                .requests_per_thousand
+                .add_distribution(&stats_b.requests_per_thousand);
+            stats_a
+                .average_reward
+                .add_distribution(&stats_b.average_reward);
+            stats_a.duration += stats_b.duration;
+            Ok(stats_a)
+        })
+}
@@ -1,35 +1,82 @@
 mod condition;
 mod condition_learner;
 mod effect;
+pub mod explanation;
 mod hypothesis;
 mod mcelearner;
 mod multirewardlearner;
+mod outcome_learner;
 mod reward;
-mod term;
 
+use std::collections::HashSet;
 use std::f64;
+use std::fs;
 
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
 
 use crate::actions::Actions;
+use crate::oomdp;
+use crate::oomdp::taxi::TaxiObjects;
+use crate::oomdp::ObjectState;
 use crate::state::{State, StateIterator};
 use crate::state_indexer::StateIndexer;
 use crate::world::World;
 
 use self::condition::Condition;
+pub use self::explanation::Explanation;
+use self::explanation::UnobservedConditions;
 use self::mcelearner::MCELearner;
 use self::multirewardlearner::MultiRewardLearner;
+use self::outcome_learner::MultiOutcomeLearner;
 use self::reward::Rewards;
 
-use crate::runner::{Attempt, Runner};
+use crate::runner::{measure_model_accuracy, Attempt, ModelAccuracy, Runner, TransferRunner};
+
+// How the effects of actions are learnt.  max_effects is the most effects
+// of each effect type for an attribute, the k of DOORmax.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EffectModel {
+    // Each action always has the same effect under a condition.
+    Deterministic {
+        max_effects: usize,
+    },
+
+    // The chance of each effect under a condition is counted, and known
+    // once the condition has been seen known_count times.
+    Stochastic {
+        max_effects: usize,
+        known_count: f64,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum EffectLearner {
+    Deterministic(MCELearner),
+    Stochastic(MultiOutcomeLearner),
+}
+
+// The reward and the chance of each next state index for an action.
+type Transition = (f64, Vec<(f64, usize)>);
+
+// A state as the learners see it.
+#[derive(Debug, Clone)]
+struct StateObjects {
+    objects: ObjectState,
+    condition: Condition,
+    object_conditions: Vec<Condition>,
+}
 
 #[derive(Debug, Clone)]
 pub struct DoorMax {
     state_indexer: StateIndexer,
     rmax: f64,
 
-    mcelearner: MCELearner,
+    taxi_objects: TaxiObjects,
+    state_objects: Vec<StateObjects>,
+    effectlearner: EffectLearner,
+    // The conditions each action has been tried under, indexed by action.
+    tried_conditions: Vec<HashSet<Condition>>,
 
     use_reward_learner: bool,
     rewardlearner: MultiRewardLearner,
@@ -41,11 +88,16 @@ pub struct DoorMax {
 
     gamma: f64,
     error_delta: f64,
+
+    // Where to write the explanation as JSON when reporting.
+    explanation_file: Option<String>,
 }
 
 impl DoorMax {
     pub fn new(
         world: &World,
+        mut taxi_objects: TaxiObjects,
+        effect_model: EffectModel,
         gamma: f64,
         use_reward_learner: bool,
         known_reward_count: f64,
@@ -56,17 +108,34 @@ impl DoorMax {
         let value_table = vec![0.0; num_states];
 
         let rewards = Rewards::new(world, known_reward_count);
-        let rmax = if gamma < 1.0 {
-            world.max_reward() / (1.0 - gamma)
-        } else {
-            world.max_reward()
+
+        taxi_objects.add_terrain(world);
+
+        let domain = taxi_objects.domain();
+        let effectlearner = match effect_model {
+            EffectModel::Deterministic { max_effects } => {
+                EffectLearner::Deterministic(MCELearner::new(domain, max_effects))
+            }
+            EffectModel::Stochastic {
+                max_effects,
+                known_count,
+            } => EffectLearner::Stochastic(MultiOutcomeLearner::new(
+                domain,
+                max_effects,
+                known_count,
+            )),
         };
 
+        let state_objects = build_state_objects(world, &state_indexer, &taxi_objects);
+
         DoorMax {
             state_indexer,
-            rmax,
+            rmax: world.max_value(gamma),
 
-            mcelearner: MCELearner::new(),
+            taxi_objects,
+            state_objects,
+            effectlearner,
+            tried_conditions: vec![HashSet::new(); Actions::NUM_ELEMENTS],
 
             use_reward_learner,
             rewardlearner: MultiRewardLearner::new(error_delta),
@@ -78,6 +147,60 @@ impl DoorMax {
 
             gamma,
             error_delta,
+
+            explanation_file: None,
+        }
+    }
+
+    pub fn set_explanation_file(&mut self, filename: Option<String>) {
+        self.explanation_file = filename;
+    }
+
+    // The rules learnt so far, and the conditions of the world's states
+    // each action has not been tried under.
+    pub fn explain(&self) -> Explanation {
+        let (domain, attributes) = match self.effectlearner {
+            EffectLearner::Deterministic(ref mcelearner) => {
+                (mcelearner.domain(), mcelearner.explain())
+            }
+            EffectLearner::Stochastic(ref outcomelearner) => {
+                (outcomelearner.domain(), outcomelearner.explain())
+            }
+        };
+
+        let mut conditions: Vec<&Condition> = Vec::new();
+        for seen in &self.state_objects {
+            if !conditions.contains(&&seen.condition) {
+                conditions.push(&seen.condition);
+            }
+        }
+        conditions.sort_by(|first, second| first.words().cmp(second.words()));
+
+        let unobserved = self
+            .tried_conditions
+            .iter()
+            .enumerate()
+            .map(|(action_index, tried)| {
+                let action = Actions::from_index(action_index).unwrap();
+                let untried = conditions
+                    .iter()
+                    .cloned()
+                    .filter(|condition| !tried.contains(*condition));
+
+                UnobservedConditions::new(domain, action, untried)
+            })
+            .collect();
+
+        let rewards = if self.use_reward_learner {
+            self.rewardlearner.explain(domain)
+        } else {
+            Vec::new()
+        };
+
+        Explanation {
+            attributes,
+            rewards,
+            unobserved,
         }
     }
 
@@ -89,63 +212,134 @@ impl DoorMax {
         new_state: &State,
         reward: f64,
     ) {
-        self.mcelearner
-            .apply_experience(world, state, action, new_state);
+        let state_index = self.state_indexer.get_index(world, state).unwrap();
+        let new_state_index = self.state_indexer.get_index(world, new_state).unwrap();
+
+        let seen = &self.state_objects[state_index];
+        let new_objects = &self.state_objects[new_state_index].objects;
+        match self.effectlearner {
+            EffectLearner::Deterministic(ref mut mcelearner) => {
+                mcelearner.apply_experience(world, &seen.objects, action, new_objects)
+            }
+            EffectLearner::Stochastic(ref mut outcomelearner) => outcomelearner.apply_experience(
+                &seen.objects,
+                &seen.object_conditions,
+                action,
+                new_objects,
+            ),
+        }
+
+        if !self.tried_conditions[action.to_index()].contains(&seen.condition) {
+            self.tried_conditions[action.to_index()].insert(seen.condition.clone());
+        }
 
         if self.use_reward_learner {
             self.rewardlearner
-                .apply_experience(world, state, action, reward);
+                .apply_experience(&seen.condition, action, reward);
         } else {
             self.rewards.apply_experience(reward, world, state, action);
         }
     }
 
+    fn state_objects(&self, world: &World, state: &State) -> &StateObjects {
+        let state_index = self.state_indexer.get_index(world, state).unwrap();
+
+        &self.state_objects[state_index]
+    }
+
+    // The chance of each next state, None if they are not known.
+    fn predict_states(
+        &self,
+        world: &World,
+        state: &State,
+        action: Actions,
+    ) -> Result<Option<Vec<(f64, State)>>, oomdp::Error> {
+        let seen = self.state_objects(world, state);
+
+        let predicted = match self.effectlearner {
+            EffectLearner::Deterministic(ref mcelearner) => mcelearner
+                .predict(&seen.objects, &seen.object_conditions, action)
+                .map(|next_objects| vec![(1.0, next_objects)]),
+            EffectLearner::Stochastic(ref outcomelearner) => {
+                outcomelearner.predict(&seen.objects, &seen.object_conditions, action)
+            }
+        };
+
+        match predicted {
+            Some(predicted) => {
+                let mut next_states = Vec::with_capacity(predicted.len());
+                for (probability, next_objects) in predicted {
+                    next_states.push((
+                        probability,
+                        self.taxi_objects.to_state(world, &next_objects)?,
+                    ));
+                }
+
+                Ok(Some(next_states))
+            }
+            None => Ok(None),
+        }
+    }
+
     fn measure_reward(&self, world: &World, state: &State, action: Actions) -> Option<f64> {
         if self.use_reward_learner {
-            self.rewardlearner.predict(world, state, action)
+            self.rewardlearner
+                .predict(&self.state_objects(world, state).condition, action)
         } else {
             self.rewards.get_reward(world, state, action)
         }
     }
 
-    fn measure_value(
+    // The reward and the chance of each next state index predicted for each
+    // action by what has been learnt, None where either is not known yet.
+    fn predict_transitions(
         &self,
         world: &World,
         state: &State,
-        action: Actions,
-    ) -> Result<f64, effect::Error> {
-        let state_index = self.state_indexer.get_index(world, state).unwrap();
+    ) -> Result<[Option<Transition>; Actions::NUM_ELEMENTS], oomdp::Error> {
+        let mut transitions: [Option<Transition>; Actions::NUM_ELEMENTS] = Default::default();
+        for (action_index, transition) in transitions.iter_mut().enumerate() {
+            let action = Actions::from_index(action_index).unwrap();
 
-        if let Some(reward) = self.measure_reward(world, state, action) {
-            if let Some(next_state) = self.mcelearner.predict(world, state, action)? {
-                let mut action_value = reward;
+            if let Some(reward) = self.measure_reward(world, state, action) {
+                if let Some(next_states) = self.predict_states(world, state, action)? {
+                    let next_state_indices = next_states
+                        .iter()
+                        .map(|(probability, next_state)| {
+                            let next_state_index =
+                                self.state_indexer.get_index(world, next_state).unwrap();
 
-                let next_state_index = self.state_indexer.get_index(world, &next_state).unwrap();
-                action_value += self.gamma * self.value_table[next_state_index];
+                            (*probability, next_state_index)
+                        })
+                        .collect();
 
-                return Ok(action_value);
+                    *transition = Some((reward, next_state_indices));
+                }
             }
         }
 
-        // Either reward or state is not known, so return max value to encourage
-        // exploration.  Assume we will stay at our current state, and add in our
-        // value.
-        Ok(self.rmax + self.gamma * self.value_table[state_index])
+        Ok(transitions)
     }
 
-    fn measure_best_value(&self, world: &World, state: &State) -> Result<f64, effect::Error> {
-        let mut best_value = -f64::MAX;
-
-        for action_index in 0..Actions::NUM_ELEMENTS {
-            let action = Actions::from_index(action_index).unwrap();
-            let action_value = self.measure_value(world, state, action)?;
-
-            if action_value > best_value {
-                best_value = action_value;
+    fn transition_value(&self, state_index: usize, transition: &Option<Transition>) -> f64 {
+        match transition {
+            // The expected value over the next states.
+            Some((reward, next_state_indices)) => {
+                let next_value: f64 = next_state_indices
+                    .iter()
+                    .map(|&(probability, next_state_index)| {
+                        probability * self.value_table[next_state_index]
+                    })
+                    .sum();
+
+                reward + self.gamma * next_value
             }
-        }
 
-        Ok(best_value)
+            // Either reward or state is not known, so return max value to encourage
+            // exploration.  Assume we will stay at our current state, and add in our
+            // value.
+            None => self.rmax + self.gamma * self.value_table[state_index],
+        }
     }
 
     fn select_best_action<R: Rng>(
@@ -153,14 +347,17 @@ impl DoorMax {
         world: &World,
         state: &State,
         rng: &mut R,
-    ) -> Result<Option<Actions>, effect::Error> {
+    ) -> Result<Option<Actions>, oomdp::Error> {
+        let state_index = self.state_indexer.get_index(world, state).unwrap();
+        let transitions = self.predict_transitions(world, state)?;
+
         let mut best_value = -f64::MAX;
         let mut best_action = None;
         let mut num_found = 0;
 
-        for action_index in 0..Actions::NUM_ELEMENTS {
+        for (action_index, transition) in transitions.iter().enumerate() {
             let action = Actions::from_index(action_index).unwrap();
-            let action_value = self.measure_value(world, state, action)?;
+            let action_value = self.transition_value(state_index, transition);
 
             if approx_eq!(f64, action_value, best_value, ulps = 2) {
                 num_found += 1;
@@ -178,16 +375,25 @@ impl DoorMax {
         Ok(best_action)
     }
 
-    fn rebuild_value_table(&mut self, world: &World) -> Result<(), effect::Error> {
+    fn rebuild_value_table(&mut self, world: &World) -> Result<(), oomdp::Error> {
+        // The learners do not change while the values settle, so each
+        // state's transitions only need predicting once.
+        let mut transitions = Vec::with_capacity(self.state_indexer.num_states());
+        for state in StateIterator::new(world) {
+            let state_index = self.state_indexer.get_index(world, &state).unwrap();
+            transitions.push((state_index, self.predict_transitions(world, &state)?));
+        }
+
         for _ in 0..10_000 {
             let mut error = 0.0;
 
-            for state in StateIterator::new(world) {
-                let state_index = self.state_indexer.get_index(world, &state).unwrap();
-
+            for &(state_index, ref state_transitions) in &transitions {
                 let old_value = self.value_table[state_index];
 
-                let new_value = self.measure_best_value(world, &state)?;
+                let new_value = state_transitions
+                    .iter()
+                    .map(|transition| self.transition_value(state_index, transition))
+                    .fold(-f64::MAX, f64::max);
 
                 self.value_table[state_index] = new_value;
 
@@ -211,7 +417,7 @@ impl DoorMax {
         mut state: State,
         max_steps: usize,
         rng: &mut R,
-    ) -> Result<Option<usize>, effect::Error> {
+    ) -> Result<Option<usize>, oomdp::Error> {
         for step in 0..max_steps {
             if state.at_destination() {
                 return Ok(Some(step));
@@ -242,7 +448,7 @@ impl DoorMax {
         mut state: State,
         max_steps: usize,
         rng: &mut R,
-    ) -> Result<Attempt, effect::Error> {
+    ) -> Result<Attempt, oomdp::Error> {
         let mut attempt = Attempt::new(state, max_steps);
 
         for _ in 0..max_steps {
@@ -272,7 +478,7 @@ impl DoorMax {
         mut state: State,
         max_steps: usize,
         rng: &mut R,
-    ) -> Result<bool, effect::Error> {
+    ) -> Result<bool, oomdp::Error> {
         for _ in 0..max_steps {
             if state.at_destination() {
                 return Ok(true);
@@ -290,6 +496,24 @@ impl DoorMax {
     }
 }
 
+fn build_state_objects(
+    world: &World,
+    state_indexer: &StateIndexer,
+    taxi_objects: &TaxiObjects,
+) -> Vec<StateObjects> {
+    let domain = taxi_objects.domain();
+
+    taxi_objects
+        .all_objects(world, state_indexer)
+        .into_iter()
+        .map(|objects| StateObjects {
+            condition: Condition::new(domain, world, &objects, None),
+            object_conditions: condition::object_conditions(domain, world, &objects),
+            objects,
+        })
+        .collect()
+}
+
 impl Runner for DoorMax {
     fn learn<R: Rng>(
         &mut self,
@@ -315,6 +539,24 @@ impl Runner for DoorMax {
         self.solves(world, state, max_steps, rng).unwrap()
     }
 
+    fn evaluate_model(&self, world: &World) -> Option<ModelAccuracy> {
+        Some(measure_model_accuracy(
+            world,
+            |state, action, next_state| {
+                let next_states = self.predict_states(world, state, action).unwrap()?;
+
+                Some(
+                    next_states
+                        .iter()
+                        .filter(|(_, predicted)| predicted == next_state)
+                        .map(|(chance, _)| chance)
+                        .sum(),
+                )
+            },
+            |state, action| self.measure_reward(world, state, action),
+        ))
+    }
+
     fn report_training_result(&self, world: &World, _steps: Option<usize>) {
         let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
 
@@ -327,18 +569,25 @@ impl Runner for DoorMax {
                 {
                     println!("===================");
                     println!("{}", state.display(world));
-                    println!("Condition: {}", Condition::new(world, &state));
+                    println!("Condition: {}", self.state_objects[state_index].condition);
                     println!("Best action: {}", next_action);
 
-                    if let Some(next_state) =
-                        self.mcelearner.predict(world, &state, next_action).unwrap()
+                    if let Some(next_states) =
+                        self.predict_states(world, &state, next_action).unwrap()
                     {
-                        println!("{}", next_state.display(world));
+                        let several = next_states.len() > 1;
+                        for (probability, next_state) in next_states {
+                            if several {
+                                println!("Chance: {}", probability);
+                            }
+                            println!("{}", next_state.display(world));
+                        }
                     } else {
                         println!("Situation unknown.");
                     }
 
-                    for action_index in 0..Actions::NUM_ELEMENTS {
+                    let transitions = self.predict_transitions(world, &state).unwrap();
+                    for (action_index, transition) in transitions.iter().enumerate() {
                         let action = Actions::from_index(action_index).unwrap();
 
                         let reward =
@@ -348,7 +597,7 @@ impl Runner for DoorMax {
                                 self.rmax
                             };
 
-                        let action_value = self.measure_value(world, &state, action).unwrap();
+                        let action_value = self.transition_value(state_index, transition);
                         println!(
                             "{} - {} + {} = {}",
                             action,
@@ -361,11 +610,50 @@ impl Runner for DoorMax {
             }
         }
 
-        println!("MCELearner:");
-        println!("{}", self.mcelearner);
+        let explanation = self.explain();
+        println!("Learnt effects:");
+        println!("{}", explanation);
 
-        if self.use_reward_learner {
-            println!("{}", self.rewardlearner);
+        if let Some(ref filename) = self.explanation_file {
+            if let Err(error) = fs::write(filename, explanation.to_json()) {
+                println!("Failed to write the explanation to {}: {}", filename, error);
+            }
         }
     }
 }
+
+// The effects, the conditions tried and the rewards learnt by condition
+// carry over.  Rewards learnt by taxi position belong to the old world's
+// layout, so they are learnt again.
+impl TransferRunner for DoorMax {
+    fn transfer(&self, world: &World) -> Self {
+        let state_indexer = StateIndexer::new(world);
+        let num_states = state_indexer.num_states();
+
+        let mut doormax = DoorMax {
+            state_objects: build_state_objects(world, &state_indexer, &self.taxi_objects),
+            state_indexer,
+            rmax: world.max_value(self.gamma),
+
+            taxi_objects: self.taxi_objects.clone(),
+            effectlearner: self.effectlearner.clone(),
+            tried_conditions: self.tried_conditions.clone(),
+
+            use_reward_learner: self.use_reward_learner,
+            rewardlearner: self.rewardlearner.clone(),
+
+            rewards: Rewards::new(world, self.known_reward_count),
+            known_reward_count: self.known_reward_count,
+
+            value_table: vec![0.0; num_states],
+
+            gamma: self.gamma,
+            error_delta: self.error_delta,
+
+            explanation_file: self.explanation_file.clone(),
+        };
+
+        doormax.rebuild_value_table(world).unwrap();
+        doormax
+    }
+}
@@ -1,155 +1,139 @@
 use std::fmt;
 
-use enum_map::EnumMap;
-
-use crate::state::State;
+use crate::oomdp::{Domain, ObjectState};
 use crate::world::World;
 
-use crate::doormax::term::Term;
+pub const WORD_BITS: usize = 64;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Condition(pub EnumMap<Term, bool>);
+// The truth of each of a domain's terms, bit i of the words for term i.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Condition {
+    words: Vec<u64>,
+    num_terms: usize,
+}
 
 impl Condition {
-    pub fn new(world: &World, state: &State) -> Self {
-        let taxi_pos = state.get_taxi();
-        let walls = world.get_wall(taxi_pos);
+    pub fn new(domain: &Domain, world: &World, state: &ObjectState, focus: Option<usize>) -> Self {
+        let num_terms = domain.terms().len();
+        let mut words = vec![0; num_words(num_terms)];
 
-        let on_passenger = if let Some(passenger_id) = state.get_passenger() {
-            if let Some(passenger_pos) = world.get_fixed_position(passenger_id) {
-                passenger_pos == taxi_pos
-            } else {
-                false
+        for (index, term) in domain.terms().iter().enumerate() {
+            if domain.evaluate(term, world, state, focus) {
+                words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
             }
-        } else {
-            false
-        };
+        }
 
-        let on_destination =
-            if let Some(destination_pos) = world.get_fixed_position(state.get_destination()) {
-                destination_pos == taxi_pos
-            } else {
-                false
-            };
+        Condition { words, num_terms }
+    }
 
-        let enum_map = enum_map! {
-            Term::TouchWallN => walls.north,
-            Term::TouchWallS => walls.south,
-            Term::TouchWallE => walls.east,
-            Term::TouchWallW => walls.west,
+    #[cfg(test)]
+    pub fn from_terms(terms: &[bool]) -> Self {
+        let mut words = vec![0; num_words(terms.len())];
 
-            Term::OnPassenger => on_passenger,
-            Term::OnDestination => on_destination,
-            Term::HasPassenger => state.get_passenger().is_none(),
-        };
+        for (index, _) in terms.iter().enumerate().filter(|&(_, &value)| value) {
+            words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+        }
 
-        Condition(enum_map)
+        Condition {
+            words,
+            num_terms: terms.len(),
+        }
     }
 
-    pub fn enumerate_all() -> Vec<Condition> {
-        let num_conditions = 2_usize.pow(7);
-        let mut result = Vec::with_capacity(num_conditions);
-
-        let mut accumulator = enum_map! {
-            Term::TouchWallN => false,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => false,
-        };
-
-        loop {
-            result.push(Condition(accumulator));
-
-            if !accumulator[Term::TouchWallN] {
-                accumulator[Term::TouchWallN] = true;
-            } else if !accumulator[Term::TouchWallS] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = true;
-            } else if !accumulator[Term::TouchWallE] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = false;
-                accumulator[Term::TouchWallE] = true;
-            } else if !accumulator[Term::TouchWallW] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = false;
-                accumulator[Term::TouchWallE] = false;
-                accumulator[Term::TouchWallW] = true;
-            } else if !accumulator[Term::OnPassenger] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = false;
-                accumulator[Term::TouchWallE] = false;
-                accumulator[Term::TouchWallW] = false;
-                accumulator[Term::OnPassenger] = true;
-            } else if !accumulator[Term::OnDestination] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = false;
-                accumulator[Term::TouchWallE] = false;
-                accumulator[Term::TouchWallW] = false;
-                accumulator[Term::OnPassenger] = false;
-                accumulator[Term::OnDestination] = true;
-            } else if !accumulator[Term::HasPassenger] {
-                accumulator[Term::TouchWallN] = false;
-                accumulator[Term::TouchWallS] = false;
-                accumulator[Term::TouchWallE] = false;
-                accumulator[Term::TouchWallW] = false;
-                accumulator[Term::OnPassenger] = false;
-                accumulator[Term::OnDestination] = false;
-                accumulator[Term::HasPassenger] = true;
-            } else {
-                break;
-            }
-        }
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn num_terms(&self) -> usize {
+        self.num_terms
+    }
 
-        result
+    pub fn get(&self, term: usize) -> bool {
+        self.words[term / WORD_BITS] & (1 << (term % WORD_BITS)) != 0
     }
 }
 
-impl fmt::Display for Condition {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let &Condition(ref cond_map) = self;
+pub fn num_words(num_terms: usize) -> usize {
+    num_terms.div_ceil(WORD_BITS)
+}
 
-        fn show_bool(b: bool) -> &'static str {
-            if b {
-                "1"
+// The condition each object is seen under.  Focusing on an object only
+// makes a difference when there are others of its class.
+pub fn object_conditions(domain: &Domain, world: &World, state: &ObjectState) -> Vec<Condition> {
+    let shared = Condition::new(domain, world, state, None);
+
+    state
+        .objects()
+        .iter()
+        .enumerate()
+        .map(|(index, object)| {
+            let alone = state
+                .objects()
+                .iter()
+                .filter(|other| other.class == object.class)
+                .count()
+                == 1;
+
+            if alone {
+                shared.clone()
             } else {
-                "0"
+                Condition::new(domain, world, state, Some(index))
             }
-        }
+        })
+        .collect()
+}
 
-        write!(
-            f,
-            "Condition({}{}{}{} {}{}{})",
-            show_bool(cond_map[Term::TouchWallN]),
-            show_bool(cond_map[Term::TouchWallS]),
-            show_bool(cond_map[Term::TouchWallE]),
-            show_bool(cond_map[Term::TouchWallW]),
-            show_bool(cond_map[Term::OnPassenger]),
-            show_bool(cond_map[Term::OnDestination]),
-            show_bool(cond_map[Term::HasPassenger]),
-        )
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Condition(")?;
+        for term in 0..self.num_terms {
+            write!(f, "{}", if self.get(term) { "1" } else { "0" })?;
+        }
+        write!(f, ")")
     }
 }
 
 #[cfg(test)]
 mod condition_test {
 
+    use std::collections::HashSet;
+
     use super::*;
+    use crate::doormax::hypothesis::Hypothesis;
+    use crate::oomdp::taxi::TaxiObjects;
+    use crate::state::State;
     use crate::world::Costs;
 
-    #[test]
-    fn enumerates_all() {
-        let all = Condition::enumerate_all();
+    fn taxi_condition(world: &World, state: &State) -> String {
+        let taxi_objects = TaxiObjects::new();
+        let objects = taxi_objects.to_objects(world, state);
 
-        assert_eq!(all.len(), 2_usize.pow(7));
+        Condition::new(taxi_objects.domain(), world, &objects, None).to_string()
+    }
 
-        for i in 0..all.len() {
-            for j in (i + 1)..all.len() {
-                assert!(all[i] != all[j]);
-            }
+    #[test]
+    fn compares_and_hashes_across_words() {
+        // Terms either side of the boundary between the first two words.
+        let differing: Vec<Condition> = [63, 64, 65]
+            .iter()
+            .map(|&set| {
+                let terms: Vec<bool> = (0..70).map(|term| term == set).collect();
+                Condition::from_terms(&terms)
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for condition in &differing {
+            assert!(seen.insert(condition.clone()));
         }
+        assert!(!seen.insert(differing[1].clone()));
+        assert_ne!(differing[0], differing[1]);
+        assert_ne!(differing[1], differing[2]);
+
+        let hypothesis = Hypothesis::from(differing[1].clone());
+        assert!(hypothesis.matches_cond(&differing[1]));
+        assert!(!hypothesis.matches_cond(&differing[0]));
+        assert!(!hypothesis.matches_cond(&differing[2]));
     }
 
     #[test]
@@ -170,56 +154,35 @@ mod condition_test {
         let costs = Costs::default();
         let w = World::build_from_str(source, costs).unwrap();
 
-        let state0 = State::build(&w, (1, 2), Some('R'), 'G').unwrap();
-        let cond0 = Condition::new(&w, &state0);
-        let expected_cond0 = Condition(enum_map! {
-            Term::TouchWallN => false,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => false,
-        });
-        assert_eq!(cond0, expected_cond0);
-
-        let state1 = State::build(&w, (1, 3), Some('R'), 'G').unwrap();
-        let cond1 = Condition::new(&w, &state1);
-        let expected_cond1 = Condition(enum_map! {
-            Term::TouchWallN => false,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => true,
-            Term::OnPassenger => true,
-            Term::OnDestination => false,
-            Term::HasPassenger => false,
-        });
-        assert_eq!(cond1, expected_cond1);
-
-        let state2 = State::build(&w, (1, 4), None, 'G').unwrap();
-        let cond2 = Condition::new(&w, &state2);
-        let expected_cond2 = Condition(enum_map! {
-            Term::TouchWallN => false,
-            Term::TouchWallS => true,
-            Term::TouchWallE => false,
-            Term::TouchWallW => true,
-            Term::OnPassenger => false,
-            Term::OnDestination => true,
-            Term::HasPassenger => true,
-        });
-        assert_eq!(cond2, expected_cond2);
-
-        let state3 = State::build(&w, (4, 0), None, 'G').unwrap();
-        let cond3 = Condition::new(&w, &state3);
-        let expected_cond3 = Condition(enum_map! {
-            Term::TouchWallN => true,
-            Term::TouchWallS => false,
-            Term::TouchWallE => true,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => true,
-        });
-        assert_eq!(cond3, expected_cond3);
+        // Terms are the N, S, E, W walls, on passenger, on destination and
+        // passenger in taxi.
+        let state0 = State::build_named(&w, (1, 2), Some("R"), "G").unwrap();
+        assert_eq!(taxi_condition(&w, &state0), "Condition(0000000)");
+
+        let state1 = State::build_named(&w, (1, 3), Some("R"), "G").unwrap();
+        assert_eq!(taxi_condition(&w, &state1), "Condition(0001100)");
+
+        // A carried passenger is not somewhere the taxi can be on.
+        let state2 = State::build_named(&w, (1, 4), None, "G").unwrap();
+        assert_eq!(taxi_condition(&w, &state2), "Condition(0101011)");
+
+        let state3 = State::build_named(&w, (4, 0), None, "G").unwrap();
+        assert_eq!(taxi_condition(&w, &state3), "Condition(1010001)");
+    }
+
+    #[test]
+    fn reads_bits() {
+        let condition = Condition::from_terms(&[true, false, true]);
+
+        assert!(condition.get(0));
+        assert!(!condition.get(1));
+        assert!(condition.get(2));
+        assert_eq!(condition.to_string(), "Condition(101)");
+
+        let mut terms = vec![false; 70];
+        terms[66] = true;
+        let long = Condition::from_terms(&terms);
+        assert_eq!(long.words(), &[0, 0b100][..]);
+        assert!(long.get(66));
     }
 }
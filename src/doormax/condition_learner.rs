@@ -3,38 +3,42 @@ use std::fmt;
 use crate::doormax::condition::Condition;
 use crate::doormax::hypothesis::Hypothesis;
 
+// Learns whether an effect happens under a condition.  Conditions are
+// never listed up front, as there are 2^n of them for n terms.  A condition
+// the best hypothesis does not match is only known to be false once it has
+// been seen without the effect, or belongs to another learner.
 #[derive(Debug, Clone)]
 pub struct ConditionLearner {
-    true_conditions: Vec<Condition>,
     false_conditions: Vec<Condition>,
+    // The hypotheses of other learners when this one was created.
+    excluded: Vec<Hypothesis>,
     best: Option<Hypothesis>,
 }
 
 impl ConditionLearner {
     pub fn new() -> Self {
-        let true_conditions = Condition::enumerate_all();
-        let false_conditions = Condition::enumerate_all();
-
         ConditionLearner {
-            true_conditions,
-            false_conditions,
+            false_conditions: Vec::new(),
+            excluded: Vec::new(),
 
             best: None,
         }
     }
 
     pub fn predict(&self, condition: &Condition) -> Option<bool> {
-        let has_failure = self.false_conditions.iter().any(|c| c == condition);
-
         if let Some(ref best_hypothesis) = self.best {
-            if best_hypothesis.matches_cond(condition) && !has_failure {
-                Some(true)
-            } else if has_failure && !self.true_conditions.iter().any(|c| c == condition) {
-                Some(false)
-            } else {
-                None
+            if best_hypothesis.matches_cond(condition) {
+                return Some(true);
             }
-        } else if has_failure && !self.true_conditions.iter().any(|c| c == condition) {
+        }
+
+        let known_false = self.false_conditions.contains(condition)
+            || self
+                .excluded
+                .iter()
+                .any(|hypothesis| hypothesis.matches_cond(condition));
+
+        if known_false {
             Some(false)
         } else {
             None
@@ -51,19 +55,20 @@ impl ConditionLearner {
                     *best_hypothesis = best_hypothesis.combine_cond(condition);
                 }
             }
-
-            if let Some(ref best_hypothesis) = self.best {
-                self.false_conditions
-                    .retain(|c| !best_hypothesis.matches_cond(c));
-            }
-        } else {
-            self.true_conditions.retain(|c| c != condition);
+        } else if !self.false_conditions.contains(condition) {
+            self.false_conditions.push(condition.clone());
         }
     }
 
+    // The most specific conjunction true under every condition seen with
+    // the effect.
+    pub fn hypothesis(&self) -> Option<&Hypothesis> {
+        self.best.as_ref()
+    }
+
     pub fn remove_overlap(&mut self, other: &ConditionLearner) {
         if let Some(ref other_best) = other.best {
-            self.true_conditions.retain(|c| !other_best.matches_cond(c));
+            self.excluded.push(other_best.clone());
         }
     }
 
@@ -98,14 +103,22 @@ impl fmt::Display for ConditionLearner {
 #[cfg(test)]
 mod condition_learner_test {
     use super::*;
+    use crate::oomdp::taxi::TaxiObjects;
     use crate::state::State;
     use crate::world::{Costs, World};
 
+    fn taxi_condition(world: &World, state: &State) -> Condition {
+        let taxi_objects = TaxiObjects::new();
+        let objects = taxi_objects.to_objects(world, state);
+
+        Condition::new(taxi_objects.domain(), world, &objects, None)
+    }
+
     #[test]
     fn learns_north() {
         let source = "\
                       ┌───────┐\n\
-                      │. . . .│\n\
+                      │. . . R│\n\
                       │       │\n\
                       │. . . G│\n\
                       └───────┘\n\
@@ -116,23 +129,23 @@ mod condition_learner_test {
 
         let mut ce = ConditionLearner::new();
 
-        let state_0_0 = State::build(&w, (0, 0), None, 'G').unwrap();
-        let cond_0_0 = Condition::new(&w, &state_0_0);
+        let state_0_0 = State::build_named(&w, (0, 0), None, "G").unwrap();
+        let cond_0_0 = taxi_condition(&w, &state_0_0);
 
-        let state_0_1 = State::build(&w, (0, 1), None, 'G').unwrap();
-        let cond_0_1 = Condition::new(&w, &state_0_1);
+        let state_0_1 = State::build_named(&w, (0, 1), None, "G").unwrap();
+        let cond_0_1 = taxi_condition(&w, &state_0_1);
 
-        let state_1_0 = State::build(&w, (1, 0), None, 'G').unwrap();
-        let cond_1_0 = Condition::new(&w, &state_1_0);
+        let state_1_0 = State::build_named(&w, (1, 0), None, "G").unwrap();
+        let cond_1_0 = taxi_condition(&w, &state_1_0);
 
-        let state_1_1 = State::build(&w, (1, 1), None, 'G').unwrap();
-        let cond_1_1 = Condition::new(&w, &state_1_1);
+        let state_1_1 = State::build_named(&w, (1, 1), None, "G").unwrap();
+        let cond_1_1 = taxi_condition(&w, &state_1_1);
 
-        let state_2_0 = State::build(&w, (2, 0), None, 'G').unwrap();
-        let cond_2_0 = Condition::new(&w, &state_2_0);
+        let state_2_0 = State::build_named(&w, (2, 0), None, "G").unwrap();
+        let cond_2_0 = taxi_condition(&w, &state_2_0);
 
-        let state_2_1 = State::build(&w, (2, 1), None, 'G').unwrap();
-        let cond_2_1 = Condition::new(&w, &state_2_1);
+        let state_2_1 = State::build_named(&w, (2, 1), None, "G").unwrap();
+        let cond_2_1 = taxi_condition(&w, &state_2_1);
 
         assert_eq!(ce.predict(&cond_0_1), None);
         ce.apply_experience(&cond_0_1, true);
@@ -162,7 +175,7 @@ mod condition_learner_test {
     fn learns_nw_corner() {
         let source = "\
                       ┌───────┐\n\
-                      │. . . .│\n\
+                      │. . . R│\n\
                       │       │\n\
                       │. . . G│\n\
                       └───────┘\n\
@@ -175,8 +188,8 @@ mod condition_learner_test {
 
         for x in 0..4 {
             for y in 0..2 {
-                let state = State::build(&w, (x, y), None, 'G').unwrap();
-                let cond = Condition::new(&w, &state);
+                let state = State::build_named(&w, (x, y), None, "G").unwrap();
+                let cond = taxi_condition(&w, &state);
 
                 let is_nw_corner = x == 0 && y == 0;
 
@@ -184,12 +197,12 @@ mod condition_learner_test {
             }
         }
 
-        let state_0_0 = State::build(&w, (0, 0), None, 'G').unwrap();
-        let cond_0_0 = Condition::new(&w, &state_0_0);
+        let state_0_0 = State::build_named(&w, (0, 0), None, "G").unwrap();
+        let cond_0_0 = taxi_condition(&w, &state_0_0);
         assert_eq!(ce.predict(&cond_0_0), Some(true));
 
-        let state_0_1 = State::build(&w, (0, 1), None, 'G').unwrap();
-        let cond_0_1 = Condition::new(&w, &state_0_1);
+        let state_0_1 = State::build_named(&w, (0, 1), None, "G").unwrap();
+        let cond_0_1 = taxi_condition(&w, &state_0_1);
         assert_eq!(ce.predict(&cond_0_1), Some(false));
     }
 
@@ -199,7 +212,7 @@ mod condition_learner_test {
         // it should, but I don't see how it could.
         let source = "\
                       ┌───────┐\n\
-                      │. . . .│\n\
+                      │. . . R│\n\
                       │       │\n\
                       │. . . G│\n\
                       └───────┘\n\
@@ -212,8 +225,8 @@ mod condition_learner_test {
 
         for x in 0..4 {
             for y in 0..2 {
-                let state = State::build(&w, (x, y), None, 'G').unwrap();
-                let cond = Condition::new(&w, &state);
+                let state = State::build_named(&w, (x, y), None, "G").unwrap();
+                let cond = taxi_condition(&w, &state);
 
                 let is_nw_corner = x == 0 && y == 0;
 
@@ -237,13 +250,13 @@ mod condition_learner_test {
         //     None => println!("best: None"),
         // }
 
-        let state_0_0 = State::build(&w, (0, 0), None, 'G').unwrap();
-        let cond_0_0 = Condition::new(&w, &state_0_0);
+        let state_0_0 = State::build_named(&w, (0, 0), None, "G").unwrap();
+        let cond_0_0 = taxi_condition(&w, &state_0_0);
         println!("testing {}", cond_0_0);
         //assert_eq!(ce.predict(&cond_0_0), Some(false));
 
-        let state_0_1 = State::build(&w, (0, 1), None, 'G').unwrap();
-        let cond_0_1 = Condition::new(&w, &state_0_1);
+        let state_0_1 = State::build_named(&w, (0, 1), None, "G").unwrap();
+        let cond_0_1 = taxi_condition(&w, &state_0_1);
         println!("testing {}", cond_0_1);
         //assert_eq!(ce.predict(&cond_0_1), Some(true));
     }
@@ -1,166 +1,166 @@
-use std;
 use std::fmt;
 
-use crate::state;
-use crate::state::State;
-use crate::world::World;
+use crate::oomdp::Value;
 
-pub enum Error {
-    InvalidState(state::Error),
+// The kinds of change DOORmax can learn for an attribute.  Several types
+// may explain the same change, 2 -> 4 is Add(2), Multiply(2) and Set(4),
+// until later changes tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectType {
+    Add,
+    Multiply,
+    Set,
 }
 
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Error::InvalidState(ref state_error) => {
-                write!(f, "Failed to create state from effect: {:?}", state_error)
+impl EffectType {
+    pub const ALL: [EffectType; 3] = [EffectType::Add, EffectType::Multiply, EffectType::Set];
+
+    // The effect of this type that turns old_value into new_value, None if
+    // the attribute did not change or this type cannot explain the change.
+    pub fn generate_effect(self, old_value: Value, new_value: Value) -> Option<Effect> {
+        if old_value == new_value {
+            return None;
+        }
+
+        match (self, old_value, new_value) {
+            (EffectType::Add, Value::Int(old), Value::Int(new)) => Some(Effect::Add(new - old)),
+            (EffectType::Multiply, Value::Int(old), Value::Int(new)) => {
+                if old != 0 && new % old == 0 {
+                    Some(Effect::Multiply(new / old))
+                } else {
+                    None
+                }
             }
+            (EffectType::Set, _, _) => Some(Effect::Set(new_value)),
+            _ => None,
         }
     }
 }
 
-impl From<state::Error> for Error {
-    fn from(error: state::Error) -> Self {
-        Error::InvalidState(error)
+impl fmt::Display for EffectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EffectType::Add => write!(f, "Add"),
+            EffectType::Multiply => write!(f, "Multiply"),
+            EffectType::Set => write!(f, "Set"),
+        }
     }
 }
 
-pub trait Effect
-where
-    Self: std::marker::Sized,
-{
-    fn generate_effects(old_state: &State, new_state: &State) -> Option<Self>;
-
-    fn apply(&self, world: &World, state: &State) -> Result<State, Error>;
-}
-
+// How an action changes one attribute of an object.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ChangeTaxiX {
-    delta: i32,
-}
-
-impl ChangeTaxiX {
-    pub fn new(delta: i32) -> Self {
-        ChangeTaxiX { delta }
-    }
-}
-
-impl Effect for ChangeTaxiX {
-    fn generate_effects(old_state: &State, new_state: &State) -> Option<Self> {
-        let old_x = old_state.get_taxi().x;
-        let new_x = new_state.get_taxi().x;
-
-        if old_x != new_x {
-            let delta = new_x - old_x;
-            Some(ChangeTaxiX::new(delta))
-        } else {
-            None
+pub enum Effect {
+    Add(i32),
+    Multiply(i32),
+    Set(Value),
+}
+
+impl Effect {
+    pub fn apply(&self, value: Value) -> Value {
+        match (*self, value) {
+            (Effect::Add(delta), Value::Int(old)) => Value::Int(old + delta),
+            (Effect::Multiply(factor), Value::Int(old)) => Value::Int(old * factor),
+            (Effect::Set(new_value), _) => new_value,
+            // Arithmetic effects are only learnt for Int attributes.
+            (Effect::Add(_), _) | (Effect::Multiply(_), _) => value,
         }
     }
-
-    fn apply(&self, world: &World, state: &State) -> Result<State, Error> {
-        let new_taxi_x = state.get_taxi().x + self.delta;
-
-        Ok(State::build(
-            world,
-            (new_taxi_x, state.get_taxi().y),
-            state.get_passenger(),
-            state.get_destination(),
-        )?)
-    }
 }
 
-impl fmt::Display for ChangeTaxiX {
+impl fmt::Display for Effect {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ChangeTaxiX({})", self.delta)
+        match *self {
+            Effect::Add(delta) => write!(f, "Add({})", delta),
+            Effect::Multiply(factor) => write!(f, "Multiply({})", factor),
+            Effect::Set(value) => write!(f, "Set({})", value),
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ChangeTaxiY {
-    delta: i32,
-}
+// Learns an observation under each effect type left, with learn given the
+// type's learnt effects and the effect seen, None where the value did not
+// change.  A type is dropped when it cannot describe the change or learn
+// rules it out.
+pub fn learn_effects<T>(
+    effect_types: &mut Vec<(EffectType, T)>,
+    old_value: Value,
+    new_value: Value,
+    mut learn: impl FnMut(&mut T, Option<Effect>) -> bool,
+) {
+    effect_types.retain_mut(|(effect_type, learnt)| {
+        let observed_effect = effect_type.generate_effect(old_value, new_value);
+
+        // The value changed in a way this type cannot describe.
+        if old_value != new_value && observed_effect.is_none() {
+            return false;
+        }
 
-impl ChangeTaxiY {
-    pub fn new(delta: i32) -> Self {
-        ChangeTaxiY { delta }
-    }
+        learn(learnt, observed_effect)
+    });
 }
 
-impl Effect for ChangeTaxiY {
-    fn generate_effects(old_state: &State, new_state: &State) -> Option<Self> {
-        let old_y = old_state.get_taxi().y;
-        let new_y = new_state.get_taxi().y;
+// The prediction every effect type left agrees on, None if any of them does
+// not know it or two disagree.  It is None as well when every type has been
+// ruled out, so nothing can be predicted.
+pub fn agreed_prediction<T, R>(
+    effect_types: &[(EffectType, T)],
+    mut predict: impl FnMut(&T) -> Option<R>,
+    same: impl Fn(&R, &R) -> bool,
+) -> Option<R> {
+    let mut full_result = None;
+
+    for (_, learnt) in effect_types {
+        let result = predict(learnt)?;
 
-        if old_y != new_y {
-            let delta = new_y - old_y;
-            Some(ChangeTaxiY::new(delta))
+        if let Some(ref full_result) = full_result {
+            if !same(full_result, &result) {
+                return None;
+            }
         } else {
-            None
+            full_result = Some(result);
         }
     }
 
-    fn apply(&self, world: &World, state: &State) -> Result<State, Error> {
-        let new_taxi_y = state.get_taxi().y + self.delta;
-
-        Ok(State::build(
-            world,
-            (state.get_taxi().x, new_taxi_y),
-            state.get_passenger(),
-            state.get_destination(),
-        )?)
-    }
+    full_result
 }
 
-impl fmt::Display for ChangeTaxiY {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ChangeTaxiY({})", self.delta)
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ChangePassenger {
-    on_destination: bool,
-}
+#[cfg(test)]
+mod effect_test {
+    use super::*;
 
-impl ChangePassenger {
-    pub fn new(on_destination: bool) -> Self {
-        ChangePassenger { on_destination }
-    }
-}
+    #[test]
+    fn generates_and_applies() {
+        let doubled =
+            |effect_type: EffectType| effect_type.generate_effect(Value::Int(2), Value::Int(4));
+        assert_eq!(doubled(EffectType::Add), Some(Effect::Add(2)));
+        assert_eq!(doubled(EffectType::Multiply), Some(Effect::Multiply(2)));
+        assert_eq!(doubled(EffectType::Set), Some(Effect::Set(Value::Int(4))));
 
-impl Effect for ChangePassenger {
-    fn generate_effects(old_state: &State, new_state: &State) -> Option<Self> {
-        let old_passenger = old_state.get_passenger();
-        let new_passenger = new_state.get_passenger();
+        assert_eq!(Effect::Add(2).apply(Value::Int(5)), Value::Int(7));
+        assert_eq!(Effect::Multiply(2).apply(Value::Int(5)), Value::Int(10));
+        assert_eq!(
+            Effect::Set(Value::Int(4)).apply(Value::Int(5)),
+            Value::Int(4)
+        );
 
-        if old_passenger != new_passenger {
-            // Assume that a non-None value for new_passenger
-            // means that the passenger was placed on the destination.
-            Some(ChangePassenger::new(new_passenger.is_some()))
-        } else {
-            None
-        }
-    }
+        // Only some types can explain a change.
+        let from_zero = EffectType::Multiply.generate_effect(Value::Int(0), Value::Int(3));
+        assert_eq!(from_zero, None);
+        let odd = EffectType::Multiply.generate_effect(Value::Int(2), Value::Int(3));
+        assert_eq!(odd, None);
 
-    fn apply(&self, world: &World, state: &State) -> Result<State, Error> {
-        let passenger = if self.on_destination {
-            Some(state.get_destination())
-        } else {
-            None
+        let picked_up = |effect_type: EffectType| {
+            effect_type.generate_effect(Value::Bool(false), Value::Bool(true))
         };
-
-        Ok(State::build(
-            world,
-            (state.get_taxi().x, state.get_taxi().y),
-            passenger,
-            state.get_destination(),
-        )?)
-    }
-}
-
-impl fmt::Display for ChangePassenger {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ChangePassenger({:#?})", self.on_destination)
+        assert_eq!(picked_up(EffectType::Add), None);
+        assert_eq!(
+            picked_up(EffectType::Set),
+            Some(Effect::Set(Value::Bool(true)))
+        );
+
+        assert_eq!(
+            EffectType::Add.generate_effect(Value::Int(4), Value::Int(4)),
+            None
+        );
     }
 }
@@ -0,0 +1,333 @@
+use std::fmt;
+
+use crate::doormax::condition::Condition;
+use crate::doormax::effect::{Effect, EffectType};
+
+use crate::actions::Actions;
+use crate::oomdp::Domain;
+
+// What DOORmax has learnt, in the names of the domain's classes and terms.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub attributes: Vec<AttributeRules>,
+    pub rewards: Vec<RewardRule>,
+    pub unobserved: Vec<UnobservedConditions>,
+}
+
+impl Explanation {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Explanations can always be written as JSON")
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for attribute in &self.attributes {
+            if attribute.effect_types.is_empty() {
+                writeln!(
+                    f,
+                    "{} - {}.{} cannot be learnt, no effect type explains it.",
+                    attribute.action, attribute.class, attribute.attribute
+                )?;
+            }
+
+            for rule in &attribute.rules {
+                if rule.unknown {
+                    writeln!(f, "{} (unknown)", rule.text)?;
+                } else {
+                    writeln!(f, "{}", rule.text)?;
+                }
+            }
+        }
+
+        for reward in &self.rewards {
+            writeln!(f, "{}", reward.text)?;
+        }
+
+        for unobserved in &self.unobserved {
+            for condition in &unobserved.conditions {
+                let terms = if condition.is_empty() {
+                    String::from("no terms")
+                } else {
+                    condition.join(", ")
+                };
+                writeln!(f, "{} never tried with {}.", unobserved.action, terms)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The rules learnt for how an action changes one attribute of a class.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AttributeRules {
+    pub action: String,
+    pub class: String,
+    pub attribute: String,
+    // The effect types that still explain everything seen, none if the
+    // attribute cannot be learnt.
+    pub effect_types: Vec<String>,
+    pub rules: Vec<Rule>,
+}
+
+impl AttributeRules {
+    pub fn new(
+        domain: &Domain,
+        action: Actions,
+        class_index: usize,
+        attribute_index: usize,
+        effect_types: &[EffectType],
+    ) -> Self {
+        let class = &domain.classes()[class_index];
+
+        AttributeRules {
+            action: format!("{:?}", action),
+            class: class.name.clone(),
+            attribute: class.attributes[attribute_index].0.clone(),
+            effect_types: effect_types
+                .iter()
+                .map(|effect_type| effect_type.to_string())
+                .collect(),
+            rules: Vec::new(),
+        }
+    }
+
+    // requirements are the terms the rule needs, with the value each must
+    // have.  A rule is unknown while other effect types explain the same
+    // changes, or while it has not been seen often enough.
+    pub fn add_rule(
+        &mut self,
+        domain: &Domain,
+        effect_type: EffectType,
+        outcomes: &[(Option<Effect>, f64)],
+        requirements: impl Iterator<Item = (usize, bool)>,
+        unknown: bool,
+    ) {
+        let (when, unless) = name_requirements(domain, requirements);
+
+        let target = format!("{}.{}", self.class, self.attribute);
+        let several = outcomes.len() > 1;
+        let changes: Vec<String> = outcomes
+            .iter()
+            .map(|&(effect, chance)| {
+                let change = match effect {
+                    Some(Effect::Add(delta)) => format!("moves {} by {:+}", target, delta),
+                    Some(Effect::Multiply(factor)) => {
+                        format!("multiplies {} by {}", target, factor)
+                    }
+                    Some(Effect::Set(value)) => format!("sets {} to {}", target, value),
+                    None => format!("leaves {}", target),
+                };
+
+                if several {
+                    format!("{} ({:.0}%)", change, chance * 100.0)
+                } else {
+                    change
+                }
+            })
+            .collect();
+
+        let text = format!(
+            "{} {}{}",
+            self.action,
+            changes.join(" or "),
+            describe_requirements(&when, &unless)
+        );
+
+        self.rules.push(Rule {
+            effect_type: effect_type.to_string(),
+            outcomes: outcomes
+                .iter()
+                .map(|&(effect, chance)| Outcome {
+                    effect: effect
+                        .map_or_else(|| String::from("None"), |effect| effect.to_string()),
+                    chance,
+                })
+                .collect(),
+            when,
+            unless,
+            unknown,
+            text,
+        });
+    }
+}
+
+// The rule applies when all the when terms are true and none of the unless
+// terms are.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub effect_type: String,
+    pub outcomes: Vec<Outcome>,
+    pub when: Vec<String>,
+    pub unless: Vec<String>,
+    pub unknown: bool,
+    pub text: String,
+}
+
+// The reward an action gives under the conditions of the when and unless
+// terms, as with Rule.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RewardRule {
+    pub action: String,
+    pub reward: f64,
+    pub when: Vec<String>,
+    pub unless: Vec<String>,
+    pub text: String,
+}
+
+impl RewardRule {
+    pub fn new(
+        domain: &Domain,
+        action: Actions,
+        reward: f64,
+        requirements: impl Iterator<Item = (usize, bool)>,
+    ) -> Self {
+        let action = format!("{:?}", action);
+        let (when, unless) = name_requirements(domain, requirements);
+        let text = format!(
+            "{} rewards {}{}",
+            action,
+            reward,
+            describe_requirements(&when, &unless)
+        );
+
+        RewardRule {
+            action,
+            reward,
+            when,
+            unless,
+            text,
+        }
+    }
+}
+
+// The names of the terms that must be true, and those that must be false.
+fn name_requirements(
+    domain: &Domain,
+    requirements: impl Iterator<Item = (usize, bool)>,
+) -> (Vec<String>, Vec<String>) {
+    let mut when = Vec::new();
+    let mut unless = Vec::new();
+    for (term, value) in requirements {
+        let name = domain.describe_term(&domain.terms()[term]);
+        if value {
+            when.push(name);
+        } else {
+            unless.push(name);
+        }
+    }
+
+    (when, unless)
+}
+
+// When a rule applies, to follow what it does.
+fn describe_requirements(when: &[String], unless: &[String]) -> String {
+    let mut text = String::new();
+    if when.is_empty() && unless.is_empty() {
+        text.push_str(" always");
+    }
+    if !when.is_empty() {
+        text.push_str(&format!(" when {}", when.join(" and ")));
+    }
+    if !unless.is_empty() {
+        text.push_str(&format!(" unless {}", unless.join(" or ")));
+    }
+
+    text
+}
+
+// An effect, None where the attribute does not change, with its chance.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub effect: String,
+    pub chance: f64,
+}
+
+// The conditions of the world's states an action has never been tried
+// under, each given by its true terms.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UnobservedConditions {
+    pub action: String,
+    pub conditions: Vec<Vec<String>>,
+}
+
+impl UnobservedConditions {
+    pub fn new<'a>(
+        domain: &Domain,
+        action: Actions,
+        conditions: impl Iterator<Item = &'a Condition>,
+    ) -> Self {
+        UnobservedConditions {
+            action: format!("{:?}", action),
+            conditions: conditions
+                .map(|condition| {
+                    domain
+                        .terms()
+                        .iter()
+                        .enumerate()
+                        .filter(|&(index, _)| condition.get(index))
+                        .map(|(_, term)| domain.describe_term(term))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod explanation_test {
+    use super::*;
+    use crate::doormax::mcelearner::MCELearner;
+    use crate::oomdp::taxi::TaxiObjects;
+    use crate::state::State;
+    use crate::world::{Costs, World};
+
+    #[test]
+    fn names_learnt_rules() {
+        let source = "\
+                      ┌─────────┐\n\
+                      │R . . . .│\n\
+                      │         │\n\
+                      │. . . . G│\n\
+                      └─────────┘\n\
+                      ";
+        let w = World::build_from_str(source, Costs::default()).unwrap();
+        let taxi_objects = TaxiObjects::new();
+        let mut learner = MCELearner::new(taxi_objects.domain(), 2);
+
+        for &x in &[1, 2] {
+            let state = State::build_named(&w, (x, 1), Some("R"), "G").unwrap();
+            let (_, new_state) = state.apply_action(&w, Actions::East);
+
+            let objects = taxi_objects.to_objects(&w, &state);
+            let new_objects = taxi_objects.to_objects(&w, &new_state);
+            learner.apply_experience(&w, &objects, Actions::East, &new_objects);
+        }
+
+        let explained = learner.explain();
+        let taxi_x = explained
+            .iter()
+            .find(|rules| rules.action == "East" && rules.class == "taxi" && rules.attribute == "x")
+            .unwrap();
+
+        // Only an increment explains both moves.
+        assert_eq!(taxi_x.effect_types, vec!["Add"]);
+        assert_eq!(taxi_x.rules.len(), 1);
+
+        let rule = &taxi_x.rules[0];
+        assert!(!rule.unknown);
+        assert_eq!(rule.when, vec!["touch_s(taxi, wall)"]);
+        assert!(rule.unless.contains(&String::from("touch_e(taxi, wall)")));
+        assert!(rule
+            .text
+            .starts_with("East moves taxi.x by +1 when touch_s(taxi, wall) unless "));
+
+        let explanation = Explanation {
+            attributes: explained,
+            rewards: Vec::new(),
+            unobserved: Vec::new(),
+        };
+        assert!(explanation.to_json().contains("\"effect\": \"Add(1)\""));
+    }
+}
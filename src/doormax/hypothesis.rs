@@ -1,137 +1,102 @@
 use std::fmt;
 
-use enum_map::EnumMap;
-
-use crate::doormax::condition::Condition;
-use crate::doormax::term::Term;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Require {
-    None,
-    True,
-    False,
-}
-
-impl Default for Require {
-    fn default() -> Self {
-        Require::None
-    }
-}
-
-impl From<bool> for Require {
-    fn from(val: bool) -> Self {
-        if val {
-            Require::True
-        } else {
-            Require::False
-        }
-    }
+use crate::doormax::condition::{num_words, Condition, WORD_BITS};
+
+// A conjunction over a condition's terms.  Terms with their bit set in
+// required must have the value of the same bit in values, the rest may be
+// anything.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hypothesis {
+    required: Vec<u64>,
+    values: Vec<u64>,
+    num_terms: usize,
 }
 
-#[derive(Clone, Debug)]
-pub struct Hypothesis(EnumMap<Term, Require>);
-
 impl Hypothesis {
-    // pub fn combine(&self, &Hypothesis(ref other_map): &Hypothesis) -> Hypothesis {
-    //     let &Hypothesis(ref self_map) = self;
-
-    //     let mut result_map = *self_map;
-
-    //     for (key, &value) in self_map {
-    //         if value != Require::None {
-    //             result_map[key] = if other_map[key] == value {
-    //                 value
-    //             } else {
-    //                 Require::None
-    //             };
-    //         }
-    //     }
-
-    //     Hypothesis(result_map)
-    // }
-
-    pub fn combine_cond(&self, &Condition(ref cond_map): &Condition) -> Hypothesis {
-        let &Hypothesis(ref self_map) = self;
-
-        let mut result_map = *self_map;
-
-        for (key, &value) in self_map {
-            match value {
-                Require::True => {
-                    if !cond_map[key] {
-                        result_map[key] = Require::None;
-                    }
-                }
-
-                Require::False => {
-                    if cond_map[key] {
-                        result_map[key] = Require::None;
-                    }
-                }
-
-                Require::None => {}
-            }
+    pub fn combine_cond(&self, condition: &Condition) -> Hypothesis {
+        let required: Vec<u64> = self
+            .required
+            .iter()
+            .zip(&self.values)
+            .zip(condition.words())
+            .map(|((&required, &values), &condition)| required & !(values ^ condition))
+            .collect();
+        let values = self
+            .values
+            .iter()
+            .zip(&required)
+            .map(|(&values, &required)| values & required)
+            .collect();
+
+        Hypothesis {
+            required,
+            values,
+            num_terms: self.num_terms,
         }
-
-        Hypothesis(result_map)
     }
 
-    pub fn matches(&self, &Hypothesis(ref other): &Hypothesis) -> bool {
-        let &Hypothesis(ref self_map) = self;
+    pub fn matches(&self, other: &Hypothesis) -> bool {
+        self.required
+            .iter()
+            .zip(&self.values)
+            .zip(other.required.iter().zip(&other.values))
+            .all(|((&required, &values), (&other_required, &other_values))| {
+                required & !other_required == 0 && (values ^ other_values) & required == 0
+            })
+    }
 
-        self_map
+    pub fn matches_cond(&self, condition: &Condition) -> bool {
+        self.required
             .iter()
-            .all(|(key, &value)| value == Require::None || other[key] == value)
+            .zip(&self.values)
+            .zip(condition.words())
+            .all(|((&required, &values), &condition)| (values ^ condition) & required == 0)
     }
 
-    pub fn matches_cond(&self, &Condition(ref cond_map): &Condition) -> bool {
-        let &Hypothesis(ref self_map) = self;
+    // The terms the hypothesis requires, with the value each must have.
+    pub fn requirements(&self) -> impl Iterator<Item = (usize, bool)> + '_ {
+        (0..self.num_terms)
+            .filter(move |&term| Hypothesis::bit(&self.required, term))
+            .map(move |term| (term, Hypothesis::bit(&self.values, term)))
+    }
 
-        self_map.iter().all(|(key, &value)| match value {
-            Require::None => true,
-            Require::True => cond_map[key],
-            Require::False => !cond_map[key],
-        })
+    fn bit(words: &[u64], term: usize) -> bool {
+        words[term / WORD_BITS] & (1 << (term % WORD_BITS)) != 0
     }
 }
 
 impl fmt::Display for Hypothesis {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let &Hypothesis(ref hyp_map) = self;
-
-        fn show_require(r: Require) -> &'static str {
-            match r {
-                Require::None => "*",
-                Require::True => "1",
-                Require::False => "0",
-            }
+        write!(f, "Condition(")?;
+        for term in 0..self.num_terms {
+            let shown = if !Hypothesis::bit(&self.required, term) {
+                "*"
+            } else if Hypothesis::bit(&self.values, term) {
+                "1"
+            } else {
+                "0"
+            };
+            write!(f, "{}", shown)?;
         }
-
-        write!(
-            f,
-            "Condition({}{}{}{} {}{}{})",
-            show_require(hyp_map[Term::TouchWallN]),
-            show_require(hyp_map[Term::TouchWallS]),
-            show_require(hyp_map[Term::TouchWallE]),
-            show_require(hyp_map[Term::TouchWallW]),
-            show_require(hyp_map[Term::OnPassenger]),
-            show_require(hyp_map[Term::OnDestination]),
-            show_require(hyp_map[Term::HasPassenger]),
-        )
+        write!(f, ")")
     }
 }
 
 impl From<Condition> for Hypothesis {
-    fn from(Condition(cond_map): Condition) -> Self {
-        Hypothesis(enum_map! {
-            Term::TouchWallN => Require::from(cond_map[Term::TouchWallN]),
-            Term::TouchWallS => Require::from(cond_map[Term::TouchWallS]),
-            Term::TouchWallE => Require::from(cond_map[Term::TouchWallE]),
-            Term::TouchWallW => Require::from(cond_map[Term::TouchWallW]),
-            Term::OnPassenger => Require::from(cond_map[Term::OnPassenger]),
-            Term::OnDestination => Require::from(cond_map[Term::OnDestination]),
-            Term::HasPassenger => Require::from(cond_map[Term::HasPassenger]),
-        })
+    fn from(condition: Condition) -> Self {
+        let num_terms = condition.num_terms();
+
+        let mut required = vec![!0; num_words(num_terms)];
+        let spare_bits = num_terms % WORD_BITS;
+        if let (Some(last), true) = (required.last_mut(), spare_bits != 0) {
+            *last = (1 << spare_bits) - 1;
+        }
+
+        Hypothesis {
+            required,
+            values: condition.words().to_vec(),
+            num_terms,
+        }
     }
 }
 
@@ -140,58 +105,45 @@ mod test_hypthothesis {
 
     use super::*;
 
+    // Reads a hypothesis written like its Display, term 0 first.
+    fn hypothesis(terms: &str) -> Hypothesis {
+        let mut required = vec![0; num_words(terms.len())];
+        let mut values = vec![0; num_words(terms.len())];
+
+        for (term, c) in terms.chars().enumerate() {
+            let bit = 1 << (term % WORD_BITS);
+            match c {
+                '1' => {
+                    required[term / WORD_BITS] |= bit;
+                    values[term / WORD_BITS] |= bit;
+                }
+                '0' => required[term / WORD_BITS] |= bit,
+                _ => {}
+            }
+        }
+
+        Hypothesis {
+            required,
+            values,
+            num_terms: terms.len(),
+        }
+    }
+
+    fn condition(terms: &str) -> Condition {
+        let terms: Vec<bool> = terms.chars().map(|c| c == '1').collect();
+
+        Condition::from_terms(&terms)
+    }
+
     #[allow(clippy::cognitive_complexity)]
     #[test]
     fn hypothesis_matches() {
-        let hyp_a = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::True,
-            Term::TouchWallE => Require::True,
-            Term::TouchWallW => Require::True,
-            Term::OnPassenger => Require::True,
-            Term::OnDestination => Require::True,
-            Term::HasPassenger => Require::True,
-        });
-
-        let hyp_b = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::False,
-            Term::TouchWallS => Require::False,
-            Term::TouchWallE => Require::False,
-            Term::TouchWallW => Require::False,
-            Term::OnPassenger => Require::False,
-            Term::OnDestination => Require::False,
-            Term::HasPassenger => Require::False,
-        });
-
-        let hyp_c = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::None,
-            Term::TouchWallE => Require::None,
-            Term::TouchWallW => Require::None,
-            Term::OnPassenger => Require::None,
-            Term::OnDestination => Require::None,
-            Term::HasPassenger => Require::None,
-        });
-
-        let hyp_d = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::False,
-            Term::TouchWallE => Require::None,
-            Term::TouchWallW => Require::None,
-            Term::OnPassenger => Require::None,
-            Term::OnDestination => Require::None,
-            Term::HasPassenger => Require::None,
-        });
-
-        let hyp_e = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::False,
-            Term::TouchWallE => Require::True,
-            Term::TouchWallW => Require::True,
-            Term::OnPassenger => Require::True,
-            Term::OnDestination => Require::True,
-            Term::HasPassenger => Require::True,
-        });
+        let hyp_a = hypothesis("11111111");
+        let hyp_b = hypothesis("00000000");
+        let hyp_c = hypothesis("1*******");
+        let hyp_d = hypothesis("10******");
+        let hyp_e = hypothesis("10111111");
+
         assert!(hyp_a.matches(&hyp_a));
         assert!(!hyp_a.matches(&hyp_b));
         assert!(!hyp_a.matches(&hyp_c));
@@ -225,65 +177,13 @@ mod test_hypthothesis {
 
     #[test]
     fn hypothesis_matches_cond() {
-        let hyp_a = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::None,
-            Term::TouchWallE => Require::None,
-            Term::TouchWallW => Require::None,
-            Term::OnPassenger => Require::None,
-            Term::OnDestination => Require::None,
-            Term::HasPassenger => Require::None,
-        });
-
-        let hyp_b = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::False,
-            Term::TouchWallE => Require::None,
-            Term::TouchWallW => Require::None,
-            Term::OnPassenger => Require::None,
-            Term::OnDestination => Require::None,
-            Term::HasPassenger => Require::None,
-        });
-
-        let hyp_c = Hypothesis(enum_map! {
-            Term::TouchWallN => Require::True,
-            Term::TouchWallS => Require::False,
-            Term::TouchWallE => Require::True,
-            Term::TouchWallW => Require::True,
-            Term::OnPassenger => Require::True,
-            Term::OnDestination => Require::True,
-            Term::HasPassenger => Require::True,
-        });
-
-        let cond_a = Condition(enum_map! {
-            Term::TouchWallN => true,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => false,
-        });
-
-        let cond_b = Condition(enum_map! {
-            Term::TouchWallN => true,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => true,
-        });
-
-        let cond_c = Condition(enum_map! {
-            Term::TouchWallN => false,
-            Term::TouchWallS => false,
-            Term::TouchWallE => false,
-            Term::TouchWallW => false,
-            Term::OnPassenger => false,
-            Term::OnDestination => false,
-            Term::HasPassenger => false,
-        });
+        let hyp_a = hypothesis("1*******");
+        let hyp_b = hypothesis("10******");
+        let hyp_c = hypothesis("10111111");
+
+        let cond_a = condition("10000000");
+        let cond_b = condition("10000010");
+        let cond_c = condition("00000000");
 
         assert!(hyp_a.matches_cond(&cond_a));
         assert!(hyp_a.matches_cond(&cond_b));
@@ -297,4 +197,33 @@ mod test_hypthothesis {
         assert!(!hyp_c.matches_cond(&cond_b));
         assert!(!hyp_c.matches_cond(&cond_c));
     }
+
+    #[test]
+    fn combines_and_shows() {
+        let hyp = Hypothesis::from(condition("1010"));
+        assert_eq!(hyp.to_string(), "Condition(1010)");
+
+        let combined = hyp.combine_cond(&condition("1001"));
+        assert_eq!(combined, hypothesis("10**"));
+        assert_eq!(combined.to_string(), "Condition(10**)");
+    }
+
+    #[test]
+    fn spans_several_words() {
+        let first = condition(&"10".repeat(40));
+        let second = condition(&format!("{}{}", "10".repeat(39), "01"));
+
+        let hyp = Hypothesis::from(first.clone());
+        assert!(hyp.matches_cond(&first));
+        assert!(!hyp.matches_cond(&second));
+
+        let combined = hyp.combine_cond(&second);
+        assert_eq!(
+            combined,
+            hypothesis(&format!("{}{}", "10".repeat(39), "**"))
+        );
+        assert!(combined.matches_cond(&second));
+        assert!(combined.matches(&hyp));
+        assert!(!hyp.matches(&combined));
+    }
 }
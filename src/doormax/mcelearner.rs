@@ -1,250 +1,342 @@
 use std::fmt;
 
-use crate::doormax::condition::Condition;
+use crate::doormax::condition::{self, Condition};
 use crate::doormax::condition_learner::ConditionLearner;
-use crate::doormax::effect;
-use crate::doormax::effect::{ChangePassenger, ChangeTaxiX, ChangeTaxiY, Effect};
+use crate::doormax::effect::{self, Effect, EffectType};
+use crate::doormax::explanation::AttributeRules;
 
 use crate::actions::Actions;
-use crate::state::State;
+use crate::oomdp::{Domain, ObjectState, Value};
 use crate::world::World;
 
+// Learns the effects of one action on one attribute of a class.  Every
+// effect type starts out as a candidate, and is ruled out once it cannot
+// explain an observation, needs more than max_effects effects, or its
+// conditions stop telling its effects apart.
 #[derive(Debug, Clone)]
-pub struct CELearner<E: Effect> {
-    condition_effects: Vec<(ConditionLearner, E)>,
+pub struct CELearner {
+    effect_types: Vec<(EffectType, Vec<(ConditionLearner, Effect)>)>,
+    max_effects: usize,
 }
 
-impl<E: Effect> CELearner<E> {
-    pub fn new() -> Self {
+impl CELearner {
+    pub fn new(max_effects: usize) -> Self {
         CELearner {
-            condition_effects: Vec::new(),
+            effect_types: EffectType::ALL
+                .iter()
+                .map(|&effect_type| (effect_type, Vec::new()))
+                .collect(),
+            max_effects,
         }
     }
 
-    pub fn predict(
-        &self,
-        world: &World,
-        state: &State,
-        condition: &Condition,
-    ) -> Result<Option<State>, effect::Error> {
-        let mut full_result = None;
-
-        for &(ref condition_learner, ref learned_effect) in &self.condition_effects {
-            let matches_condition = condition_learner.predict(condition);
-            match matches_condition {
-                // A condition learner returns None if it does not have enough
-                // information to know if this condition applies.  So we
-                // return None to show that this needs to be explored.
-                None => {
-                    return Ok(None);
-                }
+    // The predicted value of the attribute, None if it is not known.  With
+    // every effect type ruled out nothing is predicted, which
+    // apply_experience starts over from unless max_effects is 0.
+    pub fn predict(&self, condition: &Condition, value: Value) -> Option<Value> {
+        effect::agreed_prediction(
+            &self.effect_types,
+            |condition_effects| predict_effects(condition_effects, condition, value),
+            |first, second| first == second,
+        )
+    }
 
-                // If the condition does not match this learner, ignore it.
-                Some(false) => (),
-
-                // There is a match.  If we supported multiple effect types per
-                // learner, there could be a conflict (ie. a set value and increment
-                // value effect could have been learned for the same condition).  This
-                // code does not really support multiple effect types per learner, but
-                // we go ahead and pretend it does just to show where the conflict checking
-                // needs to be.
-                Some(true) => {
-                    let result = learned_effect.apply(world, state)?;
-
-                    if let Some(full_result) = full_result {
-                        if full_result != result {
-                            // Conflicting result
-                            return Ok(None);
-                        }
-                    } else {
-                        full_result = Some(result);
-                    }
-                }
-            };
-        }
+    pub fn apply_experience(&mut self, condition: &Condition, old_value: Value, new_value: Value) {
+        self.rule_out(condition, old_value, new_value);
 
-        if full_result.is_some() {
-            Ok(full_result)
-        } else {
-            // full_result is None only if we know that
-            // this condition does not match any effects.
-            // Hence, full_result == None does _not_ mean
-            // unknown effect (which is what return Ok(None) means),
-            // but instead it means that there is no effect on the
-            // state.
-            Ok(Some(*state))
+        // Every type has been ruled out, as when the attribute changes in
+        // more ways than the terms tell apart.  Rather than never predicting
+        // it again, start over from this observation.
+        if self.effect_types.is_empty() {
+            *self = CELearner::new(self.max_effects);
+            self.rule_out(condition, old_value, new_value);
         }
     }
 
-    pub fn apply_experience(&mut self, condition: &Condition, old_state: &State, new_state: &State)
-    where
-        E: Clone + PartialEq,
-    {
-        let observed_effect = E::generate_effects(old_state, new_state);
+    // Learns the observation under each effect type left, dropping the
+    // types it rules out.
+    fn rule_out(&mut self, condition: &Condition, old_value: Value, new_value: Value) {
+        let max_effects = self.max_effects;
+
+        effect::learn_effects(
+            &mut self.effect_types,
+            old_value,
+            new_value,
+            |condition_effects, observed_effect| {
+                let mut consistent = true;
+                let mut found_entry = false;
+                for (condition_learner, learned_effect) in condition_effects.iter_mut() {
+                    let truth = observed_effect == Some(*learned_effect);
 
-        match observed_effect {
-            None => {
-                for &mut (ref mut condition_learner, _) in &mut self.condition_effects {
-                    condition_learner.apply_experience(condition, false);
+                    // A learner that was sure of the opposite shows that this
+                    // type's conditions cannot separate its effects.
+                    if let Some(predicted) = condition_learner.predict(condition) {
+                        consistent &= predicted == truth;
+                    }
+
+                    condition_learner.apply_experience(condition, truth);
+                    found_entry |= truth;
                 }
-            }
 
-            Some(observed_effect) => {
-                let mut found_entry = false;
-                for &mut (ref mut condition_learner, ref learned_effect) in
-                    &mut self.condition_effects
-                {
-                    if observed_effect == *learned_effect {
+                if let Some(observed_effect) = observed_effect {
+                    if !found_entry {
+                        let mut condition_learner = ConditionLearner::new();
                         condition_learner.apply_experience(condition, true);
-                        found_entry = true;
-                    } else {
-                        condition_learner.apply_experience(condition, false);
+
+                        for (other_condition_learner, _) in condition_effects.iter() {
+                            condition_learner.remove_overlap(other_condition_learner);
+                        }
+
+                        condition_effects.push((condition_learner, observed_effect));
                     }
                 }
 
-                if !found_entry {
-                    let mut condition_learner = ConditionLearner::new();
-                    condition_learner.apply_experience(condition, true);
+                consistent
+                    && condition_effects.len() <= max_effects
+                    && !has_overlap(condition_effects)
+            },
+        );
+    }
+}
 
-                    for &(ref other_condition_learner, _) in &self.condition_effects {
-                        condition_learner.remove_overlap(other_condition_learner);
-                    }
+// The value one effect type predicts, None if it is not known.
+fn predict_effects(
+    condition_effects: &[(ConditionLearner, Effect)],
+    condition: &Condition,
+    value: Value,
+) -> Option<Value> {
+    let mut full_result = None;
+
+    for (condition_learner, learned_effect) in condition_effects {
+        match condition_learner.predict(condition) {
+            // A condition learner returns None if it does not have enough
+            // information to know if this condition applies.  So we
+            // return None to show that this needs to be explored.
+            None => {
+                return None;
+            }
 
-                    self.condition_effects
-                        .push((condition_learner, observed_effect));
-                } else {
-                    // Check for overlapping conditions.
-                    if !self.condition_effects.is_empty() {
-                        let mut has_conflict = false;
-
-                        for i in 0..(self.condition_effects.len() - 1) {
-                            let &(ref condition_learner, _) = &self.condition_effects[i];
-
-                            for j in (i + 1)..self.condition_effects.len() {
-                                let &(ref other_condition_learner, _) = &self.condition_effects[j];
-
-                                // overlaps checks if either learner's truth hypothesis
-                                // is contained in the other's
-                                if condition_learner.overlaps(other_condition_learner) {
-                                    has_conflict = true;
-                                    break;
-                                }
-                            }
-                        }
+            // If the condition does not match this learner, ignore it.
+            Some(false) => (),
 
-                        if has_conflict {
-                            self.condition_effects = Vec::new();
-                        }
+            // Overlapping conditions rule a type out, but a condition can
+            // still match two effects that have not been seen together.
+            Some(true) => {
+                let result = learned_effect.apply(value);
+
+                if let Some(full_result) = full_result {
+                    if full_result != result {
+                        return None;
                     }
+                } else {
+                    full_result = Some(result);
                 }
             }
-        }
+        };
     }
+
+    // full_result is None only if we know that this condition does not
+    // match any effects, so the value does not change.
+    Some(full_result.unwrap_or(value))
 }
 
-impl<E: Effect> Default for CELearner<E> {
-    fn default() -> Self {
-        CELearner::new()
-    }
+// Whether either of two learners' truth hypotheses contains the other's.
+fn has_overlap(condition_effects: &[(ConditionLearner, Effect)]) -> bool {
+    condition_effects
+        .iter()
+        .enumerate()
+        .any(|(i, (condition_learner, _))| {
+            condition_effects[(i + 1)..]
+                .iter()
+                .any(|(other_condition_learner, _)| {
+                    condition_learner.overlaps(other_condition_learner)
+                })
+        })
 }
 
-impl<E: Effect + fmt::Display> fmt::Display for CELearner<E> {
+impl fmt::Display for CELearner {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "CL(")?;
-        let mut leader = " ";
-        for &(ref condition_learner, ref learned_effect) in &self.condition_effects {
-            write!(f, "{}{} => {}", leader, condition_learner, learned_effect)?;
-            leader = ", ";
+        for (effect_type, condition_effects) in &self.effect_types {
+            write!(f, " {}[", effect_type)?;
+            let mut leader = " ";
+            for (condition_learner, learned_effect) in condition_effects {
+                write!(f, "{}{} => {}", leader, condition_learner, learned_effect)?;
+                leader = ", ";
+            }
+            write!(f, " ]")?;
         }
         write!(f, " )")
     }
 }
 
+// One CELearner for each action and attribute of each class.  Objects of a
+// class share their learners, each under a condition focused on itself.
 #[derive(Debug, Clone)]
 pub struct MCELearner {
-    taxi_x_learners: [CELearner<ChangeTaxiX>; Actions::NUM_ELEMENTS],
-    taxi_y_learners: [CELearner<ChangeTaxiY>; Actions::NUM_ELEMENTS],
-    passenger_learners: [CELearner<ChangePassenger>; Actions::NUM_ELEMENTS],
+    domain: Domain,
+    // Indexed by action, then class, then attribute.
+    learners: Vec<Vec<Vec<CELearner>>>,
 }
 
 impl MCELearner {
-    pub fn new() -> Self {
+    // max_effects is the most effects each effect type may learn for an
+    // attribute, the k of DOORmax.
+    pub fn new(domain: &Domain, max_effects: usize) -> Self {
+        let learners = (0..Actions::NUM_ELEMENTS)
+            .map(|_| {
+                domain
+                    .classes()
+                    .iter()
+                    .map(|class| vec![CELearner::new(max_effects); class.attributes.len()])
+                    .collect()
+            })
+            .collect();
+
         MCELearner {
-            taxi_x_learners: Default::default(),
-            taxi_y_learners: Default::default(),
-            passenger_learners: Default::default(),
+            domain: domain.clone(),
+            learners,
         }
     }
 
+    pub fn object_conditions(&self, world: &World, state: &ObjectState) -> Vec<Condition> {
+        condition::object_conditions(&self.domain, world, state)
+    }
+
+    // The conditions are those object_conditions gives for the state.
     pub fn predict(
         &self,
-        world: &World,
-        state: &State,
+        state: &ObjectState,
+        conditions: &[Condition],
         action: Actions,
-    ) -> Result<Option<State>, effect::Error> {
-        let condition = Condition::new(world, state);
-        let action_index = action.to_index();
+    ) -> Option<ObjectState> {
+        let action_learners = &self.learners[action.to_index()];
+        let mut changes = Vec::new();
 
-        if let Some(predicted_taxi_x) =
-            self.taxi_x_learners[action_index].predict(world, state, &condition)?
-        {
-            if let Some(predicted_taxi_y) =
-                self.taxi_y_learners[action_index].predict(world, state, &condition)?
-            {
-                if let Some(predicted_passenger) =
-                    self.passenger_learners[action_index].predict(world, state, &condition)?
-                {
-                    return Ok(Some(State::build(
-                        world,
-                        (predicted_taxi_x.get_taxi().x, predicted_taxi_y.get_taxi().y),
-                        predicted_passenger.get_passenger(),
-                        state.get_destination(),
-                    )?));
+        for (index, object) in state.objects().iter().enumerate() {
+            let class_learners = &action_learners[object.class.index()];
+
+            for (attribute, &value) in object.values.iter().enumerate() {
+                if self.domain.rides_along(object, attribute) {
+                    continue;
+                }
+
+                let predicted = class_learners[attribute].predict(&conditions[index], value)?;
+
+                if predicted != value {
+                    changes.push((index, attribute, predicted));
                 }
             }
         }
 
-        Ok(None)
+        let mut result = state.clone();
+        for (index, attribute, value) in changes {
+            result.set_value(index, attribute, value);
+        }
+        self.domain.carry(&mut result);
+
+        Some(result)
     }
 
+    // The objects in new_state must be those of state, in the same order.
     pub fn apply_experience(
         &mut self,
         world: &World,
-        state: &State,
+        state: &ObjectState,
         action: Actions,
-        new_state: &State,
+        new_state: &ObjectState,
     ) {
-        let condition = Condition::new(world, state);
-        let action_index = action.to_index();
+        let conditions = self.object_conditions(world, state);
+        let action_learners = &mut self.learners[action.to_index()];
 
-        self.taxi_x_learners[action_index].apply_experience(&condition, state, new_state);
-        self.taxi_y_learners[action_index].apply_experience(&condition, state, new_state);
-        self.passenger_learners[action_index].apply_experience(&condition, state, new_state);
+        for (index, (object, new_object)) in
+            state.objects().iter().zip(new_state.objects()).enumerate()
+        {
+            let class_learners = &mut action_learners[object.class.index()];
+
+            for (attribute, (&value, &new_value)) in
+                object.values.iter().zip(&new_object.values).enumerate()
+            {
+                // Where a carried object goes says nothing about the action.
+                if self.domain.rides_along(object, attribute) {
+                    continue;
+                }
+
+                class_learners[attribute].apply_experience(&conditions[index], value, new_value);
+            }
+        }
     }
 }
 
-impl fmt::Display for MCELearner {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "taxi_x:")?;
-        for action_index in 0..Actions::NUM_ELEMENTS {
-            let action = Actions::from_index(action_index).unwrap();
-            writeln!(f, "{} - {}", action, self.taxi_x_learners[action_index])?;
-        }
-        writeln!(f)?;
+impl MCELearner {
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    // The rules learnt for each action and attribute.  While more than one
+    // effect type remains, every type's rules are unknown.
+    pub fn explain(&self) -> Vec<AttributeRules> {
+        let mut explained = Vec::new();
 
-        writeln!(f, "taxi_y:")?;
-        for action_index in 0..Actions::NUM_ELEMENTS {
+        for (action_index, action_learners) in self.learners.iter().enumerate() {
             let action = Actions::from_index(action_index).unwrap();
-            writeln!(f, "{} - {}", action, self.taxi_y_learners[action_index])?;
+
+            for (class_index, class_learners) in action_learners.iter().enumerate() {
+                for (attribute_index, learner) in class_learners.iter().enumerate() {
+                    let effect_types: Vec<EffectType> = learner
+                        .effect_types
+                        .iter()
+                        .map(|&(effect_type, _)| effect_type)
+                        .collect();
+                    let unknown = effect_types.len() > 1;
+
+                    let mut rules = AttributeRules::new(
+                        &self.domain,
+                        action,
+                        class_index,
+                        attribute_index,
+                        &effect_types,
+                    );
+
+                    for (effect_type, condition_effects) in &learner.effect_types {
+                        for (condition_learner, learned_effect) in condition_effects {
+                            if let Some(hypothesis) = condition_learner.hypothesis() {
+                                rules.add_rule(
+                                    &self.domain,
+                                    *effect_type,
+                                    &[(Some(*learned_effect), 1.0)],
+                                    hypothesis.requirements(),
+                                    unknown,
+                                );
+                            }
+                        }
+                    }
+
+                    explained.push(rules);
+                }
+            }
         }
-        writeln!(f)?;
 
-        writeln!(f, "passenger:")?;
-        for action_index in 0..Actions::NUM_ELEMENTS {
-            let action = Actions::from_index(action_index).unwrap();
-            writeln!(f, "{} - {}", action, self.passenger_learners[action_index])?;
+        explained
+    }
+}
+
+impl fmt::Display for MCELearner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (class_index, class) in self.domain.classes().iter().enumerate() {
+            for (attribute_index, (attribute, _)) in class.attributes.iter().enumerate() {
+                writeln!(f, "{}.{}:", class.name, attribute)?;
+                for action_index in 0..Actions::NUM_ELEMENTS {
+                    let action = Actions::from_index(action_index).unwrap();
+                    writeln!(
+                        f,
+                        "{} - {}",
+                        action, self.learners[action_index][class_index][attribute_index]
+                    )?;
+                }
+                writeln!(f)?;
+            }
         }
-        writeln!(f)?;
 
         Ok(())
     }
@@ -253,9 +345,97 @@ impl fmt::Display for MCELearner {
 #[cfg(test)]
 mod mcelearner_test {
     use super::*;
+    use crate::oomdp::taxi::TaxiObjects;
     use crate::position::Position;
+    use crate::state::State;
     use crate::world::Costs;
 
+    fn learn(
+        learner: &mut MCELearner,
+        taxi_objects: &TaxiObjects,
+        world: &World,
+        state: &State,
+        action: Actions,
+        new_state: &State,
+    ) {
+        let objects = taxi_objects.to_objects(world, state);
+        let new_objects = taxi_objects.to_objects(world, new_state);
+        learner.apply_experience(world, &objects, action, &new_objects);
+    }
+
+    fn predict(
+        learner: &MCELearner,
+        taxi_objects: &TaxiObjects,
+        world: &World,
+        state: &State,
+        action: Actions,
+    ) -> Option<State> {
+        let objects = taxi_objects.to_objects(world, state);
+        let conditions = learner.object_conditions(world, &objects);
+        learner
+            .predict(&objects, &conditions, action)
+            .map(|predicted| taxi_objects.to_state(world, &predicted).unwrap())
+    }
+
+    #[test]
+    fn rules_out_effect_types() {
+        let condition = Condition::from_terms(&[true]);
+        let mut learner = CELearner::new(2);
+
+        // 1 -> 2 could be an increment, a doubling or setting to 2.
+        learner.apply_experience(&condition, Value::Int(1), Value::Int(2));
+        assert_eq!(learner.predict(&condition, Value::Int(3)), None);
+
+        // Only an increment explains 2 -> 3 as well.
+        learner.apply_experience(&condition, Value::Int(2), Value::Int(3));
+        assert_eq!(
+            learner.predict(&condition, Value::Int(5)),
+            Some(Value::Int(6))
+        );
+    }
+
+    #[test]
+    fn limits_effects_per_type() {
+        let observe = |max_effects: usize| {
+            let mut learner = CELearner::new(max_effects);
+            let experiences = [([true, false], 1), ([false, true], 2), ([true, true], 3)];
+            for &(terms, old_value) in &experiences {
+                let condition = Condition::from_terms(&terms);
+                learner.apply_experience(&condition, Value::Int(old_value), Value::Int(5));
+            }
+
+            learner.predict(&Condition::from_terms(&[false, false]), Value::Int(4))
+        };
+
+        // Three increments are too many, which leaves setting to 5.
+        assert_eq!(observe(2), Some(Value::Int(5)));
+
+        // Otherwise the increments have not seen this condition.
+        assert_eq!(observe(3), None);
+    }
+
+    #[test]
+    fn recovers_when_every_type_is_ruled_out() {
+        let condition = Condition::from_terms(&[true]);
+        let mut learner = CELearner::new(2);
+
+        // No effect type gives 1 two different values under one condition.
+        learner.apply_experience(&condition, Value::Int(1), Value::Int(2));
+        learner.apply_experience(&condition, Value::Int(1), Value::Int(3));
+        assert_eq!(learner.effect_types.len(), EffectType::ALL.len());
+        assert_eq!(
+            learner.predict(&condition, Value::Int(1)),
+            Some(Value::Int(3))
+        );
+
+        // Learning goes on from the last observation.
+        learner.apply_experience(&condition, Value::Int(2), Value::Int(4));
+        assert_eq!(
+            learner.predict(&condition, Value::Int(5)),
+            Some(Value::Int(7))
+        );
+    }
+
     #[test]
     fn learns_taxi_east_simple() {
         let source_world = "\
@@ -275,14 +455,22 @@ mod mcelearner_test {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let old_state = State::build(&w, (1, 3), Some('R'), 'B').unwrap();
+        let old_state = State::build_named(&w, (1, 3), Some("R"), "B").unwrap();
         let (_, new_state) = old_state.apply_action(&w, Actions::East);
         assert_eq!(new_state.get_taxi(), Position::new(2, 3));
 
-        let mut learner = MCELearner::new();
-        learner.apply_experience(&w, &old_state, Actions::East, &new_state);
-
-        let predicted_0 = learner.predict(&w, &old_state, Actions::East).unwrap();
+        let taxi_objects = TaxiObjects::new();
+        let mut learner = MCELearner::new(taxi_objects.domain(), 2);
+        learn(
+            &mut learner,
+            &taxi_objects,
+            &w,
+            &old_state,
+            Actions::East,
+            &new_state,
+        );
+
+        let predicted_0 = predict(&learner, &taxi_objects, &w, &old_state, Actions::East);
         assert_eq!(predicted_0, Some(new_state));
     }
 
@@ -304,26 +492,128 @@ mod mcelearner_test {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let clear_state = State::build(&w, (1, 2), Some('R'), 'B').unwrap();
+        let clear_state = State::build_named(&w, (1, 2), Some("R"), "B").unwrap();
         let (_, clear_final_state) = clear_state.apply_action(&w, Actions::East);
         assert_eq!(clear_final_state.get_taxi(), Position::new(2, 2));
 
-        let mut learner = MCELearner::new();
-        learner.apply_experience(&w, &clear_state, Actions::East, &clear_final_state);
-
-        let predicted_0 = learner.predict(&w, &clear_state, Actions::East).unwrap();
+        let taxi_objects = TaxiObjects::new();
+        let mut learner = MCELearner::new(taxi_objects.domain(), 2);
+        learn(
+            &mut learner,
+            &taxi_objects,
+            &w,
+            &clear_state,
+            Actions::East,
+            &clear_final_state,
+        );
+
+        let predicted_0 = predict(&learner, &taxi_objects, &w, &clear_state, Actions::East);
         assert_eq!(predicted_0, Some(clear_final_state));
 
-        let blocked_state = State::build(&w, (1, 1), Some('R'), 'B').unwrap();
+        let blocked_state = State::build_named(&w, (1, 1), Some("R"), "B").unwrap();
         let (_, blocked_final_state) = blocked_state.apply_action(&w, Actions::East);
         assert_eq!(blocked_final_state.get_taxi(), Position::new(1, 1));
 
-        learner.apply_experience(&w, &blocked_state, Actions::East, &blocked_final_state);
+        learn(
+            &mut learner,
+            &taxi_objects,
+            &w,
+            &blocked_state,
+            Actions::East,
+            &blocked_final_state,
+        );
 
-        let predicted_0b = learner.predict(&w, &clear_state, Actions::East).unwrap();
+        let predicted_0b = predict(&learner, &taxi_objects, &w, &clear_state, Actions::East);
         assert_eq!(predicted_0b, Some(clear_final_state));
 
-        let predicted_1 = learner.predict(&w, &blocked_state, Actions::East).unwrap();
+        let predicted_1 = predict(&learner, &taxi_objects, &w, &blocked_state, Actions::East);
         assert_eq!(predicted_1, Some(blocked_final_state));
     }
+
+    #[test]
+    fn carried_passenger_rides_along() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      └─────┘\n\
+                      ";
+        let w = World::build_from_str(source, Costs::default()).unwrap();
+        let taxi_objects = TaxiObjects::new();
+        let mut learner = MCELearner::new(taxi_objects.domain(), 2);
+
+        let carried = State::build_named(&w, (0, 0), None, "G").unwrap();
+        let (_, moved) = carried.apply_action(&w, Actions::East);
+        learn(
+            &mut learner,
+            &taxi_objects,
+            &w,
+            &carried,
+            Actions::East,
+            &moved,
+        );
+
+        // Only the taxi is learnt to move, and the passenger goes with it.
+        let passenger = taxi_objects.domain().find_class("passenger").unwrap();
+        let passenger_learners = &learner.learners[Actions::East.to_index()][passenger.index()];
+        assert!(passenger_learners
+            .iter()
+            .all(|attribute_learner| attribute_learner
+                .effect_types
+                .iter()
+                .all(|(_, condition_effects)| condition_effects.is_empty())));
+
+        let objects = taxi_objects.to_objects(&w, &carried);
+        let conditions = learner.object_conditions(&w, &objects);
+        assert_eq!(
+            learner.predict(&objects, &conditions, Actions::East),
+            Some(taxi_objects.to_objects(&w, &moved))
+        );
+    }
+
+    #[test]
+    fn shares_learners_between_objects() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      └─────┘\n\
+                      ";
+        let w = World::build_from_str(source, Costs::default()).unwrap();
+        let taxi_objects = TaxiObjects::new();
+        let domain = taxi_objects.domain();
+        let taxi = domain.find_class("taxi").unwrap();
+        let passenger = domain.find_class("passenger").unwrap();
+
+        let two_passengers = |taxi_x: i32, first_x: i32, second_x: i32| {
+            let mut objects = ObjectState::new();
+            objects
+                .add(domain, taxi, vec![Value::Int(taxi_x), Value::Int(0)])
+                .unwrap();
+            for &x in &[first_x, second_x] {
+                objects
+                    .add(
+                        domain,
+                        passenger,
+                        vec![Value::Int(x), Value::Int(0), Value::Bool(false)],
+                    )
+                    .unwrap();
+            }
+            objects
+        };
+
+        let mut learner = MCELearner::new(domain, 2);
+        let old_objects = two_passengers(0, 0, 2);
+        let mut new_objects = old_objects.clone();
+        new_objects.set_value(1, 2, Value::Bool(true));
+        learner.apply_experience(&w, &old_objects, Actions::PickUp, &new_objects);
+
+        // The passengers have swapped places, so the second is picked up.
+        let swapped = two_passengers(0, 2, 0);
+        let mut expected = swapped.clone();
+        expected.set_value(2, 2, Value::Bool(true));
+        let conditions = learner.object_conditions(&w, &swapped);
+        assert_eq!(
+            learner.predict(&swapped, &conditions, Actions::PickUp),
+            Some(expected)
+        );
+    }
 }
@@ -2,10 +2,10 @@ use std::fmt;
 
 use crate::doormax::condition::Condition;
 use crate::doormax::condition_learner::ConditionLearner;
+use crate::doormax::explanation::RewardRule;
 
 use crate::actions::Actions;
-use crate::state::State;
-use crate::world::World;
+use crate::oomdp::Domain;
 
 #[derive(Debug, Clone)]
 pub struct RewardLearner {
@@ -151,18 +151,16 @@ impl MultiRewardLearner {
         MultiRewardLearner { reward_learners }
     }
 
-    pub fn predict(&self, world: &World, state: &State, action: Actions) -> Option<f64> {
-        let condition = Condition::new(world, state);
+    pub fn predict(&self, condition: &Condition, action: Actions) -> Option<f64> {
         let action_index = action.to_index();
 
-        self.reward_learners[action_index].predict(&condition)
+        self.reward_learners[action_index].predict(condition)
     }
 
-    pub fn apply_experience(&mut self, world: &World, state: &State, action: Actions, reward: f64) {
-        let condition = Condition::new(world, state);
+    pub fn apply_experience(&mut self, condition: &Condition, action: Actions, reward: f64) {
         let action_index = action.to_index();
 
-        self.reward_learners[action_index].apply_experience(&condition, reward);
+        self.reward_learners[action_index].apply_experience(condition, reward);
 
         // if action == Actions::DropOff {
         //     println!(
@@ -171,6 +169,28 @@ impl MultiRewardLearner {
         //     );
         // }
     }
+
+    // The rewards learnt for each action, by the terms of domain.
+    pub fn explain(&self, domain: &Domain) -> Vec<RewardRule> {
+        let mut explained = Vec::new();
+
+        for (action_index, reward_learner) in self.reward_learners.iter().enumerate() {
+            let action = Actions::from_index(action_index).unwrap();
+
+            for (condition_learner, reward) in &reward_learner.condition_rewards {
+                if let Some(hypothesis) = condition_learner.hypothesis() {
+                    explained.push(RewardRule::new(
+                        domain,
+                        action,
+                        *reward,
+                        hypothesis.requirements(),
+                    ));
+                }
+            }
+        }
+
+        explained
+    }
 }
 
 impl fmt::Display for MultiRewardLearner {
@@ -189,7 +209,17 @@ impl fmt::Display for MultiRewardLearner {
 mod multirewardlearner_test {
     use super::*;
     use crate::actions::Actions;
-    use crate::world::Costs;
+    use crate::oomdp::taxi::TaxiObjects;
+    use crate::state::State;
+    use crate::world::{Costs, World};
+
+    fn taxi_condition(world: &World, state: &State) -> Condition {
+        let mut taxi_objects = TaxiObjects::new();
+        taxi_objects.add_terrain(world);
+        let objects = taxi_objects.to_objects(world, state);
+
+        Condition::new(taxi_objects.domain(), world, &objects, None)
+    }
 
     #[test]
     fn learns_pickup() {
@@ -210,7 +240,7 @@ mod multirewardlearner_test {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let off_passenger = State::build(&w, (0, 1), Some('R'), 'B').unwrap();
+        let off_passenger = State::build_named(&w, (0, 1), Some("R"), "B").unwrap();
         let (off_passenger_reward, _) = off_passenger.apply_action(&w, Actions::PickUp);
         assert!(approx_eq!(
             f64,
@@ -221,37 +251,84 @@ mod multirewardlearner_test {
 
         let mut learner = MultiRewardLearner::new(1.0e-6);
 
-        assert_eq!(learner.predict(&w, &off_passenger, Actions::PickUp), None);
+        assert_eq!(
+            learner.predict(&taxi_condition(&w, &off_passenger), Actions::PickUp),
+            None
+        );
 
-        learner.apply_experience(&w, &off_passenger, Actions::PickUp, off_passenger_reward);
+        learner.apply_experience(
+            &taxi_condition(&w, &off_passenger),
+            Actions::PickUp,
+            off_passenger_reward,
+        );
 
         assert_eq!(
-            learner.predict(&w, &off_passenger, Actions::PickUp),
+            learner.predict(&taxi_condition(&w, &off_passenger), Actions::PickUp),
             Some(off_passenger_reward)
         );
 
-        let on_passenger = State::build(&w, (0, 0), Some('R'), 'B').unwrap();
+        let on_passenger = State::build_named(&w, (0, 0), Some("R"), "B").unwrap();
         let (on_passenger_reward, _) = on_passenger.apply_action(&w, Actions::PickUp);
         assert!(approx_eq!(f64, on_passenger_reward, 0.0, ulps = 1));
 
-        assert_eq!(learner.predict(&w, &on_passenger, Actions::PickUp), None);
         assert_eq!(
-            learner.predict(&w, &off_passenger, Actions::PickUp),
+            learner.predict(&taxi_condition(&w, &on_passenger), Actions::PickUp),
+            None
+        );
+        assert_eq!(
+            learner.predict(&taxi_condition(&w, &off_passenger), Actions::PickUp),
             Some(off_passenger_reward)
         );
 
-        learner.apply_experience(&w, &on_passenger, Actions::PickUp, on_passenger_reward);
+        learner.apply_experience(
+            &taxi_condition(&w, &on_passenger),
+            Actions::PickUp,
+            on_passenger_reward,
+        );
 
         assert_eq!(
-            learner.predict(&w, &on_passenger, Actions::PickUp),
+            learner.predict(&taxi_condition(&w, &on_passenger), Actions::PickUp),
             Some(on_passenger_reward)
         );
         assert_eq!(
-            learner.predict(&w, &off_passenger, Actions::PickUp),
+            learner.predict(&taxi_condition(&w, &off_passenger), Actions::PickUp),
             Some(off_passenger_reward)
         );
     }
 
+    #[test]
+    fn explains_rewards_by_term() {
+        let source_world = "\
+                            ┌─────┐\n\
+                            │R . G│\n\
+                            └─────┘\n\
+                            ";
+        let w = World::build_from_str(source_world, Costs::default()).unwrap();
+        let domain = TaxiObjects::new().domain().clone();
+
+        let mut learner = MultiRewardLearner::new(1.0e-6);
+        for &taxi_pos in &[(0, 0), (1, 0)] {
+            let state = State::build_named(&w, taxi_pos, Some("R"), "G").unwrap();
+            let (reward, _) = state.apply_action(&w, Actions::PickUp);
+            learner.apply_experience(&taxi_condition(&w, &state), Actions::PickUp, reward);
+        }
+
+        let rules = learner.explain(&domain);
+        assert_eq!(rules.len(), 2);
+        assert!(rules.iter().all(|rule| rule.action == "PickUp"));
+
+        let picked_up = rules.iter().find(|rule| rule.reward == 0.0).unwrap();
+        assert!(picked_up
+            .when
+            .contains(&String::from("on(taxi, passenger)")));
+        assert!(picked_up
+            .text
+            .starts_with("PickUp rewards 0 when touch_n(taxi, wall) and "));
+
+        let missed = rules.iter().find(|rule| rule.reward != 0.0).unwrap();
+        assert!(missed.text.starts_with("PickUp rewards -10 "));
+    }
+
     #[test]
     fn learns_dropoff() {
         let source_world = "\
@@ -271,7 +348,7 @@ mod multirewardlearner_test {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let no_passenger = State::build(&w, (3, 3), Some('R'), 'B').unwrap();
+        let no_passenger = State::build_named(&w, (3, 3), Some("R"), "B").unwrap();
         let (no_passenger_reward, _) = no_passenger.apply_action(&w, Actions::DropOff);
         assert!(approx_eq!(
             f64,
@@ -282,16 +359,23 @@ mod multirewardlearner_test {
 
         let mut learner = MultiRewardLearner::new(1.0e-6);
 
-        assert_eq!(learner.predict(&w, &no_passenger, Actions::DropOff), None);
+        assert_eq!(
+            learner.predict(&taxi_condition(&w, &no_passenger), Actions::DropOff),
+            None
+        );
 
-        learner.apply_experience(&w, &no_passenger, Actions::DropOff, no_passenger_reward);
+        learner.apply_experience(
+            &taxi_condition(&w, &no_passenger),
+            Actions::DropOff,
+            no_passenger_reward,
+        );
 
         assert_eq!(
-            learner.predict(&w, &no_passenger, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &no_passenger), Actions::DropOff),
             Some(no_passenger_reward)
         );
 
-        let off_destination = State::build(&w, (1, 3), None, 'B').unwrap();
+        let off_destination = State::build_named(&w, (1, 3), None, "B").unwrap();
         let (off_destination_reward, _) = off_destination.apply_action(&w, Actions::DropOff);
         assert!(approx_eq!(
             f64,
@@ -301,50 +385,151 @@ mod multirewardlearner_test {
         ));
 
         assert_eq!(
-            learner.predict(&w, &off_destination, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &off_destination), Actions::DropOff),
             None
         );
 
         learner.apply_experience(
-            &w,
-            &off_destination,
+            &taxi_condition(&w, &off_destination),
             Actions::DropOff,
             off_destination_reward,
         );
 
         assert_eq!(
-            learner.predict(&w, &off_destination, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &off_destination), Actions::DropOff),
             Some(off_destination_reward)
         );
         assert_eq!(
-            learner.predict(&w, &no_passenger, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &no_passenger), Actions::DropOff),
             Some(no_passenger_reward)
         );
 
-        let on_destination = State::build(&w, (3, 3), None, 'B').unwrap();
+        let on_destination = State::build_named(&w, (3, 3), None, "B").unwrap();
         let (on_destination_reward, _) = on_destination.apply_action(&w, Actions::DropOff);
         assert!(approx_eq!(f64, on_destination_reward, 0.0, ulps = 1));
 
         // This fails if miss_dropoff  and empty_dropoff are both -10. It will predict Some(-10)
         // because no_passenger and off_destination states have taught that those 2 conditions are **
         // for that effect.  This is what Diuk is talking about when he says disjunctions cannot be learned.
-        // assert_eq!(learner.predict(&w, &on_destination, Actions::DropOff), None);
+        // assert_eq!(learner.predict(&taxi_condition(&w, &on_destination), Actions::DropOff), None);
 
-        learner.apply_experience(&w, &on_destination, Actions::DropOff, on_destination_reward);
+        learner.apply_experience(
+            &taxi_condition(&w, &on_destination),
+            Actions::DropOff,
+            on_destination_reward,
+        );
 
         // off_destination and no_passenger will now be None because they were removed
         // as conflicts.
         assert_eq!(
-            learner.predict(&w, &on_destination, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &on_destination), Actions::DropOff),
             Some(on_destination_reward)
         );
         assert_eq!(
-            learner.predict(&w, &off_destination, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &off_destination), Actions::DropOff),
             Some(off_destination_reward)
         );
         assert_eq!(
-            learner.predict(&w, &no_passenger, Actions::DropOff),
+            learner.predict(&taxi_condition(&w, &no_passenger), Actions::DropOff),
             Some(no_passenger_reward)
         );
     }
+
+    #[test]
+    fn learns_terrain_movement() {
+        let source_world = "\
+                            ┌─────┐\n\
+                            │R . .│\n\
+                            │     │\n\
+                            │. . G│\n\
+                            └─────┘\n\
+                            ";
+
+        let costs = Costs::default();
+        let mut w = World::build_from_str(source_world, costs).unwrap();
+        w.apply_terrain_layer(". 4 .\n. . .\n").unwrap();
+
+        let on_terrain = State::build_named(&w, (1, 0), Some("R"), "G").unwrap();
+        let (on_terrain_reward, _) = on_terrain.apply_action(&w, Actions::South);
+        assert!(approx_eq!(
+            f64,
+            on_terrain_reward,
+            4.0 * costs.movement,
+            ulps = 1
+        ));
+
+        let off_terrain = State::build_named(&w, (1, 1), Some("R"), "G").unwrap();
+        let (off_terrain_reward, _) = off_terrain.apply_action(&w, Actions::North);
+        assert!(approx_eq!(
+            f64,
+            off_terrain_reward,
+            costs.movement,
+            ulps = 1
+        ));
+
+        let mut learner = MultiRewardLearner::new(1.0e-6);
+
+        learner.apply_experience(
+            &taxi_condition(&w, &on_terrain),
+            Actions::South,
+            on_terrain_reward,
+        );
+        learner.apply_experience(
+            &taxi_condition(&w, &off_terrain),
+            Actions::North,
+            off_terrain_reward,
+        );
+        learner.apply_experience(
+            &taxi_condition(&w, &off_terrain),
+            Actions::South,
+            off_terrain_reward,
+        );
+
+        assert_eq!(
+            learner.predict(&taxi_condition(&w, &on_terrain), Actions::South),
+            Some(on_terrain_reward)
+        );
+        assert_eq!(
+            learner.predict(&taxi_condition(&w, &off_terrain), Actions::South),
+            Some(off_terrain_reward)
+        );
+    }
+
+    #[test]
+    fn learns_each_terrain_cost() {
+        let source_world = "\
+                            ┌─────────┐\n\
+                            │R . . . .│\n\
+                            │         │\n\
+                            │. . . . G│\n\
+                            └─────────┘\n\
+                            ";
+
+        let costs = Costs::default();
+        let mut w = World::build_from_str(source_world, costs).unwrap();
+        w.apply_terrain_layer(". . . . .\n. 2 4 . .\n").unwrap();
+
+        // The three cells have the same walls, so only the terrain tells
+        // their costs apart.
+        let cells = [(1, 1), (2, 1), (3, 1)];
+        let expected = [2.0 * costs.movement, 4.0 * costs.movement, costs.movement];
+
+        let mut learner = MultiRewardLearner::new(1.0e-6);
+
+        for (&taxi_pos, &expected_reward) in cells.iter().zip(&expected) {
+            let state = State::build_named(&w, taxi_pos, Some("R"), "G").unwrap();
+            let (reward, _) = state.apply_action(&w, Actions::North);
+            assert!(approx_eq!(f64, reward, expected_reward, ulps = 1));
+
+            learner.apply_experience(&taxi_condition(&w, &state), Actions::North, reward);
+        }
+
+        for (&taxi_pos, &expected_reward) in cells.iter().zip(&expected) {
+            let state = State::build_named(&w, taxi_pos, Some("R"), "G").unwrap();
+            assert_eq!(
+                learner.predict(&taxi_condition(&w, &state), Actions::North),
+                Some(expected_reward)
+            );
+        }
+    }
 }
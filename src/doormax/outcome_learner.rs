@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::doormax::condition::Condition;
+use crate::doormax::effect::{self, Effect, EffectType};
+use crate::doormax::explanation::AttributeRules;
+
+use crate::actions::Actions;
+use crate::oomdp::{Domain, ObjectState, Value};
+
+// How often each effect has been seen under one condition, None where the
+// value did not change.
+#[derive(Debug, Clone, Default)]
+struct Outcomes {
+    counts: Vec<(Option<Effect>, f64)>,
+    total: f64,
+}
+
+impl Outcomes {
+    fn add(&mut self, effect: Option<Effect>) {
+        match self.counts.iter_mut().find(|(seen, _)| *seen == effect) {
+            Some((_, count)) => *count += 1.0,
+            None => self.counts.push((effect, 1.0)),
+        }
+
+        self.total += 1.0;
+    }
+
+    fn num_effects(&self) -> usize {
+        self.counts
+            .iter()
+            .filter(|(effect, _)| effect.is_some())
+            .count()
+    }
+
+    // The chance of each value the attribute could take next.
+    fn distribution(&self, value: Value) -> Vec<(f64, Value)> {
+        let mut distribution: Vec<(f64, Value)> = Vec::new();
+
+        for &(effect, count) in &self.counts {
+            let probability = count / self.total;
+            let next_value = effect.map_or(value, |effect| effect.apply(value));
+
+            match distribution
+                .iter_mut()
+                .find(|(_, seen)| *seen == next_value)
+            {
+                Some((seen_probability, _)) => *seen_probability += probability,
+                None => distribution.push((probability, next_value)),
+            }
+        }
+
+        distribution
+    }
+}
+
+// Learns the chances of the effects of one action on one attribute of a
+// class, for worlds where an action does not always do the same thing.
+// Rather than generalising over conditions, the effects are counted under
+// each condition, and known once it has been seen known_count times.  An
+// effect type is ruled out once it cannot explain a change, or sees more
+// than max_effects effects under a condition.
+#[derive(Debug, Clone)]
+pub struct OutcomeLearner {
+    effect_types: Vec<(EffectType, HashMap<Condition, Outcomes>)>,
+    max_effects: usize,
+    known_count: f64,
+}
+
+impl OutcomeLearner {
+    pub fn new(max_effects: usize, known_count: f64) -> Self {
+        OutcomeLearner {
+            effect_types: EffectType::ALL
+                .iter()
+                .map(|&effect_type| (effect_type, HashMap::new()))
+                .collect(),
+            max_effects,
+            known_count,
+        }
+    }
+
+    // The chance of each value of the attribute, None if it is not known.
+    pub fn predict(&self, condition: &Condition, value: Value) -> Option<Vec<(f64, Value)>> {
+        let known_count = self.known_count;
+
+        effect::agreed_prediction(
+            &self.effect_types,
+            |condition_outcomes| {
+                let outcomes = condition_outcomes.get(condition)?;
+
+                if outcomes.total < known_count {
+                    None
+                } else {
+                    Some(outcomes.distribution(value))
+                }
+            },
+            |first, second| same_distribution(first, second),
+        )
+    }
+
+    pub fn apply_experience(&mut self, condition: &Condition, old_value: Value, new_value: Value) {
+        let max_effects = self.max_effects;
+
+        effect::learn_effects(
+            &mut self.effect_types,
+            old_value,
+            new_value,
+            |condition_outcomes, observed_effect| {
+                let outcomes = condition_outcomes.entry(condition.clone()).or_default();
+                outcomes.add(observed_effect);
+
+                outcomes.num_effects() <= max_effects
+            },
+        );
+    }
+}
+
+fn same_distribution(first: &[(f64, Value)], second: &[(f64, Value)]) -> bool {
+    first.len() == second.len()
+        && first.iter().all(|&(probability, value)| {
+            second.iter().any(|&(other_probability, other_value)| {
+                value == other_value && approx_eq!(f64, probability, other_probability, ulps = 2)
+            })
+        })
+}
+
+impl fmt::Display for OutcomeLearner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OL(")?;
+        for (effect_type, condition_outcomes) in &self.effect_types {
+            write!(f, " {}[", effect_type)?;
+            let mut leader = " ";
+            for (condition, outcomes) in condition_outcomes {
+                write!(f, "{}{} =>", leader, condition)?;
+                for &(effect, count) in &outcomes.counts {
+                    match effect {
+                        Some(effect) => write!(f, " {}", effect)?,
+                        None => write!(f, " None")?,
+                    }
+                    write!(f, " x{}", count)?;
+                }
+                leader = ", ";
+            }
+            write!(f, " ]")?;
+        }
+        write!(f, " )")
+    }
+}
+
+// One OutcomeLearner for each action and attribute of each class, shared by
+// the objects of a class like the learners of MCELearner.
+#[derive(Debug, Clone)]
+pub struct MultiOutcomeLearner {
+    domain: Domain,
+    // Indexed by action, then class, then attribute.
+    learners: Vec<Vec<Vec<OutcomeLearner>>>,
+}
+
+impl MultiOutcomeLearner {
+    pub fn new(domain: &Domain, max_effects: usize, known_count: f64) -> Self {
+        let learners = (0..Actions::NUM_ELEMENTS)
+            .map(|_| {
+                domain
+                    .classes()
+                    .iter()
+                    .map(|class| {
+                        vec![OutcomeLearner::new(max_effects, known_count); class.attributes.len()]
+                    })
+                    .collect()
+            })
+            .collect();
+
+        MultiOutcomeLearner {
+            domain: domain.clone(),
+            learners,
+        }
+    }
+
+    // The chance of each next state, None if any attribute is not known.
+    // The attributes are taken to change independently of each other.
+    pub fn predict(
+        &self,
+        state: &ObjectState,
+        conditions: &[Condition],
+        action: Actions,
+    ) -> Option<Vec<(f64, ObjectState)>> {
+        let action_learners = &self.learners[action.to_index()];
+        let mut results = vec![(1.0, state.clone())];
+
+        for (index, object) in state.objects().iter().enumerate() {
+            let class_learners = &action_learners[object.class.index()];
+
+            for (attribute, &value) in object.values.iter().enumerate() {
+                if self.domain.rides_along(object, attribute) {
+                    continue;
+                }
+
+                let predicted = class_learners[attribute].predict(&conditions[index], value)?;
+
+                if let [(_, next_value)] = predicted[..] {
+                    if next_value != value {
+                        for (_, result) in &mut results {
+                            result.set_value(index, attribute, next_value);
+                        }
+                    }
+                } else {
+                    let mut next_results = Vec::with_capacity(results.len() * predicted.len());
+                    for (probability, result) in &results {
+                        for &(next_probability, next_value) in &predicted {
+                            let mut next_result = result.clone();
+                            next_result.set_value(index, attribute, next_value);
+                            next_results.push((probability * next_probability, next_result));
+                        }
+                    }
+                    results = next_results;
+                }
+            }
+        }
+
+        for (_, result) in &mut results {
+            self.domain.carry(result);
+        }
+
+        Some(results)
+    }
+
+    // The conditions are those object_conditions gives for state, and the
+    // objects in new_state must be those of state, in the same order.
+    pub fn apply_experience(
+        &mut self,
+        state: &ObjectState,
+        conditions: &[Condition],
+        action: Actions,
+        new_state: &ObjectState,
+    ) {
+        let action_learners = &mut self.learners[action.to_index()];
+
+        for (index, (object, new_object)) in
+            state.objects().iter().zip(new_state.objects()).enumerate()
+        {
+            let class_learners = &mut action_learners[object.class.index()];
+
+            for (attribute, (&value, &new_value)) in
+                object.values.iter().zip(&new_object.values).enumerate()
+            {
+                if self.domain.rides_along(object, attribute) {
+                    continue;
+                }
+
+                class_learners[attribute].apply_experience(&conditions[index], value, new_value);
+            }
+        }
+    }
+}
+
+impl MultiOutcomeLearner {
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    // A rule for each condition seen with each action, unknown while more
+    // than one effect type remains or the condition is not yet known.
+    pub fn explain(&self) -> Vec<AttributeRules> {
+        let mut explained = Vec::new();
+
+        for (action_index, action_learners) in self.learners.iter().enumerate() {
+            let action = Actions::from_index(action_index).unwrap();
+
+            for (class_index, class_learners) in action_learners.iter().enumerate() {
+                for (attribute_index, learner) in class_learners.iter().enumerate() {
+                    let effect_types: Vec<EffectType> = learner
+                        .effect_types
+                        .iter()
+                        .map(|&(effect_type, _)| effect_type)
+                        .collect();
+
+                    let mut rules = AttributeRules::new(
+                        &self.domain,
+                        action,
+                        class_index,
+                        attribute_index,
+                        &effect_types,
+                    );
+
+                    for (effect_type, condition_outcomes) in &learner.effect_types {
+                        let mut conditions: Vec<(&Condition, &Outcomes)> =
+                            condition_outcomes.iter().collect();
+                        conditions
+                            .sort_by(|(first, _), (second, _)| first.words().cmp(second.words()));
+
+                        // Conditions the attribute never changed under make
+                        // no rule.
+                        for (condition, outcomes) in conditions {
+                            if outcomes.num_effects() == 0 {
+                                continue;
+                            }
+
+                            let chances: Vec<(Option<Effect>, f64)> = outcomes
+                                .counts
+                                .iter()
+                                .map(|&(effect, count)| (effect, count / outcomes.total))
+                                .collect();
+                            let unknown =
+                                effect_types.len() > 1 || outcomes.total < learner.known_count;
+
+                            rules.add_rule(
+                                &self.domain,
+                                *effect_type,
+                                &chances,
+                                (0..condition.num_terms()).map(|term| (term, condition.get(term))),
+                                unknown,
+                            );
+                        }
+                    }
+
+                    explained.push(rules);
+                }
+            }
+        }
+
+        explained
+    }
+}
+
+impl fmt::Display for MultiOutcomeLearner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (class_index, class) in self.domain.classes().iter().enumerate() {
+            for (attribute_index, (attribute, _)) in class.attributes.iter().enumerate() {
+                writeln!(f, "{}.{}:", class.name, attribute)?;
+                for action_index in 0..Actions::NUM_ELEMENTS {
+                    let action = Actions::from_index(action_index).unwrap();
+                    writeln!(
+                        f,
+                        "{} - {}",
+                        action, self.learners[action_index][class_index][attribute_index]
+                    )?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod outcome_learner_test {
+    use super::*;
+
+    #[test]
+    fn learns_chances() {
+        let condition = Condition::from_terms(&[true]);
+        let mut learner = OutcomeLearner::new(2, 4.0);
+
+        // The move east slips one time in four.
+        learner.apply_experience(&condition, Value::Int(1), Value::Int(2));
+        learner.apply_experience(&condition, Value::Int(2), Value::Int(3));
+        learner.apply_experience(&condition, Value::Int(3), Value::Int(3));
+        assert_eq!(learner.predict(&condition, Value::Int(0)), None);
+
+        learner.apply_experience(&condition, Value::Int(0), Value::Int(1));
+        let predicted = learner.predict(&condition, Value::Int(5)).unwrap();
+        assert_eq!(
+            predicted,
+            vec![(0.75, Value::Int(6)), (0.25, Value::Int(5))]
+        );
+
+        // Other conditions have not been seen.
+        let other = Condition::from_terms(&[false]);
+        assert_eq!(learner.predict(&other, Value::Int(5)), None);
+    }
+
+    #[test]
+    fn rules_out_effect_types() {
+        let condition = Condition::from_terms(&[true]);
+        let mut learner = OutcomeLearner::new(2, 1.0);
+
+        // Setting the value needs a new effect for each move east.
+        for x in 0..3 {
+            learner.apply_experience(&condition, Value::Int(x), Value::Int(x + 1));
+        }
+        assert_eq!(
+            learner.predict(&condition, Value::Int(7)),
+            Some(vec![(1.0, Value::Int(8))])
+        );
+
+        // No arithmetic effect picks up a passenger.
+        let mut learner = OutcomeLearner::new(2, 1.0);
+        learner.apply_experience(&condition, Value::Bool(false), Value::Bool(true));
+        assert_eq!(
+            learner.predict(&condition, Value::Bool(false)),
+            Some(vec![(1.0, Value::Bool(true))])
+        );
+    }
+}
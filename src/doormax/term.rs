@@ -1,10 +0,0 @@
-#[derive(Debug, Clone, Copy, Enum)]
-pub enum Term {
-    TouchWallN,
-    TouchWallS,
-    TouchWallE,
-    TouchWallW,
-    OnPassenger,
-    OnDestination,
-    HasPassenger,
-}
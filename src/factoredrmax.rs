@@ -1,3 +1,6 @@
+pub mod dbn;
+mod structure;
+
 use std::f64;
 
 use rand::Rng;
@@ -7,12 +10,30 @@ use crate::actions::Actions;
 use crate::state::{State, StateIterator};
 use crate::world::World;
 
-use crate::runner::{Attempt, Runner};
+pub use self::dbn::{Dbn, DbnError, Factor, Parents};
+use self::dbn::{Estimate, FactorValues};
+use self::structure::StructureLearner;
+
+use crate::runner::{measure_model_accuracy, Attempt, ModelAccuracy, Runner};
 use crate::state_indexer::StateIndexer;
 
+// Which factors the next value of each factor, and the reward, depend on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Structure {
+    // Parents given up front, such as Dbn::taxi().
+    Fixed(Dbn),
+
+    // Parents learnt from sets of at most max_parents factors, each ruled
+    // out once its chances or rewards differ by more than tolerance from a
+    // larger set's.
+    Learnt { max_parents: usize, tolerance: f64 },
+}
+
 #[derive(Debug, Clone)]
 struct Transitions {
-    parent_index_starts: [usize; 4 * Actions::NUM_ELEMENTS],
+    dbn: Dbn,
+
+    parent_index_starts: [usize; Factor::NUM_ELEMENTS * Actions::NUM_ELEMENTS],
     occurences: Vec<f64>, // parent_index_start + parent_index
 
     transition_starts: Vec<usize>, // parent_index_start + parent_index
@@ -22,19 +43,14 @@ struct Transitions {
 }
 
 impl Transitions {
-    fn new(world: &World, known_count: f64) -> Transitions {
-        let num_x_states = world.width as usize;
-        let num_y_states = world.height as usize;
-        let num_destination_states = world.num_fixed_positions();
-        let num_passenger_states = num_destination_states + 1;
-
-        let num_total_variable_parents = total_variable_parents(world);
+    fn new(world: &World, dbn: Dbn, known_count: f64) -> Transitions {
+        let num_total_variable_parents = total_variable_parents(world, &dbn);
         let mut next_parent_index = 0;
-        let mut parent_index_starts = [0; 4 * Actions::NUM_ELEMENTS];
+        let mut parent_index_starts = [0; Factor::NUM_ELEMENTS * Actions::NUM_ELEMENTS];
 
         let occurences = vec![0.0; num_total_variable_parents];
 
-        let num_total_transitions = total_transitions(world);
+        let num_total_transitions = total_transitions(world, &dbn);
         let mut next_transition_index = 0;
         let mut transition_starts = vec![0; num_total_variable_parents];
         let transitions = vec![0.0; num_total_transitions];
@@ -42,56 +58,19 @@ impl Transitions {
         for action_index in 0..Actions::NUM_ELEMENTS {
             let action = Actions::from_index(action_index).unwrap();
 
-            {
-                let num_x_parents = num_x_parents(world, action);
-                let parent_index_start = &mut parent_index_starts[action_index * 4];
-
-                *parent_index_start = next_parent_index;
-                next_parent_index += num_x_parents;
-
-                for t in 0..num_x_parents {
-                    transition_starts[*parent_index_start + t] = next_transition_index;
-                    next_transition_index += num_x_states;
-                }
-            }
-
-            {
-                let num_y_parents = num_y_parents(world, action);
-                let parent_index_start = &mut parent_index_starts[action_index * 4 + 1];
-
-                *parent_index_start = next_parent_index;
-                next_parent_index += num_y_parents;
-
-                for t in 0..num_y_parents {
-                    transition_starts[*parent_index_start + t] = next_transition_index;
-                    next_transition_index += num_y_states;
-                }
-            }
-
-            {
-                let num_passenger_parents = num_passenger_parents(world, action);
-                let parent_index_start = &mut parent_index_starts[action_index * 4 + 2];
-
-                *parent_index_start = next_parent_index;
-                next_parent_index += num_passenger_parents;
-
-                for t in 0..num_passenger_parents {
-                    transition_starts[*parent_index_start + t] = next_transition_index;
-                    next_transition_index += num_passenger_states;
-                }
-            }
-
-            {
-                let num_destination_parents = num_destination_parents(world, action);
-
-                let parent_index_start = &mut parent_index_starts[action_index * 4 + 3];
+            for &factor in Factor::ALL.iter() {
+                let num_parents = dbn
+                    .transition_parents(action, factor)
+                    .num_configurations(world);
+                let num_states = factor.num_values(world);
+                let parent_index_start = &mut parent_index_starts[transition_index(action, factor)];
 
                 *parent_index_start = next_parent_index;
-                next_parent_index += num_destination_parents;
+                next_parent_index += num_parents;
 
-                for t in 0..num_destination_parents {
+                for t in 0..num_parents {
                     transition_starts[*parent_index_start + t] = next_transition_index;
-                    next_transition_index += num_destination_states;
+                    next_transition_index += num_states;
                 }
             }
         }
@@ -100,6 +79,8 @@ impl Transitions {
         assert_eq!(next_transition_index, num_total_transitions);
 
         Transitions {
+            dbn,
+
             parent_index_starts,
             occurences,
             transition_starts,
@@ -109,90 +90,40 @@ impl Transitions {
         }
     }
 
-    fn generate_x_parent_index(
+    // The parent index of each factor, in factor order.
+    fn generate_parent_indices(
         &self,
         world: &World,
         action: Actions,
-        x_index: usize,
-        y_index: usize,
-    ) -> usize {
-        let offset = match action {
-            Actions::East | Actions::West => y_index * (world.width as usize) + x_index,
-            _ => x_index,
-        };
-
-        self.parent_index_starts[action.to_index() * 4] + offset
-    }
-
-    fn generate_y_parent_index(&self, _world: &World, action: Actions, y_index: usize) -> usize {
-        self.parent_index_starts[action.to_index() * 4 + 1] + y_index
-    }
-
-    fn generate_passenger_parent_index(
-        &self,
-        world: &World,
-        action: Actions,
-        x_index: usize,
-        y_index: usize,
-        passenger_index: usize,
-        destination_index: usize,
-    ) -> usize {
-        let offset = match action {
-            Actions::DropOff => {
-                let mut result = y_index;
-
-                result *= world.width as usize;
-                result += x_index;
-
-                let num_destination_states = world.num_fixed_positions();
-
-                result *= num_destination_states;
-                result += destination_index;
+        values: &FactorValues,
+    ) -> [usize; Factor::NUM_ELEMENTS] {
+        let mut parent_indices = [0; Factor::NUM_ELEMENTS];
 
-                result *= num_destination_states + 1;
-                result += passenger_index;
+        for &factor in Factor::ALL.iter() {
+            let parents = self.dbn.transition_parents(action, factor);
 
-                result
-            }
-            Actions::PickUp => {
-                let mut result = y_index;
-
-                result *= world.width as usize;
-                result += x_index;
-
-                let num_destination_states = world.num_fixed_positions();
-                result *= num_destination_states + 1;
-                result += passenger_index;
-
-                result
-            }
-            _ => passenger_index,
-        };
-
-        self.parent_index_starts[action.to_index() * 4 + 2] + offset
-    }
+            parent_indices[factor.to_index()] = self.parent_index_starts
+                [transition_index(action, factor)]
+                + parents.configuration(world, values);
+        }
 
-    fn generate_destination_parent_index(
-        &self,
-        _world: &World,
-        action: Actions,
-        destination_index: usize,
-    ) -> usize {
-        self.parent_index_starts[action.to_index() * 4 + 3] + destination_index
+        parent_indices
     }
 
     fn is_known(&self, parent_index: usize) -> bool {
         self.occurences[parent_index] >= self.known_count
     }
 
-    fn get_transition(&self, parent_index: usize, next_index: usize) -> Option<f64> {
+    fn estimate(&self, parent_index: usize, num_values: usize) -> Option<Estimate<'_>> {
         let occurence_count = self.occurences[parent_index];
 
         if occurence_count >= self.known_count {
-            let transition_count =
-                self.transitions[self.transition_starts[parent_index] + next_index];
+            let transition_start = self.transition_starts[parent_index];
 
-            Some(transition_count / occurence_count)
+            Some(Estimate::new(
+                &self.transitions[transition_start..transition_start + num_values],
+                occurence_count,
+            ))
         } else {
             None
         }
@@ -206,6 +137,8 @@ impl Transitions {
 
 #[derive(Debug, Clone)]
 struct Rewards {
+    dbn: Dbn,
+
     reward_starts: [usize; Actions::NUM_ELEMENTS],
     occurences: Vec<f64>,
     rewards: Vec<f64>,
@@ -214,8 +147,8 @@ struct Rewards {
 }
 
 impl Rewards {
-    fn new(world: &World, known_count: f64) -> Rewards {
-        let num_total_reward_parents = total_reward_parents(world);
+    fn new(world: &World, dbn: Dbn, known_count: f64) -> Rewards {
+        let num_total_reward_parents = total_reward_parents(world, &dbn);
         let mut next_reward_index = 0;
         let mut reward_starts = [0; Actions::NUM_ELEMENTS];
 
@@ -223,7 +156,7 @@ impl Rewards {
             let action = Actions::from_index(action_index).unwrap();
 
             *reward_start = next_reward_index;
-            next_reward_index += num_reward_parents(world, action);
+            next_reward_index += dbn.reward_parents(action).num_configurations(world);
         }
 
         assert_eq!(next_reward_index, num_total_reward_parents);
@@ -232,6 +165,8 @@ impl Rewards {
         let rewards = vec![0.0; num_total_reward_parents];
 
         Rewards {
+            dbn,
+
             reward_starts,
             occurences,
             rewards,
@@ -240,10 +175,24 @@ impl Rewards {
         }
     }
 
-    fn apply_experience(&mut self, reward: f64, action: Actions, reward_parent_index: usize) {
-        let action_index = action.to_index();
+    fn generate_reward_index(
+        &self,
+        world: &World,
+        action: Actions,
+        values: &FactorValues,
+    ) -> usize {
+        self.reward_starts[action.to_index()]
+            + self.dbn.reward_parents(action).configuration(world, values)
+    }
 
-        let reward_index = self.reward_starts[action_index] + reward_parent_index;
+    fn apply_experience(
+        &mut self,
+        world: &World,
+        reward: f64,
+        action: Actions,
+        values: &FactorValues,
+    ) {
+        let reward_index = self.generate_reward_index(world, action, values);
         let count = &mut self.occurences[reward_index];
         if *count < self.known_count {
             *count += 1.0;
@@ -255,27 +204,8 @@ impl Rewards {
         }
     }
 
-    fn get_reward(
-        &self,
-        world: &World,
-        action: Actions,
-        x_index: usize,
-        y_index: usize,
-        passenger_index: usize,
-        destination_index: usize,
-    ) -> Option<f64> {
-        let reward_parent_index = generate_reward_parent_index(
-            world,
-            action,
-            x_index,
-            y_index,
-            passenger_index,
-            destination_index,
-        );
-
-        let action_index = action.to_index();
-
-        let reward_index = self.reward_starts[action_index] + reward_parent_index;
+    fn get_reward(&self, world: &World, action: Actions, values: &FactorValues) -> Option<f64> {
+        let reward_index = self.generate_reward_index(world, action, values);
         let count = self.occurences[reward_index];
         if count >= self.known_count {
             Some(self.rewards[reward_index])
@@ -285,13 +215,21 @@ impl Rewards {
     }
 }
 
+#[derive(Debug, Clone)]
+enum Model {
+    Fixed {
+        transitions: Box<Transitions>,
+        rewards: Rewards,
+    },
+    Learnt(StructureLearner),
+}
+
 #[derive(Debug, Clone)]
 pub struct FactoredRMax {
     state_indexer: StateIndexer,
     rmax: f64,
 
-    transitions: Transitions,
-    rewards: Rewards,
+    model: Model,
 
     value_table: Vec<f64>,
 
@@ -300,26 +238,40 @@ pub struct FactoredRMax {
 }
 
 impl FactoredRMax {
-    pub fn new(world: &World, gamma: f64, known_count: f64, error_delta: f64) -> FactoredRMax {
+    pub fn new(
+        world: &World,
+        structure: Structure,
+        gamma: f64,
+        known_count: f64,
+        error_delta: f64,
+    ) -> FactoredRMax {
         let state_indexer = StateIndexer::new(world);
         let num_states = state_indexer.num_states();
         let value_table = vec![0.0; num_states];
 
-        let transitions = Transitions::new(world, known_count);
-        let rewards = Rewards::new(world, known_count);
-
-        let rmax = if gamma < 1.0 {
-            world.max_reward() / (1.0 - gamma)
-        } else {
-            world.max_reward()
+        let model = match structure {
+            Structure::Fixed(dbn) => Model::Fixed {
+                transitions: Box::new(Transitions::new(world, dbn, known_count)),
+                rewards: Rewards::new(world, dbn, known_count),
+            },
+            Structure::Learnt {
+                max_parents,
+                tolerance,
+            } => Model::Learnt(StructureLearner::new(
+                world,
+                max_parents,
+                known_count,
+                tolerance,
+            )),
         };
 
+        let rmax = world.max_value(gamma);
+
         FactoredRMax {
             state_indexer,
             rmax,
 
-            transitions,
-            rewards,
+            model,
 
             value_table,
 
@@ -336,176 +288,142 @@ impl FactoredRMax {
         next_state: &State,
         reward: f64,
     ) {
-        let x_index = state.get_taxi().x as usize;
-        let y_index = state.get_taxi().y as usize;
-
-        let x_parent_index = self
-            .transitions
-            .generate_x_parent_index(world, action, x_index, y_index);
-
-        let y_parent_index = self
-            .transitions
-            .generate_y_parent_index(world, action, y_index);
-
-        if let Some(passenger_index) = generate_passenger_index(world, state) {
-            if let Some(destination_index) = generate_destination_index(world, state) {
-                let passenger_parent_index = self.transitions.generate_passenger_parent_index(
-                    world,
-                    action,
-                    x_index,
-                    y_index,
-                    passenger_index,
-                    destination_index,
-                );
-
-                let destination_parent_index = self.transitions.generate_destination_parent_index(
-                    world,
-                    action,
-                    destination_index,
-                );
-
-                if !self.transitions.is_known(x_parent_index)
-                    || !self.transitions.is_known(y_parent_index)
-                    || !self.transitions.is_known(passenger_parent_index)
-                    || !self.transitions.is_known(destination_parent_index)
+        let values = match FactorValues::from_state(world, state) {
+            Some(values) => values,
+            None => return,
+        };
+        let next_values = FactorValues::from_state(world, next_state);
+
+        match self.model {
+            Model::Fixed {
+                ref mut transitions,
+                ref mut rewards,
+            } => {
+                let parent_indices = transitions.generate_parent_indices(world, action, &values);
+
+                if parent_indices
+                    .iter()
+                    .any(|&parent_index| !transitions.is_known(parent_index))
                 {
-                    let next_x_index = next_state.get_taxi().x as usize;
-                    self.transitions
-                        .apply_experience(x_parent_index, next_x_index);
-
-                    let next_y_index = next_state.get_taxi().y as usize;
-                    self.transitions
-                        .apply_experience(y_parent_index, next_y_index);
-
-                    if let Some(next_passenger_index) = generate_passenger_index(world, next_state)
-                    {
-                        self.transitions
-                            .apply_experience(passenger_parent_index, next_passenger_index);
-
-                        if let Some(next_destination_index) =
-                            generate_destination_index(world, next_state)
-                        {
-                            self.transitions
-                                .apply_experience(destination_parent_index, next_destination_index);
+                    if let Some(next_values) = next_values {
+                        for &factor in Factor::ALL.iter() {
+                            transitions.apply_experience(
+                                parent_indices[factor.to_index()],
+                                next_values.get(factor),
+                            );
                         }
                     }
                 }
 
-                let reward_parent_index = generate_reward_parent_index(
-                    world,
-                    action,
-                    x_index,
-                    y_index,
-                    passenger_index,
-                    destination_index,
-                );
-
-                self.rewards
-                    .apply_experience(reward, action, reward_parent_index);
+                rewards.apply_experience(world, reward, action, &values);
+            }
+
+            Model::Learnt(ref mut learner) => {
+                if let Some(next_values) = next_values {
+                    learner.apply_experience(world, action, &values, &next_values, reward);
+                }
             }
         }
     }
 
-    fn predict_transition(
+    // The chance of each next value of each factor after taking action, None
+    // if any of them is not known.
+    fn predict_factors(
         &self,
         world: &World,
-        x_parent_index: usize,
-        y_parent_index: usize,
-        passenger_parent_index: usize,
-        destination_parent_index: usize,
-        next_state: &State,
+        values: &FactorValues,
+        action: Actions,
+    ) -> Option<[Estimate<'_>; Factor::NUM_ELEMENTS]> {
+        let mut estimates = [Estimate::default(); Factor::NUM_ELEMENTS];
+
+        match self.model {
+            Model::Fixed {
+                ref transitions, ..
+            } => {
+                let parent_indices = transitions.generate_parent_indices(world, action, values);
+
+                for &factor in Factor::ALL.iter() {
+                    estimates[factor.to_index()] = transitions
+                        .estimate(parent_indices[factor.to_index()], factor.num_values(world))?;
+                }
+            }
+
+            Model::Learnt(ref learner) => {
+                for &factor in Factor::ALL.iter() {
+                    estimates[factor.to_index()] =
+                        learner.predict_transition(world, action, factor, values)?;
+                }
+            }
+        }
+
+        Some(estimates)
+    }
+
+    fn predict_factor_reward(
+        &self,
+        world: &World,
+        values: &FactorValues,
+        action: Actions,
     ) -> Option<f64> {
-        let next_x_index = next_state.get_taxi().x as usize;
-        let x_transition = self
-            .transitions
-            .get_transition(x_parent_index, next_x_index)?;
+        match self.model {
+            Model::Fixed { ref rewards, .. } => rewards.get_reward(world, action, values),
+            Model::Learnt(ref learner) => learner.predict_reward(world, action, values),
+        }
+    }
 
-        let next_y_index = next_state.get_taxi().y as usize;
-        let y_transition = self
-            .transitions
-            .get_transition(y_parent_index, next_y_index)?;
+    // The chance the model gives next_state after taking action in state,
+    // None if any part of it is not known.
+    fn predict_next_state(
+        &self,
+        world: &World,
+        state: &State,
+        action: Actions,
+        next_state: &State,
+    ) -> Option<f64> {
+        let values = FactorValues::from_state(world, state)?;
+        let estimates = self.predict_factors(world, &values, action)?;
+        let next_values = FactorValues::from_state(world, next_state)?;
 
-        let next_passenger_index = generate_passenger_index(world, next_state)?;
-        let passenger_transition = self
-            .transitions
-            .get_transition(passenger_parent_index, next_passenger_index)?;
+        Some(predict_transition(&estimates, &next_values))
+    }
 
-        let next_destination_index = generate_destination_index(world, next_state)?;
-        let destination_transition = self
-            .transitions
-            .get_transition(destination_parent_index, next_destination_index)?;
+    fn predict_reward(&self, world: &World, state: &State, action: Actions) -> Option<f64> {
+        let values = FactorValues::from_state(world, state)?;
 
-        Some(x_transition * y_transition * destination_transition * passenger_transition)
+        self.predict_factor_reward(world, &values, action)
     }
 
+    // An action whose reward or next state is not known is taken to earn
+    // rmax and stay put, as the parents of the two need not be known
+    // together.
     fn measure_value(&self, world: &World, state: &State, action: Actions) -> f64 {
-        if let Some(passenger_index) = generate_passenger_index(world, state) {
-            if let Some(destination_index) = generate_destination_index(world, state) {
-                let x_index = state.get_taxi().x as usize;
-                let y_index = state.get_taxi().y as usize;
-
-                let reward = match self.rewards.get_reward(
-                    world,
-                    action,
-                    x_index,
-                    y_index,
-                    passenger_index,
-                    destination_index,
-                ) {
-                    Some(reward) => reward,
-                    None => self.rmax,
-                };
-
-                let x_parent_index = self
-                    .transitions
-                    .generate_x_parent_index(world, action, x_index, y_index);
-
-                let y_parent_index = self
-                    .transitions
-                    .generate_y_parent_index(world, action, y_index);
-
-                let passenger_parent_index = self.transitions.generate_passenger_parent_index(
-                    world,
-                    action,
-                    x_index,
-                    y_index,
-                    passenger_index,
-                    destination_index,
-                );
-
-                let destination_parent_index = self.transitions.generate_destination_parent_index(
-                    world,
-                    action,
-                    destination_index,
-                );
+        let values = FactorValues::from_state(world, state).expect("Failed to find factor values");
 
+        let reward = self.predict_factor_reward(world, &values, action);
+        let estimates = self.predict_factors(world, &values, action);
+
+        match (reward, estimates) {
+            (Some(reward), Some(estimates)) => {
                 let mut action_value = reward;
 
                 for next_state in StateIterator::new(world) {
-                    if let Some(transition) = self.predict_transition(
-                        world,
-                        x_parent_index,
-                        y_parent_index,
-                        passenger_parent_index,
-                        destination_parent_index,
-                        &next_state,
-                    ) {
+                    let next_values = FactorValues::from_state(world, &next_state).unwrap();
+
+                    let transition = predict_transition(&estimates, &next_values);
+                    if transition > 0.0 {
                         let next_state_index =
                             self.state_indexer.get_index(world, &next_state).unwrap();
                         action_value +=
                             transition * self.gamma * self.value_table[next_state_index];
-                    } else if *state == next_state {
-                        let next_state_index =
-                            self.state_indexer.get_index(world, &next_state).unwrap();
-                        action_value += self.gamma * self.value_table[next_state_index];
                     }
                 }
+
                 action_value
-            } else {
-                panic!("Failed to find destination_index")
             }
-        } else {
-            panic!("Failed to find passenger_index");
+            _ => {
+                let state_index = self.state_indexer.get_index(world, state).unwrap();
+                self.rmax + self.gamma * self.value_table[state_index]
+            }
         }
     }
 
@@ -677,7 +595,22 @@ impl Runner for FactoredRMax {
         state.at_destination()
     }
 
+    fn evaluate_model(&self, world: &World) -> Option<ModelAccuracy> {
+        Some(measure_model_accuracy(
+            world,
+            |state, action, next_state| self.predict_next_state(world, state, action, next_state),
+            |state, action| self.predict_reward(world, state, action),
+        ))
+    }
+
     fn report_training_result(&self, world: &World, _steps: Option<usize>) {
+        if let Model::Learnt(ref learner) = self.model {
+            println!("Learnt structure:");
+            for description in learner.describe() {
+                println!("{}", description);
+            }
+        }
+
         let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
 
         let num_states = self.state_indexer.num_states();
@@ -703,150 +636,59 @@ impl Runner for FactoredRMax {
     }
 }
 
-fn num_x_parents(world: &World, action: Actions) -> usize {
-    match action {
-        Actions::East | Actions::West => (world.width * world.height) as usize,
-        _ => world.width as usize,
-    }
+fn transition_index(action: Actions, factor: Factor) -> usize {
+    action.to_index() * Factor::NUM_ELEMENTS + factor.to_index()
 }
 
-fn total_x_parents(world: &World) -> usize {
-    2 * (world.width * world.height) as usize + (Actions::NUM_ELEMENTS - 2) * world.width as usize
-}
-
-fn num_y_parents(world: &World, _: Actions) -> usize {
-    world.height as usize
-}
+fn total_variable_parents(world: &World, dbn: &Dbn) -> usize {
+    let mut total = 0;
+    for action_index in 0..Actions::NUM_ELEMENTS {
+        let action = Actions::from_index(action_index).unwrap();
 
-fn total_y_parents(world: &World) -> usize {
-    Actions::NUM_ELEMENTS * world.height as usize
-}
-
-fn generate_passenger_index(world: &World, state: &State) -> Option<usize> {
-    match state.get_passenger() {
-        None => Some(0),
-        Some(passenger_id) => world.get_fixed_index(passenger_id).map(|i| i + 1),
-    }
-}
-
-fn num_passenger_parents(world: &World, action: Actions) -> usize {
-    let num_destination_states = world.num_fixed_positions() as usize;
-    let num_passenger_states = (num_destination_states + 1) as usize;
-
-    let num_taxi_states = (world.height * world.width) as usize;
-
-    match action {
-        Actions::DropOff => num_taxi_states * num_destination_states * num_passenger_states,
-        Actions::PickUp => num_taxi_states * num_passenger_states,
-        _ => num_passenger_states,
+        for &factor in Factor::ALL.iter() {
+            total += dbn
+                .transition_parents(action, factor)
+                .num_configurations(world);
+        }
     }
-}
-
-fn total_passenger_parents(world: &World) -> usize {
-    let num_destination_states = world.num_fixed_positions() as usize;
-    let num_passenger_states = (num_destination_states + 1) as usize;
-
-    let num_taxi_states = (world.height * world.width) as usize;
-
-    num_taxi_states * num_destination_states * num_passenger_states
-        + num_taxi_states * num_passenger_states
-        + (Actions::NUM_ELEMENTS - 2) * num_passenger_states
-}
-
-fn num_destination_parents(world: &World, _: Actions) -> usize {
-    world.num_fixed_positions()
-}
-
-fn total_destination_parents(world: &World) -> usize {
-    Actions::NUM_ELEMENTS * world.num_fixed_positions()
-}
-
-fn generate_destination_index(world: &World, state: &State) -> Option<usize> {
-    world.get_fixed_index(state.get_destination())
-}
-
-fn total_variable_parents(world: &World) -> usize {
-    total_x_parents(world)
-        + total_y_parents(world)
-        + total_passenger_parents(world)
-        + total_destination_parents(world)
-}
-
-fn total_transitions(world: &World) -> usize {
-    let num_x_states = world.width as usize;
-    let num_y_states = world.height as usize;
-    let num_destination_states = world.num_fixed_positions();
-    let num_passenger_states = num_destination_states + 1;
 
-    total_x_parents(world) * num_x_states
-        + total_y_parents(world) * num_y_states
-        + total_passenger_parents(world) * num_passenger_states
-        + total_destination_parents(world) * num_destination_states
+    total
 }
 
-fn num_reward_parents(world: &World, action: Actions) -> usize {
-    let num_taxi_values = (world.width * world.height) as usize;
-    let num_destination_values = world.num_fixed_positions();
-    let num_passenger_values = num_destination_values + 1;
-    match action {
-        Actions::North | Actions::South | Actions::East | Actions::West => num_taxi_values,
+fn total_transitions(world: &World, dbn: &Dbn) -> usize {
+    let mut total = 0;
+    for action_index in 0..Actions::NUM_ELEMENTS {
+        let action = Actions::from_index(action_index).unwrap();
 
-        Actions::PickUp => num_passenger_values * num_taxi_values,
-
-        Actions::DropOff => num_destination_values * num_passenger_values * num_taxi_values,
+        for &factor in Factor::ALL.iter() {
+            total += dbn
+                .transition_parents(action, factor)
+                .num_configurations(world)
+                * factor.num_values(world);
+        }
     }
-}
-
-fn total_reward_parents(world: &World) -> usize {
-    let num_taxi_values = (world.width * world.height) as usize;
-    let num_destination_values = world.num_fixed_positions();
-    let num_passenger_values = num_destination_values + 1;
 
-    num_taxi_values * 4
-        + num_passenger_values * num_taxi_values
-        + num_destination_values * num_passenger_values * num_taxi_values
+    total
 }
 
-fn generate_reward_parent_index(
-    world: &World,
-    action: Actions,
-    x_index: usize,
-    y_index: usize,
-    passenger_index: usize,
-    destination_index: usize,
-) -> usize {
-    match action {
-        Actions::North | Actions::South | Actions::East | Actions::West => {
-            y_index * (world.width as usize) + x_index
-        }
-
-        Actions::PickUp => {
-            let mut result = passenger_index;
-
-            result *= world.height as usize;
-            result += y_index;
-
-            result *= world.width as usize;
-            result += x_index;
-
-            result
-        }
-
-        Actions::DropOff => {
-            let mut result = destination_index;
-
-            result *= world.num_fixed_positions() + 1;
-            result += passenger_index;
-
-            result *= world.height as usize;
-            result += y_index;
-
-            result *= world.width as usize;
-            result += x_index;
+fn total_reward_parents(world: &World, dbn: &Dbn) -> usize {
+    (0..Actions::NUM_ELEMENTS)
+        .map(|action_index| {
+            let action = Actions::from_index(action_index).unwrap();
+            dbn.reward_parents(action).num_configurations(world)
+        })
+        .sum()
+}
 
-            result
-        }
-    }
+// The chance of next_values from the chance of each factor's next value.
+fn predict_transition(
+    estimates: &[Estimate; Factor::NUM_ELEMENTS],
+    next_values: &FactorValues,
+) -> f64 {
+    Factor::ALL
+        .iter()
+        .map(|&factor| estimates[factor.to_index()].get(next_values.get(factor)))
+        .product()
 }
 
 #[cfg(test)]
@@ -867,9 +709,10 @@ mod test_factoredrmax {
         let costs = Costs::default();
         let world = World::build_from_str(world_str, costs).unwrap();
 
-        let mut factoredrmax = FactoredRMax::new(&world, 0.3, 1.0, 1.0e-6);
+        let mut factoredrmax =
+            FactoredRMax::new(&world, Structure::Fixed(Dbn::taxi()), 0.3, 1.0, 1.0e-6);
 
-        let state = State::build(&world, (0, 1), Some('R'), 'G').unwrap();
+        let state = State::build_named(&world, (0, 1), Some("R"), "G").unwrap();
 
         let expected_initial_state = "\
                                       ┌───┐\n\
@@ -887,4 +730,146 @@ mod test_factoredrmax {
         let result = factoredrmax.learn(&world, state, 100, &mut rng);
         assert!(result.is_some());
     }
+
+    #[test]
+    fn lays_out_tables_by_declared_parents() {
+        let world_str = "\
+                         ┌───┐\n\
+                         │R .│\n\
+                         │   │\n\
+                         │. G│\n\
+                         └───┘\n\
+                         ";
+        let world = World::build_from_str(world_str, Costs::default()).unwrap();
+
+        let mut dbn = Dbn::taxi();
+        dbn.set_transition_parents(
+            Actions::North,
+            Factor::X,
+            Parents::new(&[Factor::X, Factor::Y]),
+        );
+        dbn.set_reward_parents(Actions::North, Parents::default());
+
+        let taxi_transitions = Transitions::new(&world, Dbn::taxi(), 1.0);
+        let transitions = Transitions::new(&world, dbn, 1.0);
+
+        // North's x has a parent index for each of the 4 cells rather than
+        // each of the 2 columns, each with a chance for every column, and
+        // the tables after it move along to make room.
+        assert_eq!(
+            transitions.occurences.len(),
+            taxi_transitions.occurences.len() + 2
+        );
+        assert_eq!(
+            transitions.transitions.len(),
+            taxi_transitions.transitions.len() + 4
+        );
+        let north_y = transition_index(Actions::North, Factor::Y);
+        assert_eq!(
+            transitions.parent_index_starts[north_y],
+            taxi_transitions.parent_index_starts[north_y] + 2
+        );
+
+        // North's reward is the same everywhere, so it has one entry rather
+        // than one for each cell.
+        let taxi_rewards = Rewards::new(&world, Dbn::taxi(), 1.0);
+        let rewards = Rewards::new(&world, dbn, 1.0);
+        assert_eq!(rewards.rewards.len(), taxi_rewards.rewards.len() - 3);
+        assert_eq!(rewards.reward_starts[Actions::South.to_index()], 1);
+    }
+
+    #[test]
+    fn learns_with_declared_structure() {
+        let world_str = "\
+                         ┌───┐\n\
+                         │R .│\n\
+                         │   │\n\
+                         │. G│\n\
+                         └───┘\n\
+                         ";
+        let world = World::build_from_str(world_str, Costs::default()).unwrap();
+
+        // Every move costs the same here, wherever the taxi is.
+        let mut dbn = Dbn::taxi();
+        for &action in &[Actions::North, Actions::South, Actions::East, Actions::West] {
+            dbn.set_reward_parents(action, Parents::default());
+        }
+        assert!(dbn.validate(&world).is_ok());
+
+        let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
+        let state = State::build_named(&world, (0, 1), Some("R"), "G").unwrap();
+
+        let mut taxi = FactoredRMax::new(&world, Structure::Fixed(Dbn::taxi()), 0.3, 1.0, 1.0e-6);
+        let mut declared = FactoredRMax::new(&world, Structure::Fixed(dbn), 0.3, 1.0, 1.0e-6);
+        assert!(taxi.learn(&world, state, 100, &mut rng.clone()).is_some());
+        assert!(declared.learn(&world, state, 100, &mut rng).is_some());
+
+        let taxi_rewards = taxi.evaluate_model(&world).unwrap().rewards;
+        let declared_rewards = declared.evaluate_model(&world).unwrap().rewards;
+        assert_eq!(declared_rewards.wrong, 0);
+        assert!(declared_rewards.unknown < taxi_rewards.unknown);
+    }
+
+    #[test]
+    fn learns_with_learnt_structure() {
+        let world_str = "\
+                         ┌─────┐\n\
+                         │R . .│\n\
+                         │     │\n\
+                         │.│. G│\n\
+                         └─┴───┘\n\
+                         ";
+        let world = World::build_from_str(world_str, Costs::default()).unwrap();
+
+        // Dropping off depends on the taxi and the passenger together.
+        let structure = Structure::Learnt {
+            max_parents: 3,
+            tolerance: 0.1,
+        };
+        let mut factoredrmax = FactoredRMax::new(&world, structure, 0.3, 1.0, 1.0e-6);
+
+        let state = State::build_named(&world, (0, 1), Some("R"), "G").unwrap();
+        let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
+
+        let result = factoredrmax.learn(&world, state, 200, &mut rng);
+        assert!(result.is_some());
+
+        // Hypotheses only predict once those left agree, which in a
+        // deterministic world is once the wrong ones are ruled out.
+        let learnt = factoredrmax.evaluate_model(&world).unwrap();
+        assert!(learnt.transitions.correct > 0);
+        assert_eq!(learnt.transitions.wrong, 0);
+    }
+
+    #[test]
+    fn measures_model_accuracy() {
+        let world_str = "\
+                         ┌───┐\n\
+                         │R .│\n\
+                         │   │\n\
+                         │. G│\n\
+                         └───┘\n\
+                         ";
+        let world = World::build_from_str(world_str, Costs::default()).unwrap();
+
+        let mut factoredrmax =
+            FactoredRMax::new(&world, Structure::Fixed(Dbn::taxi()), 0.3, 1.0, 1.0e-6);
+
+        let initial = factoredrmax.evaluate_model(&world).unwrap();
+        assert_eq!(initial.transitions.correct, 0);
+        assert_eq!(initial.transitions.unknown, initial.transitions.total());
+        assert_eq!(initial.rewards.unknown, initial.rewards.total());
+
+        let state = State::build_named(&world, (0, 1), Some("R"), "G").unwrap();
+        let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
+        factoredrmax.learn(&world, state, 100, &mut rng);
+
+        // What has been learnt is right, in a deterministic world.
+        let learnt = factoredrmax.evaluate_model(&world).unwrap();
+        assert!(learnt.transitions.correct > 0);
+        assert_eq!(learnt.transitions.wrong, 0);
+        assert!(learnt.rewards.correct > 0);
+        assert_eq!(learnt.rewards.wrong, 0);
+        assert_eq!(learnt.transitions.total(), initial.transitions.total());
+    }
 }
@@ -0,0 +1,430 @@
+use std::fmt;
+
+use crate::actions::Actions;
+use crate::state::State;
+use crate::world::World;
+
+// The variables a taxi state is factored into.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Factor {
+    X,
+    Y,
+    Passenger,
+    Destination,
+}
+
+impl Factor {
+    pub const NUM_ELEMENTS: usize = 4;
+
+    pub const ALL: [Factor; Factor::NUM_ELEMENTS] =
+        [Factor::X, Factor::Y, Factor::Passenger, Factor::Destination];
+
+    pub fn to_index(self) -> usize {
+        match self {
+            Factor::X => 0,
+            Factor::Y => 1,
+            Factor::Passenger => 2,
+            Factor::Destination => 3,
+        }
+    }
+
+    pub fn num_values(self, world: &World) -> usize {
+        match self {
+            Factor::X => world.width as usize,
+            Factor::Y => world.height as usize,
+            Factor::Passenger => world.num_fixed_positions() + 1,
+            Factor::Destination => world.num_fixed_positions(),
+        }
+    }
+}
+
+impl fmt::Display for Factor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Factor::X => write!(f, "x"),
+            Factor::Y => write!(f, "y"),
+            Factor::Passenger => write!(f, "passenger"),
+            Factor::Destination => write!(f, "destination"),
+        }
+    }
+}
+
+// The value of each factor in a state.  The passenger is 0 in the taxi,
+// otherwise one more than the index of its fixed position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FactorValues([usize; Factor::NUM_ELEMENTS]);
+
+impl FactorValues {
+    pub fn from_state(world: &World, state: &State) -> Option<FactorValues> {
+        let passenger = match state.get_passenger() {
+            None => 0,
+            Some(passenger_id) => world.get_fixed_index(passenger_id)? + 1,
+        };
+        let destination = world.get_fixed_index(state.get_destination())?;
+
+        Some(FactorValues([
+            state.get_taxi().x as usize,
+            state.get_taxi().y as usize,
+            passenger,
+            destination,
+        ]))
+    }
+
+    pub fn get(&self, factor: Factor) -> usize {
+        self.0[factor.to_index()]
+    }
+}
+
+// A set of factors, as one bit for each factor index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Parents(u8);
+
+impl Parents {
+    pub fn new(factors: &[Factor]) -> Parents {
+        Parents(
+            factors
+                .iter()
+                .fold(0, |bits, factor| bits | 1 << factor.to_index()),
+        )
+    }
+
+    // Every set of at most max_parents factors, smallest first.
+    pub fn up_to(max_parents: usize) -> Vec<Parents> {
+        let mut all: Vec<Parents> = (0..1 << Factor::NUM_ELEMENTS)
+            .map(Parents)
+            .filter(|parents| parents.len() <= max_parents)
+            .collect();
+        all.sort_by_key(|parents| parents.len());
+
+        all
+    }
+
+    pub fn contains(self, factor: Factor) -> bool {
+        self.0 & 1 << factor.to_index() != 0
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_subset(self, other: Parents) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    pub fn factors(self) -> impl Iterator<Item = Factor> {
+        Factor::ALL
+            .iter()
+            .cloned()
+            .filter(move |&factor| self.contains(factor))
+    }
+
+    pub fn num_configurations(self, world: &World) -> usize {
+        self.factors()
+            .map(|factor| factor.num_values(world))
+            .product()
+    }
+
+    // As num_configurations, None if there are more than can be counted.
+    fn checked_num_configurations(self, world: &World) -> Option<usize> {
+        self.factors().try_fold(1usize, |total, factor| {
+            total.checked_mul(factor.num_values(world))
+        })
+    }
+
+    // The index of the parents' values among all their configurations.
+    pub fn configuration(self, world: &World, values: &FactorValues) -> usize {
+        self.factors().fold(0, |index, factor| {
+            index * factor.num_values(world) + values.get(factor)
+        })
+    }
+}
+
+impl fmt::Display for Parents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (index, factor) in self.factors().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", factor)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+// The parents of each factor's next value, and of the reward, for each
+// action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dbn {
+    transitions: [[Parents; Factor::NUM_ELEMENTS]; Actions::NUM_ELEMENTS],
+    rewards: [Parents; Actions::NUM_ELEMENTS],
+}
+
+#[derive(Clone, PartialEq)]
+pub enum DbnError {
+    // The world gives the factor no values, so it can neither be predicted
+    // nor be a parent.
+    NoValues {
+        factor: Factor,
+    },
+
+    // The parents of factor, or of the reward when None, have more
+    // configurations in the world than can be counted.
+    TooManyConfigurations {
+        action: Actions,
+        factor: Option<Factor>,
+        parents: Parents,
+    },
+}
+
+impl fmt::Display for DbnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbnError::NoValues { factor } => write!(
+                f,
+                "{} has no values in this world, so it can neither be predicted nor be a parent.",
+                factor
+            ),
+            DbnError::TooManyConfigurations {
+                action,
+                factor,
+                parents,
+            } => {
+                let target = match factor {
+                    Some(factor) => factor.to_string(),
+                    None => String::from("the reward"),
+                };
+                write!(
+                    f,
+                    "{} after {:?} has parents {} with more configurations than can be counted.",
+                    target, action, parents
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DbnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Dbn {
+    // The parents the taxi world is known to have.  Going east or west can
+    // be blocked by a wall in one row and not another, and picking up and
+    // dropping off depend on where the taxi is.  Moves depend on the taxi's
+    // cell for their reward, so the cost of terrain is learnt with the rest.
+    pub fn taxi() -> Dbn {
+        let x = Parents::new(&[Factor::X]);
+        let y = Parents::new(&[Factor::Y]);
+        let passenger = Parents::new(&[Factor::Passenger]);
+        let destination = Parents::new(&[Factor::Destination]);
+        let cell = Parents::new(&[Factor::X, Factor::Y]);
+        let pick_up = Parents::new(&[Factor::X, Factor::Y, Factor::Passenger]);
+        let every_factor = Parents::new(&Factor::ALL);
+
+        let mut transitions = [[x, y, passenger, destination]; Actions::NUM_ELEMENTS];
+        transitions[Actions::East.to_index()][Factor::X.to_index()] = cell;
+        transitions[Actions::West.to_index()][Factor::X.to_index()] = cell;
+        transitions[Actions::PickUp.to_index()][Factor::Passenger.to_index()] = pick_up;
+        transitions[Actions::DropOff.to_index()][Factor::Passenger.to_index()] = every_factor;
+
+        let mut rewards = [cell; Actions::NUM_ELEMENTS];
+        rewards[Actions::PickUp.to_index()] = pick_up;
+        rewards[Actions::DropOff.to_index()] = every_factor;
+
+        Dbn {
+            transitions,
+            rewards,
+        }
+    }
+
+    pub fn transition_parents(&self, action: Actions, factor: Factor) -> Parents {
+        self.transitions[action.to_index()][factor.to_index()]
+    }
+
+    pub fn set_transition_parents(&mut self, action: Actions, factor: Factor, parents: Parents) {
+        self.transitions[action.to_index()][factor.to_index()] = parents;
+    }
+
+    pub fn reward_parents(&self, action: Actions) -> Parents {
+        self.rewards[action.to_index()]
+    }
+
+    pub fn set_reward_parents(&mut self, action: Actions, parents: Parents) {
+        self.rewards[action.to_index()] = parents;
+    }
+
+    // Checks the tables the parents need can be laid out for the world's
+    // factors.
+    pub fn validate(&self, world: &World) -> Result<(), Vec<DbnError>> {
+        let mut errors: Vec<DbnError> = Factor::ALL
+            .iter()
+            .filter(|factor| factor.num_values(world) == 0)
+            .map(|&factor| DbnError::NoValues { factor })
+            .collect();
+
+        if errors.is_empty() {
+            for action_index in 0..Actions::NUM_ELEMENTS {
+                let action = Actions::from_index(action_index).unwrap();
+
+                for &factor in Factor::ALL.iter() {
+                    let parents = self.transition_parents(action, factor);
+                    let size =
+                        parents
+                            .checked_num_configurations(world)
+                            .and_then(|configurations| {
+                                configurations.checked_mul(factor.num_values(world))
+                            });
+
+                    if size.is_none() {
+                        errors.push(DbnError::TooManyConfigurations {
+                            action,
+                            factor: Some(factor),
+                            parents,
+                        });
+                    }
+                }
+
+                let parents = self.reward_parents(action);
+                if parents.checked_num_configurations(world).is_none() {
+                    errors.push(DbnError::TooManyConfigurations {
+                        action,
+                        factor: None,
+                        parents,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Default for Dbn {
+    fn default() -> Self {
+        Dbn::taxi()
+    }
+}
+
+// What was seen under one parent configuration: how often, and the sum for
+// each outcome, so each outcome's chance, or the mean reward.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate<'a> {
+    sums: &'a [f64],
+    occurences: f64,
+}
+
+impl<'a> Estimate<'a> {
+    pub fn new(sums: &'a [f64], occurences: f64) -> Estimate<'a> {
+        Estimate { sums, occurences }
+    }
+
+    pub fn get(&self, outcome: usize) -> f64 {
+        self.sums[outcome] / self.occurences
+    }
+
+    // Whether any outcome is further than tolerance from other's.
+    pub fn differs(&self, other: &Estimate, tolerance: f64) -> bool {
+        (0..self.sums.len())
+            .any(|outcome| (self.get(outcome) - other.get(outcome)).abs() > tolerance)
+    }
+}
+
+#[cfg(test)]
+mod test_dbn {
+    use super::*;
+    use crate::state::StateIterator;
+    use crate::world::Costs;
+
+    fn build_world() -> World {
+        let world_str = "\
+                         ┌───┐\n\
+                         │R .│\n\
+                         │   │\n\
+                         │. G│\n\
+                         └───┘\n\
+                         ";
+        World::build_from_str(world_str, Costs::default()).unwrap()
+    }
+
+    #[test]
+    fn configurations_cover_parent_values() {
+        let world = build_world();
+
+        let parents = Parents::new(&[Factor::Passenger, Factor::X]);
+        assert_eq!(parents.to_string(), "{x, passenger}");
+        assert_eq!(parents.num_configurations(&world), 6);
+
+        let mut seen = vec![false; parents.num_configurations(&world)];
+        for state in StateIterator::new(&world) {
+            let values = FactorValues::from_state(&world, &state).unwrap();
+            seen[parents.configuration(&world, &values)] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+
+        assert!(Dbn::taxi().validate(&world).is_ok());
+
+        let candidates = Parents::up_to(2);
+        assert_eq!(candidates.len(), 11);
+        assert!(candidates[0].is_empty());
+        assert!(candidates.iter().all(|candidate| candidate.len() <= 2));
+        assert!(Parents::new(&[Factor::X]).is_subset(parents));
+        assert!(!parents.is_subset(Parents::new(&[Factor::X])));
+    }
+
+    #[test]
+    fn rejects_factors_without_values() {
+        let mut world = build_world();
+        world.width = 0;
+
+        let errors = Dbn::taxi().validate(&world).unwrap_err();
+        assert_eq!(errors, vec![DbnError::NoValues { factor: Factor::X }]);
+        assert_eq!(
+            errors[0].to_string(),
+            "x has no values in this world, so it can neither be predicted nor be a parent."
+        );
+    }
+
+    #[test]
+    fn rejects_parents_with_too_many_configurations() {
+        let mut world = build_world();
+        world.width = i32::MAX;
+        world.height = i32::MAX;
+
+        // A cell fits, but not a cell and its next x, nor every factor.
+        let errors = Dbn::taxi().validate(&world).unwrap_err();
+        let cell = Parents::new(&[Factor::X, Factor::Y]);
+        assert!(errors.contains(&DbnError::TooManyConfigurations {
+            action: Actions::East,
+            factor: Some(Factor::X),
+            parents: cell,
+        }));
+        assert!(errors.contains(&DbnError::TooManyConfigurations {
+            action: Actions::DropOff,
+            factor: None,
+            parents: Parents::new(&Factor::ALL),
+        }));
+        assert!(!errors.iter().any(|error| matches!(
+            error,
+            DbnError::TooManyConfigurations {
+                action: Actions::North,
+                ..
+            }
+        )));
+        assert_eq!(
+            errors[0].to_string(),
+            "x after East has parents {x, y} with more configurations than can be counted."
+        );
+    }
+}
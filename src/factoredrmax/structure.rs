@@ -0,0 +1,371 @@
+use crate::actions::Actions;
+use crate::world::World;
+
+use super::dbn::{Estimate, Factor, FactorValues, Parents};
+use super::transition_index;
+
+// What one candidate parent set has seen of a factor's next value, or of
+// the reward, under each of its configurations.
+#[derive(Debug, Clone)]
+struct Candidate {
+    parents: Parents,
+    occurences: Vec<f64>, // configuration
+    sums: Vec<f64>,       // configuration * num_outcomes + outcome
+    num_outcomes: usize,
+
+    refuted: bool,
+}
+
+impl Candidate {
+    fn new(world: &World, parents: Parents, num_outcomes: usize) -> Candidate {
+        let num_configurations = parents.num_configurations(world);
+
+        Candidate {
+            parents,
+            occurences: vec![0.0; num_configurations],
+            sums: vec![0.0; num_configurations * num_outcomes],
+            num_outcomes,
+
+            refuted: false,
+        }
+    }
+
+    fn estimate(&self, configuration: usize, known_count: f64) -> Option<Estimate<'_>> {
+        let occurences = self.occurences[configuration];
+
+        if occurences >= known_count {
+            let start = configuration * self.num_outcomes;
+            Some(Estimate::new(
+                &self.sums[start..start + self.num_outcomes],
+                occurences,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// The candidate parent sets of one factor, or of the reward, for one
+// action, smallest first.  The first num_hypotheses are those of at most
+// max_parents factors.  The set of every factor is always the last, to rule
+// the others out against, and to fall back on once they all have been.
+#[derive(Debug, Clone)]
+struct Candidates {
+    candidates: Vec<Candidate>,
+    num_hypotheses: usize,
+}
+
+impl Candidates {
+    fn new(world: &World, max_parents: usize, num_outcomes: usize) -> Candidates {
+        let mut all_parents = Parents::up_to(max_parents);
+        let num_hypotheses = all_parents.len();
+        if max_parents < Factor::NUM_ELEMENTS {
+            all_parents.push(Parents::new(&Factor::ALL));
+        }
+
+        Candidates {
+            candidates: all_parents
+                .into_iter()
+                .map(|parents| Candidate::new(world, parents, num_outcomes))
+                .collect(),
+            num_hypotheses,
+        }
+    }
+
+    // Adds amount to the outcome under each candidate's configuration, then
+    // rules out the candidates that no longer agree with a larger one.  A
+    // set holding every parent predicts just as its supersets do, so only
+    // sets missing some parent can disagree, once both have seen enough.
+    fn apply_experience(
+        &mut self,
+        world: &World,
+        values: &FactorValues,
+        outcome: usize,
+        amount: f64,
+        known_count: f64,
+        tolerance: f64,
+    ) {
+        let configurations: Vec<usize> = self
+            .candidates
+            .iter()
+            .map(|candidate| candidate.parents.configuration(world, values))
+            .collect();
+
+        for (candidate, &configuration) in self.candidates.iter_mut().zip(&configurations) {
+            candidate.occurences[configuration] += 1.0;
+            candidate.sums[configuration * candidate.num_outcomes + outcome] += amount;
+        }
+
+        let mut refuted = Vec::new();
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            if candidate.refuted {
+                continue;
+            }
+
+            let estimate = match candidate.estimate(configurations[index], known_count) {
+                Some(estimate) => estimate,
+                None => continue,
+            };
+
+            let disagrees = self
+                .candidates
+                .iter()
+                .enumerate()
+                .any(|(other_index, other)| {
+                    other.parents != candidate.parents
+                        && candidate.parents.is_subset(other.parents)
+                        && other
+                            .estimate(configurations[other_index], known_count)
+                            .is_some_and(|other_estimate| {
+                                estimate.differs(&other_estimate, tolerance)
+                            })
+                });
+
+            if disagrees {
+                refuted.push(index);
+            }
+        }
+
+        for index in refuted {
+            self.candidates[index].refuted = true;
+        }
+    }
+
+    // As with k-meteorologists, the estimate is only known when every
+    // hypothesis left has seen enough of these values and they agree, so a
+    // parent set that only looks right where it has been tried does not
+    // stop the rest being explored.  The smallest one's estimate is given.
+    fn predict(
+        &self,
+        world: &World,
+        values: &FactorValues,
+        known_count: f64,
+        tolerance: f64,
+    ) -> Option<Estimate<'_>> {
+        let mut hypotheses = self.candidates[..self.num_hypotheses]
+            .iter()
+            .filter(|candidate| !candidate.refuted)
+            .map(|candidate| {
+                candidate.estimate(candidate.parents.configuration(world, values), known_count)
+            });
+
+        match hypotheses.next() {
+            Some(estimate) => {
+                let estimate = estimate?;
+                for other_estimate in hypotheses {
+                    if estimate.differs(&other_estimate?, tolerance) {
+                        return None;
+                    }
+                }
+
+                Some(estimate)
+            }
+            None => {
+                let every_factor = self.candidates.last().unwrap();
+                every_factor.estimate(
+                    every_factor.parents.configuration(world, values),
+                    known_count,
+                )
+            }
+        }
+    }
+
+    // The smallest hypothesis not ruled out, or every factor.
+    fn best(&self) -> Parents {
+        self.candidates[..self.num_hypotheses]
+            .iter()
+            .find(|candidate| !candidate.refuted)
+            .unwrap_or_else(|| self.candidates.last().unwrap())
+            .parents
+    }
+}
+
+// Learns which factors each factor's next value and the reward depend on,
+// for each action, in the manner of SLF-RMAX and Met-RMAX.  Every set of
+// at most max_parents factors is a hypothesis, counted alongside the
+// others, and is ruled out once it predicts differently from a superset.
+// What is learnt in one place carries over to others that no hypothesis
+// left tells apart.  Should a factor have more than max_parents parents,
+// every hypothesis is eventually ruled out and the whole state is used.
+#[derive(Debug, Clone)]
+pub struct StructureLearner {
+    transitions: Vec<Candidates>, // action_index * Factor::NUM_ELEMENTS + factor_index
+    rewards: Vec<Candidates>,     // action_index
+
+    known_count: f64,
+    tolerance: f64,
+}
+
+impl StructureLearner {
+    pub fn new(
+        world: &World,
+        max_parents: usize,
+        known_count: f64,
+        tolerance: f64,
+    ) -> StructureLearner {
+        let mut transitions = Vec::with_capacity(Actions::NUM_ELEMENTS * Factor::NUM_ELEMENTS);
+        for _ in 0..Actions::NUM_ELEMENTS {
+            for factor in Factor::ALL.iter() {
+                transitions.push(Candidates::new(
+                    world,
+                    max_parents,
+                    factor.num_values(world),
+                ));
+            }
+        }
+
+        let rewards = (0..Actions::NUM_ELEMENTS)
+            .map(|_| Candidates::new(world, max_parents, 1))
+            .collect();
+
+        StructureLearner {
+            transitions,
+            rewards,
+
+            known_count,
+            tolerance,
+        }
+    }
+
+    pub fn apply_experience(
+        &mut self,
+        world: &World,
+        action: Actions,
+        values: &FactorValues,
+        next_values: &FactorValues,
+        reward: f64,
+    ) {
+        for &factor in Factor::ALL.iter() {
+            let transition_index = transition_index(action, factor);
+            self.transitions[transition_index].apply_experience(
+                world,
+                values,
+                next_values.get(factor),
+                1.0,
+                self.known_count,
+                self.tolerance,
+            );
+        }
+
+        self.rewards[action.to_index()].apply_experience(
+            world,
+            values,
+            0,
+            reward,
+            self.known_count,
+            self.tolerance,
+        );
+    }
+
+    // The chance of each next value of factor.
+    pub fn predict_transition(
+        &self,
+        world: &World,
+        action: Actions,
+        factor: Factor,
+        values: &FactorValues,
+    ) -> Option<Estimate<'_>> {
+        self.transitions[transition_index(action, factor)].predict(
+            world,
+            values,
+            self.known_count,
+            self.tolerance,
+        )
+    }
+
+    pub fn predict_reward(
+        &self,
+        world: &World,
+        action: Actions,
+        values: &FactorValues,
+    ) -> Option<f64> {
+        self.rewards[action.to_index()]
+            .predict(world, values, self.known_count, self.tolerance)
+            .map(|estimate| estimate.get(0))
+    }
+
+    // A line for each action naming the parents it is thought to have.
+    pub fn describe(&self) -> Vec<String> {
+        (0..Actions::NUM_ELEMENTS)
+            .map(|action_index| {
+                let action = Actions::from_index(action_index).unwrap();
+
+                let mut parts: Vec<String> = Factor::ALL
+                    .iter()
+                    .map(|&factor| {
+                        describe_parents(
+                            &factor.to_string(),
+                            &self.transitions[transition_index(action, factor)],
+                        )
+                    })
+                    .collect();
+                parts.push(describe_parents("reward", &self.rewards[action_index]));
+
+                format!("{}: {}", action, parts.join(", "))
+            })
+            .collect()
+    }
+}
+
+fn describe_parents(name: &str, candidates: &Candidates) -> String {
+    format!("{} <- {}", name, candidates.best())
+}
+
+#[cfg(test)]
+mod test_structure {
+    use super::*;
+    use crate::state::StateIterator;
+    use crate::world::Costs;
+
+    #[test]
+    fn learns_parents_from_every_transition() {
+        let world_str = "\
+                         ┌─────┐\n\
+                         │R . .│\n\
+                         │     │\n\
+                         │.│. G│\n\
+                         └─┴───┘\n\
+                         ";
+        let world = World::build_from_str(world_str, Costs::default()).unwrap();
+
+        let mut learner = StructureLearner::new(&world, 2, 1.0, 0.1);
+
+        for state in StateIterator::new(&world) {
+            let values = FactorValues::from_state(&world, &state).unwrap();
+            for action_index in 0..Actions::NUM_ELEMENTS {
+                let action = Actions::from_index(action_index).unwrap();
+                let (reward, next_state) = state.apply_action(&world, action);
+                let next_values = FactorValues::from_state(&world, &next_state).unwrap();
+
+                learner.apply_experience(&world, action, &values, &next_values, reward);
+            }
+        }
+
+        let best = |action, factor| learner.transitions[transition_index(action, factor)].best();
+
+        // The wall between the bottom cells makes x depend on y going east.
+        assert_eq!(
+            best(Actions::East, Factor::X),
+            Parents::new(&[Factor::X, Factor::Y])
+        );
+        assert_eq!(best(Actions::North, Factor::X), Parents::new(&[Factor::X]));
+
+        // With two rows, going north always ends in the top one.
+        assert_eq!(best(Actions::North, Factor::Y), Parents::default());
+        assert_eq!(
+            best(Actions::East, Factor::Destination),
+            Parents::new(&[Factor::Destination])
+        );
+        assert_eq!(
+            learner.rewards[Actions::North.to_index()].best(),
+            Parents::default()
+        );
+
+        // Picking up depends on the taxi and passenger together, which two
+        // parents cannot capture, so every factor is needed.
+        assert_eq!(
+            best(Actions::PickUp, Factor::Passenger),
+            Parents::new(&Factor::ALL)
+        );
+    }
+}
@@ -3,21 +3,26 @@
 extern crate assert_matches;
 
 #[macro_use]
-extern crate enum_map;
+extern crate float_cmp;
 
 #[macro_use]
-extern crate float_cmp;
+extern crate serde_derive;
 
 pub mod actions;
 pub mod distribution;
 pub mod doormax;
 pub mod factoredrmax;
 pub mod maxq;
+pub mod oomdp;
 pub mod position;
 pub mod qlearner;
 pub mod random_solver;
+pub mod rlearner;
 pub mod rmax;
 pub mod runner;
+pub mod schedule;
+pub mod schemanet;
+pub mod start;
 pub mod state;
 pub mod state_indexer;
 pub mod world;
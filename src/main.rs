@@ -1,31 +1,47 @@
 #[macro_use]
 extern crate serde_derive;
 
+mod accuracy;
+mod cli;
 mod configuration;
+mod continuing;
 mod replay;
+mod sweep;
+mod transfer;
 
 use rand::Rng;
 use std::env;
 use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
 use std::time;
 
 use rand_pcg::Pcg64Mcg;
 
 use rayon::prelude::*;
 
-use crate::configuration::{Configuration, ReportConfig, SolverChoice};
+use crate::cli::{Arguments, Command, Options};
+use crate::configuration::{
+    find_profile, Configuration, GeneratorConfig, ReportConfig, SolverChoice,
+};
 
 use taxi::distribution::MeasureDistribution;
 use taxi::state::State;
-use taxi::world::{Costs, World};
+use taxi::world::generator::{generate_probes, Generator};
+use taxi::world::{Costs, Format, World};
 
-use taxi::doormax::DoorMax;
-use taxi::factoredrmax::FactoredRMax;
+use taxi::doormax::{DoorMax, EffectModel};
+use taxi::factoredrmax::{FactoredRMax, Structure};
 use taxi::maxq::MaxQ;
+use taxi::oomdp::taxi::TaxiObjects;
 use taxi::qlearner::QLearner;
 use taxi::random_solver::RandomSolver;
 use taxi::rmax::RMax;
 use taxi::runner::{run_training_session, Probe, Runner};
+use taxi::schedule::Schedule;
+use taxi::schemanet::SchemaNet;
+use taxi::start::Curriculum;
 
 use crossterm::event;
 use crossterm::event::{Event, KeyCode};
@@ -34,33 +50,94 @@ use std::io;
 use crate::replay::Replay;
 
 enum AppError {
-    NoConfiguration,
     Configuration(configuration::Error),
+    Validation {
+        filename: String,
+        errors: Vec<configuration::ValidationError>,
+    },
+    SolverNotConfigured(SolverChoice),
+    NoSessions,
+    NoRerunSeeds,
     World(taxi::world::Error),
+    Generator(taxi::world::generator::Error),
     BuildProbes(taxi::state::Error),
     Runner(taxi::runner::Error),
+    Start(taxi::start::Error),
+    Terms(taxi::oomdp::Error),
+    ReplayNotConfigured,
     ReplayRunnerNotConfigured(SolverChoice),
     ReplayTraining(taxi::runner::Error),
     ReplayState(taxi::state::Error),
     WaitForReplay(crossterm::ErrorKind),
     Replay(io::Error),
+    SweepNotConfigured,
+    ContinuingNotConfigured,
+    TransferNotConfigured,
+    SweepSolverNotConfigured(SolverChoice),
+    SweepUnknownParameter {
+        solver: SolverChoice,
+        parameter: String,
+    },
+    SweepParameters {
+        solver: SolverChoice,
+        error: toml::de::Error,
+    },
+    Output {
+        filename: String,
+        error: io::Error,
+    },
 }
 
-impl fmt::Debug for AppError {
+impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            AppError::NoConfiguration => write!(f, "Configuration file not specified."),
             AppError::Configuration(ref config_error) => {
-                write!(f, "Failed to read configuration:\n{:?}", config_error)
+                write!(f, "Failed to read configuration:\n{}", config_error)
             }
+            AppError::Validation {
+                ref filename,
+                ref errors,
+            } => {
+                write!(f, "Invalid configuration '{}':", filename)?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+            AppError::SolverNotConfigured(ref runner_type) => write!(
+                f,
+                "Attempting to select {:?} solver with out a valid configuration \
+                 for that solver.",
+                runner_type
+            ),
+            AppError::NoSessions => write!(
+                f,
+                "No sessions to run, set 'sessions' in the configuration or pass --sessions."
+            ),
+            AppError::NoRerunSeeds => write!(
+                f,
+                "No seeds to rerun, set 'rerun_seeds' in the configuration or pass --seed."
+            ),
             AppError::World(ref world_error) => {
-                write!(f, "Failed to build world:\n{:?}", world_error)
+                write!(f, "Failed to build world:\n{}", world_error)
+            }
+            AppError::Generator(ref generator_error) => {
+                write!(f, "Failed to generate world:\n{}", generator_error)
             }
             AppError::BuildProbes(ref state_error) => {
-                write!(f, "Failed to build probe state:\n{:?}", state_error)
+                write!(f, "Failed to build probe state:\n{}", state_error)
             }
             AppError::Runner(ref runner_error) => {
-                write!(f, "Failed to run trial:\n{:?}", runner_error)
+                write!(f, "Failed to run trial:\n{}", runner_error)
+            }
+            AppError::Start(ref start_error) => {
+                write!(f, "Failed to build start distribution:\n{}", start_error)
+            }
+            AppError::Terms(ref terms_error) => {
+                write!(f, "Failed to build DoorMax terms:\n{}", terms_error)
+            }
+            AppError::ReplayNotConfigured => {
+                write!(f, "The configuration has no [replay] section.")
             }
             AppError::ReplayRunnerNotConfigured(ref runner_type) => write!(
                 f,
@@ -69,31 +146,87 @@ impl fmt::Debug for AppError {
                 runner_type
             ),
             AppError::ReplayTraining(ref runner_error) => {
-                write!(f, "Failed to run training for replay:\n{:?}", runner_error)
+                write!(f, "Failed to run training for replay:\n{}", runner_error)
             }
             AppError::ReplayState(ref state_error) => {
-                write!(f, "Failed to build replay state:\n{:?}", state_error)
+                write!(f, "Failed to build replay state:\n{}", state_error)
             }
             AppError::WaitForReplay(ref crossterm_error) => write!(
                 f,
-                "Failed to read input while waiting for replay:\n{:?}",
+                "Failed to read input while waiting for replay:\n{}",
                 crossterm_error
             ),
             AppError::Replay(ref replay_error) => {
-                write!(f, "Failed to replay:\n{:?}", replay_error)
+                write!(f, "Failed to replay:\n{}", replay_error)
+            }
+            AppError::SweepNotConfigured => {
+                write!(f, "The configuration has no [sweep] section.")
             }
+            AppError::ContinuingNotConfigured => {
+                write!(f, "The configuration has no [continuing] section.")
+            }
+            AppError::TransferNotConfigured => {
+                write!(f, "The configuration has no [transfer] section.")
+            }
+            AppError::SweepSolverNotConfigured(ref runner_type) => write!(
+                f,
+                "Attempting to sweep {:?} solver with out a valid configuration \
+                 for that solver.",
+                runner_type
+            ),
+            AppError::SweepUnknownParameter {
+                ref solver,
+                ref parameter,
+            } => write!(
+                f,
+                "Sweep parameter '{}' is not set in the {:?} solver configuration.",
+                parameter, solver
+            ),
+            AppError::SweepParameters {
+                ref solver,
+                ref error,
+            } => write!(
+                f,
+                "Failed to build {:?} solver configuration for sweep:\n{}",
+                solver, error
+            ),
+            AppError::Output {
+                ref filename,
+                ref error,
+            } => write!(f, "Failed to write results to '{}':\n{}", filename, error),
         }
     }
 }
 
-fn main() -> Result<(), AppError> {
-    let args: Vec<String> = env::args().collect();
+fn main() {
+    let arguments = match cli::parse(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("Error: {}\n", error);
+            eprint!("{}", cli::usage(error.command()));
+            process::exit(2);
+        }
+    };
 
-    if args.len() < 2 {
-        return Err(AppError::NoConfiguration);
+    match arguments {
+        Arguments::Help(command) => print!("{}", cli::usage(command)),
+        Arguments::Run(options) => {
+            if let Err(error) = run(&options) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+        }
     }
+}
 
-    let config = Configuration::from_file(&args[1]).map_err(AppError::Configuration)?;
+fn run(options: &Options) -> Result<(), AppError> {
+    let mut config =
+        Configuration::from_file(&options.config_path).map_err(AppError::Configuration)?;
+    apply_overrides(&mut config, options)?;
+    config.validate().map_err(|errors| AppError::Validation {
+        filename: options.config_path.clone(),
+        errors,
+    })?;
 
     let costs = Costs::new(
         config.costs.movement,
@@ -101,394 +234,738 @@ fn main() -> Result<(), AppError> {
         config.costs.miss_dropoff,
         config.costs.empty_dropoff,
     );
-    let world = World::build_from_str(&config.world, costs).map_err(AppError::World)?;
-    let probes = build_probes(&config, &world)?;
 
-    let root_seed = if let Some((seed_high, seed_low)) = config.root_seed {
-        (seed_high as u128).rotate_left(64) + (seed_low as u128)
+    let root_seed = if let Some(seed) = config.root_seed {
+        build_seed(seed)
     } else {
         rand::random()
     };
 
-    if config.sessions > 0 {
-        let mut results = Vec::new();
+    let (world, probes) = if let Some(ref generator_config) = config.generator {
+        let seed = generator_config.seed.map_or(root_seed, build_seed);
+        generate_world(generator_config, costs, &mut Pcg64Mcg::new(seed))?
+    } else {
+        let world = config.build_world(costs).map_err(AppError::World)?;
+        let probes = build_probes(&config.probes, &world)?;
+        (world, probes)
+    };
 
-        if let Some(ref random_config) = config.random_solver {
-            gather_stats(
-                RandomSolver::new,
-                random_config,
-                &world,
-                &probes,
-                &config,
-                root_seed,
-                &mut results,
-            )?;
-        };
-
-        if let Some(ref qlearner_config) = config.q_learner {
-            gather_stats(
-                || {
-                    QLearner::new(
-                        &world,
-                        qlearner_config.alpha,
-                        qlearner_config.gamma,
-                        qlearner_config.epsilon,
-                    )
-                },
-                qlearner_config,
-                &world,
-                &probes,
-                &config,
-                root_seed,
-                &mut results,
-            )?;
-        };
+    match options.command {
+        Command::All => {
+            if config.sessions > 0 {
+                run_stats(&config, &world, &probes, root_seed, None)?;
+            }
 
-        if let Some(ref rmax_config) = config.r_max {
-            gather_stats(
-                || {
-                    RMax::new(
-                        &world,
-                        rmax_config.gamma,
-                        rmax_config.known_count,
-                        rmax_config.error_delta,
-                    )
-                },
-                rmax_config,
-                &world,
-                &probes,
-                &config,
-                root_seed,
-                &mut results,
-            )?;
-        };
+            if let Some(ref sweep_config) = config.sweep {
+                sweep::run_sweep(sweep_config, &config, &world, &probes, root_seed)?;
+            }
 
-        if let Some(ref factored_rmax_config) = config.factored_r_max {
-            gather_stats(
-                || {
-                    FactoredRMax::new(
-                        &world,
-                        factored_rmax_config.gamma,
-                        factored_rmax_config.known_count,
-                        factored_rmax_config.error_delta,
-                    )
-                },
-                factored_rmax_config,
-                &world,
-                &probes,
+            if let Some(ref continuing_config) = config.continuing {
+                if config.sessions > 0 {
+                    continuing::run_continuing(continuing_config, &config, &world, root_seed)?;
+                }
+            }
+
+            for &seed in &config.rerun_seeds {
+                train_solvers(&config, &world, &probes, build_seed(seed))?;
+            }
+
+            if let Some(ref replay_config) = config.replay {
+                replay_solver(replay_config, &config, &world, &probes)?;
+            }
+        }
+
+        Command::Train => train_solvers(&config, &world, &probes, root_seed)?,
+
+        Command::Stats => {
+            if config.sessions == 0 {
+                return Err(AppError::NoSessions);
+            }
+
+            run_stats(&config, &world, &probes, root_seed, options.output.as_ref())?;
+        }
+
+        Command::Sweep => {
+            let sweep_config = config.sweep.as_ref().ok_or(AppError::SweepNotConfigured)?;
+            sweep::run_sweep(sweep_config, &config, &world, &probes, root_seed)?;
+        }
+
+        Command::Continuing => {
+            let continuing_config = config
+                .continuing
+                .as_ref()
+                .ok_or(AppError::ContinuingNotConfigured)?;
+
+            if config.sessions == 0 {
+                return Err(AppError::NoSessions);
+            }
+
+            continuing::run_continuing(continuing_config, &config, &world, root_seed)?;
+        }
+
+        Command::Accuracy => {
+            accuracy::run_accuracy(&config, &world, &probes, root_seed, options.output.as_ref())?
+        }
+
+        Command::Transfer => {
+            let transfer_config = config
+                .transfer
+                .as_ref()
+                .ok_or(AppError::TransferNotConfigured)?;
+
+            if config.sessions == 0 {
+                return Err(AppError::NoSessions);
+            }
+
+            transfer::run_transfer(
+                transfer_config,
                 &config,
-                root_seed,
-                &mut results,
-            )?;
-        };
-
-        if let Some(ref maxq_config) = config.max_q {
-            gather_stats(
-                || {
-                    MaxQ::new(
-                        &world,
-                        maxq_config.alpha,
-                        maxq_config.gamma,
-                        maxq_config.epsilon,
-                        maxq_config.show_learning,
-                    )
-                },
-                maxq_config,
                 &world,
                 &probes,
-                &config,
                 root_seed,
-                &mut results,
+                options.output.as_ref(),
             )?;
-        };
+        }
 
-        if let Some(ref doormax_config) = config.door_max {
-            gather_stats(
-                || {
-                    DoorMax::new(
-                        &world,
-                        doormax_config.gamma,
-                        doormax_config.use_reward_learner,
-                        doormax_config.known_count,
-                        doormax_config.error_delta,
-                    )
-                },
-                doormax_config,
-                &world,
-                &probes,
-                &config,
-                root_seed,
-                &mut results,
-            )?;
-        };
+        Command::Rerun => {
+            if config.rerun_seeds.is_empty() {
+                return Err(AppError::NoRerunSeeds);
+            }
 
-        println!();
+            for &seed in &config.rerun_seeds {
+                train_solvers(&config, &world, &probes, build_seed(seed))?;
+            }
+        }
 
-        for (solver_choice, stats) in results {
-            let (avg_steps, stddev_steps) = stats.distribution.get_distribution();
+        Command::Replay => {
+            let replay_config = config
+                .replay
+                .as_ref()
+                .ok_or(AppError::ReplayNotConfigured)?;
+            replay_solver(replay_config, &config, &world, &probes)?;
+        }
 
-            let elapsed_time =
-                stats.duration.as_secs() as f64 + f64::from(stats.duration.subsec_nanos()) * 1e-9;
+        Command::ValidateConfig => {
+            println!("Configuration '{}' is valid.", options.config_path);
+        }
 
-            println!(
-                "{:?} - finished {} sessions in {:.1} average steps with stddev of {:.2} \
-                 in {:.3} secs. Using seed [{}, {}]",
-                solver_choice,
-                stats.distribution.get_count() as usize,
-                avg_steps,
-                stddev_steps,
-                elapsed_time,
-                root_seed.rotate_right(64) as i64,
-                root_seed as i64,
-            );
+        Command::ShowWorld => {
+            let format = Format::from(options.format.unwrap_or(config.world_format));
+            print!("{}", world.write(format));
+
+            // Keeps TOML and JSON output readable by World::build.
+            if format != Format::Toml && format != Format::Json {
+                print!("{}", describe_world(&world));
+            }
         }
     }
 
-    for (seed_high, seed_low) in config.rerun_seeds {
-        let seed = (seed_high as u128).rotate_left(64) + (seed_low as u128);
+    Ok(())
+}
 
-        if let Some(ref random_config) = config.random_solver {
-            rerun_session(
-                RandomSolver::new,
-                random_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
-
-        if let Some(ref qlearner_config) = config.q_learner {
-            rerun_session(
-                || {
-                    QLearner::new(
-                        &world,
-                        qlearner_config.alpha,
-                        qlearner_config.gamma,
-                        qlearner_config.epsilon,
-                    )
-                },
-                qlearner_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
+// The distances between fixed positions, and anything about the layout that
+// is likely to be a mistake.
+fn describe_world(world: &World) -> String {
+    let analysis = world.analyze();
+
+    let names: Vec<&str> = (0..world.num_fixed_positions())
+        .filter_map(|index| world.get_fixed_id_from_index(index))
+        .filter_map(|id| world.location_name(id))
+        .collect();
+
+    let label_width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .fold(1, usize::max);
+    let width = label_width.max(3);
+
+    let mut result = format!(
+        "\nDistances between fixed positions:\n{:width$}",
+        "",
+        width = label_width + 2
+    );
+    for name in &names {
+        result += &format!(" {:>width$}", name, width = width);
+    }
+    result.push('\n');
+
+    for (name, distances) in names.iter().zip(analysis.fixed_distances()) {
+        result += &format!("  {:<width$}", name, width = label_width);
+        for distance in distances {
+            match *distance {
+                Some(distance) => result += &format!(" {:>width$}", distance, width = width),
+                None => result += &format!(" {:>width$}", "-", width = width),
+            }
+        }
+        result.push('\n');
+    }
+
+    if analysis.num_components() > 1 {
+        result += &format!(
+            "\nThe world is split into {} parts the taxi cannot drive between.\n",
+            analysis.num_components()
+        );
+    }
+
+    if !analysis.dead_ends().is_empty() {
+        let dead_ends: Vec<String> = analysis
+            .dead_ends()
+            .iter()
+            .map(|position| format!("({}, {})", position.x, position.y))
+            .collect();
+        result += &format!("\nDead ends: {}\n", dead_ends.join(" "));
+    }
 
-        if let Some(ref rmax_config) = config.r_max {
-            rerun_session(
-                || {
-                    RMax::new(
-                        &world,
+    result
+}
+
+// TOML only allows for signed 64 bit integers, so seeds are stored as two halves.
+fn build_seed((seed_high, seed_low): (i64, i64)) -> u128 {
+    (seed_high as u128).rotate_left(64) + (seed_low as u128)
+}
+
+fn apply_overrides(config: &mut Configuration, options: &Options) -> Result<(), AppError> {
+    if let Some(sessions) = options.sessions {
+        config.sessions = sessions;
+    }
+
+    if options.command == Command::Rerun {
+        if !options.seeds.is_empty() {
+            config.rerun_seeds = options.seeds.clone();
+        }
+    } else if let Some(&seed) = options.seeds.first() {
+        config.root_seed = Some(seed);
+    }
+
+    if let Some(solver) = options.solver {
+        if !config.is_solver_configured(solver) {
+            return Err(AppError::SolverNotConfigured(solver));
+        }
+
+        config.retain_solver(solver);
+
+        if let Some(ref mut replay_config) = config.replay {
+            replay_config.solver = solver;
+        }
+
+        if let Some(ref mut sweep_config) = config.sweep {
+            sweep_config.solver = solver;
+        }
+    }
+
+    if options.command == Command::Sweep && options.output.is_some() {
+        if let Some(ref mut sweep_config) = config.sweep {
+            sweep_config.output = options.output.clone();
+        }
+    }
+
+    Ok(())
+}
+
+fn run_stats(
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    root_seed: u128,
+    output: Option<&String>,
+) -> Result<(), AppError> {
+    let mut results = Vec::new();
+
+    for random_config in &config.random_solver {
+        gather_stats(
+            |_| RandomSolver::new(),
+            random_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for qlearner_config in &config.q_learner {
+        gather_stats(
+            |world| {
+                QLearner::new(
+                    world,
+                    Schedule::from(qlearner_config.alpha),
+                    qlearner_config.gamma,
+                    Schedule::from(qlearner_config.epsilon),
+                )
+            },
+            qlearner_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for rmax_config in &config.r_max {
+        gather_stats(
+            |world| {
+                RMax::new(
+                    world,
+                    rmax_config.gamma,
+                    rmax_config.known_count,
+                    rmax_config.error_delta,
+                )
+            },
+            rmax_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for factored_rmax_config in &config.factored_r_max {
+        gather_stats(
+            |world| {
+                FactoredRMax::new(
+                    world,
+                    Structure::from(factored_rmax_config),
+                    factored_rmax_config.gamma,
+                    factored_rmax_config.known_count,
+                    factored_rmax_config.error_delta,
+                )
+            },
+            factored_rmax_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for maxq_config in &config.max_q {
+        gather_stats(
+            |world| {
+                MaxQ::new(
+                    world,
+                    Schedule::from(maxq_config.alpha),
+                    maxq_config.gamma,
+                    Schedule::from(maxq_config.epsilon),
+                    maxq_config.show_learning,
+                )
+            },
+            maxq_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for doormax_config in &config.door_max {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+        gather_stats(
+            |world| {
+                let mut doormax = DoorMax::new(
+                    world,
+                    taxi_objects.clone(),
+                    EffectModel::from(doormax_config),
+                    doormax_config.gamma,
+                    doormax_config.use_reward_learner,
+                    doormax_config.known_count,
+                    doormax_config.error_delta,
+                );
+                doormax.set_explanation_file(doormax_config.explanation.clone());
+                doormax
+            },
+            doormax_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    for schemanet_config in &config.schema_net {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&schemanet_config.terms).map_err(AppError::Terms)?;
+        gather_stats(
+            |world| {
+                SchemaNet::new(
+                    world,
+                    taxi_objects.clone(),
+                    schemanet_config.gamma,
+                    schemanet_config.error_delta,
+                )
+            },
+            schemanet_config,
+            world,
+            probes,
+            config,
+            root_seed,
+            &mut results,
+        )?;
+    }
+
+    let mut summary = String::new();
+
+    for (label, stats) in results {
+        let (avg_steps, stddev_steps) = stats.distribution.get_distribution();
+
+        let elapsed_time =
+            stats.duration.as_secs() as f64 + f64::from(stats.duration.subsec_nanos()) * 1e-9;
+
+        summary += &format!(
+            "{} - finished {} sessions in {:.1} average steps with stddev of {:.2} \
+             in {:.3} secs. Using seed [{}, {}]\n",
+            label,
+            stats.distribution.get_count() as usize,
+            avg_steps,
+            stddev_steps,
+            elapsed_time,
+            root_seed.rotate_right(64) as i64,
+            root_seed as i64,
+        );
+    }
+
+    println!();
+    print!("{}", summary);
+
+    if let Some(filename) = output {
+        write_output(filename, &summary)?;
+    }
+
+    Ok(())
+}
+
+fn write_output(filename: &str, contents: &str) -> Result<(), AppError> {
+    File::create(filename)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|error| AppError::Output {
+            filename: String::from(filename),
+            error,
+        })
+}
+
+fn train_solvers(
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    seed: u128,
+) -> Result<(), AppError> {
+    for random_config in &config.random_solver {
+        rerun_session(
+            |_| RandomSolver::new(),
+            random_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for qlearner_config in &config.q_learner {
+        rerun_session(
+            |world| {
+                QLearner::new(
+                    world,
+                    Schedule::from(qlearner_config.alpha),
+                    qlearner_config.gamma,
+                    Schedule::from(qlearner_config.epsilon),
+                )
+            },
+            qlearner_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for rmax_config in &config.r_max {
+        rerun_session(
+            |world| {
+                RMax::new(
+                    world,
+                    rmax_config.gamma,
+                    rmax_config.known_count,
+                    rmax_config.error_delta,
+                )
+            },
+            rmax_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for factored_rmax_config in &config.factored_r_max {
+        rerun_session(
+            |world| {
+                FactoredRMax::new(
+                    world,
+                    Structure::from(factored_rmax_config),
+                    factored_rmax_config.gamma,
+                    factored_rmax_config.known_count,
+                    factored_rmax_config.error_delta,
+                )
+            },
+            factored_rmax_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for maxq_config in &config.max_q {
+        rerun_session(
+            |world| {
+                MaxQ::new(
+                    world,
+                    Schedule::from(maxq_config.alpha),
+                    maxq_config.gamma,
+                    Schedule::from(maxq_config.epsilon),
+                    maxq_config.show_learning,
+                )
+            },
+            maxq_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for doormax_config in &config.door_max {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+        rerun_session(
+            |world| {
+                let mut doormax = DoorMax::new(
+                    world,
+                    taxi_objects.clone(),
+                    EffectModel::from(doormax_config),
+                    doormax_config.gamma,
+                    doormax_config.use_reward_learner,
+                    doormax_config.known_count,
+                    doormax_config.error_delta,
+                );
+                doormax.set_explanation_file(doormax_config.explanation.clone());
+                doormax
+            },
+            doormax_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    for schemanet_config in &config.schema_net {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&schemanet_config.terms).map_err(AppError::Terms)?;
+        rerun_session(
+            |world| {
+                SchemaNet::new(
+                    world,
+                    taxi_objects.clone(),
+                    schemanet_config.gamma,
+                    schemanet_config.error_delta,
+                )
+            },
+            schemanet_config,
+            world,
+            probes,
+            config,
+            seed,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn replay_solver(
+    replay_config: &configuration::Replay,
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+) -> Result<(), AppError> {
+    let mut rng = rand::thread_rng();
+    let profile = replay_config.profile.as_deref();
+    let starts = config.build_starts(world).map_err(AppError::Start)?;
+
+    match replay_config.solver {
+        SolverChoice::Random => {
+            if find_profile(&config.random_solver, profile).is_some() {
+                run_replay(
+                    &mut RandomSolver::new(),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::QLearner => {
+            if let Some(qlearner_config) = find_profile(&config.q_learner, profile) {
+                run_replay(
+                    &mut QLearner::new(
+                        world,
+                        Schedule::from(qlearner_config.alpha),
+                        qlearner_config.gamma,
+                        Schedule::from(qlearner_config.epsilon),
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::RMax => {
+            if let Some(rmax_config) = find_profile(&config.r_max, profile) {
+                run_replay(
+                    &mut RMax::new(
+                        world,
                         rmax_config.gamma,
                         rmax_config.known_count,
                         rmax_config.error_delta,
-                    )
-                },
-                rmax_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
-
-        if let Some(ref factored_rmax_config) = config.factored_r_max {
-            rerun_session(
-                || {
-                    FactoredRMax::new(
-                        &world,
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::FactoredRMax => {
+            if let Some(factored_rmax_config) = find_profile(&config.factored_r_max, profile) {
+                run_replay(
+                    &mut FactoredRMax::new(
+                        world,
+                        Structure::from(factored_rmax_config),
                         factored_rmax_config.gamma,
                         factored_rmax_config.known_count,
                         factored_rmax_config.error_delta,
-                    )
-                },
-                factored_rmax_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
-
-        if let Some(ref maxq_config) = config.max_q {
-            rerun_session(
-                || {
-                    MaxQ::new(
-                        &world,
-                        maxq_config.alpha,
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::MaxQ => {
+            if let Some(maxq_config) = find_profile(&config.max_q, profile) {
+                run_replay(
+                    &mut MaxQ::new(
+                        world,
+                        Schedule::from(maxq_config.alpha),
                         maxq_config.gamma,
-                        maxq_config.epsilon,
+                        Schedule::from(maxq_config.epsilon),
                         maxq_config.show_learning,
-                    )
-                },
-                maxq_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
-
-        if let Some(ref doormax_config) = config.door_max {
-            rerun_session(
-                || {
-                    DoorMax::new(
-                        &world,
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::DoorMax => {
+            if let Some(doormax_config) = find_profile(&config.door_max, profile) {
+                let taxi_objects =
+                    TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+                run_replay(
+                    &mut DoorMax::new(
+                        world,
+                        taxi_objects,
+                        EffectModel::from(doormax_config),
                         doormax_config.gamma,
                         doormax_config.use_reward_learner,
                         doormax_config.known_count,
                         doormax_config.error_delta,
-                    )
-                },
-                doormax_config,
-                &world,
-                &probes,
-                (config.max_trials, config.max_trial_steps),
-                seed,
-            )?;
-        };
-    }
-
-    {
-        let mut rng = rand::thread_rng();
-
-        if let Some(ref replay_config) = config.replay {
-            match replay_config.solver {
-                SolverChoice::Random => {
-                    if config.random_solver.is_some() {
-                        run_replay(
-                            &mut RandomSolver::new(),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-                SolverChoice::QLearner => {
-                    if let Some(ref qlearner_config) = config.q_learner {
-                        run_replay(
-                            &mut QLearner::new(
-                                &world,
-                                qlearner_config.alpha,
-                                qlearner_config.gamma,
-                                qlearner_config.epsilon,
-                            ),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-                SolverChoice::RMax => {
-                    if let Some(ref rmax_config) = config.r_max {
-                        run_replay(
-                            &mut RMax::new(
-                                &world,
-                                rmax_config.gamma,
-                                rmax_config.known_count,
-                                rmax_config.error_delta,
-                            ),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-                SolverChoice::FactoredRMax => {
-                    if let Some(ref factored_rmax_config) = config.factored_r_max {
-                        run_replay(
-                            &mut FactoredRMax::new(
-                                &world,
-                                factored_rmax_config.gamma,
-                                factored_rmax_config.known_count,
-                                factored_rmax_config.error_delta,
-                            ),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-                SolverChoice::MaxQ => {
-                    if let Some(ref maxq_config) = config.max_q {
-                        run_replay(
-                            &mut MaxQ::new(
-                                &world,
-                                maxq_config.alpha,
-                                maxq_config.gamma,
-                                maxq_config.epsilon,
-                                maxq_config.show_learning,
-                            ),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-                SolverChoice::DoorMax => {
-                    if let Some(ref doormax_config) = config.door_max {
-                        run_replay(
-                            &mut DoorMax::new(
-                                &world,
-                                doormax_config.gamma,
-                                doormax_config.use_reward_learner,
-                                doormax_config.known_count,
-                                doormax_config.error_delta,
-                            ),
-                            replay_config,
-                            &world,
-                            &probes,
-                            config.max_trials,
-                            config.max_trial_steps,
-                            &mut rng,
-                        )?
-                    } else {
-                        return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
-                    }
-                }
-            };
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
+        }
+        SolverChoice::SchemaNet => {
+            if let Some(schemanet_config) = find_profile(&config.schema_net, profile) {
+                let taxi_objects = TaxiObjects::with_predicates(&schemanet_config.terms)
+                    .map_err(AppError::Terms)?;
+                run_replay(
+                    &mut SchemaNet::new(
+                        world,
+                        taxi_objects,
+                        schemanet_config.gamma,
+                        schemanet_config.error_delta,
+                    ),
+                    replay_config,
+                    world,
+                    &starts,
+                    probes,
+                    config.max_trials,
+                    config.max_trial_steps,
+                    &mut rng,
+                )?
+            } else {
+                return Err(AppError::ReplayRunnerNotConfigured(replay_config.solver));
+            }
         }
     }
 
     Ok(())
 }
 
-fn build_probes(config: &Configuration, world: &World) -> Result<Vec<Probe>, AppError> {
+fn build_probes(
+    probe_configs: &[configuration::Probe],
+    world: &World,
+) -> Result<Vec<Probe>, AppError> {
     let mut probes = Vec::new();
 
-    for probe_config in &config.probes {
-        let state = State::build(
+    for probe_config in probe_configs {
+        let state = State::build_named(
             world,
             probe_config.taxi_pos,
-            probe_config.passenger_loc,
-            probe_config.destination_loc,
+            probe_config.passenger_loc.as_deref(),
+            &probe_config.destination_loc,
         )
         .map_err(AppError::BuildProbes)?;
 
@@ -498,12 +975,59 @@ fn build_probes(config: &Configuration, world: &World) -> Result<Vec<Probe>, App
     Ok(probes)
 }
 
+fn generate_world<R: Rng>(
+    generator_config: &GeneratorConfig,
+    costs: Costs,
+    rng: &mut R,
+) -> Result<(World, Vec<Probe>), AppError> {
+    let generator = Generator::new(
+        generator_config.width,
+        generator_config.height,
+        generator_config.wall_density,
+        generator_config.fixed_positions,
+    )
+    .map_err(AppError::Generator)?;
+
+    let world = generator.generate(costs, rng);
+    let probes =
+        generate_probes(&world, generator_config.probes, rng).map_err(AppError::BuildProbes)?;
+
+    Ok((world, probes))
+}
+
+// A fresh world for a single session, when the generator asks for one.
+fn generate_session_world<R: Rng>(
+    config: &Configuration,
+    costs: Costs,
+    rng: &mut R,
+) -> Result<Option<(World, Vec<Probe>)>, AppError> {
+    match config.generator {
+        Some(ref generator_config) if generator_config.per_session => {
+            generate_world(generator_config, costs, rng).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
 #[derive(Default)]
 struct Stats {
     distribution: MeasureDistribution,
+    failures: usize,
     duration: time::Duration,
 }
 
+impl Stats {
+    fn failure_rate(&self) -> f64 {
+        let num_sessions = self.distribution.get_count() + self.failures as f64;
+
+        if num_sessions > 0.0 {
+            self.failures as f64 / num_sessions
+        } else {
+            0.0
+        }
+    }
+}
+
 fn gather_stats<B, Rnr>(
     builder: B,
     report_config: &dyn ReportConfig,
@@ -511,10 +1035,38 @@ fn gather_stats<B, Rnr>(
     probes: &[Probe],
     config: &Configuration,
     root_seed: u128,
-    results: &mut Vec<(SolverChoice, Stats)>,
+    results: &mut Vec<(String, Stats)>,
 ) -> Result<(), AppError>
 where
-    B: Fn() -> Rnr + Sync,
+    B: Fn(&World) -> Rnr + Sync,
+    Rnr: Runner + Sync,
+{
+    let stats = measure_sessions(
+        builder,
+        report_config,
+        world,
+        probes,
+        config,
+        root_seed,
+        true,
+    )?;
+
+    results.push((report_config.label(), stats));
+
+    Ok(())
+}
+
+fn measure_sessions<B, Rnr>(
+    builder: B,
+    report_config: &dyn ReportConfig,
+    world: &World,
+    probes: &[Probe],
+    config: &Configuration,
+    root_seed: u128,
+    log_sessions: bool,
+) -> Result<Stats, AppError>
+where
+    B: Fn(&World) -> Rnr + Sync,
     Rnr: Runner + Sync,
 {
     let mut seed_generator = Pcg64Mcg::new(root_seed);
@@ -522,23 +1074,32 @@ where
         .map(|session_id| (session_id, seed_generator.gen()))
         .collect();
 
-    let solver_choice = report_config.solver_choice();
+    let label = report_config.label();
     let report = report_config.report();
 
-    let stats = session_ids
+    session_ids
         .par_iter()
         .fold(
             || Ok(Stats::default()),
             |current_result, (session_number, seed)| -> Result<Stats, AppError> {
                 current_result.and_then(|mut stats| {
-                    let start_time = time::Instant::now();
+                    let mut rng = Pcg64Mcg::new(*seed);
 
-                    let mut solver = builder();
+                    let session_world = generate_session_world(config, world.costs, &mut rng)?;
+                    let (world, probes) = match session_world {
+                        Some((ref world, ref probes)) => (world, &probes[..]),
+                        None => (world, probes),
+                    };
 
-                    let mut rng = Pcg64Mcg::new(*seed);
+                    let starts = config.build_starts(world).map_err(AppError::Start)?;
+
+                    let start_time = time::Instant::now();
+
+                    let mut solver = builder(world);
 
                     let training_step_count = run_training_session(
                         world,
+                        &starts,
                         probes,
                         config.max_trials,
                         config.max_trial_steps,
@@ -553,29 +1114,34 @@ where
 
                     match training_step_count {
                         Some(num_steps) => {
-                            println!(
-                                "{:?} - Finished session {} [{}, {}] in {} steps in {:.3} secs.",
-                                solver_choice,
-                                session_number,
-                                seed.rotate_right(64) as i64,
-                                *seed as i64,
-                                num_steps,
-                                elapsed_time,
-                            );
+                            if log_sessions {
+                                println!(
+                                    "{} - Finished session {} [{}, {}] in {} steps in {:.3} secs.",
+                                    label,
+                                    session_number,
+                                    seed.rotate_right(64) as i64,
+                                    *seed as i64,
+                                    num_steps,
+                                    elapsed_time,
+                                );
+                            }
                             stats.distribution.add_value(num_steps as f64);
                         }
                         None => {
-                            println!(
-                                "{:?} - Failed session {} [{},{}] with maximums {} trials of {} steps \
-                                 in {:.3} secs.",
-                                solver_choice,
-                                session_number,
-                                seed.rotate_right(64) as i64,
-                                *seed as i64,
-                                config.max_trials,
-                                config.max_trial_steps,
-                                elapsed_time,
-                            );
+                            if log_sessions {
+                                println!(
+                                    "{} - Failed session {} [{},{}] with maximums {} trials of {} steps \
+                                     in {:.3} secs.",
+                                    label,
+                                    session_number,
+                                    seed.rotate_right(64) as i64,
+                                    *seed as i64,
+                                    config.max_trials,
+                                    config.max_trial_steps,
+                                    elapsed_time,
+                                );
+                            }
+                            stats.failures += 1;
                         }
                     };
 
@@ -600,16 +1166,13 @@ where
                 result_a.and_then(|mut stats_a| {
                     result_b.and_then(|stats_b| {
                         stats_a.distribution.add_distribution(&stats_b.distribution);
+                        stats_a.failures += stats_b.failures;
                         stats_a.duration += stats_b.duration;
                         Ok(stats_a)
                     })
                 })
             },
-        )?;
-
-    results.push((solver_choice, stats));
-
-    Ok(())
+        )
 }
 
 fn rerun_session<B, Rnr>(
@@ -617,25 +1180,35 @@ fn rerun_session<B, Rnr>(
     report_config: &dyn ReportConfig,
     world: &World,
     probes: &[Probe],
-    (max_trials, max_trial_steps): (usize, usize),
+    config: &Configuration,
     seed: u128,
 ) -> Result<(), AppError>
 where
-    B: Fn() -> Rnr,
+    B: Fn(&World) -> Rnr,
     Rnr: Runner,
 {
-    let solver_choice = report_config.solver_choice();
+    let label = report_config.label();
+
+    let mut rng = Pcg64Mcg::new(seed);
+
+    let session_world = generate_session_world(config, world.costs, &mut rng)?;
+    let (world, probes) = match session_world {
+        Some((ref world, ref probes)) => (world, &probes[..]),
+        None => (world, probes),
+    };
+
+    let starts = config.build_starts(world).map_err(AppError::Start)?;
 
     let start_time = time::Instant::now();
 
-    let mut solver = builder();
-    let mut rng = Pcg64Mcg::new(seed);
+    let mut solver = builder(world);
 
     let training_step_count = run_training_session(
         world,
+        &starts,
         probes,
-        max_trials,
-        max_trial_steps,
+        config.max_trials,
+        config.max_trial_steps,
         &mut solver,
         &mut rng,
     )
@@ -647,8 +1220,8 @@ where
     match training_step_count {
         Some(num_steps) => {
             println!(
-                "{:?} - Finished seed [{}, {}] in {} steps in {:.3} secs.",
-                solver_choice,
+                "{} - Finished seed [{}, {}] in {} steps in {:.3} secs.",
+                label,
                 seed.rotate_right(64) as i64,
                 seed as i64,
                 num_steps,
@@ -657,13 +1230,13 @@ where
         }
         None => {
             println!(
-                "{:?} - Failed seed [{},{}] with maximums {} trials of {} steps \
+                "{} - Failed seed [{},{}] with maximums {} trials of {} steps \
                  in {:.3} secs.",
-                solver_choice,
+                label,
                 seed.rotate_right(64) as i64,
                 seed as i64,
-                max_trials,
-                max_trial_steps,
+                config.max_trials,
+                config.max_trial_steps,
                 elapsed_time,
             );
         }
@@ -678,6 +1251,7 @@ fn run_replay<Rnr, R>(
     solver: &mut Rnr,
     replay_config: &configuration::Replay,
     world: &World,
+    starts: &Curriculum,
     probes: &[Probe],
     max_trials: usize,
     max_trial_steps: usize,
@@ -687,17 +1261,25 @@ where
     Rnr: Runner,
     R: Rng,
 {
-    run_training_session(world, probes, max_trials, max_trial_steps, solver, &mut rng)
-        .map_err(AppError::ReplayTraining)?;
+    run_training_session(
+        world,
+        starts,
+        probes,
+        max_trials,
+        max_trial_steps,
+        solver,
+        &mut rng,
+    )
+    .map_err(AppError::ReplayTraining)?;
 
     let do_replay = ask_for_replay().map_err(AppError::WaitForReplay)?;
 
     if do_replay {
-        let replay_state = State::build(
+        let replay_state = State::build_named(
             world,
             replay_config.taxi_pos,
-            replay_config.passenger_loc,
-            replay_config.destination_loc,
+            replay_config.passenger_loc.as_deref(),
+            &replay_config.destination_loc,
         )
         .map_err(AppError::ReplayState)?;
 
@@ -6,6 +6,7 @@ mod qnode;
 use rand::Rng;
 
 use crate::actions::Actions;
+use crate::schedule::{Schedule, ScheduleClock};
 use crate::state::State;
 use crate::state_indexer::StateIndexer;
 use crate::world::World;
@@ -17,13 +18,28 @@ use self::qnode::QChild;
 
 #[derive(Debug, Clone, Copy)]
 pub struct MaxQParams {
-    alpha: f64,
+    alpha: Schedule,
     gamma: f64,
-    epsilon: f64,
+    epsilon: Schedule,
+    clock: ScheduleClock,
 
     show_learning: bool,
 }
 
+impl MaxQParams {
+    fn alpha(&self, visits: usize) -> f64 {
+        self.alpha.value(&self.clock, visits)
+    }
+
+    // Exploration is chosen per max node rather than per state, so there
+    // are no visit counts to hand to a visit based schedule.  Use the
+    // elapsed count for the schedule's timescale instead.
+    fn epsilon(&self) -> f64 {
+        let elapsed = self.clock.elapsed(self.epsilon.timescale());
+        self.epsilon.value(&self.clock, elapsed + 1)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MaxQ {
     params: MaxQParams,
@@ -31,19 +47,20 @@ pub struct MaxQ {
 }
 
 impl MaxQ {
-    pub fn new(world: &World, alpha: f64, gamma: f64, epsilon: f64, show_learning: bool) -> MaxQ {
-        let initial_q_value = if gamma < 1.0 {
-            world.max_reward() / (1.0 - gamma)
-        } else {
-            world.max_reward()
-        };
+    pub fn new<A, E>(world: &World, alpha: A, gamma: f64, epsilon: E, show_learning: bool) -> MaxQ
+    where
+        A: Into<Schedule>,
+        E: Into<Schedule>,
+    {
+        let initial_q_value = world.max_value(gamma);
 
         let nodes = NodeStorage::new(initial_q_value, world);
 
         let params = MaxQParams {
-            alpha,
+            alpha: alpha.into(),
             gamma,
-            epsilon,
+            epsilon: epsilon.into(),
+            clock: ScheduleClock::new(),
 
             show_learning,
         };
@@ -72,6 +89,7 @@ impl MaxQ {
                 let (reward, next_state) = state.apply_action(world, primitive_node.get_action());
 
                 primitive_node.apply_experience(&self.params, world, &state, reward, &next_state);
+                self.params.clock.step();
 
                 Some((next_state, vec![state]))
             }
@@ -200,7 +218,10 @@ impl Runner for MaxQ {
             println!("Learning:\n{:#?}\n{}\n", state, state.display(world));
         }
 
-        let (final_state, seq) = self.maxq_q(0, world, state, max_steps, rng)?;
+        let result = self.maxq_q(0, world, state, max_steps, rng);
+        self.params.clock.finish_trial();
+
+        let (final_state, seq) = result?;
 
         if self.params.show_learning {
             println!(
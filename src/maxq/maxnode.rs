@@ -6,7 +6,7 @@ use rand::Rng;
 
 use crate::actions::Actions;
 use crate::state::State;
-use crate::world::World;
+use crate::world::{LocationId, World};
 
 use crate::maxq::nodestorage::NodeStorage;
 use crate::maxq::qnode::{QNode, QNodeType};
@@ -17,7 +17,7 @@ pub enum MaxNodeType {
     Root,
     Get,
     Put,
-    Navigate(char),
+    Navigate(LocationId),
 }
 
 #[derive(Debug, Clone)]
@@ -108,7 +108,7 @@ impl MaxNode {
     ) -> Option<usize> {
         let nongreedy_roll = rng.gen_range(0.0f64, 1.0f64);
 
-        if nongreedy_roll < params.epsilon {
+        if nongreedy_roll < params.epsilon() {
             self.qnodes.choose(rng).cloned()
         } else {
             self.evaluate(nodes, world, state)
@@ -216,7 +216,7 @@ impl fmt::Display for MaxNode {
             MaxNodeType::Root => write!(f, "Root"),
             MaxNodeType::Get => write!(f, "Get"),
             MaxNodeType::Put => write!(f, "Put"),
-            MaxNodeType::Navigate(id) => write!(f, "Navigate({})", id),
+            MaxNodeType::Navigate(id) => write!(f, "Navigate({})", id.index()),
         }
     }
 }
@@ -8,6 +8,7 @@ use crate::maxq::MaxQParams;
 pub struct PrimitiveNode {
     action: Actions,
     values: Vec<f64>,
+    visits: Vec<usize>,
 }
 
 impl PrimitiveNode {
@@ -20,6 +21,7 @@ impl PrimitiveNode {
         PrimitiveNode {
             action,
             values: vec![initial_q_value; num_values],
+            visits: vec![0; num_values],
         }
     }
 
@@ -57,8 +59,11 @@ impl PrimitiveNode {
     ) {
         let value_index = self.get_value_index(world, state);
 
-        self.values[value_index] *= 1.0 - params.alpha;
-        self.values[value_index] += params.alpha * reward;
+        self.visits[value_index] += 1;
+        let alpha = params.alpha(self.visits[value_index]);
+
+        self.values[value_index] *= 1.0 - alpha;
+        self.values[value_index] += alpha * reward;
     }
 
     pub fn get_action(&self) -> Actions {
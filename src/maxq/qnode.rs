@@ -2,7 +2,7 @@ use std::fmt;
 
 use crate::actions::Actions;
 use crate::state::State;
-use crate::world::World;
+use crate::world::{LocationId, World};
 
 use crate::maxq::maxnode::{MaxNode, MaxNodeType};
 use crate::maxq::nodestorage::NodeStorage;
@@ -17,10 +17,10 @@ pub enum QNodeType {
     Put,
     NavigateForPut,
     DropOff,
-    North(char),
-    South(char),
-    East(char),
-    West(char),
+    North(LocationId),
+    South(LocationId),
+    East(LocationId),
+    West(LocationId),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -34,6 +34,7 @@ pub struct QNode {
     node_type: QNodeType,
     completions: Vec<f64>,
     learning_completions: Vec<f64>,
+    visits: Vec<usize>,
 }
 
 impl QNode {
@@ -55,6 +56,7 @@ impl QNode {
             node_type,
             completions: vec![0.0; num_completions],
             learning_completions: vec![0.0; num_completions],
+            visits: vec![0; num_completions],
         }
     }
 
@@ -143,12 +145,15 @@ impl QNode {
         if let Some(completion_index) = self.get_completion_index(world, state) {
             let old_completion = self.completions[completion_index];
 
-            self.learning_completions[completion_index] *= 1.0 - params.alpha;
+            self.visits[completion_index] += 1;
+            let alpha = params.alpha(self.visits[completion_index]);
+
+            self.learning_completions[completion_index] *= 1.0 - alpha;
             self.learning_completions[completion_index] +=
-                params.alpha * gamma * result_learning_completion;
+                alpha * gamma * result_learning_completion;
 
-            self.completions[completion_index] *= 1.0 - params.alpha;
-            self.completions[completion_index] += params.alpha * gamma * result_completion;
+            self.completions[completion_index] *= 1.0 - alpha;
+            self.completions[completion_index] += alpha * gamma * result_completion;
 
             if params.show_learning {
                 println!(
@@ -317,10 +322,10 @@ impl fmt::Display for QNode {
             QNodeType::Put => write!(f, "Put"),
             QNodeType::NavigateForPut => write!(f, "NavigateForPut"),
             QNodeType::DropOff => write!(f, "DropOff"),
-            QNodeType::North(id) => write!(f, "North({})", id),
-            QNodeType::South(id) => write!(f, "South({})", id),
-            QNodeType::East(id) => write!(f, "East({})", id),
-            QNodeType::West(id) => write!(f, "West({})", id),
+            QNodeType::North(id) => write!(f, "North({})", id.index()),
+            QNodeType::South(id) => write!(f, "South({})", id.index()),
+            QNodeType::East(id) => write!(f, "East({})", id.index()),
+            QNodeType::West(id) => write!(f, "West({})", id.index()),
         }
     }
 }
@@ -0,0 +1,782 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::position::Position;
+use crate::state;
+use crate::world::World;
+
+pub mod taxi;
+
+pub enum Error {
+    UnknownClass { class: ClassId },
+    InvalidTerm { term: String, reason: &'static str },
+    UnknownPredicate { name: String },
+    DuplicatePredicate { name: String },
+    WrongValues { class: String },
+    MissingObject { class: String },
+    NotALocation { position: Position },
+    InvalidState(state::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::UnknownClass { class } => {
+                write!(f, "There is no object class {} in the domain.", class.0)
+            }
+            Error::InvalidTerm { ref term, reason } => {
+                write!(f, "Term {} is not valid, {}.", term, reason)
+            }
+            Error::UnknownPredicate { ref name } => {
+                write!(f, "There is no predicate named '{}'.", name)
+            }
+            Error::DuplicatePredicate { ref name } => {
+                write!(f, "The domain already has a predicate named '{}'.", name)
+            }
+            Error::WrongValues { ref class } => write!(
+                f,
+                "The values do not match the attributes of class '{}'.",
+                class
+            ),
+            Error::MissingObject { ref class } => {
+                write!(f, "The state has no object of class '{}'.", class)
+            }
+            Error::NotALocation { position } => write!(
+                f,
+                "({}, {}) is not a location in the world.",
+                position.x, position.y
+            ),
+            Error::InvalidState(ref state_error) => {
+                write!(f, "Failed to create state from objects: {}", state_error)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<state::Error> for Error {
+    fn from(error: state::Error) -> Self {
+        Error::InvalidState(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClassId(usize);
+
+impl ClassId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    Int,
+    Bool,
+    // A bool, true while the object is carried by an object of the class.
+    In(ClassId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Value {
+    Int(i32),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn as_int(self) -> Option<i32> {
+        match self {
+            Value::Int(value) => Some(value),
+            Value::Bool(_) => None,
+        }
+    }
+
+    pub fn as_bool(self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(value),
+            Value::Int(_) => None,
+        }
+    }
+
+    fn fits(self, kind: AttributeKind) -> bool {
+        matches!(
+            (self, kind),
+            (Value::Int(_), AttributeKind::Int)
+                | (Value::Bool(_), AttributeKind::Bool)
+                | (Value::Bool(_), AttributeKind::In(_))
+        )
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectClass {
+    pub name: String,
+    pub attributes: Vec<(String, AttributeKind)>,
+
+    // The indices of the x and y attributes, for classes that have a place
+    // in the world.
+    position: Option<(usize, usize)>,
+}
+
+impl ObjectClass {
+    pub fn attribute_index(&self, name: &str) -> Option<usize> {
+        self.attributes
+            .iter()
+            .position(|(attribute, _)| attribute == name)
+    }
+
+    pub fn is_placed(&self) -> bool {
+        self.position.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    TouchNorth,
+    TouchSouth,
+    TouchEast,
+    TouchWest,
+    On,
+    In,
+}
+
+impl Relation {
+    fn name(self) -> &'static str {
+        match self {
+            Relation::TouchNorth => "touch_n",
+            Relation::TouchSouth => "touch_s",
+            Relation::TouchEast => "touch_e",
+            Relation::TouchWest => "touch_w",
+            Relation::On => "on",
+            Relation::In => "in",
+        }
+    }
+
+    fn step(self) -> Option<Position> {
+        match self {
+            Relation::TouchNorth => Some(Position::new(0, -1)),
+            Relation::TouchSouth => Some(Position::new(0, 1)),
+            Relation::TouchEast => Some(Position::new(1, 0)),
+            Relation::TouchWest => Some(Position::new(-1, 0)),
+            Relation::On | Relation::In => None,
+        }
+    }
+}
+
+// What the subject of a term is related to, the walls and terrain belong
+// to the world rather than to any object.  Terrain is told apart by its
+// level, see World::terrain_level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Class(ClassId),
+    Walls,
+    Terrain(usize),
+}
+
+pub type PredicateFn = dyn Fn(&Domain, &World, &ObjectState, Option<usize>) -> bool + Send + Sync;
+
+// A named test of a state, for terms that are not a relation between
+// classes.  It is given the same focus as Domain::evaluate.
+#[derive(Clone)]
+pub struct Predicate {
+    name: String,
+    test: Arc<PredicateFn>,
+}
+
+impl Predicate {
+    pub fn new<F>(name: &str, test: F) -> Predicate
+    where
+        F: Fn(&Domain, &World, &ObjectState, Option<usize>) -> bool + Send + Sync + 'static,
+    {
+        Predicate {
+            name: String::from(name),
+            test: Arc::new(test),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Debug for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Predicate({})", self.name)
+    }
+}
+
+// Predicates are known by their names.
+impl PartialEq for Predicate {
+    fn eq(&self, other: &Predicate) -> bool {
+        self.name == other.name
+    }
+}
+
+// Predicates that can be added to a domain by name, so they can be chosen
+// from configuration.
+#[derive(Debug, Clone, Default)]
+pub struct PredicateRegistry {
+    predicates: BTreeMap<String, Predicate>,
+}
+
+impl PredicateRegistry {
+    pub fn new() -> PredicateRegistry {
+        PredicateRegistry::default()
+    }
+
+    // Replaces any predicate registered with the same name.
+    pub fn register(&mut self, predicate: Predicate) {
+        self.predicates.insert(predicate.name.clone(), predicate);
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Predicate, Error> {
+        self.predicates
+            .get(name)
+            .ok_or_else(|| Error::UnknownPredicate {
+                name: String::from(name),
+            })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.predicates.keys().map(|name| name.as_str())
+    }
+}
+
+// A condition is the truth of each of its domain's terms.  A relation
+// between a subject class and a target is true in a state if any pair of
+// objects satisfies it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Relation {
+        relation: Relation,
+        subject: ClassId,
+        target: Target,
+    },
+    Predicate(Predicate),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Object {
+    pub class: ClassId,
+    pub values: Vec<Value>,
+}
+
+// Objects keep their index for as long as they exist, so the same index in
+// two states is the same object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ObjectState {
+    objects: Vec<Object>,
+}
+
+impl ObjectState {
+    pub fn new() -> ObjectState {
+        ObjectState::default()
+    }
+
+    pub fn add(
+        &mut self,
+        domain: &Domain,
+        class: ClassId,
+        values: Vec<Value>,
+    ) -> Result<usize, Error> {
+        let object_class = domain.get_class(class)?;
+
+        let fits = values.len() == object_class.attributes.len()
+            && values
+                .iter()
+                .zip(&object_class.attributes)
+                .all(|(value, &(_, kind))| value.fits(kind));
+
+        if !fits {
+            return Err(Error::WrongValues {
+                class: object_class.name.clone(),
+            });
+        }
+
+        self.objects.push(Object { class, values });
+        Ok(self.objects.len() - 1)
+    }
+
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    // The objects of a class, with their indices.  If the focus is one of
+    // them, it is the only one.
+    pub fn instances(
+        &self,
+        class: ClassId,
+        focus: Option<usize>,
+    ) -> impl Iterator<Item = (usize, &Object)> {
+        let focus_class = focus.map(|index| self.objects[index].class);
+
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(move |&(index, object)| {
+                object.class == class && (focus_class != Some(class) || focus == Some(index))
+            })
+    }
+
+    pub fn first_of(&self, class: ClassId) -> Option<&Object> {
+        self.objects.iter().find(|object| object.class == class)
+    }
+
+    pub fn set_value(&mut self, object: usize, attribute: usize, value: Value) {
+        self.objects[object].values[attribute] = value;
+    }
+}
+
+// The object classes of a problem, and the terms that make up the
+// conditions DOORmax learns effects under.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Domain {
+    classes: Vec<ObjectClass>,
+    terms: Vec<Term>,
+}
+
+impl Domain {
+    pub fn new() -> Domain {
+        Domain::default()
+    }
+
+    // Classes with Int attributes named x and y have a place in the world,
+    // so they can touch and be on things.
+    pub fn add_class(&mut self, name: &str, attributes: &[(&str, AttributeKind)]) -> ClassId {
+        let attributes: Vec<(String, AttributeKind)> = attributes
+            .iter()
+            .map(|&(attribute, kind)| (String::from(attribute), kind))
+            .collect();
+
+        let int_attribute = |wanted: &str| {
+            attributes
+                .iter()
+                .position(|(attribute, kind)| attribute == wanted && *kind == AttributeKind::Int)
+        };
+        let position = int_attribute("x").and_then(|x| int_attribute("y").map(|y| (x, y)));
+
+        self.classes.push(ObjectClass {
+            name: String::from(name),
+            attributes,
+            position,
+        });
+
+        ClassId(self.classes.len() - 1)
+    }
+
+    pub fn add_term(
+        &mut self,
+        relation: Relation,
+        subject: ClassId,
+        target: Target,
+    ) -> Result<(), Error> {
+        let term = Term::Relation {
+            relation,
+            subject,
+            target,
+        };
+
+        self.get_class(subject)?;
+        if let Target::Class(target_class) = target {
+            self.get_class(target_class)?;
+        }
+
+        let invalid = |reason| Error::InvalidTerm {
+            term: self.describe_term(&term),
+            reason,
+        };
+
+        let subject_placed = self.classes[subject.0].is_placed();
+        let target_placed = match target {
+            Target::Class(class) => self.classes[class.0].is_placed(),
+            Target::Walls | Target::Terrain(_) => true,
+        };
+
+        match (relation, target) {
+            (Relation::In, Target::Class(class)) => {
+                let carried = self.classes[subject.0]
+                    .attributes
+                    .iter()
+                    .any(|&(_, kind)| kind == AttributeKind::In(class));
+
+                if !carried {
+                    return Err(invalid(
+                        "the subject has no attribute for being in the target",
+                    ));
+                }
+            }
+            (Relation::In, _) => return Err(invalid("only objects can be in objects")),
+            (Relation::On, Target::Walls) => return Err(invalid("nothing can be on a wall")),
+            (_, Target::Terrain(_)) if relation != Relation::On => {
+                return Err(invalid("terrain can only be stood on"))
+            }
+            _ => {
+                if !subject_placed || !target_placed {
+                    return Err(invalid("both sides need a place in the world"));
+                }
+            }
+        }
+
+        self.terms.push(term);
+        Ok(())
+    }
+
+    pub fn add_predicate(&mut self, predicate: Predicate) -> Result<(), Error> {
+        let duplicate = self.terms.iter().any(|term| match *term {
+            Term::Predicate(ref other) => other.name == predicate.name,
+            Term::Relation { .. } => false,
+        });
+
+        if duplicate {
+            return Err(Error::DuplicatePredicate {
+                name: predicate.name,
+            });
+        }
+
+        self.terms.push(Term::Predicate(predicate));
+        Ok(())
+    }
+
+    pub fn get_class(&self, class: ClassId) -> Result<&ObjectClass, Error> {
+        self.classes
+            .get(class.0)
+            .ok_or(Error::UnknownClass { class })
+    }
+
+    pub fn find_class(&self, name: &str) -> Option<ClassId> {
+        self.classes
+            .iter()
+            .position(|object_class| object_class.name == name)
+            .map(ClassId)
+    }
+
+    pub fn classes(&self) -> &[ObjectClass] {
+        &self.classes
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn describe_term(&self, term: &Term) -> String {
+        let class_name = |class: ClassId| {
+            self.classes
+                .get(class.0)
+                .map_or("?", |object_class| object_class.name.as_str())
+        };
+
+        match *term {
+            Term::Relation {
+                relation,
+                subject,
+                target,
+            } => {
+                let target = match target {
+                    Target::Class(class) => String::from(class_name(class)),
+                    Target::Walls => String::from("wall"),
+                    Target::Terrain(level) => format!("terrain{}", level),
+                };
+
+                format!("{}({}, {})", relation.name(), class_name(subject), target)
+            }
+            Term::Predicate(ref predicate) => predicate.name.clone(),
+        }
+    }
+
+    // An object inside another has no place of its own, so it touches and
+    // is on nothing, and nothing is on it.
+    pub fn position(&self, object: &Object) -> Option<Position> {
+        let (x, y) = self.classes[object.class.0].position?;
+
+        if self.carrier(object).is_some() {
+            return None;
+        }
+
+        Some(Position::new(
+            object.values[x].as_int()?,
+            object.values[y].as_int()?,
+        ))
+    }
+
+    // The class of the object carrying this one, if it is inside another.
+    fn carrier(&self, object: &Object) -> Option<ClassId> {
+        self.classes[object.class.0]
+            .attributes
+            .iter()
+            .zip(&object.values)
+            .find_map(|(&(_, kind), &value)| match (kind, value) {
+                (AttributeKind::In(class), Value::Bool(true)) => Some(class),
+                _ => None,
+            })
+    }
+
+    // Whether the attribute is the x or y of an object inside another.  It
+    // only rides along with its carrier, so actions are not learnt to change
+    // it, and carry moves it instead.
+    pub fn rides_along(&self, object: &Object, attribute: usize) -> bool {
+        match self.classes[object.class.0].position {
+            Some((x, y)) => (attribute == x || attribute == y) && self.carrier(object).is_some(),
+            None => false,
+        }
+    }
+
+    // Moves each object inside another to where the first object of its
+    // carrier's class is.
+    pub fn carry(&self, state: &mut ObjectState) {
+        for index in 0..state.objects.len() {
+            let object = &state.objects[index];
+            let (x, y) = match self.classes[object.class.0].position {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let position = self
+                .carrier(object)
+                .and_then(|class| state.first_of(class))
+                .and_then(|carrier| self.position(carrier));
+
+            if let Some(position) = position {
+                state.set_value(index, x, Value::Int(position.x));
+                state.set_value(index, y, Value::Int(position.y));
+            }
+        }
+    }
+
+    // Whether the term holds in the state.  With a focus object, terms about
+    // its class are only checked against the focus, so conditions can tell
+    // apart objects of the same class.
+    pub fn evaluate(
+        &self,
+        term: &Term,
+        world: &World,
+        state: &ObjectState,
+        focus: Option<usize>,
+    ) -> bool {
+        let (relation, subject, target) = match *term {
+            Term::Relation {
+                relation,
+                subject,
+                target,
+            } => (relation, subject, target),
+            Term::Predicate(ref predicate) => return (predicate.test)(self, world, state, focus),
+        };
+
+        state
+            .instances(subject, focus)
+            .any(|(subject_index, subject)| match target {
+                Target::Walls => self.touches_wall(relation, world, subject),
+                Target::Terrain(level) => self
+                    .position(subject)
+                    .is_some_and(|position| world.terrain_level(position) == Some(level)),
+                Target::Class(class) => {
+                    state.instances(class, focus).any(|(target_index, target)| {
+                        target_index != subject_index
+                            && self.relates(relation, world, subject, target)
+                    })
+                }
+            })
+    }
+
+    fn touches_wall(&self, relation: Relation, world: &World, subject: &Object) -> bool {
+        let position = match self.position(subject) {
+            Some(position) => position,
+            None => return false,
+        };
+        let barriers = world.get_barriers(position);
+
+        match relation {
+            Relation::TouchNorth => barriers.north,
+            Relation::TouchSouth => barriers.south,
+            Relation::TouchEast => barriers.east,
+            Relation::TouchWest => barriers.west,
+            Relation::On | Relation::In => false,
+        }
+    }
+
+    fn relates(
+        &self,
+        relation: Relation,
+        world: &World,
+        subject: &Object,
+        target: &Object,
+    ) -> bool {
+        match relation {
+            Relation::In => self.classes[subject.class.0]
+                .attributes
+                .iter()
+                .zip(&subject.values)
+                .any(|(&(_, kind), &value)| {
+                    kind == AttributeKind::In(target.class) && value == Value::Bool(true)
+                }),
+
+            Relation::On => match (self.position(subject), self.position(target)) {
+                (Some(subject_position), Some(target_position)) => {
+                    subject_position == target_position
+                }
+                _ => false,
+            },
+
+            _ => match (self.position(subject), self.position(target)) {
+                (Some(subject_position), Some(target_position)) => {
+                    let step = relation.step().unwrap();
+
+                    target_position == subject_position + step
+                        && !self.touches_wall(relation, world, subject)
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_oomdp {
+
+    use super::*;
+
+    use crate::world::Costs;
+
+    fn build_world() -> World {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │  ─  │\n\
+                      │. . .│\n\
+                      └─────┘\n\
+                      ";
+
+        World::build_from_str(source, Costs::default()).unwrap()
+    }
+
+    #[test]
+    fn evaluates_relations() {
+        let world = build_world();
+
+        let mut domain = Domain::new();
+        let taxi = domain.add_class(
+            "taxi",
+            &[("x", AttributeKind::Int), ("y", AttributeKind::Int)],
+        );
+        let passenger = domain.add_class(
+            "passenger",
+            &[
+                ("x", AttributeKind::Int),
+                ("y", AttributeKind::Int),
+                ("in_taxi", AttributeKind::In(taxi)),
+            ],
+        );
+
+        domain
+            .add_term(Relation::TouchSouth, taxi, Target::Walls)
+            .unwrap();
+        domain
+            .add_term(Relation::TouchEast, taxi, Target::Class(passenger))
+            .unwrap();
+        domain
+            .add_term(Relation::On, taxi, Target::Class(passenger))
+            .unwrap();
+        domain
+            .add_term(Relation::In, passenger, Target::Class(taxi))
+            .unwrap();
+
+        let mut state = ObjectState::new();
+        state
+            .add(&domain, taxi, vec![Value::Int(1), Value::Int(0)])
+            .unwrap();
+        let first = state
+            .add(
+                &domain,
+                passenger,
+                vec![Value::Int(2), Value::Int(0), Value::Bool(false)],
+            )
+            .unwrap();
+        let second = state
+            .add(
+                &domain,
+                passenger,
+                vec![Value::Int(1), Value::Int(0), Value::Bool(true)],
+            )
+            .unwrap();
+        let third = state
+            .add(
+                &domain,
+                passenger,
+                vec![Value::Int(1), Value::Int(0), Value::Bool(false)],
+            )
+            .unwrap();
+
+        let holds =
+            |index: usize, focus| domain.evaluate(&domain.terms()[index], &world, &state, focus);
+
+        assert!(holds(0, None));
+        assert!(holds(1, None));
+        assert!(holds(2, None));
+        assert!(holds(3, None));
+
+        // Bound to one passenger, only that passenger counts.
+        assert!(holds(1, Some(first)));
+        assert!(!holds(2, Some(first)));
+        assert!(!holds(3, Some(first)));
+
+        // A carried passenger is nowhere the taxi could be on.
+        assert!(!holds(1, Some(second)));
+        assert!(!holds(2, Some(second)));
+        assert!(holds(3, Some(second)));
+
+        assert!(!holds(1, Some(third)));
+        assert!(holds(2, Some(third)));
+        assert!(!holds(3, Some(third)));
+    }
+
+    #[test]
+    fn rejects_invalid_terms_and_values() {
+        let mut domain = Domain::new();
+        let taxi = domain.add_class(
+            "taxi",
+            &[("x", AttributeKind::Int), ("y", AttributeKind::Int)],
+        );
+        let fuel = domain.add_class("fuel", &[("level", AttributeKind::Int)]);
+
+        assert_matches!(
+            domain.add_term(Relation::On, taxi, Target::Class(fuel)),
+            Err(Error::InvalidTerm { .. })
+        );
+        assert_matches!(
+            domain.add_term(Relation::In, fuel, Target::Class(taxi)),
+            Err(Error::InvalidTerm { .. })
+        );
+        assert_matches!(
+            domain.add_term(Relation::TouchNorth, taxi, Target::Terrain(0)),
+            Err(Error::InvalidTerm { .. })
+        );
+        assert_matches!(
+            domain.add_term(Relation::On, taxi, Target::Class(ClassId(7))),
+            Err(Error::UnknownClass { .. })
+        );
+
+        let mut state = ObjectState::new();
+        assert_matches!(
+            state.add(&domain, fuel, vec![Value::Bool(true)]),
+            Err(Error::WrongValues { .. })
+        );
+        assert_matches!(
+            state.add(&domain, taxi, vec![Value::Int(0)]),
+            Err(Error::WrongValues { .. })
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use crate::position::Position;
+use crate::state::State;
+use crate::state_indexer::StateIndexer;
+use crate::world::{LocationId, World};
+
+use super::{
+    AttributeKind, ClassId, Domain, Error, ObjectState, Predicate, PredicateRegistry, Relation,
+    Target, Term, Value,
+};
+
+const X: usize = 0;
+const Y: usize = 1;
+const IN_TAXI: usize = 2;
+
+// The taxi problem as objects: the taxi, the passenger, who rides along
+// with the taxi while carried, and the destination.
+#[derive(Debug, Clone)]
+pub struct TaxiObjects {
+    domain: Domain,
+    taxi: ClassId,
+    passenger: ClassId,
+    destination: ClassId,
+}
+
+impl TaxiObjects {
+    pub fn new() -> TaxiObjects {
+        let mut domain = Domain::new();
+
+        let taxi = domain.add_class(
+            "taxi",
+            &[("x", AttributeKind::Int), ("y", AttributeKind::Int)],
+        );
+        let passenger = domain.add_class(
+            "passenger",
+            &[
+                ("x", AttributeKind::Int),
+                ("y", AttributeKind::Int),
+                ("in_taxi", AttributeKind::In(taxi)),
+            ],
+        );
+        let destination = domain.add_class(
+            "destination",
+            &[("x", AttributeKind::Int), ("y", AttributeKind::Int)],
+        );
+
+        let terms = [
+            (Relation::TouchNorth, taxi, Target::Walls),
+            (Relation::TouchSouth, taxi, Target::Walls),
+            (Relation::TouchEast, taxi, Target::Walls),
+            (Relation::TouchWest, taxi, Target::Walls),
+            (Relation::On, taxi, Target::Class(passenger)),
+            (Relation::On, taxi, Target::Class(destination)),
+            (Relation::In, passenger, Target::Class(taxi)),
+        ];
+
+        for &(relation, subject, target) in &terms {
+            domain.add_term(relation, subject, target).unwrap();
+        }
+
+        TaxiObjects {
+            domain,
+            taxi,
+            passenger,
+            destination,
+        }
+    }
+
+    // The taxi domain with predicates from registry added, by name.
+    pub fn with_predicates(names: &[String]) -> Result<TaxiObjects, Error> {
+        let mut taxi_objects = TaxiObjects::new();
+        let registry = taxi_objects.registry();
+
+        for name in names {
+            taxi_objects.add_predicate(registry.get(name)?.clone())?;
+        }
+
+        Ok(taxi_objects)
+    }
+
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    pub fn add_predicate(&mut self, predicate: Predicate) -> Result<(), Error> {
+        self.domain.add_predicate(predicate)
+    }
+
+    // A term for the taxi being on each level of the world's terrain, so
+    // moves that cost differently have different conditions.  Levels the
+    // domain already has are kept, so a model can move to another world.
+    pub fn add_terrain(&mut self, world: &World) {
+        for level in 0..world.num_terrain_levels() {
+            let term = Term::Relation {
+                relation: Relation::On,
+                subject: self.taxi,
+                target: Target::Terrain(level),
+            };
+
+            if !self.domain.terms().contains(&term) {
+                self.domain
+                    .add_term(Relation::On, self.taxi, Target::Terrain(level))
+                    .unwrap();
+            }
+        }
+    }
+
+    // The predicates with_predicates can add.
+    pub fn registry(&self) -> PredicateRegistry {
+        let mut registry = PredicateRegistry::new();
+        let taxi = self.taxi;
+        let destination = self.destination;
+
+        // The destination lies further in the direction of the step.
+        let directions = [
+            ("destination_north", Relation::TouchNorth),
+            ("destination_south", Relation::TouchSouth),
+            ("destination_east", Relation::TouchEast),
+            ("destination_west", Relation::TouchWest),
+        ];
+
+        for &(name, relation) in &directions {
+            let step = relation.step().unwrap();
+
+            registry.register(Predicate::new(name, move |domain, _, state, focus| {
+                state.instances(taxi, focus).any(|(_, taxi_object)| {
+                    state
+                        .instances(destination, focus)
+                        .any(|(_, destination_object)| {
+                            match (
+                                domain.position(taxi_object),
+                                domain.position(destination_object),
+                            ) {
+                                (Some(from), Some(to)) => {
+                                    (to.x - from.x) * step.x + (to.y - from.y) * step.y > 0
+                                }
+                                _ => false,
+                            }
+                        })
+                })
+            }));
+        }
+
+        // A wall blocks a move that would take the taxi further from the
+        // destination.
+        registry.register(Predicate::new(
+            "wall_behind",
+            move |domain, world, state, focus| {
+                state.instances(taxi, focus).any(|(_, taxi_object)| {
+                    state
+                        .instances(destination, focus)
+                        .any(|(_, destination_object)| {
+                            let (from, to) = match (
+                                domain.position(taxi_object),
+                                domain.position(destination_object),
+                            ) {
+                                (Some(from), Some(to)) => (from, to),
+                                _ => return false,
+                            };
+
+                            directions.iter().any(|&(_, relation)| {
+                                let step = relation.step().unwrap();
+
+                                (to.x - from.x) * step.x + (to.y - from.y) * step.y < 0
+                                    && domain.touches_wall(relation, world, taxi_object)
+                            })
+                        })
+                })
+            },
+        ));
+
+        // A location is one move away.
+        registry.register(Predicate::new(
+            "adjacent_to_stop",
+            move |domain, world, state, focus| {
+                let touches = [
+                    Relation::TouchNorth,
+                    Relation::TouchSouth,
+                    Relation::TouchEast,
+                    Relation::TouchWest,
+                ];
+
+                state.instances(taxi, focus).any(|(_, taxi_object)| {
+                    let position = match domain.position(taxi_object) {
+                        Some(position) => position,
+                        None => return false,
+                    };
+
+                    touches.iter().any(|&relation| {
+                        !domain.touches_wall(relation, world, taxi_object)
+                            && world
+                                .get_fixed_id(position + relation.step().unwrap())
+                                .is_some()
+                    })
+                })
+            },
+        ));
+
+        registry
+    }
+
+    pub fn to_objects(&self, world: &World, state: &State) -> ObjectState {
+        let taxi = state.get_taxi();
+
+        let (passenger, in_taxi) = match state.get_passenger() {
+            Some(id) => (world.get_fixed_position(id).unwrap(), false),
+            None => (taxi, true),
+        };
+        let destination = world.get_fixed_position(state.get_destination()).unwrap();
+
+        let mut objects = ObjectState::new();
+
+        // The values always fit the classes built in new.
+        objects
+            .add(
+                &self.domain,
+                self.taxi,
+                vec![Value::Int(taxi.x), Value::Int(taxi.y)],
+            )
+            .unwrap();
+        objects
+            .add(
+                &self.domain,
+                self.passenger,
+                vec![
+                    Value::Int(passenger.x),
+                    Value::Int(passenger.y),
+                    Value::Bool(in_taxi),
+                ],
+            )
+            .unwrap();
+        objects
+            .add(
+                &self.domain,
+                self.destination,
+                vec![Value::Int(destination.x), Value::Int(destination.y)],
+            )
+            .unwrap();
+
+        objects
+    }
+
+    // The objects of every state, by state index.  They never change, so
+    // learners work them out once rather than at every step.
+    pub fn all_objects(&self, world: &World, state_indexer: &StateIndexer) -> Vec<ObjectState> {
+        (0..state_indexer.num_states())
+            .map(|state_index| {
+                let state = state_indexer.get_state(world, state_index).unwrap();
+                self.to_objects(world, &state)
+            })
+            .collect()
+    }
+
+    pub fn to_state(&self, world: &World, objects: &ObjectState) -> Result<State, Error> {
+        let taxi = self.position_of(objects, self.taxi)?;
+        let destination = self.location_of(world, objects, self.destination)?;
+
+        let passenger_object = objects
+            .first_of(self.passenger)
+            .ok_or(Error::MissingObject {
+                class: String::from("passenger"),
+            })?;
+
+        let passenger = if passenger_object.values[IN_TAXI] == Value::Bool(true) {
+            None
+        } else {
+            Some(self.location_of(world, objects, self.passenger)?)
+        };
+
+        Ok(State::build(
+            world,
+            (taxi.x, taxi.y),
+            passenger,
+            destination,
+        )?)
+    }
+
+    fn position_of(&self, objects: &ObjectState, class: ClassId) -> Result<Position, Error> {
+        let object = objects
+            .first_of(class)
+            .ok_or_else(|| Error::MissingObject {
+                class: self.domain.classes()[class.index()].name.clone(),
+            })?;
+
+        match (object.values[X], object.values[Y]) {
+            (Value::Int(x), Value::Int(y)) => Ok(Position::new(x, y)),
+            _ => Err(Error::WrongValues {
+                class: self.domain.classes()[class.index()].name.clone(),
+            }),
+        }
+    }
+
+    fn location_of(
+        &self,
+        world: &World,
+        objects: &ObjectState,
+        class: ClassId,
+    ) -> Result<LocationId, Error> {
+        let position = self.position_of(objects, class)?;
+
+        world
+            .get_fixed_id(position)
+            .ok_or(Error::NotALocation { position })
+    }
+}
+
+impl Default for TaxiObjects {
+    fn default() -> Self {
+        TaxiObjects::new()
+    }
+}
+
+#[cfg(test)]
+mod test_taxi {
+
+    use super::*;
+
+    use crate::world::Costs;
+
+    #[test]
+    fn round_trips_states() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │     │\n\
+                      │Y . B│\n\
+                      └─────┘\n\
+                      ";
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+        let taxi_objects = TaxiObjects::new();
+
+        let waiting = State::build_named(&world, (1, 1), Some("Y"), "G").unwrap();
+        let carried = State::build_named(&world, (1, 1), None, "G").unwrap();
+
+        for state in &[waiting, carried] {
+            let objects = taxi_objects.to_objects(&world, state);
+            assert_eq!(taxi_objects.to_state(&world, &objects).unwrap(), *state);
+        }
+
+        // A carried passenger rides along, but has no place of its own.
+        let objects = taxi_objects.to_objects(&world, &carried);
+        assert_eq!(objects.objects()[1].values[X], Value::Int(1));
+        assert_eq!(taxi_objects.domain().position(&objects.objects()[1]), None);
+
+        let mut lost = taxi_objects.to_objects(&world, &waiting);
+        lost.set_value(1, X, Value::Int(1));
+        assert_matches!(
+            taxi_objects.to_state(&world, &lost),
+            Err(Error::NotALocation { .. })
+        );
+    }
+
+    #[test]
+    fn adds_registered_predicates() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │  ─  │\n\
+                      │Y . B│\n\
+                      └─────┘\n\
+                      ";
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+
+        let names = vec![
+            String::from("destination_north"),
+            String::from("destination_east"),
+            String::from("adjacent_to_stop"),
+            String::from("wall_behind"),
+        ];
+        let taxi_objects = TaxiObjects::with_predicates(&names).unwrap();
+        let terms = taxi_objects.domain().terms();
+        assert_eq!(terms.len(), 11);
+
+        let holds = |state: &State| -> Vec<bool> {
+            let objects = taxi_objects.to_objects(&world, state);
+            terms[7..]
+                .iter()
+                .map(|term| taxi_objects.domain().evaluate(term, &world, &objects, None))
+                .collect()
+        };
+
+        let below = State::build_named(&world, (1, 1), Some("Y"), "G").unwrap();
+        // The bottom edge is behind the taxi, going away from G.
+        assert_eq!(holds(&below), vec![true, true, true, true]);
+
+        // R is on the same row, to the west, and no wall is to the east.
+        let beside = State::build_named(&world, (1, 0), Some("Y"), "R").unwrap();
+        assert_eq!(holds(&beside), vec![false, false, true, false]);
+
+        assert_matches!(
+            TaxiObjects::with_predicates(&[String::from("fuel_low")]),
+            Err(Error::UnknownPredicate { .. })
+        );
+
+        let mut twice = TaxiObjects::with_predicates(&names).unwrap();
+        let registry = twice.registry();
+        assert_matches!(
+            twice.add_predicate(registry.get("adjacent_to_stop").unwrap().clone()),
+            Err(Error::DuplicatePredicate { .. })
+        );
+    }
+
+    #[test]
+    fn adds_a_term_per_terrain_level() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │     │\n\
+                      │Y . B│\n\
+                      └─────┘\n\
+                      ";
+        let mut world = World::build_from_str(source, Costs::default()).unwrap();
+        world.apply_terrain_layer("2 . 4\n. 4 .\n").unwrap();
+
+        let mut taxi_objects = TaxiObjects::new();
+        let num_terms = taxi_objects.domain().terms().len();
+
+        taxi_objects.add_terrain(&world);
+        taxi_objects.add_terrain(&world);
+        let terms = &taxi_objects.domain().terms()[num_terms..];
+        assert_eq!(terms.len(), 2);
+        assert_eq!(
+            taxi_objects.domain().describe_term(&terms[1]),
+            "on(taxi, terrain1)"
+        );
+
+        let holds = |taxi_pos| -> Vec<bool> {
+            let state = State::build_named(&world, taxi_pos, Some("Y"), "G").unwrap();
+            let objects = taxi_objects.to_objects(&world, &state);
+            terms
+                .iter()
+                .map(|term| taxi_objects.domain().evaluate(term, &world, &objects, None))
+                .collect()
+        };
+
+        assert_eq!(holds((0, 0)), vec![true, false]);
+        assert_eq!(holds((2, 0)), vec![false, true]);
+        assert_eq!(holds((1, 1)), vec![false, true]);
+        assert_eq!(holds((1, 0)), vec![false, false]);
+    }
+}
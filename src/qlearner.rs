@@ -1,37 +1,47 @@
 use rand::Rng;
 
 use crate::actions::Actions;
+use crate::schedule::{Schedule, ScheduleClock};
 use crate::state::State;
 use crate::state_indexer::StateIndexer;
 use crate::world::World;
 
-use crate::runner::{Attempt, Runner};
+use crate::runner::{Attempt, ContinuingRunner, Runner};
 
 #[derive(Debug, Clone)]
 pub struct QLearner {
-    alpha: f64,
+    alpha: Schedule,
     gamma: f64,
-    epsilon: f64,
+    epsilon: Schedule,
+    clock: ScheduleClock,
 
     state_indexer: StateIndexer,
     qtable: Vec<[f64; Actions::NUM_ELEMENTS]>,
+    visits: Vec<[usize; Actions::NUM_ELEMENTS]>,
 }
 
 impl QLearner {
-    pub fn new(world: &World, alpha: f64, gamma: f64, epsilon: f64) -> QLearner {
+    pub fn new<A, E>(world: &World, alpha: A, gamma: f64, epsilon: E) -> QLearner
+    where
+        A: Into<Schedule>,
+        E: Into<Schedule>,
+    {
         let initial_q_value = world.max_reward() / (1.0 - gamma);
 
         let state_indexer = StateIndexer::new(world);
         let num_states = state_indexer.num_states();
         let qtable = vec![[initial_q_value; Actions::NUM_ELEMENTS]; num_states];
+        let visits = vec![[0; Actions::NUM_ELEMENTS]; num_states];
 
         QLearner {
-            alpha,
+            alpha: alpha.into(),
             gamma,
-            epsilon,
+            epsilon: epsilon.into(),
+            clock: ScheduleClock::new(),
 
             state_indexer,
             qtable,
+            visits,
         }
     }
 
@@ -67,9 +77,12 @@ impl QLearner {
         state_index: usize,
         mut rng: &mut R,
     ) -> Option<Actions> {
+        let state_visits = self.visits[state_index].iter().sum::<usize>() + 1;
+        let epsilon = self.epsilon.value(&self.clock, state_visits);
+
         let nongreedy_roll = rng.gen_range(0.0f64, 1.0f64);
 
-        if nongreedy_roll < self.epsilon {
+        if nongreedy_roll < epsilon {
             Actions::from_index(rng.gen_range(0, Actions::NUM_ELEMENTS))
         } else {
             self.determine_greedy_action(state_index, &mut rng)
@@ -104,20 +117,22 @@ impl QLearner {
         reward: f64,
     ) {
         if let Some(next_state_value) = self.find_maximal_value(next_state_index) {
+            let action_visits = &mut self.visits[state_index][next_action.to_index()];
+            *action_visits += 1;
+
+            let alpha = self.alpha.value(&self.clock, *action_visits);
+
             let state_values = &mut self.qtable[state_index];
             let action_entry = &mut state_values[next_action.to_index()];
 
-            if self.alpha > 0.0 {
-                *action_entry *= 1.0 - self.alpha;
+            if alpha > 0.0 {
+                *action_entry *= 1.0 - alpha;
             }
 
-            *action_entry += self.alpha * (reward + self.gamma * next_state_value);
+            *action_entry += alpha * (reward + self.gamma * next_state_value);
         }
     }
-}
-
-impl Runner for QLearner {
-    fn learn<R: Rng>(
+    fn learn_trial<R: Rng>(
         &mut self,
         world: &World,
         mut state: State,
@@ -140,6 +155,7 @@ impl Runner for QLearner {
                         return None;
                     }
 
+                    self.clock.step();
                     state = next_state;
                 } else {
                     return None;
@@ -155,6 +171,21 @@ impl Runner for QLearner {
             None
         }
     }
+}
+
+impl Runner for QLearner {
+    fn learn<R: Rng>(
+        &mut self,
+        world: &World,
+        state: State,
+        max_steps: usize,
+        mut rng: &mut R,
+    ) -> Option<usize> {
+        let result = self.learn_trial(world, state, max_steps, &mut rng);
+        self.clock.finish_trial();
+
+        result
+    }
 
     fn attempt<R: Rng>(
         &self,
@@ -228,6 +259,36 @@ impl Runner for QLearner {
     }
 }
 
+impl ContinuingRunner for QLearner {
+    fn choose_action<R: Rng>(
+        &mut self,
+        world: &World,
+        state: &State,
+        mut rng: &mut R,
+    ) -> Option<Actions> {
+        let state_index = self.state_indexer.get_index(world, state)?;
+        self.determine_learning_action(state_index, &mut rng)
+    }
+
+    fn observe(
+        &mut self,
+        world: &World,
+        state: &State,
+        action: Actions,
+        reward: f64,
+        next_state: &State,
+    ) {
+        let state_index = self.state_indexer.get_index(world, state);
+        let next_state_index = self.state_indexer.get_index(world, next_state);
+
+        if let (Some(state_index), Some(next_state_index)) = (state_index, next_state_index) {
+            self.apply_experience(state_index, action, next_state_index, reward);
+        }
+
+        self.clock.step();
+    }
+}
+
 #[cfg(test)]
 mod test_qlearner {
 
@@ -257,7 +318,7 @@ mod test_qlearner {
 
         let mut rng = thread_rng();
 
-        let initial_state = State::build(&world, (0, 1), Some('R'), 'G').unwrap();
+        let initial_state = State::build_named(&world, (0, 1), Some("R"), "G").unwrap();
         assert_eq!(expected_initial_str, initial_state.display(&world));
 
         let mut qlearner = QLearner::new(&world, 1.0, 1.0, 0.0);
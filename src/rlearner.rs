@@ -0,0 +1,240 @@
+use rand::Rng;
+
+use crate::actions::Actions;
+use crate::runner::ContinuingRunner;
+use crate::schedule::{Schedule, ScheduleClock};
+use crate::state::State;
+use crate::state_indexer::StateIndexer;
+use crate::world::World;
+
+// R-learning (Schwartz 1993), which maximises the reward per step rather than
+// a discounted return, so it suits the continuing task.  The table holds
+// values relative to the running average reward rho.
+#[derive(Debug, Clone)]
+pub struct RLearner {
+    alpha: Schedule,
+    beta: Schedule,
+    epsilon: Schedule,
+    clock: ScheduleClock,
+
+    rho: f64,
+    rho_updates: usize,
+
+    state_indexer: StateIndexer,
+    rtable: Vec<[f64; Actions::NUM_ELEMENTS]>,
+    visits: Vec<[usize; Actions::NUM_ELEMENTS]>,
+}
+
+impl RLearner {
+    pub fn new<A, B, E>(world: &World, alpha: A, beta: B, epsilon: E) -> RLearner
+    where
+        A: Into<Schedule>,
+        B: Into<Schedule>,
+        E: Into<Schedule>,
+    {
+        let state_indexer = StateIndexer::new(world);
+        let num_states = state_indexer.num_states();
+
+        // Every reward is at most max_reward, so starting rho there makes
+        // untried actions look better than tried ones.
+        RLearner {
+            alpha: alpha.into(),
+            beta: beta.into(),
+            epsilon: epsilon.into(),
+            clock: ScheduleClock::new(),
+
+            rho: world.max_reward(),
+            rho_updates: 0,
+
+            state_indexer,
+            rtable: vec![[0.0; Actions::NUM_ELEMENTS]; num_states],
+            visits: vec![[0; Actions::NUM_ELEMENTS]; num_states],
+        }
+    }
+
+    // The learnt average reward per step.
+    pub fn average_reward(&self) -> f64 {
+        self.rho
+    }
+
+    fn maximal_value(&self, state_index: usize) -> f64 {
+        self.rtable[state_index]
+            .iter()
+            .cloned()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    fn determine_greedy_action<R: Rng>(&self, state_index: usize, rng: &mut R) -> Option<Actions> {
+        let best_value = self.maximal_value(state_index);
+
+        let best_actions: Vec<usize> = self.rtable[state_index]
+            .iter()
+            .enumerate()
+            .filter(|&(_, value)| approx_eq!(f64, *value, best_value, ulps = 2))
+            .map(|(index, _)| index)
+            .collect();
+
+        Actions::from_index(best_actions[rng.gen_range(0, best_actions.len())])
+    }
+
+    fn apply_experience(
+        &mut self,
+        state_index: usize,
+        action: Actions,
+        next_state_index: usize,
+        reward: f64,
+    ) {
+        let next_state_value = self.maximal_value(next_state_index);
+
+        let action_visits = &mut self.visits[state_index][action.to_index()];
+        *action_visits += 1;
+
+        let alpha = self.alpha.value(&self.clock, *action_visits);
+
+        let entry = &mut self.rtable[state_index][action.to_index()];
+        *entry += alpha * (reward - self.rho + next_state_value - *entry);
+        let entry = *entry;
+
+        // rho only follows greedy steps, exploration would drag it down.
+        let state_value = self.maximal_value(state_index);
+        if approx_eq!(f64, entry, state_value, ulps = 2) {
+            self.rho_updates += 1;
+            let beta = self.beta.value(&self.clock, self.rho_updates);
+
+            self.rho += beta * (reward - self.rho + next_state_value - state_value);
+        }
+    }
+}
+
+impl ContinuingRunner for RLearner {
+    fn choose_action<R: Rng>(
+        &mut self,
+        world: &World,
+        state: &State,
+        mut rng: &mut R,
+    ) -> Option<Actions> {
+        let state_index = self.state_indexer.get_index(world, state)?;
+
+        let state_visits = self.visits[state_index].iter().sum::<usize>() + 1;
+        let epsilon = self.epsilon.value(&self.clock, state_visits);
+
+        if rng.gen_range(0.0f64, 1.0f64) < epsilon {
+            Actions::from_index(rng.gen_range(0, Actions::NUM_ELEMENTS))
+        } else {
+            self.determine_greedy_action(state_index, &mut rng)
+        }
+    }
+
+    fn observe(
+        &mut self,
+        world: &World,
+        state: &State,
+        action: Actions,
+        reward: f64,
+        next_state: &State,
+    ) {
+        let state_index = self.state_indexer.get_index(world, state);
+        let next_state_index = self.state_indexer.get_index(world, next_state);
+
+        if let (Some(state_index), Some(next_state_index)) = (state_index, next_state_index) {
+            self.apply_experience(state_index, action, next_state_index, reward);
+        }
+
+        self.clock.step();
+    }
+}
+
+#[cfg(test)]
+mod test_rlearner {
+
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64Mcg;
+
+    use crate::runner::run_continuing_session;
+    use crate::schedule::{Decay, Timescale};
+    use crate::world::Costs;
+
+    fn build_world() -> World {
+        let source = "\
+                      ┌───┐\n\
+                      │R .│\n\
+                      │   │\n\
+                      │. G│\n\
+                      └───┘\n\
+                      ";
+
+        World::build_from_str(source, Costs::default()).unwrap()
+    }
+
+    #[test]
+    fn greedy_step_moves_average_reward() {
+        let world = build_world();
+        let mut rlearner = RLearner::new(&world, 0.5, 0.5, 0.0);
+
+        let state = State::build_named(&world, (1, 0), Some("R"), "G").unwrap();
+        let (reward, next_state) = state.apply_action(&world, Actions::West);
+
+        rlearner.observe(&world, &state, Actions::West, reward, &next_state);
+
+        let state_index = rlearner.state_indexer.get_index(&world, &state).unwrap();
+        let value = rlearner.rtable[state_index][Actions::West.to_index()];
+
+        // Every other action is still at 0, so West is no longer greedy.
+        assert!(approx_eq!(f64, value, -0.5));
+        assert!(approx_eq!(f64, rlearner.average_reward(), 0.0));
+
+        // Make PickUp the only sensible action, leading somewhere worth -1.
+        let (reward, after) = next_state.apply_action(&world, Actions::PickUp);
+        let next_index = rlearner
+            .state_indexer
+            .get_index(&world, &next_state)
+            .unwrap();
+        let after_index = rlearner.state_indexer.get_index(&world, &after).unwrap();
+
+        rlearner.rtable[next_index] = [-1.0; Actions::NUM_ELEMENTS];
+        rlearner.rtable[next_index][Actions::PickUp.to_index()] = 0.0;
+        rlearner.rtable[after_index] = [-1.0; Actions::NUM_ELEMENTS];
+
+        rlearner.observe(&world, &next_state, Actions::PickUp, reward, &after);
+
+        // PickUp is now -0.5 and still greedy, so rho moves by
+        // 0.5 * (0 - 0 + -1 - -0.5).
+        assert!(approx_eq!(
+            f64,
+            rlearner.rtable[next_index][Actions::PickUp.to_index()],
+            -0.5
+        ));
+        assert!(approx_eq!(f64, rlearner.average_reward(), -0.25));
+    }
+
+    #[test]
+    fn learns_to_serve_requests() {
+        let world = build_world();
+
+        let epsilon = Schedule::new(
+            Decay::Linear {
+                start: 0.3,
+                end: 0.0,
+                duration: 15_000,
+            },
+            Timescale::Step,
+        );
+        let mut rlearner = RLearner::new(&world, 0.2, 0.01, epsilon);
+
+        let mut rng = Pcg64Mcg::seed_from_u64(13);
+
+        run_continuing_session(&world, 20_000, &mut rlearner, &mut rng).unwrap();
+        let result = run_continuing_session(&world, 5_000, &mut rlearner, &mut rng).unwrap();
+
+        // Serving takes 4 or 6 steps from the last drop off, 5 on average.
+        assert!(result.requests_per_thousand_steps() > 180.0);
+        assert!(approx_eq!(
+            f64,
+            rlearner.average_reward(),
+            result.average_reward(),
+            epsilon = 0.2
+        ));
+    }
+}
@@ -3,8 +3,9 @@ use std::fmt;
 use rand::Rng;
 
 use crate::actions::Actions;
+use crate::start::Curriculum;
 use crate::state;
-use crate::state::State;
+use crate::state::{State, StateIterator};
 use crate::world::World;
 
 #[derive(Debug)]
@@ -41,6 +42,42 @@ pub trait Runner {
     fn solves<R: Rng>(&self, world: &World, state: State, max_steps: usize, rng: &mut R) -> bool;
 
     fn report_training_result(&self, _world: &World, _steps: Option<usize>) {}
+
+    // How well the solver's learnt model predicts the world, None for
+    // solvers without a model.
+    fn evaluate_model(&self, _world: &World) -> Option<ModelAccuracy> {
+        None
+    }
+}
+
+// A solver whose learnt model does not depend on the layout of the world
+// it was learnt in, so it can be moved to another world.
+pub trait TransferRunner: Runner + Sized {
+    // A solver for world that knows what this one has learnt, and has
+    // planned with it.  Anything tied to the old world's layout is lost.
+    fn transfer(&self, world: &World) -> Self;
+}
+
+// A solver for the continuing task, where every delivery is followed by a
+// new request rather than ending the trial.
+pub trait ContinuingRunner {
+    fn choose_action<R: Rng>(
+        &mut self,
+        world: &World,
+        state: &State,
+        rng: &mut R,
+    ) -> Option<Actions>;
+
+    // next_state already holds the new request when action delivered the
+    // passenger.
+    fn observe(
+        &mut self,
+        world: &World,
+        state: &State,
+        action: Actions,
+        reward: f64,
+        next_state: &State,
+    );
 }
 
 pub struct Attempt {
@@ -67,36 +104,196 @@ impl Attempt {
     }
 }
 
+// How many of a model's predictions match the world.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PredictionCounts {
+    pub correct: usize,
+    pub wrong: usize,
+    pub unknown: usize,
+}
+
+impl PredictionCounts {
+    // None for a prediction the model does not know.
+    pub fn add(&mut self, correct: Option<bool>) {
+        match correct {
+            Some(true) => self.correct += 1,
+            Some(false) => self.wrong += 1,
+            None => self.unknown += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.correct + self.wrong + self.unknown
+    }
+
+    // The fraction of all predictions that are correct.
+    pub fn accuracy(&self) -> f64 {
+        if self.total() > 0 {
+            self.correct as f64 / self.total() as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelAccuracy {
+    pub transitions: PredictionCounts,
+    pub rewards: PredictionCounts,
+}
+
+// Compares a model with the world for every action in every state, other
+// than those at their destination, which no solver learns from.  The world
+// is deterministic, so a transition is only correct if the model gives the
+// true next state all of the chance.  predict_transition gives the chance of
+// a next state and predict_reward the reward, None where they are not known.
+pub fn measure_model_accuracy<T, W>(
+    world: &World,
+    predict_transition: T,
+    predict_reward: W,
+) -> ModelAccuracy
+where
+    T: Fn(&State, Actions, &State) -> Option<f64>,
+    W: Fn(&State, Actions) -> Option<f64>,
+{
+    let mut accuracy = ModelAccuracy::default();
+
+    for state in StateIterator::new(world).filter(|state| !state.at_destination()) {
+        for action_index in 0..Actions::NUM_ELEMENTS {
+            let action = Actions::from_index(action_index).unwrap();
+            let (reward, next_state) = state.apply_action(world, action);
+
+            accuracy.transitions.add(
+                predict_transition(&state, action, &next_state)
+                    .map(|chance| approx_eq!(f64, chance, 1.0, ulps = 2)),
+            );
+            accuracy.rewards.add(
+                predict_reward(&state, action)
+                    .map(|predicted| approx_eq!(f64, predicted, reward, ulps = 2)),
+            );
+        }
+    }
+
+    accuracy
+}
+
 pub enum Error {
     BuildRandomState(state::Error),
+    NoAction { step: usize },
 }
 
-impl fmt::Debug for Error {
+impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::BuildRandomState(ref state_error) => {
-                write!(f, "Failed to build random state:\n{:?}", state_error)
+                write!(f, "Failed to build random state:\n{}", state_error)
             }
+            Error::NoAction { step } => write!(f, "No action was chosen at step {}.", step),
         }
     }
 }
 
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 pub fn run_training_session<Rnr, R>(
     world: &World,
+    starts: &Curriculum,
+    probes: &[Probe],
+    max_trials: usize,
+    max_steps: usize,
+    runner: &mut Rnr,
+    rng: &mut R,
+) -> Result<Option<usize>, Error>
+where
+    Rnr: Runner,
+    R: Rng,
+{
+    run_trials(
+        world,
+        starts,
+        probes,
+        max_trials,
+        max_steps,
+        runner,
+        rng,
+        |_, _, _| {},
+    )
+}
+
+// The accuracy of a runner's model after a trial, with the steps taken
+// over all trials so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccuracyPoint {
+    pub trial: usize,
+    pub steps: usize,
+    pub accuracy: ModelAccuracy,
+}
+
+// Runs a training session as run_training_session does, measuring the
+// runner's model after every trial.  There are no points for runners
+// without a model.
+pub fn run_accuracy_session<Rnr, R>(
+    world: &World,
+    starts: &Curriculum,
+    probes: &[Probe],
+    max_trials: usize,
+    max_steps: usize,
+    runner: &mut Rnr,
+    rng: &mut R,
+) -> Result<(Option<usize>, Vec<AccuracyPoint>), Error>
+where
+    Rnr: Runner,
+    R: Rng,
+{
+    let mut points = Vec::new();
+
+    let steps = run_trials(
+        world,
+        starts,
+        probes,
+        max_trials,
+        max_steps,
+        runner,
+        rng,
+        |runner, trial, steps| {
+            if let Some(accuracy) = runner.evaluate_model(world) {
+                points.push(AccuracyPoint {
+                    trial,
+                    steps,
+                    accuracy,
+                });
+            }
+        },
+    )?;
+
+    Ok((steps, points))
+}
+
+// after_trial is given the runner, the trial and the total steps so far.
+#[allow(clippy::too_many_arguments)]
+fn run_trials<Rnr, R, F>(
+    world: &World,
+    starts: &Curriculum,
     probes: &[Probe],
     max_trials: usize,
     max_steps: usize,
     runner: &mut Rnr,
     mut rng: &mut R,
+    mut after_trial: F,
 ) -> Result<Option<usize>, Error>
 where
     Rnr: Runner,
     R: Rng,
+    F: FnMut(&Rnr, usize, usize),
 {
     let mut total_steps = 0;
 
-    for _ in 0..max_trials {
-        match State::build_random(world, &mut rng) {
+    for trial in 0..max_trials {
+        match starts.sample(world, trial, &mut rng) {
             Err(state_error) => {
                 return Err(Error::BuildRandomState(state_error));
             }
@@ -110,6 +307,8 @@ where
             }
         }
 
+        after_trial(runner, trial, total_steps);
+
         let probes_passed = probes
             .iter()
             .all(|probe| runner.solves(world, probe.state, probe.maximum_steps, &mut rng));
@@ -121,3 +320,68 @@ where
 
     Ok(None)
 }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ContinuingResult {
+    pub steps: usize,
+    pub requests_served: usize,
+    pub total_reward: f64,
+}
+
+impl ContinuingResult {
+    pub fn requests_per_thousand_steps(&self) -> f64 {
+        if self.steps > 0 {
+            1000.0 * self.requests_served as f64 / self.steps as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn average_reward(&self) -> f64 {
+        if self.steps > 0 {
+            self.total_reward / self.steps as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+// Runs a single unending trial for num_steps, spawning a new request where
+// the taxi is each time the passenger is delivered.
+pub fn run_continuing_session<Rnr, R>(
+    world: &World,
+    num_steps: usize,
+    runner: &mut Rnr,
+    mut rng: &mut R,
+) -> Result<ContinuingResult, Error>
+where
+    Rnr: ContinuingRunner,
+    R: Rng,
+{
+    let mut result = ContinuingResult::default();
+
+    let mut state = State::build_random(world, &mut rng).map_err(Error::BuildRandomState)?;
+
+    for step in 0..num_steps {
+        let action = runner
+            .choose_action(world, &state, &mut rng)
+            .ok_or(Error::NoAction { step })?;
+
+        let (reward, mut next_state) = state.apply_action(world, action);
+
+        if next_state.at_destination() {
+            result.requests_served += 1;
+            next_state = next_state
+                .with_new_request(world, &mut rng)
+                .map_err(Error::BuildRandomState)?;
+        }
+
+        runner.observe(world, &state, action, reward, &next_state);
+
+        result.steps += 1;
+        result.total_reward += reward;
+        state = next_state;
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timescale {
+    Step,
+    Trial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decay {
+    Constant(f64),
+
+    // Moves from start to end over duration ticks, then holds at end.
+    Linear {
+        start: f64,
+        end: f64,
+        duration: usize,
+    },
+
+    // start * rate^t, never dropping below minimum.
+    Exponential {
+        start: f64,
+        rate: f64,
+        minimum: f64,
+    },
+
+    // scale / n, where n is the number of visits to the entry being used.
+    VisitCount {
+        scale: f64,
+        minimum: f64,
+    },
+
+    // start * factor^(t / interval), never dropping below minimum.
+    StepWise {
+        start: f64,
+        factor: f64,
+        interval: usize,
+        minimum: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    decay: Decay,
+    timescale: Timescale,
+}
+
+impl Schedule {
+    pub fn new(decay: Decay, timescale: Timescale) -> Schedule {
+        Schedule { decay, timescale }
+    }
+
+    pub fn constant(value: f64) -> Schedule {
+        Schedule::new(Decay::Constant(value), Timescale::Step)
+    }
+
+    pub fn timescale(&self) -> Timescale {
+        self.timescale
+    }
+
+    // visits is the number of times the entry being updated has been
+    // used, including the current use.  It is ignored by every decay
+    // other than VisitCount.
+    pub fn value(&self, clock: &ScheduleClock, visits: usize) -> f64 {
+        let t = clock.elapsed(self.timescale);
+
+        match self.decay {
+            Decay::Constant(value) => value,
+
+            Decay::Linear {
+                start,
+                end,
+                duration,
+            } => {
+                if t >= duration {
+                    end
+                } else {
+                    start + (end - start) * (t as f64) / (duration as f64)
+                }
+            }
+
+            Decay::Exponential {
+                start,
+                rate,
+                minimum,
+            } => (start * rate.powf(t as f64)).max(minimum),
+
+            Decay::VisitCount { scale, minimum } => (scale / (visits.max(1) as f64)).max(minimum),
+
+            Decay::StepWise {
+                start,
+                factor,
+                interval,
+                minimum,
+            } => {
+                let num_intervals = t.checked_div(interval).unwrap_or(0);
+                (start * factor.powf(num_intervals as f64)).max(minimum)
+            }
+        }
+    }
+}
+
+impl From<f64> for Schedule {
+    fn from(value: f64) -> Self {
+        Schedule::constant(value)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per = match self.timescale {
+            Timescale::Step => "step",
+            Timescale::Trial => "trial",
+        };
+
+        match self.decay {
+            Decay::Constant(value) => write!(f, "{}", value),
+            Decay::Linear {
+                start,
+                end,
+                duration,
+            } => write!(f, "linear({} -> {} over {} {}s)", start, end, duration, per),
+            Decay::Exponential {
+                start,
+                rate,
+                minimum,
+            } => write!(
+                f,
+                "exponential({} * {}^{} min {})",
+                start, rate, per, minimum
+            ),
+            Decay::VisitCount { scale, minimum } => {
+                write!(f, "visit_count({} / n min {})", scale, minimum)
+            }
+            Decay::StepWise {
+                start,
+                factor,
+                interval,
+                minimum,
+            } => write!(
+                f,
+                "step_wise({} * {} every {} {}s min {})",
+                start, factor, interval, per, minimum
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScheduleClock {
+    steps: usize,
+    trials: usize,
+}
+
+impl ScheduleClock {
+    pub fn new() -> ScheduleClock {
+        ScheduleClock::default()
+    }
+
+    pub fn step(&mut self) {
+        self.steps += 1;
+    }
+
+    pub fn finish_trial(&mut self) {
+        self.trials += 1;
+    }
+
+    pub fn elapsed(&self, timescale: Timescale) -> usize {
+        match timescale {
+            Timescale::Step => self.steps,
+            Timescale::Trial => self.trials,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_schedule {
+
+    use super::*;
+
+    fn clock_at(steps: usize, trials: usize) -> ScheduleClock {
+        ScheduleClock { steps, trials }
+    }
+
+    #[test]
+    fn constant_ignores_clock() {
+        let schedule = Schedule::constant(0.3);
+
+        assert!(approx_eq!(f64, schedule.value(&clock_at(0, 0), 1), 0.3));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(500, 20), 9), 0.3));
+    }
+
+    #[test]
+    fn linear_holds_at_end() {
+        let decay = Decay::Linear {
+            start: 1.0,
+            end: 0.0,
+            duration: 10,
+        };
+        let schedule = Schedule::new(decay, Timescale::Trial);
+
+        assert!(approx_eq!(f64, schedule.value(&clock_at(0, 0), 1), 1.0));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(99, 5), 1), 0.5));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(0, 10), 1), 0.0));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(0, 50), 1), 0.0));
+    }
+
+    #[test]
+    fn exponential_respects_minimum() {
+        let decay = Decay::Exponential {
+            start: 1.0,
+            rate: 0.5,
+            minimum: 0.1,
+        };
+        let schedule = Schedule::new(decay, Timescale::Step);
+
+        assert!(approx_eq!(f64, schedule.value(&clock_at(1, 0), 1), 0.5));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(2, 0), 1), 0.25));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(10, 0), 1), 0.1));
+    }
+
+    #[test]
+    fn visit_count_uses_visits() {
+        let decay = Decay::VisitCount {
+            scale: 1.0,
+            minimum: 0.0,
+        };
+        let schedule = Schedule::new(decay, Timescale::Step);
+
+        assert!(approx_eq!(f64, schedule.value(&clock_at(7, 3), 0), 1.0));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(7, 3), 1), 1.0));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(7, 3), 4), 0.25));
+    }
+
+    #[test]
+    fn step_wise_drops_per_interval() {
+        let decay = Decay::StepWise {
+            start: 0.8,
+            factor: 0.5,
+            interval: 100,
+            minimum: 0.0,
+        };
+        let schedule = Schedule::new(decay, Timescale::Step);
+
+        assert!(approx_eq!(f64, schedule.value(&clock_at(99, 0), 1), 0.8));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(100, 0), 1), 0.4));
+        assert!(approx_eq!(f64, schedule.value(&clock_at(250, 0), 1), 0.2));
+    }
+}
@@ -0,0 +1,645 @@
+mod schema;
+
+use std::f64;
+
+use rand::Rng;
+
+use crate::actions::Actions;
+use crate::oomdp::taxi::TaxiObjects;
+use crate::oomdp::{AttributeKind, Domain, ObjectState, Value};
+use crate::state::State;
+use crate::state_indexer::StateIndexer;
+use crate::world::World;
+
+use self::schema::{Schema, SchemaSet};
+
+use crate::runner::{measure_model_accuracy, Attempt, ModelAccuracy, Runner, TransferRunner};
+
+// How an attribute changes in one step, by a difference for Int attributes
+// and to a value for Bool ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    By(i32),
+    To(bool),
+}
+
+impl Change {
+    fn between(old: Value, new: Value) -> Option<Change> {
+        match (old, new) {
+            (Value::Int(old), Value::Int(new)) => Some(Change::By(new - old)),
+            (Value::Bool(_), Value::Bool(new)) => Some(Change::To(new)),
+            _ => None,
+        }
+    }
+
+    fn apply(self, value: Value) -> Option<Value> {
+        match (self, value) {
+            (Change::By(delta), Value::Int(old)) => Some(Value::Int(old + delta)),
+            (Change::To(new), Value::Bool(_)) => Some(Value::Bool(new)),
+            _ => None,
+        }
+    }
+}
+
+// The features the schemas learn from in one state.
+#[derive(Debug, Clone)]
+struct StateFeatures {
+    objects: ObjectState,
+    // The domain's terms about each object, followed by its Bool attributes.
+    object_features: Vec<Vec<bool>>,
+    // The domain's terms about the whole state, which rewards are learnt from.
+    features: Vec<bool>,
+}
+
+// Learns schemas in the manner of Schema Networks (Kansky et al.): each
+// attribute of each class has a schema set per action predicting how it
+// changes, and each action has one predicting its reward.  Every object of
+// a class shares its class's schemas.  Plans by value iteration over the
+// states the schemas predict, treating anything they cannot predict as
+// worth rmax.
+#[derive(Debug, Clone)]
+pub struct SchemaNet {
+    state_indexer: StateIndexer,
+    rmax: f64,
+
+    taxi_objects: TaxiObjects,
+    state_features: Vec<StateFeatures>,
+
+    // Indexed by action, then class, then attribute.
+    attribute_schemas: Vec<Vec<Vec<SchemaSet<Change>>>>,
+    // Indexed by action.
+    reward_schemas: Vec<SchemaSet<f64>>,
+
+    // The reward and next state index of each action in each state, None
+    // where the schemas cannot predict them.
+    transitions: Vec<[Option<(f64, usize)>; Actions::NUM_ELEMENTS]>,
+    // Set when the schemas are learnt again, as the plan only changes then.
+    model_changed: bool,
+
+    value_table: Vec<f64>,
+
+    gamma: f64,
+    error_delta: f64,
+}
+
+impl SchemaNet {
+    pub fn new(world: &World, mut taxi_objects: TaxiObjects, gamma: f64, error_delta: f64) -> Self {
+        let state_indexer = StateIndexer::new(world);
+        let num_states = state_indexer.num_states();
+
+        taxi_objects.add_terrain(world);
+
+        let domain = taxi_objects.domain();
+        let state_features = build_state_features(world, &state_indexer, &taxi_objects);
+
+        let attribute_schemas = (0..Actions::NUM_ELEMENTS)
+            .map(|_| {
+                domain
+                    .classes()
+                    .iter()
+                    .map(|class| class.attributes.iter().map(|_| SchemaSet::new()).collect())
+                    .collect()
+            })
+            .collect();
+
+        SchemaNet {
+            state_indexer,
+            rmax: world.max_value(gamma),
+
+            taxi_objects,
+            state_features,
+
+            attribute_schemas,
+            reward_schemas: vec![SchemaSet::new(); Actions::NUM_ELEMENTS],
+
+            transitions: vec![[None; Actions::NUM_ELEMENTS]; num_states],
+            model_changed: false,
+
+            value_table: vec![0.0; num_states],
+
+            gamma,
+            error_delta,
+        }
+    }
+
+    // Every schema learnt, in the names of the domain's classes and terms.
+    pub fn describe_schemas(&self) -> Vec<String> {
+        let domain = self.taxi_objects.domain();
+        let term_names: Vec<String> = domain
+            .terms()
+            .iter()
+            .map(|term| domain.describe_term(term))
+            .collect();
+
+        let mut descriptions = Vec::new();
+        for (action_index, class_schemas) in self.attribute_schemas.iter().enumerate() {
+            let action = Actions::from_index(action_index).unwrap();
+
+            for (class, attribute_schemas) in domain.classes().iter().zip(class_schemas) {
+                let mut feature_names = term_names.clone();
+                feature_names.extend(
+                    class
+                        .attributes
+                        .iter()
+                        .filter(|&&(_, kind)| kind != AttributeKind::Int)
+                        .map(|(attribute, _)| format!("{}.{}", class.name, attribute)),
+                );
+
+                for ((attribute, _), schemas) in class.attributes.iter().zip(attribute_schemas) {
+                    for (change, change_schemas) in schemas.schemas() {
+                        for schema in change_schemas {
+                            let target = format!("{}.{}", class.name, attribute);
+                            let effect = match *change {
+                                Change::By(0) => format!("keeps {}", target),
+                                Change::By(delta) => format!("moves {} by {:+}", target, delta),
+                                Change::To(value) => format!("sets {} to {}", target, value),
+                            };
+
+                            descriptions.push(format!(
+                                "{:?} {}{}",
+                                action,
+                                effect,
+                                describe_preconditions(schema, &feature_names)
+                            ));
+                        }
+                    }
+                }
+            }
+
+            for (reward, schemas) in self.reward_schemas[action_index].schemas() {
+                for schema in schemas {
+                    descriptions.push(format!(
+                        "{:?} rewards {}{}",
+                        action,
+                        reward,
+                        describe_preconditions(schema, &term_names)
+                    ));
+                }
+            }
+        }
+
+        descriptions
+    }
+
+    fn apply_experience(
+        &mut self,
+        world: &World,
+        state: &State,
+        action: Actions,
+        new_state: &State,
+        reward: f64,
+    ) {
+        let state_index = self.state_indexer.get_index(world, state).unwrap();
+        let new_state_index = self.state_indexer.get_index(world, new_state).unwrap();
+
+        let seen = &self.state_features[state_index];
+        let new_objects = &self.state_features[new_state_index].objects;
+        let class_schemas = &mut self.attribute_schemas[action.to_index()];
+
+        for (index, (object, new_object)) in seen
+            .objects
+            .objects()
+            .iter()
+            .zip(new_objects.objects())
+            .enumerate()
+        {
+            let attribute_schemas = &mut class_schemas[object.class.index()];
+
+            for (schemas, (&old, &new)) in attribute_schemas
+                .iter_mut()
+                .zip(object.values.iter().zip(&new_object.values))
+            {
+                if let Some(change) = Change::between(old, new) {
+                    if schemas.apply_experience(&seen.object_features[index], change) {
+                        self.model_changed = true;
+                    }
+                }
+            }
+        }
+
+        if self.reward_schemas[action.to_index()].apply_experience(&seen.features, reward) {
+            self.model_changed = true;
+        }
+    }
+
+    // The next state index predicted by the schemas, None if any attribute
+    // cannot be predicted or the prediction is not a state of the world.
+    fn predict_state(&self, world: &World, state_index: usize, action: Actions) -> Option<usize> {
+        let seen = &self.state_features[state_index];
+        let class_schemas = &self.attribute_schemas[action.to_index()];
+
+        let mut next_objects = seen.objects.clone();
+        for (index, object) in seen.objects.objects().iter().enumerate() {
+            let attribute_schemas = &class_schemas[object.class.index()];
+
+            for (attribute, (schemas, &value)) in
+                attribute_schemas.iter().zip(&object.values).enumerate()
+            {
+                let change = schemas.predict(&seen.object_features[index])?;
+                next_objects.set_value(index, attribute, change.apply(value)?);
+            }
+        }
+
+        let next_state = self.taxi_objects.to_state(world, &next_objects).ok()?;
+        self.state_indexer.get_index(world, &next_state)
+    }
+
+    fn predict_reward(&self, state_index: usize, action: Actions) -> Option<f64> {
+        self.reward_schemas[action.to_index()].predict(&self.state_features[state_index].features)
+    }
+
+    fn transition_value(&self, state_index: usize, transition: Option<(f64, usize)>) -> f64 {
+        match transition {
+            Some((reward, next_state_index)) => {
+                reward + self.gamma * self.value_table[next_state_index]
+            }
+
+            // The schemas do not know what happens, so return max value to
+            // encourage exploration, assuming we stay at our current state.
+            None => self.rmax + self.gamma * self.value_table[state_index],
+        }
+    }
+
+    fn select_best_action<R: Rng>(
+        &self,
+        world: &World,
+        state: &State,
+        rng: &mut R,
+    ) -> Option<Actions> {
+        let state_index = self.state_indexer.get_index(world, state).unwrap();
+
+        let mut best_value = -f64::MAX;
+        let mut best_action = None;
+        let mut num_found = 0;
+
+        for (action_index, &transition) in self.transitions[state_index].iter().enumerate() {
+            let action = Actions::from_index(action_index).unwrap();
+            let action_value = self.transition_value(state_index, transition);
+
+            if approx_eq!(f64, action_value, best_value, ulps = 2) {
+                num_found += 1;
+
+                if 0 == rng.gen_range(0, num_found) {
+                    best_action = Some(action);
+                }
+            } else if action_value > best_value {
+                best_value = action_value;
+                best_action = Some(action);
+                num_found = 1;
+            }
+        }
+
+        best_action
+    }
+
+    // Backs the values up through the transitions the schemas predict, which
+    // only need predicting again once the schemas have changed.
+    fn rebuild_value_table(&mut self, world: &World) {
+        if !self.model_changed {
+            return;
+        }
+        self.model_changed = false;
+
+        for state_index in 0..self.state_indexer.num_states() {
+            for action_index in 0..Actions::NUM_ELEMENTS {
+                let action = Actions::from_index(action_index).unwrap();
+
+                self.transitions[state_index][action_index] =
+                    match self.predict_reward(state_index, action) {
+                        Some(reward) => self
+                            .predict_state(world, state_index, action)
+                            .map(|next_state_index| (reward, next_state_index)),
+                        None => None,
+                    };
+            }
+        }
+
+        for _ in 0..10_000 {
+            let mut error = 0.0;
+
+            for state_index in 0..self.state_indexer.num_states() {
+                let old_value = self.value_table[state_index];
+
+                let new_value = self.transitions[state_index]
+                    .iter()
+                    .map(|&transition| self.transition_value(state_index, transition))
+                    .fold(-f64::MAX, f64::max);
+
+                self.value_table[state_index] = new_value;
+
+                let state_error = (new_value - old_value).abs();
+                if state_error > error {
+                    error = state_error;
+                }
+            }
+
+            if error < self.error_delta {
+                break;
+            }
+        }
+    }
+}
+
+impl Runner for SchemaNet {
+    fn learn<R: Rng>(
+        &mut self,
+        world: &World,
+        mut state: State,
+        max_steps: usize,
+        rng: &mut R,
+    ) -> Option<usize> {
+        for step in 0..max_steps {
+            if state.at_destination() {
+                return Some(step);
+            }
+
+            self.rebuild_value_table(world);
+
+            if let Some(next_action) = self.select_best_action(world, &state, rng) {
+                let (reward, next_state) = state.apply_action(world, next_action);
+
+                self.apply_experience(world, &state, next_action, &next_state, reward);
+                state = next_state;
+            } else {
+                return None;
+            }
+        }
+
+        if state.at_destination() {
+            Some(max_steps)
+        } else {
+            None
+        }
+    }
+
+    fn attempt<R: Rng>(
+        &self,
+        world: &World,
+        mut state: State,
+        max_steps: usize,
+        rng: &mut R,
+    ) -> Attempt {
+        let mut attempt = Attempt::new(state, max_steps);
+
+        for _ in 0..max_steps {
+            if state.at_destination() {
+                break;
+            }
+
+            if let Some(next_action) = self.select_best_action(world, &state, rng) {
+                attempt.step(next_action);
+                let (_, next_state) = state.apply_action(world, next_action);
+                state = next_state;
+            } else {
+                break;
+            }
+        }
+
+        if state.at_destination() {
+            attempt.succeeded()
+        }
+
+        attempt
+    }
+
+    fn solves<R: Rng>(
+        &self,
+        world: &World,
+        mut state: State,
+        max_steps: usize,
+        rng: &mut R,
+    ) -> bool {
+        for _ in 0..max_steps {
+            if state.at_destination() {
+                return true;
+            }
+
+            if let Some(next_action) = self.select_best_action(world, &state, rng) {
+                let (_, next_state) = state.apply_action(world, next_action);
+                state = next_state;
+            } else {
+                break;
+            }
+        }
+
+        state.at_destination()
+    }
+
+    fn evaluate_model(&self, world: &World) -> Option<ModelAccuracy> {
+        Some(measure_model_accuracy(
+            world,
+            |state, action, next_state| {
+                let state_index = self.state_indexer.get_index(world, state).unwrap();
+                let predicted = self.predict_state(world, state_index, action)?;
+
+                if Some(predicted) == self.state_indexer.get_index(world, next_state) {
+                    Some(1.0)
+                } else {
+                    Some(0.0)
+                }
+            },
+            |state, action| {
+                let state_index = self.state_indexer.get_index(world, state).unwrap();
+                self.predict_reward(state_index, action)
+            },
+        ))
+    }
+
+    fn report_training_result(&self, _world: &World, _steps: Option<usize>) {
+        println!("Learnt schemas:");
+        for description in self.describe_schemas() {
+            println!("{}", description);
+        }
+    }
+}
+
+// Schemas are learnt over the domain's terms, which say nothing of where
+// things are, so all of them carry over.
+impl TransferRunner for SchemaNet {
+    fn transfer(&self, world: &World) -> Self {
+        let state_indexer = StateIndexer::new(world);
+        let num_states = state_indexer.num_states();
+
+        let mut schemanet = SchemaNet {
+            state_features: build_state_features(world, &state_indexer, &self.taxi_objects),
+            state_indexer,
+            rmax: world.max_value(self.gamma),
+
+            taxi_objects: self.taxi_objects.clone(),
+
+            attribute_schemas: self.attribute_schemas.clone(),
+            reward_schemas: self.reward_schemas.clone(),
+
+            transitions: vec![[None; Actions::NUM_ELEMENTS]; num_states],
+            model_changed: true,
+
+            value_table: vec![0.0; num_states],
+
+            gamma: self.gamma,
+            error_delta: self.error_delta,
+        };
+
+        schemanet.rebuild_value_table(world);
+        schemanet
+    }
+}
+
+fn build_state_features(
+    world: &World,
+    state_indexer: &StateIndexer,
+    taxi_objects: &TaxiObjects,
+) -> Vec<StateFeatures> {
+    let domain = taxi_objects.domain();
+
+    taxi_objects
+        .all_objects(world, state_indexer)
+        .into_iter()
+        .map(|objects| StateFeatures {
+            object_features: (0..objects.objects().len())
+                .map(|index| object_features(domain, world, &objects, index))
+                .collect(),
+            features: term_features(domain, world, &objects, None),
+            objects,
+        })
+        .collect()
+}
+
+// The truth of each of the domain's terms.
+fn term_features(
+    domain: &Domain,
+    world: &World,
+    objects: &ObjectState,
+    focus: Option<usize>,
+) -> Vec<bool> {
+    domain
+        .terms()
+        .iter()
+        .map(|term| domain.evaluate(term, world, objects, focus))
+        .collect()
+}
+
+fn object_features(
+    domain: &Domain,
+    world: &World,
+    objects: &ObjectState,
+    index: usize,
+) -> Vec<bool> {
+    let mut features = term_features(domain, world, objects, Some(index));
+    features.extend(
+        objects.objects()[index]
+            .values
+            .iter()
+            .filter_map(|value| value.as_bool()),
+    );
+
+    features
+}
+
+fn describe_preconditions(schema: &Schema, feature_names: &[String]) -> String {
+    let mut when = Vec::new();
+    let mut unless = Vec::new();
+    for &(feature, value) in schema.preconditions() {
+        if value {
+            when.push(feature_names[feature].as_str());
+        } else {
+            unless.push(feature_names[feature].as_str());
+        }
+    }
+
+    let mut text = String::new();
+    if when.is_empty() && unless.is_empty() {
+        text.push_str(" always");
+    }
+    if !when.is_empty() {
+        text.push_str(&format!(" when {}", when.join(" and ")));
+    }
+    if !unless.is_empty() {
+        text.push_str(&format!(" unless {}", unless.join(" or ")));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod test_schemanet {
+    use super::*;
+
+    use rand_pcg::Pcg64Mcg;
+
+    use crate::world::Costs;
+
+    #[test]
+    fn learns_simple_world() {
+        let source = "\
+                      ┌───┐\n\
+                      │R .│\n\
+                      │   │\n\
+                      │.│G│\n\
+                      └─┴─┘\n\
+                      ";
+
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+        let mut schemanet = SchemaNet::new(&world, TaxiObjects::new(), 0.3, 1.0e-6);
+        let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
+
+        let state = State::build_named(&world, (1, 1), Some("R"), "G").unwrap();
+        let mut learnt = false;
+        for _ in 0..20 {
+            if schemanet.learn(&world, state, 100, &mut rng).is_some()
+                && schemanet.solves(&world, state, 8, &mut rng)
+            {
+                learnt = true;
+                break;
+            }
+        }
+        assert!(learnt);
+
+        // Schemas generalize to states never visited, so may be wrong there.
+        let accuracy = schemanet.evaluate_model(&world).unwrap();
+        assert!(accuracy.transitions.correct > accuracy.transitions.wrong);
+        assert!(accuracy.rewards.correct > accuracy.rewards.wrong);
+    }
+
+    #[test]
+    fn transfers_to_another_world() {
+        let source = "\
+                      ┌───┐\n\
+                      │R .│\n\
+                      │   │\n\
+                      │.│G│\n\
+                      └─┴─┘\n\
+                      ";
+        let other_source = "\
+                            ┌─────┐\n\
+                            │. . Y│\n\
+                            │     │\n\
+                            │B│. .│\n\
+                            └─┴───┘\n\
+                            ";
+
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+        let other_world = World::build_from_str(other_source, Costs::default()).unwrap();
+
+        let mut schemanet = SchemaNet::new(&world, TaxiObjects::new(), 0.3, 1.0e-6);
+        let mut rng = Pcg64Mcg::new(0xcafe_f00d_d15e_a5e5);
+
+        for _ in 0..20 {
+            let state = State::build_random(&world, &mut rng).unwrap();
+            schemanet.learn(&world, state, 100, &mut rng);
+        }
+
+        // A solver new to the other world knows nothing of it, the moved
+        // one predicts much of it from the first world's schemas.
+        let fresh = SchemaNet::new(&other_world, TaxiObjects::new(), 0.3, 1.0e-6);
+        let fresh_accuracy = fresh.evaluate_model(&other_world).unwrap();
+        assert_eq!(fresh_accuracy.transitions.correct, 0);
+
+        let moved = schemanet.transfer(&other_world);
+        let moved_accuracy = moved.evaluate_model(&other_world).unwrap();
+        assert_eq!(
+            moved_accuracy.transitions.total(),
+            fresh_accuracy.transitions.total()
+        );
+        assert!(moved_accuracy.transitions.correct > moved_accuracy.transitions.total() / 2);
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+
+// A conjunction of features, which holds when each feature has its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    preconditions: Vec<(usize, bool)>,
+}
+
+impl Schema {
+    // The schema that holds for these features and no others.
+    pub fn most_specific(features: &[bool]) -> Self {
+        Schema {
+            preconditions: features.iter().cloned().enumerate().collect(),
+        }
+    }
+
+    pub fn holds(&self, features: &[bool]) -> bool {
+        self.preconditions
+            .iter()
+            .all(|&(feature, value)| features[feature] == value)
+    }
+
+    // Drops the preconditions features do not meet, so the schema holds for
+    // them as well.
+    pub fn generalize(&self, features: &[bool]) -> Self {
+        Schema {
+            preconditions: self
+                .preconditions
+                .iter()
+                .cloned()
+                .filter(|&(feature, value)| features[feature] == value)
+                .collect(),
+        }
+    }
+
+    // Whether this schema holds wherever other does.
+    fn subsumes(&self, other: &Schema) -> bool {
+        self.preconditions
+            .iter()
+            .all(|precondition| other.preconditions.contains(precondition))
+    }
+
+    pub fn preconditions(&self) -> &[(usize, bool)] {
+        &self.preconditions
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Schema(")?;
+        for (index, &(feature, value)) in self.preconditions.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}{}", if value { "" } else { "!" }, feature)?;
+        }
+        write!(f, ")")
+    }
+}
+
+// The features seen, with every target seen after them.
+#[derive(Debug, Clone)]
+struct Example<T> {
+    features: Vec<bool>,
+    targets: Vec<T>,
+}
+
+// Predicts a target one step later from features now, as the target whose
+// schemas hold.  Each target's schemas together cover every example of it
+// and none of any other target, so a target is predicted by a disjunction
+// of conjunctions.
+#[derive(Debug, Clone)]
+pub struct SchemaSet<T> {
+    examples: Vec<Example<T>>,
+    schemas: Vec<(T, Vec<Schema>)>,
+}
+
+impl<T: Copy + PartialEq> SchemaSet<T> {
+    pub fn new() -> Self {
+        SchemaSet {
+            examples: Vec::new(),
+            schemas: Vec::new(),
+        }
+    }
+
+    // Returns whether the schemas were learnt again, which is only needed
+    // for features or targets not seen together before.
+    pub fn apply_experience(&mut self, features: &[bool], target: T) -> bool {
+        match self
+            .examples
+            .iter_mut()
+            .find(|example| example.features == features)
+        {
+            Some(example) => {
+                if example.targets.contains(&target) {
+                    return false;
+                }
+                example.targets.push(target);
+            }
+            None => self.examples.push(Example {
+                features: features.to_vec(),
+                targets: vec![target],
+            }),
+        }
+
+        self.learn();
+        true
+    }
+
+    // The target whose schemas hold, None if no target's do or if several
+    // targets' do.
+    pub fn predict(&self, features: &[bool]) -> Option<T> {
+        let mut holding = self
+            .schemas
+            .iter()
+            .filter(|(_, schemas)| schemas.iter().any(|schema| schema.holds(features)))
+            .map(|&(target, _)| target);
+
+        let target = holding.next()?;
+        if holding.next().is_some() {
+            None
+        } else {
+            Some(target)
+        }
+    }
+
+    pub fn schemas(&self) -> &[(T, Vec<Schema>)] {
+        &self.schemas
+    }
+
+    // Keeps the schemas that still cover no other target's examples, then
+    // covers what they miss.
+    fn learn(&mut self) {
+        let mut targets: Vec<T> = Vec::new();
+        for example in &self.examples {
+            for &target in &example.targets {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+
+        let mut learnt = Vec::with_capacity(targets.len());
+        for target in targets {
+            let (positives, negatives): (Vec<&Example<T>>, Vec<&Example<T>>) = self
+                .examples
+                .iter()
+                .partition(|example| example.targets.contains(&target));
+            let positives: Vec<&[bool]> = positives
+                .iter()
+                .map(|example| &example.features[..])
+                .collect();
+            let negatives: Vec<&[bool]> = negatives
+                .iter()
+                .map(|example| &example.features[..])
+                .collect();
+
+            let mut schemas: Vec<Schema> = self
+                .schemas
+                .iter()
+                .find(|(learnt_target, _)| *learnt_target == target)
+                .map(|(_, schemas)| {
+                    schemas
+                        .iter()
+                        .filter(|schema| !covers_any(schema, &negatives))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            for positive in &positives {
+                if schemas.iter().any(|schema| schema.holds(positive)) {
+                    continue;
+                }
+
+                if let Some(schema) = cover(positive, &positives, &negatives) {
+                    schemas.push(schema);
+                }
+            }
+
+            // Schemas grown before more was seen may be covered by later,
+            // more general ones.
+            let mut general: Vec<Schema> = Vec::with_capacity(schemas.len());
+            for (index, schema) in schemas.iter().enumerate() {
+                let subsumed = schemas.iter().enumerate().any(|(other_index, other)| {
+                    other_index != index
+                        && other.subsumes(schema)
+                        && (!schema.subsumes(other) || other_index < index)
+                });
+
+                if !subsumed {
+                    general.push(schema.clone());
+                }
+            }
+
+            learnt.push((target, general));
+        }
+
+        self.schemas = learnt;
+    }
+}
+
+impl<T: Copy + PartialEq> Default for SchemaSet<T> {
+    fn default() -> Self {
+        SchemaSet::new()
+    }
+}
+
+fn covers_any(schema: &Schema, examples: &[&[bool]]) -> bool {
+    examples.iter().any(|features| schema.holds(features))
+}
+
+// Greedily grows a schema from one example, each time generalizing it to
+// the other example that leaves it covering the most, for as long as it
+// covers no negatives.  Preconditions are only dropped where examples
+// differ, so the schema never reaches features unlike any seen.  None if
+// the example's features are also those of a negative.
+fn cover(positive: &[bool], positives: &[&[bool]], negatives: &[&[bool]]) -> Option<Schema> {
+    let mut schema = Schema::most_specific(positive);
+    if covers_any(&schema, negatives) {
+        return None;
+    }
+
+    loop {
+        let best = positives
+            .iter()
+            .filter(|features| !schema.holds(features))
+            .map(|features| schema.generalize(features))
+            .filter(|candidate| !covers_any(candidate, negatives))
+            .map(|candidate| {
+                let covered = positives
+                    .iter()
+                    .filter(|features| candidate.holds(features))
+                    .count();
+
+                (covered, candidate)
+            })
+            .max_by_key(|&(covered, _)| covered);
+
+        match best {
+            Some((_, candidate)) => schema = candidate,
+            None => return Some(schema),
+        }
+    }
+}
+
+#[cfg(test)]
+mod schema_test {
+    use super::*;
+
+    #[test]
+    fn learns_disjunctions() {
+        let mut schemas = SchemaSet::new();
+
+        // The target is true when either of the first two features is.
+        assert!(schemas.apply_experience(&[true, false, false], true));
+        assert!(schemas.apply_experience(&[true, false, true], true));
+        assert!(schemas.apply_experience(&[false, true, false], true));
+        assert!(schemas.apply_experience(&[false, true, true], true));
+        assert!(schemas.apply_experience(&[false, false, false], false));
+        assert!(schemas.apply_experience(&[false, false, true], false));
+        assert!(!schemas.apply_experience(&[false, false, true], false));
+
+        for (_, target_schemas) in schemas.schemas() {
+            for schema in target_schemas {
+                let others = target_schemas.iter().filter(|&other| other != schema);
+                assert!(!others.into_iter().any(|other| other.subsumes(schema)));
+            }
+        }
+
+        assert_eq!(schemas.predict(&[true, false, true]), Some(true));
+        assert_eq!(schemas.predict(&[false, true, false]), Some(true));
+        assert_eq!(schemas.predict(&[false, false, true]), Some(false));
+    }
+
+    #[test]
+    fn only_generalizes_where_examples_differ() {
+        let mut schemas = SchemaSet::new();
+
+        schemas.apply_experience(&[true, false, false], 1);
+        schemas.apply_experience(&[true, true, false], 1);
+
+        // The second feature has been seen both ways, the others have not.
+        assert_eq!(schemas.predict(&[true, true, false]), Some(1));
+        assert_eq!(schemas.predict(&[true, false, false]), Some(1));
+        assert_eq!(schemas.predict(&[false, false, false]), None);
+        assert_eq!(schemas.predict(&[true, false, true]), None);
+
+        // Features seen before two targets predict neither.
+        schemas.apply_experience(&[true, true, false], 2);
+        assert_eq!(schemas.predict(&[true, false, false]), Some(1));
+        assert_eq!(schemas.predict(&[true, true, false]), None);
+    }
+}
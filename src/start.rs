@@ -0,0 +1,368 @@
+use std::fmt;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use crate::position::Position;
+use crate::state;
+use crate::state::State;
+use crate::world::World;
+
+pub enum Error {
+    UnknownLocation { name: String },
+    InvalidWeight { name: String, weight: f64 },
+    NoPossibleStart,
+    InvalidTaxi { taxi: Position },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::UnknownLocation { ref name } => {
+                write!(f, "The world has no location '{}'.", name)
+            }
+            Error::InvalidWeight { ref name, weight } => write!(
+                f,
+                "Weight {} for '{}' must be a finite number of at least 0.",
+                weight, name
+            ),
+            Error::NoPossibleStart => {
+                write!(f, "No passenger and destination pair has a weight above 0.")
+            }
+            Error::InvalidTaxi { taxi } => write!(
+                f,
+                "The taxi cannot start at ({}, {}), it is outside the world or blocked.",
+                taxi.x, taxi.y
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// How the first state of a trial is drawn.  Passenger and destination are
+// drawn together, each pair weighted by the product of the passenger weight
+// and the destination weight for its fixed positions.  A passenger starting
+// at its destination counts as delivered, so those trials end at once.
+#[derive(Debug, Clone)]
+pub struct StartDistribution {
+    taxi: Option<Position>,
+
+    // Indexed by passenger fixed index * num_fixed_positions + destination
+    // fixed index.  None draws the whole state like State::build_random, so
+    // seeded runs without start settings reproduce.
+    pairs: Option<WeightedIndex<f64>>,
+    num_fixed_positions: usize,
+}
+
+impl StartDistribution {
+    // The same distribution as State::build_random.
+    pub fn uniform() -> StartDistribution {
+        StartDistribution {
+            taxi: None,
+            pairs: None,
+            num_fixed_positions: 0,
+        }
+    }
+
+    // Fixed positions missing from a non-empty weight list get weight 0,
+    // an empty list weights every fixed position equally.
+    pub fn new(
+        world: &World,
+        taxi: Option<Position>,
+        passenger_weights: &[(&str, f64)],
+        destination_weights: &[(&str, f64)],
+        allow_same: bool,
+    ) -> Result<StartDistribution, Error> {
+        if let Some(taxi) = taxi {
+            let inside =
+                taxi.x >= 0 && taxi.x < world.width && taxi.y >= 0 && taxi.y < world.height;
+
+            if !inside || world.is_blocked(taxi) {
+                return Err(Error::InvalidTaxi { taxi });
+            }
+        }
+
+        let num_fixed_positions = world.num_fixed_positions();
+
+        let passenger = fixed_position_weights(world, passenger_weights)?;
+        let destination = fixed_position_weights(world, destination_weights)?;
+
+        if taxi.is_none()
+            && passenger_weights.is_empty()
+            && destination_weights.is_empty()
+            && !allow_same
+        {
+            return Ok(StartDistribution::uniform());
+        }
+
+        let mut weights = Vec::with_capacity(num_fixed_positions * num_fixed_positions);
+        for (passenger_index, passenger_weight) in passenger.iter().enumerate() {
+            for (destination_index, destination_weight) in destination.iter().enumerate() {
+                if passenger_index == destination_index && !allow_same {
+                    weights.push(0.0);
+                } else {
+                    weights.push(passenger_weight * destination_weight);
+                }
+            }
+        }
+
+        let pairs = WeightedIndex::new(weights).map_err(|_| Error::NoPossibleStart)?;
+
+        Ok(StartDistribution {
+            taxi,
+            pairs: Some(pairs),
+            num_fixed_positions,
+        })
+    }
+
+    pub fn sample<R: Rng>(&self, world: &World, rng: &mut R) -> Result<State, state::Error> {
+        let pairs = match self.pairs {
+            Some(ref pairs) => pairs,
+            None => return State::build_random(world, rng),
+        };
+
+        let taxi = match self.taxi {
+            Some(taxi) => taxi,
+            None => {
+                let index = rng.gen_range(0, world.num_open_cells());
+                world.open_cell_position(index).unwrap()
+            }
+        };
+
+        let pair = pairs.sample(rng);
+        let passenger = world.get_fixed_id_from_index(pair / self.num_fixed_positions);
+        let destination = world
+            .get_fixed_id_from_index(pair % self.num_fixed_positions)
+            .unwrap();
+
+        State::build(world, (taxi.x, taxi.y), passenger, destination)
+    }
+}
+
+fn fixed_position_weights(world: &World, weights: &[(&str, f64)]) -> Result<Vec<f64>, Error> {
+    if weights.is_empty() {
+        return Ok(vec![1.0; world.num_fixed_positions()]);
+    }
+
+    let mut result = vec![0.0; world.num_fixed_positions()];
+
+    for &(name, weight) in weights {
+        let index = world
+            .find_location(name)
+            .and_then(|id| world.get_fixed_index(id))
+            .ok_or_else(|| Error::UnknownLocation {
+                name: String::from(name),
+            })?;
+
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(Error::InvalidWeight {
+                name: String::from(name),
+                weight,
+            });
+        }
+
+        result[index] = weight;
+    }
+
+    Ok(result)
+}
+
+// Start distributions that change as training goes on.  Each stage takes
+// over from its first trial, trials before every stage use the uniform
+// distribution.
+#[derive(Debug, Clone, Default)]
+pub struct Curriculum {
+    stages: Vec<(usize, StartDistribution)>,
+}
+
+impl Curriculum {
+    pub fn new() -> Curriculum {
+        Curriculum::default()
+    }
+
+    pub fn add_stage(&mut self, first_trial: usize, distribution: StartDistribution) {
+        let index = self
+            .stages
+            .iter()
+            .position(|&(first, _)| first > first_trial)
+            .unwrap_or(self.stages.len());
+
+        self.stages.insert(index, (first_trial, distribution));
+    }
+
+    pub fn for_trial(&self, trial: usize) -> Option<&StartDistribution> {
+        self.stages
+            .iter()
+            .rev()
+            .find(|&&(first, _)| first <= trial)
+            .map(|(_, distribution)| distribution)
+    }
+
+    pub fn sample<R: Rng>(
+        &self,
+        world: &World,
+        trial: usize,
+        rng: &mut R,
+    ) -> Result<State, state::Error> {
+        match self.for_trial(trial) {
+            Some(distribution) => distribution.sample(world, rng),
+            None => State::build_random(world, rng),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_start {
+
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64Mcg;
+
+    use crate::world::{Costs, LocationId};
+
+    fn build_world() -> World {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │     │\n\
+                      │. # .│\n\
+                      │     │\n\
+                      │Y . B│\n\
+                      └─────┘\n\
+                      ";
+
+        World::build_from_str(source, Costs::default()).unwrap()
+    }
+
+    fn location(world: &World, name: &str) -> LocationId {
+        world.find_location(name).unwrap()
+    }
+
+    #[test]
+    fn uniform_matches_build_random() {
+        let w = build_world();
+        let distribution = StartDistribution::new(&w, None, &[], &[], false).unwrap();
+
+        let mut rng = Pcg64Mcg::seed_from_u64(11);
+        let mut expected_rng = Pcg64Mcg::seed_from_u64(11);
+
+        for _ in 0..50 {
+            assert_eq!(
+                distribution.sample(&w, &mut rng).unwrap(),
+                State::build_random(&w, &mut expected_rng).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn weights_restrict_fixed_positions() {
+        let w = build_world();
+        let distribution =
+            StartDistribution::new(&w, None, &[("R", 3.0), ("G", 1.0)], &[("B", 1.0)], false)
+                .unwrap();
+
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        let mut from_r = 0;
+
+        for _ in 0..400 {
+            let state = distribution.sample(&w, &mut rng).unwrap();
+
+            assert!(!w.is_blocked(state.get_taxi()));
+            assert_eq!(state.get_destination(), location(&w, "B"));
+
+            match state.get_passenger() {
+                Some(id) if id == location(&w, "R") => from_r += 1,
+                Some(id) if id == location(&w, "G") => {}
+                other => panic!("Unexpected passenger {:?}", other),
+            }
+        }
+
+        assert!(from_r > 250 && from_r < 350, "{} from 'R'", from_r);
+    }
+
+    #[test]
+    fn same_location_only_when_allowed() {
+        let w = build_world();
+
+        let forbidden = StartDistribution::new(&w, None, &[("R", 1.0)], &[("R", 1.0)], false);
+        assert_matches!(forbidden, Err(Error::NoPossibleStart));
+
+        let allowed = StartDistribution::new(&w, None, &[("R", 1.0)], &[("R", 1.0)], true).unwrap();
+
+        let mut rng = Pcg64Mcg::seed_from_u64(5);
+        let state = allowed.sample(&w, &mut rng).unwrap();
+
+        assert_eq!(state.get_passenger(), Some(location(&w, "R")));
+        assert!(state.at_destination());
+    }
+
+    #[test]
+    fn fixed_taxi() {
+        let w = build_world();
+        let distribution =
+            StartDistribution::new(&w, Some(Position::new(2, 1)), &[], &[], false).unwrap();
+
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+
+        for _ in 0..20 {
+            let state = distribution.sample(&w, &mut rng).unwrap();
+
+            assert_eq!(state.get_taxi(), Position::new(2, 1));
+            assert_ne!(state.get_passenger(), Some(state.get_destination()));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        let w = build_world();
+
+        assert_matches!(
+            StartDistribution::new(&w, Some(Position::new(1, 1)), &[], &[], false),
+            Err(Error::InvalidTaxi { .. })
+        );
+        assert_matches!(
+            StartDistribution::new(&w, Some(Position::new(3, 0)), &[], &[], false),
+            Err(Error::InvalidTaxi { .. })
+        );
+        assert_matches!(
+            StartDistribution::new(&w, None, &[("Q", 1.0)], &[], false),
+            Err(Error::UnknownLocation { ref name }) if name == "Q"
+        );
+        assert_matches!(
+            StartDistribution::new(&w, None, &[], &[("G", -1.0)], false),
+            Err(Error::InvalidWeight { ref name, .. }) if name == "G"
+        );
+    }
+
+    #[test]
+    fn curriculum_stages_by_trial() {
+        let w = build_world();
+
+        let to_g = StartDistribution::new(&w, None, &[], &[("G", 1.0)], false).unwrap();
+        let to_y = StartDistribution::new(&w, None, &[], &[("Y", 1.0)], false).unwrap();
+
+        let mut curriculum = Curriculum::new();
+        curriculum.add_stage(10, to_y);
+        curriculum.add_stage(5, to_g);
+
+        let mut rng = Pcg64Mcg::seed_from_u64(9);
+
+        for trial in 5..10 {
+            let state = curriculum.sample(&w, trial, &mut rng).unwrap();
+            assert_eq!(state.get_destination(), location(&w, "G"));
+        }
+
+        for trial in 10..15 {
+            let state = curriculum.sample(&w, trial, &mut rng).unwrap();
+            assert_eq!(state.get_destination(), location(&w, "Y"));
+        }
+
+        assert!(curriculum.for_trial(4).is_none());
+    }
+}
@@ -4,13 +4,13 @@ use rand::Rng;
 
 use crate::actions::Actions;
 use crate::position::Position;
-use crate::world::{ActionAffect, World};
+use crate::world::{ActionAffect, LocationId, World};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct State {
     taxi: Position,
-    passenger: Option<char>,
-    destination: char,
+    passenger: Option<LocationId>,
+    destination: LocationId,
 }
 
 pub enum Error {
@@ -19,13 +19,18 @@ pub enum Error {
         world_dims: (i32, i32),
     },
 
+    BlockedTaxi {
+        taxi_pos: (i32, i32),
+        world: String,
+    },
+
     InvalidDestination {
-        id: char,
+        name: String,
         world: String,
     },
 
     InvalidPassenger {
-        id: char,
+        name: String,
         world: String,
     },
 
@@ -40,7 +45,7 @@ pub enum Error {
     },
 }
 
-impl fmt::Debug for Error {
+impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::InvalidTaxi {
@@ -52,16 +57,31 @@ impl fmt::Debug for Error {
                 taxi_pos.0, taxi_pos.1, world_dims.0, world_dims.1
             ),
 
-            Error::InvalidDestination { id, ref world } => write!(
+            Error::BlockedTaxi {
+                taxi_pos,
+                ref world,
+            } => write!(
+                f,
+                "Taxi position ({},{}) is on a blocked cell in world:\n{}",
+                taxi_pos.0, taxi_pos.1, world
+            ),
+
+            Error::InvalidDestination {
+                ref name,
+                ref world,
+            } => write!(
                 f,
                 "Failed to find destination location '{}' in world:\n{}",
-                id, world
+                name, world
             ),
 
-            Error::InvalidPassenger { id, ref world } => write!(
+            Error::InvalidPassenger {
+                ref name,
+                ref world,
+            } => write!(
                 f,
                 "Failed to find passenger location '{}' in world:\n{}",
-                id, world
+                name, world
             ),
 
             Error::TooFewFixedPositions {
@@ -87,12 +107,18 @@ impl fmt::Debug for Error {
     }
 }
 
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl State {
     pub fn build(
         world: &World,
         taxi_pos: (i32, i32),
-        passenger: Option<char>,
-        destination: char,
+        passenger: Option<LocationId>,
+        destination: LocationId,
     ) -> Result<State, Error> {
         if taxi_pos.0 < 0
             || taxi_pos.0 >= world.width
@@ -105,9 +131,16 @@ impl State {
             });
         }
 
+        if world.is_blocked(Position::new(taxi_pos.0, taxi_pos.1)) {
+            return Err(Error::BlockedTaxi {
+                taxi_pos,
+                world: world.display(),
+            });
+        }
+
         if world.get_fixed_position(destination) == None {
             return Err(Error::InvalidDestination {
-                id: destination,
+                name: format!("#{}", destination.index()),
                 world: world.display(),
             });
         }
@@ -115,7 +148,7 @@ impl State {
         if let Some(passenger_id) = passenger {
             if world.get_fixed_position(passenger_id) == None {
                 return Err(Error::InvalidPassenger {
-                    id: passenger_id,
+                    name: format!("#{}", passenger_id.index()),
                     world: world.display(),
                 });
             }
@@ -128,38 +161,68 @@ impl State {
         })
     }
 
+    // As build, with the passenger and destination given by name or glyph,
+    // as World::find_location looks them up.
+    pub fn build_named(
+        world: &World,
+        taxi_pos: (i32, i32),
+        passenger: Option<&str>,
+        destination: &str,
+    ) -> Result<State, Error> {
+        let destination_id =
+            world
+                .find_location(destination)
+                .ok_or_else(|| Error::InvalidDestination {
+                    name: String::from(destination),
+                    world: world.display(),
+                })?;
+
+        let passenger_id = match passenger {
+            Some(name) => {
+                Some(
+                    world
+                        .find_location(name)
+                        .ok_or_else(|| Error::InvalidPassenger {
+                            name: String::from(name),
+                            world: world.display(),
+                        })?,
+                )
+            }
+            None => None,
+        };
+
+        State::build(world, taxi_pos, passenger_id, destination_id)
+    }
+
     pub fn build_random<R: Rng>(world: &World, rng: &mut R) -> Result<State, Error> {
-        let taxi_x = rng.gen_range(0, world.width);
-        let taxi_y = rng.gen_range(0, world.height);
+        let (taxi_x, taxi_y) = loop {
+            let taxi_x = rng.gen_range(0, world.width);
+            let taxi_y = rng.gen_range(0, world.height);
 
-        let num_fixed_positions = world.num_fixed_positions();
+            if !world.is_blocked(Position::new(taxi_x, taxi_y)) {
+                break (taxi_x, taxi_y);
+            }
+        };
 
-        if num_fixed_positions >= 2 {
-            let destination_fp_index = rng.gen_range(0, num_fixed_positions);
-            if let Some(destination) = world.get_fixed_id_from_index(destination_fp_index) {
-                let passenger_fp_index = (destination_fp_index
-                    + rng.gen_range(1, num_fixed_positions))
-                    % num_fixed_positions;
+        let (passenger, destination) = random_request(world, rng)?;
 
-                let passenger = world.get_fixed_id_from_index(passenger_fp_index);
+        Ok(State {
+            taxi: Position::new(taxi_x, taxi_y),
+            passenger,
+            destination,
+        })
+    }
 
-                Ok(State {
-                    taxi: Position::new(taxi_x, taxi_y),
-                    passenger,
-                    destination,
-                })
-            } else {
-                Err(Error::FailedToFindDestination {
-                    destination_offset: destination_fp_index,
-                    world: world.display(),
-                })
-            }
-        } else {
-            Err(Error::TooFewFixedPositions {
-                num_fixed_positions,
-                world: world.display(),
-            })
-        }
+    // A new passenger and destination for the taxi where it is, drawn as
+    // build_random draws them.
+    pub fn with_new_request<R: Rng>(&self, world: &World, rng: &mut R) -> Result<State, Error> {
+        let (passenger, destination) = random_request(world, rng)?;
+
+        Ok(State {
+            taxi: self.taxi,
+            passenger,
+            destination,
+        })
     }
 
     pub fn display(&self, world: &World) -> String {
@@ -173,7 +236,11 @@ impl State {
             if i_r % 2 == 1 {
                 for (i_c, c) in r.chars().enumerate() {
                     if i_c % 2 == 1 {
-                        result.push(self.calc_character(c, current_position));
+                        if world.is_blocked(current_position) {
+                            result.push(c);
+                        } else {
+                            result.push(self.calc_character(world, current_position));
+                        }
 
                         current_position.x += 1;
                     } else {
@@ -193,8 +260,10 @@ impl State {
         result
     }
 
-    fn calc_character(&self, id: char, position: Position) -> char {
-        if id == self.destination {
+    fn calc_character(&self, world: &World, position: Position) -> char {
+        let id = world.get_fixed_id(position);
+
+        if id == Some(self.destination) {
             match self.passenger {
                 Some(passenger_id) if passenger_id == self.destination => 'D',
                 _ => 'd',
@@ -202,7 +271,7 @@ impl State {
         } else {
             match self.passenger {
                 Some(passenger_id) => {
-                    if passenger_id == id {
+                    if Some(passenger_id) == id {
                         'p'
                     } else if self.taxi == position {
                         't'
@@ -225,7 +294,7 @@ impl State {
         match world.determine_affect(self.taxi, action) {
             ActionAffect::Invalid => match action {
                 Actions::North | Actions::South | Actions::East | Actions::West => {
-                    (world.costs.movement, *self)
+                    (world.movement_cost(self.taxi), *self)
                 }
                 Actions::PickUp => (world.costs.miss_pickup, *self),
                 Actions::DropOff => {
@@ -237,7 +306,7 @@ impl State {
                 }
             },
             ActionAffect::Move(delta) => (
-                world.costs.movement,
+                world.movement_cost(self.taxi),
                 State {
                     taxi: self.taxi + delta,
                     ..*self
@@ -286,11 +355,11 @@ impl State {
         }
     }
 
-    pub fn get_passenger(&self) -> Option<char> {
+    pub fn get_passenger(&self) -> Option<LocationId> {
         self.passenger
     }
 
-    pub fn get_destination(&self) -> char {
+    pub fn get_destination(&self) -> LocationId {
         self.destination
     }
 
@@ -318,12 +387,8 @@ impl<'a> StateIterator<'a> {
             world,
         }
     }
-}
 
-impl<'a> Iterator for StateIterator<'a> {
-    type Item = State;
-
-    fn next(&mut self) -> Option<State> {
+    fn advance(&mut self) -> Option<State> {
         if self.current.taxi.x < self.world.width - 1 {
             self.current.taxi.x += 1;
             Some(self.current)
@@ -354,6 +419,51 @@ impl<'a> Iterator for StateIterator<'a> {
     }
 }
 
+impl<'a> Iterator for StateIterator<'a> {
+    type Item = State;
+
+    // Blocked cells are skipped, the taxi can never be there.
+    fn next(&mut self) -> Option<State> {
+        loop {
+            let state = self.advance()?;
+
+            if !self.world.is_blocked(state.taxi) {
+                return Some(state);
+            }
+        }
+    }
+}
+
+fn random_request<R: Rng>(
+    world: &World,
+    rng: &mut R,
+) -> Result<(Option<LocationId>, LocationId), Error> {
+    let num_fixed_positions = world.num_fixed_positions();
+
+    if num_fixed_positions < 2 {
+        return Err(Error::TooFewFixedPositions {
+            num_fixed_positions,
+            world: world.display(),
+        });
+    }
+
+    let destination_fp_index = rng.gen_range(0, num_fixed_positions);
+    let destination = world
+        .get_fixed_id_from_index(destination_fp_index)
+        .ok_or_else(|| Error::FailedToFindDestination {
+            destination_offset: destination_fp_index,
+            world: world.display(),
+        })?;
+
+    let passenger_fp_index =
+        (destination_fp_index + rng.gen_range(1, num_fixed_positions)) % num_fixed_positions;
+
+    Ok((
+        world.get_fixed_id_from_index(passenger_fp_index),
+        destination,
+    ))
+}
+
 #[cfg(test)]
 mod test_state {
 
@@ -380,11 +490,11 @@ mod test_state {
         let w = World::build_from_str(source_world, costs).unwrap();
         let expected_state = State {
             taxi: Position::new(1, 3),
-            passenger: Some('R'),
-            destination: 'B',
+            passenger: w.find_location("R"),
+            destination: w.find_location("B").unwrap(),
         };
 
-        let res_state = State::build(&w, (1, 3), Some('R'), 'B').unwrap();
+        let res_state = State::build_named(&w, (1, 3), Some("R"), "B").unwrap();
         assert_eq!(res_state, expected_state);
     }
 
@@ -406,7 +516,7 @@ mod test_state {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let initial_state = State::build(&w, (2, 2), Some('R'), 'G').unwrap();
+        let initial_state = State::build_named(&w, (2, 2), Some("R"), "G").unwrap();
 
         let expected_initial = "\
                                 ┌───┬─────┐\n\
@@ -449,7 +559,7 @@ mod test_state {
         let costs = Costs::default();
         let w = World::build_from_str(source_world, costs).unwrap();
 
-        let initial_state = State::build(&w, (1, 3), Some('R'), 'G').unwrap();
+        let initial_state = State::build_named(&w, (1, 3), Some("R"), "G").unwrap();
 
         let expected_initial = "\
                                 ┌───┬─────┐\n\
@@ -603,7 +713,7 @@ mod test_state {
         let costs = Costs::default();
         let w = World::build_from_str(source, costs).unwrap();
 
-        let mut state = State::build(&w, (1, 2), Some('R'), 'G').unwrap();
+        let mut state = State::build_named(&w, (1, 2), Some("R"), "G").unwrap();
         println!();
 
         for &(expected_str, expected_passenger, expected_at_destination, next_action) in
@@ -617,7 +727,10 @@ mod test_state {
                 next_action
             );
 
-            assert_eq!(expected_passenger, state.passenger);
+            assert_eq!(
+                expected_passenger,
+                state.passenger.and_then(|id| w.location_glyph(id))
+            );
             assert_eq!(expected_at_destination, state.at_destination());
             assert_eq!(expected_str, state.display(&w));
 
@@ -827,7 +940,7 @@ mod test_state {
         let costs = Costs::default();
         let w = World::build_from_str(source, costs).unwrap();
 
-        let mut state = State::build(&w, (1, 3), Some('R'), 'G').unwrap();
+        let mut state = State::build_named(&w, (1, 3), Some("R"), "G").unwrap();
         println!();
 
         for &(expected_str, expected_passenger, expected_at_destination, next_action) in
@@ -841,7 +954,10 @@ mod test_state {
                 next_action
             );
 
-            assert_eq!(expected_passenger, state.passenger);
+            assert_eq!(
+                expected_passenger,
+                state.passenger.and_then(|id| w.location_glyph(id))
+            );
             assert_eq!(expected_at_destination, state.at_destination());
             assert_eq!(expected_str, state.display(&w));
 
@@ -10,7 +10,7 @@ pub struct StateIndexer {
 
 impl StateIndexer {
     pub fn new(world: &World) -> StateIndexer {
-        let num_taxi_states = (world.width * world.height) as usize;
+        let num_taxi_states = world.num_open_cells();
         let num_destination_states = world.num_fixed_positions();
         let num_passenger_states = num_destination_states + 1;
 
@@ -36,8 +36,7 @@ impl StateIndexer {
                 result *= self.num_passenger_states;
                 result += passenger_index;
 
-                let taxi_pos = state.get_taxi();
-                let taxi_index = (world.width * taxi_pos.y + taxi_pos.x) as usize;
+                let taxi_index = world.open_cell_index(state.get_taxi())?;
 
                 result *= self.num_taxi_states;
                 result += taxi_index;
@@ -58,8 +57,7 @@ impl StateIndexer {
 
         let destination_index = state_index;
 
-        let taxi_x = taxi_index % (world.width as usize);
-        let taxi_y = taxi_index / (world.width as usize);
+        let taxi_pos = world.open_cell_position(taxi_index)?;
 
         if let Some(destination) = world.get_fixed_id_from_index(destination_index) {
             let passenger = if passenger_index < world.num_fixed_positions() {
@@ -68,13 +66,7 @@ impl StateIndexer {
                 None
             };
 
-            State::build(
-                world,
-                (taxi_x as i32, taxi_y as i32),
-                passenger,
-                destination,
-            )
-            .ok()
+            State::build(world, (taxi_pos.x, taxi_pos.y), passenger, destination).ok()
         } else {
             None
         }
@@ -0,0 +1,577 @@
+use std::cmp::Ordering;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+use rayon::prelude::*;
+
+use serde::de::DeserializeOwned;
+
+use taxi::doormax::{DoorMax, EffectModel};
+use taxi::factoredrmax::{FactoredRMax, Structure};
+use taxi::maxq::MaxQ;
+use taxi::oomdp::taxi::TaxiObjects;
+use taxi::qlearner::QLearner;
+use taxi::random_solver::RandomSolver;
+use taxi::rmax::RMax;
+use taxi::runner::Probe;
+use taxi::schedule::Schedule;
+use taxi::schemanet::SchemaNet;
+use taxi::world::World;
+
+use crate::configuration::{
+    Configuration, DoorMaxConfig, FactoredRMaxConfig, MaxQConfig, QLearnerConfig, RMaxConfig,
+    RandomSolverConfig, SchemaNetConfig, SearchChoice, SolverChoice, SweepConfig, SweepRange,
+};
+use crate::{measure_sessions, write_output, AppError, Stats};
+
+type Combination = Vec<(String, toml::Value)>;
+
+pub fn run_sweep(
+    sweep: &SweepConfig,
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    root_seed: u128,
+) -> Result<(), AppError> {
+    let base_table = base_table(sweep, config)?;
+
+    let combinations = match sweep.search {
+        SearchChoice::Grid => expand_grid(sweep),
+        SearchChoice::Random => {
+            let mut rng = Pcg64Mcg::new(root_seed);
+            sample_random(sweep, &mut rng)
+        }
+    };
+
+    println!(
+        "{} - sweeping {} combinations of {} sessions.",
+        sweep.label(),
+        combinations.len(),
+        config.sessions
+    );
+
+    // Every combination uses the same root seed, so each sees the same
+    // sequence of sessions.
+    let stats = combinations
+        .par_iter()
+        .map(|combination| {
+            let mut table = base_table.clone();
+            for (parameter, value) in combination {
+                table.insert(parameter.clone(), value.clone());
+            }
+
+            measure_combination(sweep.solver, table, config, world, probes, root_seed)
+        })
+        .collect::<Result<Vec<Stats>, AppError>>()?;
+
+    let mut results: Vec<(Combination, Stats)> = combinations.into_iter().zip(stats).collect();
+    results.sort_by(|(_, a), (_, b)| compare_stats(a, b));
+
+    let table = build_results_table(sweep, config, &results, root_seed);
+
+    println!();
+    print!("{}", table);
+
+    if let Some(ref filename) = sweep.output {
+        write_output(filename, &table)?;
+    }
+
+    Ok(())
+}
+
+// The swept solver's configuration table, once every swept parameter has
+// been checked against it.
+fn base_table<'a>(
+    sweep: &SweepConfig,
+    config: &'a Configuration,
+) -> Result<&'a toml::value::Table, AppError> {
+    let base_table = config
+        .solver_table(sweep.solver, sweep.profile.as_deref())
+        .ok_or(AppError::SweepSolverNotConfigured(sweep.solver))?;
+
+    for parameter in sweep.parameters.keys() {
+        if !base_table.contains_key(parameter) {
+            return Err(AppError::SweepUnknownParameter {
+                solver: sweep.solver,
+                parameter: parameter.clone(),
+            });
+        }
+    }
+
+    Ok(base_table)
+}
+
+fn measure_combination(
+    solver: SolverChoice,
+    table: toml::value::Table,
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    root_seed: u128,
+) -> Result<Stats, AppError> {
+    match solver {
+        SolverChoice::Random => {
+            let random_config: RandomSolverConfig = parse_solver_config(solver, table)?;
+            measure_sessions(
+                |_| RandomSolver::new(),
+                &random_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::QLearner => {
+            let qlearner_config: QLearnerConfig = parse_solver_config(solver, table)?;
+            measure_sessions(
+                |world| {
+                    QLearner::new(
+                        world,
+                        Schedule::from(qlearner_config.alpha),
+                        qlearner_config.gamma,
+                        Schedule::from(qlearner_config.epsilon),
+                    )
+                },
+                &qlearner_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::RMax => {
+            let rmax_config: RMaxConfig = parse_solver_config(solver, table)?;
+            measure_sessions(
+                |world| {
+                    RMax::new(
+                        world,
+                        rmax_config.gamma,
+                        rmax_config.known_count,
+                        rmax_config.error_delta,
+                    )
+                },
+                &rmax_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::FactoredRMax => {
+            let factored_rmax_config: FactoredRMaxConfig = parse_solver_config(solver, table)?;
+            measure_sessions(
+                |world| {
+                    FactoredRMax::new(
+                        world,
+                        Structure::from(&factored_rmax_config),
+                        factored_rmax_config.gamma,
+                        factored_rmax_config.known_count,
+                        factored_rmax_config.error_delta,
+                    )
+                },
+                &factored_rmax_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::MaxQ => {
+            let maxq_config: MaxQConfig = parse_solver_config(solver, table)?;
+            measure_sessions(
+                |world| {
+                    MaxQ::new(
+                        world,
+                        Schedule::from(maxq_config.alpha),
+                        maxq_config.gamma,
+                        Schedule::from(maxq_config.epsilon),
+                        maxq_config.show_learning,
+                    )
+                },
+                &maxq_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::DoorMax => {
+            let doormax_config: DoorMaxConfig = parse_solver_config(solver, table)?;
+            let taxi_objects =
+                TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+            measure_sessions(
+                |world| {
+                    DoorMax::new(
+                        world,
+                        taxi_objects.clone(),
+                        EffectModel::from(&doormax_config),
+                        doormax_config.gamma,
+                        doormax_config.use_reward_learner,
+                        doormax_config.known_count,
+                        doormax_config.error_delta,
+                    )
+                },
+                &doormax_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+
+        SolverChoice::SchemaNet => {
+            let schemanet_config: SchemaNetConfig = parse_solver_config(solver, table)?;
+            let taxi_objects =
+                TaxiObjects::with_predicates(&schemanet_config.terms).map_err(AppError::Terms)?;
+            measure_sessions(
+                |world| {
+                    SchemaNet::new(
+                        world,
+                        taxi_objects.clone(),
+                        schemanet_config.gamma,
+                        schemanet_config.error_delta,
+                    )
+                },
+                &schemanet_config,
+                world,
+                probes,
+                config,
+                root_seed,
+                false,
+            )
+        }
+    }
+}
+
+fn parse_solver_config<T: DeserializeOwned>(
+    solver: SolverChoice,
+    table: toml::value::Table,
+) -> Result<T, AppError> {
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|error| AppError::SweepParameters { solver, error })
+}
+
+fn range_values(range: &SweepRange) -> Vec<toml::Value> {
+    match *range {
+        SweepRange::Values { ref values } => values.clone(),
+
+        SweepRange::Range {
+            start,
+            end,
+            count,
+            log,
+        } => (0..count)
+            .map(|i| {
+                let fraction = if count > 1 {
+                    i as f64 / (count - 1) as f64
+                } else {
+                    0.0
+                };
+
+                toml::Value::Float(interpolate(start, end, fraction, log))
+            })
+            .collect(),
+    }
+}
+
+fn sample_value<R: Rng>(range: &SweepRange, rng: &mut R) -> Option<toml::Value> {
+    match *range {
+        SweepRange::Values { ref values } => values.choose(rng).cloned(),
+
+        SweepRange::Range {
+            start, end, log, ..
+        } => {
+            let fraction = rng.gen_range(0.0f64, 1.0f64);
+            Some(toml::Value::Float(interpolate(start, end, fraction, log)))
+        }
+    }
+}
+
+fn interpolate(start: f64, end: f64, fraction: f64, log: bool) -> f64 {
+    if log {
+        (start.ln() + (end.ln() - start.ln()) * fraction).exp()
+    } else {
+        start + (end - start) * fraction
+    }
+}
+
+fn expand_grid(sweep: &SweepConfig) -> Vec<Combination> {
+    let mut combinations: Vec<Combination> = vec![Vec::new()];
+
+    for (parameter, range) in &sweep.parameters {
+        let values = range_values(range);
+
+        let mut next_combinations = Vec::with_capacity(combinations.len() * values.len());
+
+        for combination in &combinations {
+            for value in &values {
+                let mut next_combination = combination.clone();
+                next_combination.push((parameter.clone(), value.clone()));
+                next_combinations.push(next_combination);
+            }
+        }
+
+        combinations = next_combinations;
+    }
+
+    combinations
+}
+
+fn sample_random<R: Rng>(sweep: &SweepConfig, rng: &mut R) -> Vec<Combination> {
+    (0..sweep.samples)
+        .map(|_| {
+            sweep
+                .parameters
+                .iter()
+                .filter_map(|(parameter, range)| {
+                    sample_value(range, rng).map(|value| (parameter.clone(), value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Fewer failures first, then fewer average steps.
+fn compare_stats(a: &Stats, b: &Stats) -> Ordering {
+    let (mean_a, _) = a.distribution.get_distribution();
+    let (mean_b, _) = b.distribution.get_distribution();
+
+    a.failure_rate()
+        .partial_cmp(&b.failure_rate())
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| match (mean_a.is_nan(), mean_b.is_nan()) {
+            (false, false) => mean_a.partial_cmp(&mean_b).unwrap_or(Ordering::Equal),
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (true, true) => Ordering::Equal,
+        })
+}
+
+// Keeps every value on one line so it fits in a table column.
+fn display_value(value: &toml::Value) -> String {
+    match *value {
+        toml::Value::Float(f) => {
+            let fixed = format!("{:.4}", f);
+            fixed
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        }
+        toml::Value::String(ref s) => s.clone(),
+        toml::Value::Array(ref values) => {
+            let values: Vec<String> = values.iter().map(display_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        toml::Value::Table(ref table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, display_value(value)))
+                .collect();
+            format!("{{{}}}", entries.join(" "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn build_results_table(
+    sweep: &SweepConfig,
+    config: &Configuration,
+    results: &[(Combination, Stats)],
+    root_seed: u128,
+) -> String {
+    let mut header: Vec<String> = vec![String::from("rank")];
+    header.extend(sweep.parameters.keys().cloned());
+    header.push(String::from("mean steps"));
+    header.push(String::from("stddev"));
+    header.push(String::from("failure rate"));
+
+    let mut rows = vec![header];
+
+    for (rank, (combination, stats)) in results.iter().enumerate() {
+        let (avg_steps, stddev_steps) = stats.distribution.get_distribution();
+
+        let mut row = vec![format!("{}", rank + 1)];
+        row.extend(combination.iter().map(|(_, value)| display_value(value)));
+        row.push(format!("{:.1}", avg_steps));
+        row.push(format!("{:.2}", stddev_steps));
+        row.push(format!("{:.1}%", 100.0 * stats.failure_rate()));
+
+        rows.push(row);
+    }
+
+    let num_columns = rows[0].len();
+    let widths: Vec<usize> = (0..num_columns)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut result = format!(
+        "{} sweep - {} combinations of {} sessions with maximums {} trials of {} steps. \
+         Using seed [{}, {}]\n",
+        sweep.label(),
+        results.len(),
+        config.sessions,
+        config.max_trials,
+        config.max_trial_steps,
+        root_seed.rotate_right(64) as i64,
+        root_seed as i64,
+    );
+
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+
+        result += line.join("  ").trim_end();
+        result.push('\n');
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test_sweep {
+    use super::*;
+    use float_cmp::approx_eq;
+
+    fn build_sweep(source: &str) -> SweepConfig {
+        toml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn grid_covers_every_combination() {
+        let sweep = build_sweep(
+            r#"
+            solver = "QLearner"
+            [parameters]
+            alpha = { values = [0.1, 0.2, 0.3] }
+            gamma = { start = 0.5, end = 0.9, count = 2 }
+            "#,
+        );
+
+        let combinations = expand_grid(&sweep);
+        assert_eq!(combinations.len(), 6);
+
+        for combination in &combinations {
+            let parameters: Vec<&str> = combination
+                .iter()
+                .map(|(parameter, _)| parameter.as_str())
+                .collect();
+            assert_eq!(parameters, vec!["alpha", "gamma"]);
+        }
+
+        for alpha in &[0.1, 0.2, 0.3] {
+            for gamma in &[0.5, 0.9] {
+                let expected = vec![
+                    (String::from("alpha"), toml::Value::Float(*alpha)),
+                    (String::from("gamma"), toml::Value::Float(*gamma)),
+                ];
+                assert!(combinations.contains(&expected));
+            }
+        }
+    }
+
+    #[test]
+    fn grid_without_parameters_is_one_combination() {
+        let sweep = build_sweep(
+            r#"
+            solver = "QLearner"
+            [parameters]
+            "#,
+        );
+
+        assert_eq!(expand_grid(&sweep), vec![Combination::new()]);
+    }
+
+    #[test]
+    fn range_includes_endpoints() {
+        let values = range_values(&SweepRange::Range {
+            start: 0.1,
+            end: 0.9,
+            count: 5,
+            log: false,
+        });
+
+        let values: Vec<f64> = values.iter().map(|v| v.as_float().unwrap()).collect();
+        assert_eq!(values.len(), 5);
+        assert!(approx_eq!(f64, values[0], 0.1, ulps = 2));
+        assert!(approx_eq!(f64, values[2], 0.5, ulps = 2));
+        assert!(approx_eq!(f64, values[4], 0.9, ulps = 2));
+    }
+
+    #[test]
+    fn log_range_is_geometric() {
+        let values = range_values(&SweepRange::Range {
+            start: 0.001,
+            end: 1.0,
+            count: 4,
+            log: true,
+        });
+
+        let values: Vec<f64> = values.iter().map(|v| v.as_float().unwrap()).collect();
+        let expected = [0.001, 0.01, 0.1, 1.0];
+        for (value, expected) in values.iter().zip(&expected) {
+            assert!(approx_eq!(f64, *value, *expected, epsilon = 1.0e-12));
+        }
+    }
+
+    #[test]
+    fn range_of_one_is_start() {
+        let values = range_values(&SweepRange::Range {
+            start: 0.3,
+            end: 0.9,
+            count: 1,
+            log: false,
+        });
+
+        assert_eq!(values, vec![toml::Value::Float(0.3)]);
+    }
+
+    #[test]
+    fn random_draws_samples_within_ranges() {
+        let sweep = build_sweep(
+            r#"
+            solver = "QLearner"
+            search = "random"
+            samples = 7
+            [parameters]
+            alpha = { values = [0.1, 0.2] }
+            gamma = { start = 0.5, end = 0.9, count = 2 }
+            "#,
+        );
+
+        let mut rng = Pcg64Mcg::new(1);
+        let combinations = sample_random(&sweep, &mut rng);
+        assert_eq!(combinations.len(), 7);
+
+        for combination in &combinations {
+            assert_eq!(combination.len(), 2);
+
+            let alpha = combination[0].1.as_float().unwrap();
+            assert!(alpha == 0.1 || alpha == 0.2);
+
+            let gamma = combination[1].1.as_float().unwrap();
+            assert!((0.5..=0.9).contains(&gamma));
+        }
+    }
+}
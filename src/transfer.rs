@@ -0,0 +1,281 @@
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+use rayon::prelude::*;
+
+use taxi::distribution::MeasureDistribution;
+use taxi::doormax::{DoorMax, EffectModel};
+use taxi::oomdp::taxi::TaxiObjects;
+use taxi::runner::{run_training_session, Probe, TransferRunner};
+use taxi::schemanet::SchemaNet;
+use taxi::start::Curriculum;
+use taxi::state::State;
+use taxi::world::World;
+
+use crate::configuration::{Configuration, ReportConfig, TransferConfig};
+use crate::{build_probes, generate_session_world, write_output, AppError};
+
+// How one session went on the new world.
+struct TransferSession {
+    probes_solved: usize,
+    attempts_solved: usize,
+    attempt_steps: MeasureDistribution,
+    // The steps taken to pass the new world's probes, once moved and from
+    // scratch, None if they were never passed.
+    moved_steps: Option<usize>,
+    fresh_steps: Option<usize>,
+}
+
+#[derive(Default)]
+struct TransferStats {
+    // The fraction of probes and attempts solved by each session as soon as
+    // the solver is moved.
+    jumpstart_probes: MeasureDistribution,
+    jumpstart_attempts: MeasureDistribution,
+    attempt_steps: MeasureDistribution,
+
+    moved_steps: MeasureDistribution,
+    moved_failures: usize,
+    fresh_steps: MeasureDistribution,
+    fresh_failures: usize,
+}
+
+pub fn run_transfer(
+    transfer: &TransferConfig,
+    config: &Configuration,
+    world: &World,
+    probes: &[Probe],
+    root_seed: u128,
+    output: Option<&String>,
+) -> Result<(), AppError> {
+    let other_world = transfer.build_world(world.costs).map_err(AppError::World)?;
+    let other_probes = build_probes(&transfer.probes, &other_world)?;
+    let worlds = (world, probes, &other_world, &other_probes[..]);
+
+    let mut results = Vec::new();
+
+    for doormax_config in &config.door_max {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&doormax_config.terms).map_err(AppError::Terms)?;
+        let stats = measure_transfer(
+            |world| {
+                DoorMax::new(
+                    world,
+                    taxi_objects.clone(),
+                    EffectModel::from(doormax_config),
+                    doormax_config.gamma,
+                    doormax_config.use_reward_learner,
+                    doormax_config.known_count,
+                    doormax_config.error_delta,
+                )
+            },
+            worlds,
+            transfer,
+            config,
+            root_seed,
+        )?;
+
+        results.push((doormax_config.label(), stats));
+    }
+
+    for schemanet_config in &config.schema_net {
+        let taxi_objects =
+            TaxiObjects::with_predicates(&schemanet_config.terms).map_err(AppError::Terms)?;
+        let stats = measure_transfer(
+            |world| {
+                SchemaNet::new(
+                    world,
+                    taxi_objects.clone(),
+                    schemanet_config.gamma,
+                    schemanet_config.error_delta,
+                )
+            },
+            worlds,
+            transfer,
+            config,
+            root_seed,
+        )?;
+
+        results.push((schemanet_config.label(), stats));
+    }
+
+    let mut summary = String::new();
+
+    for (label, stats) in results {
+        let (probes_solved, _) = stats.jumpstart_probes.get_distribution();
+        let (attempts_solved, _) = stats.jumpstart_attempts.get_distribution();
+        let (attempt_steps, _) = stats.attempt_steps.get_distribution();
+        let (moved_steps, moved_stddev) = stats.moved_steps.get_distribution();
+        let (fresh_steps, fresh_stddev) = stats.fresh_steps.get_distribution();
+
+        summary += &format!(
+            "{} - once moved solved {:.1}% of probes and {:.1}% of {} attempts in {:.1} \
+             average steps. Passed the probes in {:.1} average steps with stddev of {:.2} \
+             and {} failures, against {:.1} with stddev of {:.2} and {} failures from \
+             scratch, over {} sessions. Using seed [{}, {}]\n",
+            label,
+            100.0 * probes_solved,
+            100.0 * attempts_solved,
+            transfer.attempts,
+            attempt_steps,
+            moved_steps,
+            moved_stddev,
+            stats.moved_failures,
+            fresh_steps,
+            fresh_stddev,
+            stats.fresh_failures,
+            stats.jumpstart_probes.get_count() as usize,
+            root_seed.rotate_right(64) as i64,
+            root_seed as i64,
+        );
+    }
+
+    println!();
+    print!("{}", summary);
+
+    if let Some(filename) = output {
+        write_output(filename, &summary)?;
+    }
+
+    Ok(())
+}
+
+// The configured world and its probes, then the world to move to and its
+// probes.
+type Worlds<'a> = (&'a World, &'a [Probe], &'a World, &'a [Probe]);
+
+fn measure_transfer<B, Rnr>(
+    builder: B,
+    worlds: Worlds,
+    transfer: &TransferConfig,
+    config: &Configuration,
+    root_seed: u128,
+) -> Result<TransferStats, AppError>
+where
+    B: Fn(&World) -> Rnr + Sync,
+    Rnr: TransferRunner,
+{
+    let mut seed_generator = Pcg64Mcg::new(root_seed);
+    let seeds: Vec<u128> = (0..config.sessions).map(|_| seed_generator.gen()).collect();
+
+    let sessions = seeds
+        .par_iter()
+        .map(|&seed| transfer_session(&builder, worlds, transfer, config, seed))
+        .collect::<Result<Vec<TransferSession>, AppError>>()?;
+
+    let (_, _, _, other_probes) = worlds;
+    let mut stats = TransferStats::default();
+
+    for session in sessions {
+        if !other_probes.is_empty() {
+            stats
+                .jumpstart_probes
+                .add_value(session.probes_solved as f64 / other_probes.len() as f64);
+        }
+        if transfer.attempts > 0 {
+            stats
+                .jumpstart_attempts
+                .add_value(session.attempts_solved as f64 / transfer.attempts as f64);
+        }
+        stats.attempt_steps.add_distribution(&session.attempt_steps);
+
+        match session.moved_steps {
+            Some(steps) => stats.moved_steps.add_value(steps as f64),
+            None => stats.moved_failures += 1,
+        }
+        match session.fresh_steps {
+            Some(steps) => stats.fresh_steps.add_value(steps as f64),
+            None => stats.fresh_failures += 1,
+        }
+    }
+
+    Ok(stats)
+}
+
+// Trains a solver on the first world, moves it to the other and measures
+// how it does there before and while learning, then trains a new solver on
+// the other world to compare against.  Starts on the other world are drawn
+// uniformly, as the configured starts name the first world's locations.
+fn transfer_session<B, Rnr>(
+    builder: &B,
+    (world, probes, other_world, other_probes): Worlds,
+    transfer: &TransferConfig,
+    config: &Configuration,
+    seed: u128,
+) -> Result<TransferSession, AppError>
+where
+    B: Fn(&World) -> Rnr,
+    Rnr: TransferRunner,
+{
+    let mut rng = Pcg64Mcg::new(seed);
+
+    let session_world = generate_session_world(config, world.costs, &mut rng)?;
+    let (world, probes) = match session_world {
+        Some((ref world, ref probes)) => (world, &probes[..]),
+        None => (world, probes),
+    };
+
+    let starts = config.build_starts(world).map_err(AppError::Start)?;
+
+    let mut solver = builder(world);
+    run_training_session(
+        world,
+        &starts,
+        probes,
+        config.max_trials,
+        config.max_trial_steps,
+        &mut solver,
+        &mut rng,
+    )
+    .map_err(AppError::Runner)?;
+
+    let mut moved = solver.transfer(other_world);
+
+    let probes_solved = other_probes
+        .iter()
+        .filter(|probe| moved.solves(other_world, probe.state, probe.maximum_steps, &mut rng))
+        .count();
+
+    let mut attempts_solved = 0;
+    let mut attempt_steps = MeasureDistribution::default();
+    for _ in 0..transfer.attempts {
+        let state = State::build_random(other_world, &mut rng).map_err(AppError::BuildProbes)?;
+        let attempt = moved.attempt(other_world, state, config.max_trial_steps, &mut rng);
+
+        if attempt.success {
+            attempts_solved += 1;
+            attempt_steps.add_value(attempt.actions.len() as f64);
+        }
+    }
+
+    let other_starts = Curriculum::new();
+    let moved_steps = run_training_session(
+        other_world,
+        &other_starts,
+        other_probes,
+        config.max_trials,
+        config.max_trial_steps,
+        &mut moved,
+        &mut rng,
+    )
+    .map_err(AppError::Runner)?;
+
+    let fresh_steps = run_training_session(
+        other_world,
+        &other_starts,
+        other_probes,
+        config.max_trials,
+        config.max_trial_steps,
+        &mut builder(other_world),
+        &mut rng,
+    )
+    .map_err(AppError::Runner)?;
+
+    Ok(TransferSession {
+        probes_solved,
+        attempts_solved,
+        attempt_steps,
+        moved_steps,
+        fresh_steps,
+    })
+}
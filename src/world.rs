@@ -1,9 +1,17 @@
+use std::collections::VecDeque;
 use std::fmt;
 use std::iter;
 
 use crate::actions::Actions;
 use crate::position::Position;
 
+pub mod analysis;
+pub mod format;
+pub mod generator;
+
+pub use self::analysis::Analysis;
+pub use self::format::{Format, WorldDescription};
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Wall {
     pub north: bool,
@@ -47,10 +55,39 @@ impl fmt::Display for Wall {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+// One of a world's fixed positions, numbered in the order World keeps them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocationId(usize);
+
+impl LocationId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+// glyph marks the fixed position on the map, name defaults to the glyph.
+#[derive(Debug, Clone, PartialEq)]
 struct FixedPosition {
-    id: char,
+    glyph: char,
+    name: String,
+    position: Position,
+}
+
+impl FixedPosition {
+    fn new(glyph: char, position: Position) -> FixedPosition {
+        FixedPosition {
+            glyph,
+            name: glyph.to_string(),
+            position,
+        }
+    }
+}
+
+// A cell whose moves cost something other than Costs::movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TerrainCost {
     position: Position,
+    movement: f64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -78,53 +115,217 @@ impl Default for Costs {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct World {
     pub width: i32,
     pub height: i32,
     walls: Vec<Vec<Wall>>,
+    // Cells the taxi can never enter, in reading order.
+    blocked: Vec<Position>,
     fixed_positions: Vec<FixedPosition>,
+    // In reading order.
+    terrain: Vec<TerrainCost>,
 
     pub costs: Costs,
+
+    // Worked out from the layout by build_lookups, indexed by
+    // y * width + x.  Empty while the layout is still being put together,
+    // when lookups fall back to searching the layout.
+    fixed_ids: Vec<Option<LocationId>>,
+    affects: Vec<[ActionAffect; Actions::NUM_ELEMENTS]>,
+    // The open cell index of each cell, None if it is blocked, and the
+    // position of each open cell.
+    open_cells: Vec<Option<usize>>,
+    open_positions: Vec<Position>,
+    // Each distinct terrain cost, cheapest first, and the level of each
+    // cell's cost among them.
+    terrain_costs: Vec<f64>,
+    terrain_levels: Vec<Option<usize>>,
+}
+
+// The lookups follow from the rest, so they are left out.
+impl PartialEq for World {
+    fn eq(&self, other: &World) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.walls == other.walls
+            && self.blocked == other.blocked
+            && self.fixed_positions == other.fixed_positions
+            && self.terrain == other.terrain
+            && self.costs == other.costs
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActionAffect {
     Invalid,
     Move(Position),
-    PickUp(char),
-    DropOff(char),
+    PickUp(LocationId),
+    DropOff(LocationId),
+}
+
+// 1-based, counting characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
 }
 
 pub enum Error {
     EmptyString,
-    Parse { source: String, error: ParseError },
+    Parse {
+        source: String,
+        location: Option<Location>,
+        error: ParseError,
+    },
+    Deserialize {
+        source: String,
+        message: String,
+    },
 }
 
-impl fmt::Debug for Error {
+impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::EmptyString => write!(f, "Attempted to build world from empty string."),
             Error::Parse {
                 ref source,
+                location,
                 ref error,
-            } => write!(f, "Parse failure: {:?}\nSource string:\n{}", error, source),
+            } => {
+                match location {
+                    Some(location) => {
+                        write!(
+                            f,
+                            "Parse failure at line {}, column {}: {}",
+                            location.line, location.column, error
+                        )?;
+
+                        // Point at the problem rather than showing the whole source.
+                        if let Some(line) = source.lines().nth(location.line - 1) {
+                            let margin = " ".repeat(location.line.to_string().len());
+                            write!(
+                                f,
+                                "\n {} | {}\n {} | {}^",
+                                location.line,
+                                line,
+                                margin,
+                                " ".repeat(location.column - 1)
+                            )?;
+                        }
+                    }
+                    None => write!(f, "Parse failure: {}", error)?,
+                }
+
+                if let Some(hint) = error.hint() {
+                    write!(f, "\nHint: {}", hint)?;
+                }
+
+                Ok(())
+            }
+            Error::Deserialize {
+                ref source,
+                ref message,
+            } => write!(
+                f,
+                "Failed to read world description: {}\nSource string:\n{}",
+                message, source
+            ),
         }
     }
 }
 
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 pub enum ParseError {
     LineTooLong {
         line: usize,
         num_chars: usize,
         expected_num_chars: usize,
     },
+    UnexpectedCharacter {
+        found: char,
+        expected: &'static str,
+    },
+    MissingBottomBorder,
+    InconsistentJunction {
+        found: char,
+        expected: char,
+    },
     DuplicateFixedPosition {
         id: char,
     },
+    TooFewFixedPositions {
+        found: usize,
+    },
+    UnreachableFixedPosition {
+        from: char,
+        to: char,
+    },
+    InvalidFixedPositionId {
+        id: char,
+    },
+    BlockedFixedPosition {
+        id: char,
+    },
+    UnknownLocation {
+        id: char,
+    },
+    DuplicateLocationName {
+        name: String,
+    },
+    InvalidTerrainCost {
+        found: String,
+    },
+    TerrainSize {
+        width: usize,
+        height: usize,
+        expected_width: i32,
+        expected_height: i32,
+    },
+    OutOfBounds {
+        x: i32,
+        y: i32,
+    },
 }
 
-impl fmt::Debug for ParseError {
+impl ParseError {
+    pub fn hint(&self) -> Option<&'static str> {
+        match *self {
+            ParseError::LineTooLong { .. } => {
+                Some("Every line must be as wide as the first, including the borders.")
+            }
+            ParseError::UnexpectedCharacter { .. } => Some(
+                "Lines alternate between walls and cells, starting and ending with walls.  \
+                 Cells are at every other character, starting with the second.  \
+                 '#' blocks a cell, and '<', '>', '^' and 'v' are one-way passages.",
+            ),
+            ParseError::MissingBottomBorder => Some(
+                "Each row of cells needs a line of walls below it, the last being the bottom border.",
+            ),
+            ParseError::InconsistentJunction { .. } => {
+                Some("Junctions must join up with the walls drawn around them.")
+            }
+            ParseError::TooFewFixedPositions { .. } => {
+                Some("Mark at least two cells with a letter, such as R and G.")
+            }
+            ParseError::UnreachableFixedPosition { .. } => {
+                Some("Check for walls that cut part of the world off.")
+            }
+            ParseError::InvalidTerrainCost { .. } | ParseError::TerrainSize { .. } => Some(
+                "A terrain layer has a row per row of cells, each a list of numbers \
+                 scaling costs.movement, or '.' to leave it as it is.",
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             ParseError::LineTooLong {
@@ -137,127 +338,301 @@ impl fmt::Debug for ParseError {
                 line, num_chars, expected_num_chars
             ),
 
+            ParseError::UnexpectedCharacter { found, expected } => {
+                write!(f, "Found '{}' where {} was expected.", found, expected)
+            }
+
+            ParseError::MissingBottomBorder => {
+                write!(f, "The last row of cells has no line of walls below it.")
+            }
+
+            ParseError::InconsistentJunction { found, expected } => write!(
+                f,
+                "Found junction '{}' where the walls around it need '{}'.",
+                found, expected
+            ),
+
             ParseError::DuplicateFixedPosition { id } => {
                 write!(f, "Found duplicate fixed position '{}'.", id)
             }
+
+            ParseError::TooFewFixedPositions { found } => {
+                write!(f, "Found {} fixed positions, at least 2 are needed.", found)
+            }
+
+            ParseError::UnreachableFixedPosition { from, to } => write!(
+                f,
+                "Fixed position '{}' cannot be reached from '{}'.",
+                to, from
+            ),
+
+            ParseError::InvalidFixedPositionId { id } => {
+                write!(f, "'{}' cannot be used as a fixed position id.", id)
+            }
+
+            ParseError::BlockedFixedPosition { id } => {
+                write!(f, "Fixed position '{}' is on a blocked cell.", id)
+            }
+
+            ParseError::UnknownLocation { id } => {
+                write!(f, "There is no fixed position '{}' to name.", id)
+            }
+
+            ParseError::DuplicateLocationName { ref name } => write!(
+                f,
+                "'{}' cannot name a fixed position, it is empty or already names or marks another.",
+                name
+            ),
+
+            ParseError::InvalidTerrainCost { ref found } => write!(
+                f,
+                "Found terrain cost '{}', expected a number that is not negative or '.'.",
+                found
+            ),
+
+            ParseError::TerrainSize {
+                width,
+                height,
+                expected_width,
+                expected_height,
+            } => write!(
+                f,
+                "Terrain layer is {}x{}, but the world is {}x{}.",
+                width, height, expected_width, expected_height
+            ),
+
+            ParseError::OutOfBounds { x, y } => {
+                write!(f, "Position ({}, {}) is outside of the world.", x, y)
+            }
         }
     }
 }
 
+impl fmt::Debug for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl World {
     pub fn build_from_str(source: &str, costs: Costs) -> Result<World, Error> {
-        let mut lines = source.lines();
+        if source.lines().next().is_none() {
+            return Err(Error::EmptyString);
+        }
+
+        parse_lines(source, costs).map_err(|(location, error)| Error::Parse {
+            source: String::from(source),
+            location,
+            error,
+        })
+    }
+
+    pub fn get_fixed_position(&self, id: LocationId) -> Option<Position> {
+        self.fixed_positions.get(id.0).map(|fp| fp.position)
+    }
+
+    pub fn get_fixed_index(&self, id: LocationId) -> Option<usize> {
+        if id.0 < self.fixed_positions.len() {
+            Some(id.0)
+        } else {
+            None
+        }
+    }
 
-        if let Some(first_line) = lines.next() {
-            let line_width = first_line.chars().count();
+    pub fn get_fixed_id(&self, position: Position) -> Option<LocationId> {
+        match self.cell_index(position) {
+            Some(index) if !self.fixed_ids.is_empty() => self.fixed_ids[index],
+            _ => self
+                .fixed_positions
+                .iter()
+                .position(|fp| fp.position == position)
+                .map(LocationId),
+        }
+    }
 
-            let mut line_count = 1;
+    // Finds a fixed position by its name, or by its glyph when label is a
+    // single character that no name matches.
+    pub fn find_location(&self, label: &str) -> Option<LocationId> {
+        let by_name = self.fixed_positions.iter().position(|fp| fp.name == label);
 
-            let width = line_width / 2;
+        let by_glyph = || {
+            let mut chars = label.chars();
+            match (chars.next(), chars.next()) {
+                (Some(glyph), None) => self.fixed_positions.iter().position(|fp| fp.glyph == glyph),
+                _ => None,
+            }
+        };
 
-            let mut fixed_positions = Vec::new();
+        by_name.or_else(by_glyph).map(LocationId)
+    }
 
-            let mut walls = Vec::new();
+    pub fn location_name(&self, id: LocationId) -> Option<&str> {
+        self.fixed_positions.get(id.0).map(|fp| fp.name.as_str())
+    }
 
-            let mut wall_row = Vec::with_capacity(width);
-            wall_row.extend(iter::repeat(Wall::new()).take(width));
-            parse_wall_line(first_line, line_count, width, None, Some(&mut wall_row)).map_err(
-                |error| Error::Parse {
-                    source: String::from(source),
-                    error,
-                },
-            )?;
-
-            while let (Some(content_line), Some(wall_line)) = (lines.next(), lines.next()) {
-                let mut next_wall_row = Vec::with_capacity(width);
-                next_wall_row.extend(iter::repeat(Wall::new()).take(width));
-
-                line_count += 1;
-                parse_content_line(
-                    content_line,
-                    line_count,
-                    width,
-                    &mut wall_row,
-                    &mut fixed_positions,
-                )
-                .map_err(|error| Error::Parse {
-                    source: String::from(source),
-                    error,
-                })?;
-
-                line_count += 1;
-                parse_wall_line(
-                    wall_line,
-                    line_count,
-                    width,
-                    Some(&mut wall_row),
-                    Some(&mut next_wall_row),
-                )
-                .map_err(|error| Error::Parse {
-                    source: String::from(source),
-                    error,
-                })?;
-
-                walls.push(wall_row);
-                wall_row = next_wall_row;
-            }
-
-            let height = walls.len() as i32;
-
-            Ok(World {
-                width: width as i32,
-                height: height as i32,
-                walls,
-                fixed_positions,
-
-                costs,
-            })
+    pub fn location_glyph(&self, id: LocationId) -> Option<char> {
+        self.fixed_positions.get(id.0).map(|fp| fp.glyph)
+    }
+
+    // Gives the fixed position drawn as glyph a longer name.  Names must be
+    // unique, and may not be another fixed position's glyph.
+    pub fn name_location(&mut self, glyph: char, name: &str) -> Result<(), Error> {
+        let parse_error = |error| Error::Parse {
+            source: String::from(name),
+            location: None,
+            error,
+        };
+
+        let index = self
+            .fixed_positions
+            .iter()
+            .position(|fp| fp.glyph == glyph)
+            .ok_or_else(|| parse_error(ParseError::UnknownLocation { id: glyph }))?;
+
+        if name_clashes(&self.fixed_positions, index, name) {
+            return Err(parse_error(ParseError::DuplicateLocationName {
+                name: String::from(name),
+            }));
+        }
+
+        self.fixed_positions[index].name = String::from(name);
+        Ok(())
+    }
+
+    // Delivering the passenger is free, so this is only above 0 if some
+    // move is.
+    pub fn max_reward(&self) -> f64 {
+        self.terrain
+            .iter()
+            .map(|terrain| terrain.movement)
+            .fold(self.costs.movement.max(0.0), f64::max)
+    }
+
+    // The value of always getting the most reward, discounted by gamma.
+    pub fn max_value(&self, gamma: f64) -> f64 {
+        if gamma < 1.0 {
+            self.max_reward() / (1.0 - gamma)
         } else {
-            Err(Error::EmptyString)
+            self.max_reward()
         }
     }
 
-    pub fn get_fixed_position(&self, id: char) -> Option<Position> {
-        for fp in &self.fixed_positions {
-            if fp.id == id {
-                return Some(fp.position);
-            }
+    pub fn movement_cost(&self, position: Position) -> f64 {
+        match self.cell_index(position) {
+            Some(index) if !self.terrain_levels.is_empty() => self.terrain_levels[index]
+                .map_or(self.costs.movement, |level| self.terrain_costs[level]),
+            _ => self
+                .terrain
+                .iter()
+                .find(|terrain| terrain.position == position)
+                .map_or(self.costs.movement, |terrain| terrain.movement),
         }
+    }
 
-        None
+    pub fn has_terrain(&self, position: Position) -> bool {
+        self.terrain_level(position).is_some()
     }
 
-    pub fn get_fixed_index(&self, id: char) -> Option<usize> {
-        for (i, fp) in self.fixed_positions.iter().enumerate() {
-            if fp.id == id {
-                return Some(i);
-            }
-        }
+    // Terrain of the same cost shares a level, numbered from the cheapest.
+    pub fn num_terrain_levels(&self) -> usize {
+        self.terrain_costs.len()
+    }
 
-        None
+    // None until the lookups are built, as the levels are only known then.
+    pub fn terrain_level(&self, position: Position) -> Option<usize> {
+        let index = self.cell_index(position)?;
+        self.terrain_levels.get(index).cloned().flatten()
     }
 
-    pub fn get_fixed_id(&self, position: Position) -> Option<char> {
-        for fp in &self.fixed_positions {
-            if fp.position == position {
-                return Some(fp.id);
+    // The layer has a line for each row of cells, listing a number for each
+    // cell that scales costs.movement for moves from it, or '.' to leave it
+    // as it is.  Replaces any terrain the world already has.
+    pub fn apply_terrain_layer(&mut self, layer: &str) -> Result<(), Error> {
+        let parse_error = |location, error| Error::Parse {
+            source: String::from(layer),
+            location,
+            error,
+        };
+
+        let rows: Vec<(usize, &str)> = layer
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+
+        let mut terrain = Vec::new();
+
+        for (y, &(line_index, line)) in rows.iter().enumerate() {
+            let mut num_cells = 0;
+
+            for (column, token) in tokens_with_columns(line) {
+                num_cells += 1;
+
+                if token == "." {
+                    continue;
+                }
+
+                let scale = match token.parse::<f64>() {
+                    Ok(scale) if scale >= 0.0 => scale,
+                    _ => {
+                        return Err(parse_error(
+                            Some(Location {
+                                line: line_index + 1,
+                                column,
+                            }),
+                            ParseError::InvalidTerrainCost {
+                                found: String::from(token),
+                            },
+                        ))
+                    }
+                };
+
+                terrain.push(TerrainCost {
+                    position: Position::new(num_cells - 1, y as i32),
+                    movement: scale * self.costs.movement,
+                });
+            }
+
+            if num_cells != self.width {
+                return Err(parse_error(
+                    Some(Location {
+                        line: line_index + 1,
+                        column: 1,
+                    }),
+                    ParseError::TerrainSize {
+                        width: num_cells as usize,
+                        height: rows.len(),
+                        expected_width: self.width,
+                        expected_height: self.height,
+                    },
+                ));
             }
         }
 
-        None
-    }
+        if rows.len() != self.height as usize {
+            return Err(parse_error(
+                None,
+                ParseError::TerrainSize {
+                    width: self.width as usize,
+                    height: rows.len(),
+                    expected_width: self.width,
+                    expected_height: self.height,
+                },
+            ));
+        }
 
-    pub fn max_reward(&self) -> f64 {
-        0.0
+        self.terrain = terrain;
+        self.build_terrain_lookups();
+        Ok(())
     }
 
     pub fn num_fixed_positions(&self) -> usize {
         self.fixed_positions.len()
     }
 
-    pub fn get_fixed_id_from_index(&self, index: usize) -> Option<char> {
+    pub fn get_fixed_id_from_index(&self, index: usize) -> Option<LocationId> {
         if index < self.fixed_positions.len() {
-            Some(self.fixed_positions[index].id)
+            Some(LocationId(index))
         } else {
             None
         }
@@ -267,10 +642,53 @@ impl World {
         &self.walls[position.y as usize][position.x as usize]
     }
 
+    pub fn is_blocked(&self, position: Position) -> bool {
+        match self.cell_index(position) {
+            Some(index) if !self.open_cells.is_empty() => self.open_cells[index].is_none(),
+            _ => self.blocked.contains(&position),
+        }
+    }
+
+    // The walls of a cell, along with any side that leads into a blocked
+    // cell, so every side the taxi cannot drive through.
+    pub fn get_barriers(&self, position: Position) -> Wall {
+        let wall = self.get_wall(position);
+        let blocked = |dx, dy| self.is_blocked(position + Position::new(dx, dy));
+
+        Wall {
+            north: wall.north || blocked(0, -1),
+            south: wall.south || blocked(0, 1),
+            east: wall.east || blocked(1, 0),
+            west: wall.west || blocked(-1, 0),
+        }
+    }
+
+    pub fn num_open_cells(&self) -> usize {
+        (self.width * self.height) as usize - self.blocked.len()
+    }
+
+    // Open cells are numbered in reading order, skipping the blocked ones.
+    // Both are None until the lookups are built.
+    pub fn open_cell_index(&self, position: Position) -> Option<usize> {
+        let index = self.cell_index(position)?;
+        self.open_cells.get(index).cloned().flatten()
+    }
+
+    pub fn open_cell_position(&self, index: usize) -> Option<Position> {
+        self.open_positions.get(index).cloned()
+    }
+
     pub fn determine_affect(&self, position: Position, action: Actions) -> ActionAffect {
+        match self.cell_index(position) {
+            Some(index) if !self.affects.is_empty() => self.affects[index][action.to_index()],
+            _ => self.compute_affect(position, action),
+        }
+    }
+
+    fn compute_affect(&self, position: Position, action: Actions) -> ActionAffect {
         match action {
             Actions::North => {
-                if position.y > 0 && !self.get_wall(position).north {
+                if position.y > 0 && !self.get_barriers(position).north {
                     ActionAffect::Move(Position::new(0, -1))
                 } else {
                     ActionAffect::Invalid
@@ -278,7 +696,7 @@ impl World {
             }
 
             Actions::South => {
-                if position.y < (self.height - 1) && !self.get_wall(position).south {
+                if position.y < (self.height - 1) && !self.get_barriers(position).south {
                     ActionAffect::Move(Position::new(0, 1))
                 } else {
                     ActionAffect::Invalid
@@ -286,7 +704,7 @@ impl World {
             }
 
             Actions::East => {
-                if position.x < (self.width - 1) && !self.get_wall(position).east {
+                if position.x < (self.width - 1) && !self.get_barriers(position).east {
                     ActionAffect::Move(Position::new(1, 0))
                 } else {
                     ActionAffect::Invalid
@@ -294,7 +712,7 @@ impl World {
             }
 
             Actions::West => {
-                if position.x > 0 && !self.get_wall(position).west {
+                if position.x > 0 && !self.get_barriers(position).west {
                     ActionAffect::Move(Position::new(-1, 0))
                 } else {
                     ActionAffect::Invalid
@@ -319,170 +737,270 @@ impl World {
         }
     }
 
-    pub fn display(&self) -> String {
-        let mut result = String::new();
-
-        for s in self.display_strings() {
-            result += &s;
-            result.push('\n');
+    fn cell_index(&self, position: Position) -> Option<usize> {
+        if position.x < 0 || position.x >= self.width || position.y < 0 || position.y >= self.height
+        {
+            None
+        } else {
+            Some((position.y * self.width + position.x) as usize)
         }
-
-        result
     }
 
-    pub fn display_strings(&self) -> Vec<String> {
-        let line_count = (2 * self.height + 1) as usize;
-        let mut result = Vec::with_capacity(line_count);
-
-        let mut previous_row: Option<&[Wall]> = None;
+    // Fills in the lookups once the layout is complete, the solvers ask
+    // for fixed positions, open cells and action affects inside their
+    // innermost loops.
+    fn build_lookups(&mut self) {
+        self.fixed_ids.clear();
+        self.affects.clear();
+        self.open_cells.clear();
+        self.open_positions.clear();
+
+        let num_cells = (self.width * self.height) as usize;
+        let mut fixed_ids = vec![None; num_cells];
+        let mut affects = Vec::with_capacity(num_cells);
+
+        for (index, fp) in self.fixed_positions.iter().enumerate() {
+            if let Some(cell) = self.cell_index(fp.position) {
+                fixed_ids[cell] = Some(LocationId(index));
+            }
+        }
 
-        for (y, row) in self.walls.iter().enumerate() {
-            let mut upper_chars = String::new();
-            let mut chars = String::new();
+        let mut open_cells = vec![Some(0); num_cells];
+        for &blocked in &self.blocked {
+            if let Some(cell) = self.cell_index(blocked) {
+                open_cells[cell] = None;
+            }
+        }
 
-            let mut previous_wall = None;
+        let mut open_positions = Vec::with_capacity(num_cells);
+        for (cell, open_cell) in open_cells.iter_mut().enumerate() {
+            if open_cell.is_some() {
+                *open_cell = Some(open_positions.len());
+                open_positions.push(Position::new(
+                    cell as i32 % self.width,
+                    cell as i32 / self.width,
+                ));
+            }
+        }
 
-            for (x, w) in row.iter().enumerate() {
-                let upper_wall = if let Some(previous_row) = previous_row {
-                    Some(&previous_row[x])
-                } else {
-                    None
-                };
+        // The affects look for blocked cells, so these come first.
+        self.open_cells = open_cells;
+        self.open_positions = open_positions;
 
-                upper_chars.push(calc_upper_left_char(*w, previous_wall, upper_wall));
-                upper_chars.push(if w.north { '─' } else { ' ' });
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let position = Position::new(x, y);
 
-                chars.push(if w.west { '│' } else { ' ' });
+                let mut cell_affects = [ActionAffect::Invalid; Actions::NUM_ELEMENTS];
+                for (action_index, affect) in cell_affects.iter_mut().enumerate() {
+                    let action = Actions::from_index(action_index).unwrap();
+                    *affect = self.compute_affect(position, action);
+                }
 
-                let mut content_char = '.';
+                affects.push(cell_affects);
+            }
+        }
 
-                for fp in &self.fixed_positions {
-                    if fp.position.y == (y as i32) && fp.position.x == (x as i32) {
-                        content_char = fp.id;
-                        break;
-                    }
-                }
+        self.fixed_ids = fixed_ids;
+        self.affects = affects;
 
-                chars.push(content_char);
+        self.build_terrain_lookups();
+    }
 
-                previous_wall = Some(w);
+    // Moves are made inside the solvers' innermost loops too, so their cost
+    // is looked up by cell rather than searched for.
+    fn build_terrain_lookups(&mut self) {
+        let mut terrain_costs: Vec<f64> = self
+            .terrain
+            .iter()
+            .map(|terrain| terrain.movement)
+            .collect();
+        terrain_costs.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        terrain_costs.dedup();
+
+        let mut terrain_levels = vec![None; (self.width * self.height) as usize];
+        for terrain in &self.terrain {
+            if let Some(cell) = self.cell_index(terrain.position) {
+                terrain_levels[cell] = terrain_costs
+                    .iter()
+                    .position(|&cost| cost == terrain.movement);
             }
+        }
 
-            if let Some(w) = previous_wall {
-                let upper_wall = if let Some(previous_row) = previous_row {
-                    Some(&previous_row[(self.width - 1) as usize])
-                } else {
-                    None
-                };
+        self.terrain_costs = terrain_costs;
+        self.terrain_levels = terrain_levels;
+    }
 
-                upper_chars.push(calc_upper_right_char(*w, upper_wall));
-                chars.push(if w.east { '│' } else { ' ' });
-            }
+    // The number of moves from start to every cell, indexed by
+    // y * width + x, or None for cells that cannot be reached.
+    pub fn distances_from(&self, start: Position) -> Vec<Option<usize>> {
+        let index = |position: Position| (position.y * self.width + position.x) as usize;
 
-            result.push(upper_chars);
-            result.push(chars);
+        let mut distances = vec![None; (self.width * self.height) as usize];
+        distances[index(start)] = Some(0);
 
-            previous_row = Some(row);
-        }
+        let mut frontier = VecDeque::new();
+        frontier.push_back((start, 0));
 
-        if let Some(r) = previous_row {
-            let mut bottom_chars = String::new();
-            let mut previous_wall = None;
-            for w in r {
-                bottom_chars.push(calc_lower_left_char(*w, previous_wall));
-                bottom_chars.push(if w.south { '─' } else { ' ' });
+        while let Some((position, distance)) = frontier.pop_front() {
+            for &action in &[Actions::North, Actions::South, Actions::East, Actions::West] {
+                if let ActionAffect::Move(delta) = self.determine_affect(position, action) {
+                    let next_position = position + delta;
 
-                previous_wall = Some(w);
+                    if distances[index(next_position)].is_none() {
+                        distances[index(next_position)] = Some(distance + 1);
+                        frontier.push_back((next_position, distance + 1));
+                    }
+                }
             }
+        }
 
-            if let Some(w) = previous_wall {
-                bottom_chars.push(render_connection(w.east, false, false, w.south));
-            }
+        distances
+    }
+
+    pub fn analyze(&self) -> Analysis {
+        Analysis::build(self)
+    }
+
+    pub fn display(&self) -> String {
+        let mut result = String::new();
 
-            result.push(bottom_chars);
+        for s in self.display_strings() {
+            result += &s;
+            result.push('\n');
         }
 
         result
     }
-}
 
-fn calc_upper_left_char(
-    current_wall: Wall,
-    previous_wall: Option<&Wall>,
-    upper_wall: Option<&Wall>,
-) -> char {
-    let mut connect_north = false;
-    let mut connect_south = false;
-    let mut connect_east = false;
-    let mut connect_west = false;
+    pub fn display_strings(&self) -> Vec<String> {
+        let line_count = (2 * self.height + 1) as usize;
+        let mut result = Vec::with_capacity(line_count);
 
-    if let Some(upper_wall) = upper_wall {
-        if upper_wall.west {
-            connect_north = true;
-        }
-    }
+        let width = self.width as usize;
+        let height = self.height as usize;
 
-    if current_wall.west {
-        connect_south = true;
-    }
+        let cell = |x: usize, y: usize| -> Option<&Wall> {
+            if x < width && y < height {
+                Some(&self.walls[y][x])
+            } else {
+                None
+            }
+        };
+
+        // The wall above cell (x, y), with y == height being the bottom border.
+        let horizontal = |x: usize, y: usize| {
+            let upper = y.checked_sub(1).and_then(|y| cell(x, y));
+            render_horizontal(upper, cell(x, y))
+        };
+
+        // The wall left of cell (x, y), with x == width being the right border.
+        let vertical = |x: usize, y: usize| {
+            let left = x.checked_sub(1).and_then(|x| cell(x, y));
+            render_vertical(left, cell(x, y))
+        };
+
+        let junction = |x: usize, y: usize| {
+            render_connection(
+                y > 0 && is_vertical_wall(vertical(x, y - 1)),
+                y < height && is_vertical_wall(vertical(x, y)),
+                x < width && is_horizontal_wall(horizontal(x, y)),
+                x > 0 && is_horizontal_wall(horizontal(x - 1, y)),
+            )
+        };
+
+        for y in 0..=height {
+            let mut wall_chars = String::new();
+            for x in 0..width {
+                wall_chars.push(junction(x, y));
+                wall_chars.push(horizontal(x, y));
+            }
+            wall_chars.push(junction(width, y));
 
-    if current_wall.north {
-        connect_east = true;
-    }
+            result.push(wall_chars);
 
-    if let Some(previous_wall) = previous_wall {
-        if previous_wall.north {
-            connect_west = true;
-        }
-    }
+            if y == height {
+                break;
+            }
 
-    render_connection(connect_north, connect_south, connect_east, connect_west)
-}
+            let mut chars = String::new();
+            for x in 0..width {
+                chars.push(vertical(x, y));
 
-fn calc_upper_right_char(current_wall: Wall, upper_wall: Option<&Wall>) -> char {
-    let mut connect_north = false;
-    let mut connect_south = false;
-    let connect_east = false;
-    let mut connect_west = false;
+                let position = Position::new(x as i32, y as i32);
+                chars.push(if self.is_blocked(position) {
+                    '#'
+                } else {
+                    self.get_fixed_id(position)
+                        .and_then(|id| self.location_glyph(id))
+                        .unwrap_or('.')
+                });
+            }
+            chars.push(vertical(width, y));
 
-    if let Some(upper_wall) = upper_wall {
-        if upper_wall.east {
-            connect_north = true;
+            result.push(chars);
         }
-    }
 
-    if current_wall.east {
-        connect_south = true;
+        result
     }
+}
 
-    if current_wall.north {
-        connect_west = true;
+// Walls that cut a fixed position off leave a world that can never be solved.
+fn check_reachable(world: World, source: &str) -> Result<World, Error> {
+    if let Some((from, to)) = analysis::find_unreachable_fixed_position(&world) {
+        Err(Error::Parse {
+            source: String::from(source),
+            location: None,
+            error: ParseError::UnreachableFixedPosition { from, to },
+        })
+    } else {
+        Ok(world)
     }
-
-    render_connection(connect_north, connect_south, connect_east, connect_west)
 }
 
-fn calc_lower_left_char(current_wall: Wall, previous_wall: Option<&Wall>) -> char {
-    let mut connect_north = false;
-    let connect_south = false;
-    let mut connect_east = false;
-    let mut connect_west = false;
-
-    if current_wall.west {
-        connect_north = true;
-    }
+// Whether name cannot be given to the fixed position at index, as it is
+// empty or another fixed position already goes by it.
+fn name_clashes(fixed_positions: &[FixedPosition], index: usize, name: &str) -> bool {
+    name.is_empty()
+        || fixed_positions
+            .iter()
+            .enumerate()
+            .any(|(other, fp)| other != index && (fp.name == name || fp.glyph.to_string() == name))
+}
 
-    if current_wall.south {
-        connect_east = true;
+// A missing cell, outside the world, takes the wall of the cell inside it.
+// A wall on only one side is a one-way passage, drawn as an arrow pointing
+// the way the taxi can drive.
+fn render_horizontal(upper: Option<&Wall>, lower: Option<&Wall>) -> char {
+    let (above, below) = match (upper, lower) {
+        (Some(upper), Some(lower)) => (upper.south, lower.north),
+        (Some(upper), None) => (upper.south, upper.south),
+        (None, Some(lower)) => (lower.north, lower.north),
+        (None, None) => (false, false),
+    };
+
+    match (above, below) {
+        (true, true) => '─',
+        (false, true) => 'v',
+        (true, false) => '^',
+        (false, false) => ' ',
     }
+}
 
-    if let Some(previous_wall) = previous_wall {
-        if previous_wall.south {
-            connect_west = true;
-        }
+fn render_vertical(left: Option<&Wall>, right: Option<&Wall>) -> char {
+    let (on_left, on_right) = match (left, right) {
+        (Some(left), Some(right)) => (left.east, right.west),
+        (Some(left), None) => (left.east, left.east),
+        (None, Some(right)) => (right.west, right.west),
+        (None, None) => (false, false),
+    };
+
+    match (on_left, on_right) {
+        (true, true) => '│',
+        (false, true) => '>',
+        (true, false) => '<',
+        (false, false) => ' ',
     }
-
-    render_connection(connect_north, connect_south, connect_east, connect_west)
 }
 
 fn render_connection(
@@ -516,13 +1034,190 @@ fn render_connection(
     }
 }
 
+// The whitespace separated tokens of a line, along with the 1-based column,
+// in characters, that each starts at.
+fn tokens_with_columns(line: &str) -> Vec<(usize, &str)> {
+    let mut result = Vec::new();
+    let mut start = None;
+
+    for (column, (index, c)) in line.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column + 1, index)),
+            (Some((start_column, start_index)), true) => {
+                result.push((start_column, &line[start_index..index]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    if let Some((start_column, start_index)) = start {
+        result.push((start_column, &line[start_index..]));
+    }
+
+    result
+}
+
+type LocatedError = (Option<Location>, ParseError);
+
+fn located(line: usize, column: usize, error: ParseError) -> LocatedError {
+    (Some(Location { line, column }), error)
+}
+
+fn parse_lines(source: &str, costs: Costs) -> Result<World, LocatedError> {
+    let all_lines: Vec<&str> = source.lines().collect();
+
+    let mut lines = all_lines.iter();
+
+    let first_line = lines.next().cloned().unwrap_or_default();
+    let width = first_line.chars().count() / 2;
+
+    let mut line_count = 1;
+
+    let mut fixed_positions = Vec::new();
+
+    let mut blocked = Vec::new();
+
+    let mut walls = Vec::new();
+
+    let mut wall_row = Vec::with_capacity(width);
+    wall_row.extend(iter::repeat(Wall::new()).take(width));
+    parse_wall_line(first_line, line_count, width, None, Some(&mut wall_row))?;
+
+    while let Some(content_line) = lines.next() {
+        let mut next_wall_row = Vec::with_capacity(width);
+        next_wall_row.extend(iter::repeat(Wall::new()).take(width));
+
+        line_count += 1;
+        parse_content_line(
+            content_line,
+            line_count,
+            width,
+            &mut wall_row,
+            &mut blocked,
+            &mut fixed_positions,
+        )?;
+
+        line_count += 1;
+        let wall_line = lines
+            .next()
+            .ok_or_else(|| located(line_count - 1, 1, ParseError::MissingBottomBorder))?;
+        parse_wall_line(
+            wall_line,
+            line_count,
+            width,
+            Some(&mut wall_row),
+            Some(&mut next_wall_row),
+        )?;
+
+        walls.push(wall_row);
+        wall_row = next_wall_row;
+    }
+
+    check_junctions(&all_lines)?;
+
+    if fixed_positions.len() < 2 {
+        return Err((
+            None,
+            ParseError::TooFewFixedPositions {
+                found: fixed_positions.len(),
+            },
+        ));
+    }
+
+    let height = walls.len() as i32;
+
+    let mut world = World {
+        width: width as i32,
+        height,
+        walls,
+        blocked,
+        fixed_positions,
+        terrain: Vec::new(),
+
+        costs,
+
+        fixed_ids: Vec::new(),
+        affects: Vec::new(),
+        open_cells: Vec::new(),
+        open_positions: Vec::new(),
+        terrain_costs: Vec::new(),
+        terrain_levels: Vec::new(),
+    };
+    world.build_lookups();
+
+    if let Some((from, to)) = analysis::find_unreachable_fixed_position(&world) {
+        let location = world
+            .find_location(&to.to_string())
+            .and_then(|id| world.get_fixed_position(id))
+            .map(|position| Location {
+                line: 2 * position.y as usize + 2,
+                column: 2 * position.x as usize + 2,
+            });
+        return Err((location, ParseError::UnreachableFixedPosition { from, to }));
+    }
+
+    Ok(world)
+}
+
+fn is_horizontal_wall(c: char) -> bool {
+    c == '─' || c == '-'
+}
+
+fn is_vertical_wall(c: char) -> bool {
+    c == '│' || c == '|'
+}
+
+// The directions a junction character connects in, as north, south, east
+// and west.
+fn junction_connections(c: char) -> Option<(bool, bool, bool, bool)> {
+    let connections = match c {
+        ' ' => (false, false, false, false),
+        '─' | '-' => (false, false, true, true),
+        '│' | '|' => (true, true, false, false),
+        '┌' => (false, true, true, false),
+        '┐' => (false, true, false, true),
+        '└' => (true, false, true, false),
+        '┘' => (true, false, false, true),
+        '├' => (true, true, true, false),
+        '┤' => (true, true, false, true),
+        '┬' => (false, true, true, true),
+        '┴' => (true, false, true, true),
+        '┼' => (true, true, true, true),
+        _ => return None,
+    };
+
+    Some(connections)
+}
+
+fn check_line_length(
+    line: &str,
+    line_count: usize,
+    num_chars_read: usize,
+    expected_num_chars: usize,
+) -> Result<(), LocatedError> {
+    if num_chars_read == expected_num_chars {
+        Ok(())
+    } else {
+        Err(located(
+            line_count,
+            num_chars_read.min(expected_num_chars) + 1,
+            ParseError::LineTooLong {
+                line: line_count,
+                num_chars: line.chars().count(),
+                expected_num_chars,
+            },
+        ))
+    }
+}
+
 fn parse_wall_line(
     line: &str,
     line_count: usize,
     width: usize,
     mut previous_row: Option<&mut [Wall]>,
     mut row: Option<&mut [Wall]>,
-) -> Result<(), ParseError> {
+) -> Result<(), LocatedError> {
     let mut num_chars_read = 0;
     let expected_num_chars = 2 * width + 1;
 
@@ -536,29 +1231,44 @@ fn parse_wall_line(
         if i % 2 == 1 {
             let x = i / 2;
 
-            if x > width {}
-
-            if c == '─' {
-                if let Some(ref mut prev) = previous_row {
-                    (*prev)[x].south = true;
+            // 'v' only lets the taxi drive south through it, '^' only north.
+            let (south, north) = match c {
+                _ if is_horizontal_wall(c) => (true, true),
+                'v' => (false, true),
+                '^' => (true, false),
+                ' ' => (false, false),
+                _ => {
+                    return Err(located(
+                        line_count,
+                        i + 1,
+                        ParseError::UnexpectedCharacter {
+                            found: c,
+                            expected: "'─', '-', 'v', '^' or ' '",
+                        },
+                    ))
                 }
+            };
 
-                if let Some(ref mut current) = row {
-                    current[x].north = true;
-                }
+            if let Some(ref mut prev) = previous_row {
+                (*prev)[x].south = south;
             }
+
+            if let Some(ref mut current) = row {
+                current[x].north = north;
+            }
+        } else if c != '+' && junction_connections(c).is_none() {
+            return Err(located(
+                line_count,
+                i + 1,
+                ParseError::UnexpectedCharacter {
+                    found: c,
+                    expected: "a wall junction such as '┼' or '+'",
+                },
+            ));
         }
     }
 
-    if num_chars_read == expected_num_chars {
-        Ok(())
-    } else {
-        Err(ParseError::LineTooLong {
-            line: line_count,
-            num_chars: line.chars().count(),
-            expected_num_chars,
-        })
-    }
+    check_line_length(line, line_count, num_chars_read, expected_num_chars)
 }
 
 fn parse_content_line(
@@ -566,8 +1276,9 @@ fn parse_content_line(
     line_count: usize,
     width: usize,
     wall_row: &mut Vec<Wall>,
+    blocked: &mut Vec<Position>,
     fixed_positions: &mut Vec<FixedPosition>,
-) -> Result<(), ParseError> {
+) -> Result<(), LocatedError> {
     let mut num_chars_read = 0;
     let expected_num_chars = 2 * width + 1;
 
@@ -584,42 +1295,120 @@ fn parse_content_line(
 
         if i % 2 == 1 {
             // odd characters are points themselves
-            if c != '.' {
+            if c == '#' {
+                blocked.push(Position::new(x as i32, y as i32));
+            } else if c != '.' {
+                if !c.is_ascii_alphanumeric() {
+                    return Err(located(
+                        line_count,
+                        i + 1,
+                        ParseError::UnexpectedCharacter {
+                            found: c,
+                            expected: "'.', '#' or a letter naming a fixed position",
+                        },
+                    ));
+                }
+
                 // for now, ignore the taxi, passenger, and destination characters.
                 if c != 't' && c != 'T' && c != 'd' && c != 'D' && c != 'p' {
                     for fp in fixed_positions.iter() {
-                        if fp.id == c {
-                            return Err(ParseError::DuplicateFixedPosition { id: c });
+                        if fp.glyph == c {
+                            return Err(located(
+                                line_count,
+                                i + 1,
+                                ParseError::DuplicateFixedPosition { id: c },
+                            ));
                         }
                     }
                 }
 
-                fixed_positions.push(FixedPosition {
-                    id: c,
-                    position: Position::new(x as i32, y as i32),
-                })
+                fixed_positions.push(FixedPosition::new(c, Position::new(x as i32, y as i32)))
             }
-        } else if c == '│' {
-            // even characters can only be walls
+        } else {
+            // even characters can only be walls, '>' only letting the taxi
+            // drive east through it and '<' only west.
+            let (east, west) = match c {
+                _ if is_vertical_wall(c) => (true, true),
+                '>' => (false, true),
+                '<' => (true, false),
+                ' ' => (false, false),
+                _ => {
+                    return Err(located(
+                        line_count,
+                        i + 1,
+                        ParseError::UnexpectedCharacter {
+                            found: c,
+                            expected: "'│', '|', '>', '<' or ' '",
+                        },
+                    ))
+                }
+            };
+
             if x < width {
-                wall_row[x].west = true;
+                wall_row[x].west = west;
             }
 
             if x > 0 {
-                wall_row[x - 1].east = true;
+                wall_row[x - 1].east = east;
             }
         }
     }
 
-    if num_chars_read == expected_num_chars {
-        Ok(())
-    } else {
-        Err(ParseError::LineTooLong {
-            line: line_count,
-            num_chars: line.chars().count(),
-            expected_num_chars,
-        })
+    check_line_length(line, line_count, num_chars_read, expected_num_chars)
+}
+
+// Junctions are the even characters of wall lines.  Box drawing junctions
+// must match the walls that meet there, though a lone wall end may be left
+// blank or drawn as a straight line.  '+' joins any walls.
+fn check_junctions(lines: &[&str]) -> Result<(), LocatedError> {
+    let grid: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+
+    for (index, row) in grid.iter().enumerate().step_by(2) {
+        for (i, &c) in row.iter().enumerate().step_by(2) {
+            let north = index > 0 && is_vertical_wall(grid[index - 1][i]);
+            let south = index + 1 < grid.len() && is_vertical_wall(grid[index + 1][i]);
+            let east = i + 1 < row.len() && is_horizontal_wall(row[i + 1]);
+            let west = i > 0 && is_horizontal_wall(row[i - 1]);
+
+            let actual = (north, south, east, west);
+            let num_connections = [north, south, east, west].iter().filter(|&&c| c).count();
+
+            let consistent = if c == '+' {
+                num_connections > 0
+            } else {
+                match junction_connections(c) {
+                    Some(found) if found == actual => true,
+                    Some((n, s, e, w)) if num_connections <= 1 => {
+                        let blank = !(n || s || e || w);
+                        let covers = (n || !north) && (s || !south) && (e || !east) && (w || !west);
+                        let straight = (n == s) && (e == w) && (n != e);
+                        blank || (covers && straight)
+                    }
+                    _ => false,
+                }
+            };
+
+            if !consistent {
+                let expected = if c == '+' || c == '-' || c == '|' {
+                    if num_connections > 1 {
+                        '+'
+                    } else {
+                        ' '
+                    }
+                } else {
+                    render_connection(north, south, east, west)
+                };
+
+                return Err(located(
+                    index + 1,
+                    i + 1,
+                    ParseError::InconsistentJunction { found: c, expected },
+                ));
+            }
+        }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -768,6 +1557,102 @@ mod test_world {
         assert_matches!(res, Err(_))
     }
 
+    #[test]
+    fn build_fails_unexpected_character() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . G│\n\
+                      │  #  │\n\
+                      │. . .│\n\
+                      └─────┘\n\
+                      ";
+        let costs = Costs::default();
+        let res = World::build_from_str(source, costs);
+        assert_matches!(
+            res,
+            Err(Error::Parse {
+                location: Some(Location { line: 3, column: 4 }),
+                error: ParseError::UnexpectedCharacter { found: '#', .. },
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn build_fails_missing_bottom_border() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . .│\n\
+                      │     │\n\
+                      │. . G│\n\
+                      ";
+        let costs = Costs::default();
+        let res = World::build_from_str(source, costs);
+        assert_matches!(
+            res,
+            Err(Error::Parse {
+                location: Some(Location { line: 4, .. }),
+                error: ParseError::MissingBottomBorder,
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn build_fails_inconsistent_junction() {
+        let source = "\
+                      ┌───┬─┐\n\
+                      │R .│G│\n\
+                      └───┘─┘\n\
+                      ";
+        let costs = Costs::default();
+        let res = World::build_from_str(source, costs);
+        assert_matches!(
+            res,
+            Err(Error::Parse {
+                location: Some(Location { line: 3, column: 5 }),
+                error: ParseError::InconsistentJunction {
+                    found: '┘',
+                    expected: '┴'
+                },
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn build_fails_too_few_fixed_positions() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . .│\n\
+                      └─────┘\n\
+                      ";
+        let costs = Costs::default();
+        let res = World::build_from_str(source, costs);
+        assert_matches!(
+            res,
+            Err(Error::Parse {
+                error: ParseError::TooFewFixedPositions { found: 1 },
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn parse_error_points_at_problem() {
+        let source = "\
+                      ┌───┐\n\
+                      │R?G│\n\
+                      └───┘\n\
+                      ";
+        let costs = Costs::default();
+        let message = format!("{:?}", World::build_from_str(source, costs).unwrap_err());
+
+        assert!(message.starts_with("Parse failure at line 2, column 3:"));
+        assert!(message.contains(" 2 | │R?G│\n   |   ^"));
+        assert!(message.contains("Hint: "));
+    }
+
     fn build_wall(desc: &str) -> Wall {
         let mut result = Wall::new();
 
@@ -789,24 +1674,37 @@ mod test_world {
             width: 0,
             height: 0,
             walls: vec![],
+            blocked: vec![],
             fixed_positions: vec![],
+            terrain: vec![],
 
             costs: Costs::default(),
+
+            fixed_ids: vec![],
+            affects: vec![],
+            open_cells: vec![],
+            open_positions: vec![],
+            terrain_costs: vec![],
+            terrain_levels: vec![],
         }
     }
 
     #[test]
     fn build_very_simple() {
         let source = "\
-                      ┌─┐\n\
-                      │.│\n\
-                      └─┘\n\
+                      ┌───┐\n\
+                      │R G│\n\
+                      └───┘\n\
                       ";
 
         let mut expected_w = build_empty_world();
-        expected_w.width = 1;
+        expected_w.width = 2;
         expected_w.height = 1;
-        expected_w.walls = vec![vec![build_wall("nsew")]];
+        expected_w.walls = vec![vec![build_wall("nsw"), build_wall("nse")]];
+        expected_w.fixed_positions = vec![
+            FixedPosition::new('R', Position::new(0, 0)),
+            FixedPosition::new('G', Position::new(1, 0)),
+        ];
 
         let costs = Costs::default();
         match World::build_from_str(source, costs) {
@@ -821,13 +1719,13 @@ mod test_world {
     fn build_simple() {
         let source = "\
                       ┌─────┐\n\
-                      │. . .│\n\
+                      │R . .│\n\
                       │     │\n\
                       │. . .│\n\
                       │     │\n\
                       │. . .│\n\
                       │     │\n\
-                      │. . .│\n\
+                      │. . G│\n\
                       └─────┘\n\
                       ";
 
@@ -840,6 +1738,10 @@ mod test_world {
             vec![build_wall("w"), build_wall(""), build_wall("e")],
             vec![build_wall("sw"), build_wall("s"), build_wall("se")],
         ];
+        expected_w.fixed_positions = vec![
+            FixedPosition::new('R', Position::new(0, 0)),
+            FixedPosition::new('G', Position::new(2, 3)),
+        ];
 
         let costs = Costs::default();
         match World::build_from_str(source, costs) {
@@ -854,11 +1756,11 @@ mod test_world {
     fn build_middle_wall() {
         let source = "\
                       ┌───────┐\n\
-                      │. . . .│\n\
+                      │R . . .│\n\
                       │ ┌─    │\n\
                       │.│. . .│\n\
                       │ │     │\n\
-                      │.│. . .│\n\
+                      │.│. . G│\n\
                       └─┴─────┘\n\
                       ";
 
@@ -885,6 +1787,10 @@ mod test_world {
                 build_wall("se"),
             ],
         ];
+        expected_w.fixed_positions = vec![
+            FixedPosition::new('R', Position::new(0, 0)),
+            FixedPosition::new('G', Position::new(3, 2)),
+        ];
 
         let costs = Costs::default();
         match World::build_from_str(source, costs) {
@@ -899,7 +1805,7 @@ mod test_world {
     fn build_complex() {
         let source = "\
                       ┌───┬─────┐\n\
-                      │. .│. . .│\n\
+                      │R .│. . G│\n\
                       │   │     │\n\
                       │. .│. . .│\n\
                       │         │\n\
@@ -951,6 +1857,10 @@ mod test_world {
                 build_wall("se"),
             ],
         ];
+        expected_w.fixed_positions = vec![
+            FixedPosition::new('R', Position::new(0, 0)),
+            FixedPosition::new('G', Position::new(4, 0)),
+        ];
 
         let costs = Costs::default();
         match World::build_from_str(source, costs) {
@@ -985,18 +1895,9 @@ mod test_world {
             vec![build_wall("sw"), build_wall("s"), build_wall("se")],
         ];
         expected_w.fixed_positions = vec![
-            FixedPosition {
-                id: 'A',
-                position: Position::new(0, 0),
-            },
-            FixedPosition {
-                id: 'B',
-                position: Position::new(1, 1),
-            },
-            FixedPosition {
-                id: 'C',
-                position: Position::new(2, 2),
-            },
+            FixedPosition::new('A', Position::new(0, 0)),
+            FixedPosition::new('B', Position::new(1, 1)),
+            FixedPosition::new('C', Position::new(2, 2)),
         ];
 
         let costs = Costs::default();
@@ -1007,4 +1908,203 @@ mod test_world {
             }
         }
     }
+
+    #[test]
+    fn build_blocked_and_one_way() {
+        let source = "\
+                      ┌───────┐\n\
+                      │R . .<.│\n\
+                      │    v  │\n\
+                      │. # . G│\n\
+                      └───────┘\n\
+                      ";
+
+        let costs = Costs::default();
+        let w = World::build_from_str(source, costs).unwrap();
+
+        assert!(w.is_blocked(Position::new(1, 1)));
+        assert!(!w.is_blocked(Position::new(0, 0)));
+
+        assert_eq!(
+            w.determine_affect(Position::new(1, 0), Actions::South),
+            ActionAffect::Invalid
+        );
+        assert_eq!(
+            w.determine_affect(Position::new(0, 1), Actions::East),
+            ActionAffect::Invalid
+        );
+
+        assert_eq!(
+            w.determine_affect(Position::new(2, 0), Actions::South),
+            ActionAffect::Move(Position::new(0, 1))
+        );
+        assert_eq!(
+            w.determine_affect(Position::new(2, 1), Actions::North),
+            ActionAffect::Invalid
+        );
+
+        assert_eq!(
+            w.determine_affect(Position::new(3, 0), Actions::West),
+            ActionAffect::Move(Position::new(-1, 0))
+        );
+        assert_eq!(
+            w.determine_affect(Position::new(2, 0), Actions::East),
+            ActionAffect::Invalid
+        );
+
+        assert_eq!(w.num_open_cells(), 7);
+        assert_eq!(w.open_cell_index(Position::new(0, 1)), Some(4));
+        assert_eq!(w.open_cell_index(Position::new(2, 1)), Some(5));
+        assert_eq!(w.open_cell_index(Position::new(1, 1)), None);
+        assert_eq!(w.open_cell_position(5), Some(Position::new(2, 1)));
+
+        assert_eq!(w.display(), source);
+    }
+
+    #[test]
+    fn build_fails_blocked_cut_off() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R # G│\n\
+                      └─────┘\n\
+                      ";
+        let costs = Costs::default();
+        let res = World::build_from_str(source, costs);
+        assert_matches!(
+            res,
+            Err(Error::Parse {
+                error: ParseError::UnreachableFixedPosition { .. },
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn applies_terrain_layer() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . .│\n\
+                      │     │\n\
+                      │. . G│\n\
+                      └─────┘\n\
+                      ";
+        let costs = Costs::default();
+        let mut w = World::build_from_str(source, costs).unwrap();
+
+        w.apply_terrain_layer(". 3 .\n0.5 . .\n").unwrap();
+
+        assert_eq!(w.movement_cost(Position::new(1, 0)), 3.0 * costs.movement);
+        assert_eq!(w.movement_cost(Position::new(0, 1)), 0.5 * costs.movement);
+        assert_eq!(w.movement_cost(Position::new(2, 1)), costs.movement);
+        assert!(w.has_terrain(Position::new(1, 0)));
+        assert!(!w.has_terrain(Position::new(0, 0)));
+        assert_eq!(w.max_reward(), 0.0);
+
+        // Levels are numbered from the cheapest.
+        assert_eq!(w.num_terrain_levels(), 2);
+        assert_eq!(w.terrain_level(Position::new(0, 1)), Some(0));
+        assert_eq!(w.terrain_level(Position::new(1, 0)), Some(1));
+        assert_eq!(w.terrain_level(Position::new(2, 1)), None);
+
+        w.apply_terrain_layer(". 0 .\n. . .\n").unwrap();
+        assert_eq!(w.movement_cost(Position::new(1, 0)), 0.0);
+        assert_eq!(w.movement_cost(Position::new(0, 1)), costs.movement);
+        assert_eq!(w.num_terrain_levels(), 1);
+
+        assert_matches!(
+            w.apply_terrain_layer(". . .\n. -2 .\n"),
+            Err(Error::Parse {
+                location: Some(Location { line: 2, column: 3 }),
+                error: ParseError::InvalidTerrainCost { .. },
+                ..
+            })
+        );
+        assert_matches!(
+            w.apply_terrain_layer(". . .\n"),
+            Err(Error::Parse {
+                error: ParseError::TerrainSize { height: 1, .. },
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn lookups_match_layout() {
+        let source = "\
+                      ┌───────┐\n\
+                      │R . .<.│\n\
+                      │    v  │\n\
+                      │. # . G│\n\
+                      └───────┘\n\
+                      ";
+        let w = World::build_from_str(source, Costs::default()).unwrap();
+
+        for y in 0..w.height {
+            for x in 0..w.width {
+                let position = Position::new(x, y);
+
+                for action_index in 0..Actions::NUM_ELEMENTS {
+                    let action = Actions::from_index(action_index).unwrap();
+                    assert_eq!(
+                        w.determine_affect(position, action),
+                        w.compute_affect(position, action),
+                        "{:?} at ({}, {})",
+                        action,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+
+        assert_eq!(w.get_fixed_id(Position::new(0, 0)), w.find_location("R"));
+        assert_eq!(w.get_fixed_id(Position::new(3, 1)), w.find_location("G"));
+        assert_eq!(w.get_fixed_id(Position::new(1, 0)), None);
+        assert_eq!(w.get_fixed_id(Position::new(4, 0)), None);
+    }
+
+    #[test]
+    fn names_locations() {
+        let source = "\
+                      ┌─────┐\n\
+                      │R . .│\n\
+                      │     │\n\
+                      │. . G│\n\
+                      └─────┘\n\
+                      ";
+        let mut w = World::build_from_str(source, Costs::default()).unwrap();
+
+        let red = w.find_location("R").unwrap();
+        assert_eq!(w.location_name(red), Some("R"));
+
+        w.name_location('R', "Depot").unwrap();
+
+        assert_eq!(w.find_location("Depot"), Some(red));
+        assert_eq!(w.find_location("R"), Some(red));
+        assert_eq!(w.location_name(red), Some("Depot"));
+        assert_eq!(w.location_glyph(red), Some('R'));
+        assert_eq!(w.get_fixed_position(red), Some(Position::new(0, 0)));
+
+        assert_matches!(
+            w.name_location('G', "Depot"),
+            Err(Error::Parse {
+                error: ParseError::DuplicateLocationName { .. },
+                ..
+            })
+        );
+        assert_matches!(
+            w.name_location('G', "R"),
+            Err(Error::Parse {
+                error: ParseError::DuplicateLocationName { .. },
+                ..
+            })
+        );
+        assert_matches!(
+            w.name_location('Y', "Yard"),
+            Err(Error::Parse {
+                error: ParseError::UnknownLocation { id: 'Y' },
+                ..
+            })
+        );
+    }
 }
@@ -0,0 +1,225 @@
+use crate::actions::Actions;
+use crate::position::Position;
+
+use super::{ActionAffect, LocationId, World};
+
+const MOVES: [Actions; 4] = [Actions::North, Actions::South, Actions::East, Actions::West];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    width: i32,
+
+    // The component of every cell, indexed by y * width + x, or None for
+    // blocked cells.
+    components: Vec<Option<usize>>,
+    num_components: usize,
+
+    fixed_distances: Vec<Vec<Option<usize>>>,
+
+    dead_ends: Vec<Position>,
+}
+
+impl Analysis {
+    // Cells are in the same component when the taxi can drive from each one
+    // to the other.  Components are numbered in reading order of their
+    // first cell.  Blocked cells are in no component.
+    pub fn build(world: &World) -> Analysis {
+        let num_cells = (world.width * world.height) as usize;
+
+        let reachable: Vec<Vec<Option<usize>>> = (0..num_cells)
+            .map(|index| world.distances_from(cell_position(world, index)))
+            .collect();
+
+        let mut components = vec![None; num_cells];
+        let mut num_components = 0;
+
+        for start in 0..num_cells {
+            if components[start].is_some() || world.is_blocked(cell_position(world, start)) {
+                continue;
+            }
+
+            for other in start..num_cells {
+                if reachable[start][other].is_some() && reachable[other][start].is_some() {
+                    components[other] = Some(num_components);
+                }
+            }
+
+            num_components += 1;
+        }
+
+        let fixed_distances = world
+            .fixed_positions
+            .iter()
+            .map(|from| {
+                let distances = &reachable[cell_index(world, from.position)];
+                world
+                    .fixed_positions
+                    .iter()
+                    .map(|to| distances[cell_index(world, to.position)])
+                    .collect()
+            })
+            .collect();
+
+        let dead_ends = (0..num_cells)
+            .map(|index| cell_position(world, index))
+            .filter(|&position| !world.is_blocked(position) && num_moves(world, position) == 1)
+            .collect();
+
+        Analysis {
+            width: world.width,
+            components,
+            num_components,
+            fixed_distances,
+            dead_ends,
+        }
+    }
+
+    pub fn num_components(&self) -> usize {
+        self.num_components
+    }
+
+    pub fn component(&self, position: Position) -> Option<usize> {
+        self.components[(position.y * self.width + position.x) as usize]
+    }
+
+    // Distances between fixed positions, indexed as World::get_fixed_index.
+    pub fn fixed_distances(&self) -> &[Vec<Option<usize>>] {
+        &self.fixed_distances
+    }
+
+    pub fn fixed_distance(&self, from: LocationId, to: LocationId) -> Option<usize> {
+        *self.fixed_distances.get(from.index())?.get(to.index())?
+    }
+
+    // Cells with a single way out.
+    pub fn dead_ends(&self) -> &[Position] {
+        &self.dead_ends
+    }
+}
+
+// The first pair of fixed positions, in order, where the second cannot be
+// reached from the first.  Only searches from the fixed positions, so it is
+// cheap enough to run on every world that is built.
+pub(super) fn find_unreachable_fixed_position(world: &World) -> Option<(char, char)> {
+    for from in &world.fixed_positions {
+        let distances = world.distances_from(from.position);
+
+        for to in &world.fixed_positions {
+            if distances[cell_index(world, to.position)].is_none() {
+                return Some((from.glyph, to.glyph));
+            }
+        }
+    }
+
+    None
+}
+
+fn num_moves(world: &World, position: Position) -> usize {
+    MOVES
+        .iter()
+        .filter(|&&action| {
+            matches!(
+                world.determine_affect(position, action),
+                ActionAffect::Move(_)
+            )
+        })
+        .count()
+}
+
+fn cell_index(world: &World, position: Position) -> usize {
+    (position.y * world.width + position.x) as usize
+}
+
+fn cell_position(world: &World, index: usize) -> Position {
+    let index = index as i32;
+    Position::new(index % world.width, index / world.width)
+}
+
+#[cfg(test)]
+mod test_analysis {
+
+    use super::*;
+
+    use crate::world::{Costs, Error, ParseError};
+
+    #[test]
+    fn standard_world() {
+        let source = "\
+                      ┌───┬─────┐\n\
+                      │R .│. . G│\n\
+                      │   │     │\n\
+                      │. .│. . .│\n\
+                      │         │\n\
+                      │. . . . .│\n\
+                      │         │\n\
+                      │.│. .│. .│\n\
+                      │ │   │   │\n\
+                      │Y│. .│B .│\n\
+                      └─┴───┴───┘\n\
+                      ";
+
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+        let analysis = world.analyze();
+
+        assert_eq!(analysis.num_components(), 1);
+
+        let id = |label| world.find_location(label).unwrap();
+
+        assert_eq!(analysis.fixed_distance(id("R"), id("R")), Some(0));
+        assert_eq!(analysis.fixed_distance(id("R"), id("G")), Some(8));
+        assert_eq!(analysis.fixed_distance(id("G"), id("Y")), Some(8));
+        assert_eq!(analysis.fixed_distance(id("Y"), id("B")), Some(7));
+        assert_eq!(analysis.fixed_distance(id("R"), LocationId(4)), None);
+
+        assert_eq!(analysis.fixed_distances().len(), 4);
+        assert_eq!(
+            analysis.fixed_distances()[0],
+            vec![Some(0), Some(8), Some(4), Some(7)]
+        );
+
+        assert_eq!(analysis.dead_ends(), &[Position::new(0, 4)][..]);
+    }
+
+    #[test]
+    fn rejects_unreachable_fixed_positions() {
+        let source = "\
+                      ┌───┬───┐\n\
+                      │R .│. .│\n\
+                      │   │   │\n\
+                      │. .│. G│\n\
+                      └───┴───┘\n\
+                      ";
+
+        assert_matches!(
+            World::build_from_str(source, Costs::default()),
+            Err(Error::Parse {
+                error: ParseError::UnreachableFixedPosition { from: 'R', to: 'G' },
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn split_world() {
+        let source = "\
+                      ┌───┬───┐\n\
+                      │R .│. .│\n\
+                      │   │   │\n\
+                      │. G│. .│\n\
+                      └───┴───┘\n\
+                      ";
+
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+        let analysis = world.analyze();
+
+        assert_eq!(analysis.num_components(), 2);
+        assert_eq!(analysis.component(Position::new(0, 0)), Some(0));
+        assert_eq!(analysis.component(Position::new(1, 1)), Some(0));
+        assert_eq!(analysis.component(Position::new(2, 0)), Some(1));
+        assert_eq!(analysis.component(Position::new(3, 1)), Some(1));
+
+        let id = |label| world.find_location(label).unwrap();
+        assert_eq!(analysis.fixed_distance(id("R"), id("G")), Some(2));
+        assert!(analysis.dead_ends().is_empty());
+    }
+}
@@ -0,0 +1,702 @@
+use crate::position::Position;
+
+use super::{
+    check_reachable, name_clashes, Costs, Error, FixedPosition, ParseError, TerrainCost, Wall,
+    World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // Picked from the first character of the source.
+    Auto,
+    // Walls drawn with ┌─┬│, as World::display writes them.
+    BoxDrawing,
+    // Walls drawn with +-|.
+    Ascii,
+    // A WorldDescription, written as TOML or JSON.
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub fn detect(source: &str) -> Format {
+        match source.trim_start().chars().next() {
+            Some('+') => Format::Ascii,
+            Some('{') => Format::Json,
+            Some(c) if c.is_ascii_alphabetic() || c == '#' || c == '[' => Format::Toml,
+            _ => Format::BoxDrawing,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+// A wall on one side of a cell.  The cell on the other side of it gets the
+// matching wall, and the outside of the world always has walls.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct WallSegment {
+    pub x: i32,
+    pub y: i32,
+    pub side: Side,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CellDescription {
+    pub x: i32,
+    pub y: i32,
+}
+
+// The cost of each move from a cell, in place of Costs::movement.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TerrainDescription {
+    pub x: i32,
+    pub y: i32,
+    pub movement: f64,
+}
+
+// id is the glyph drawn on the map, name a longer label to use in its place.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FixedPositionDescription {
+    pub id: char,
+    pub x: i32,
+    pub y: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WorldDescription {
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub walls: Vec<WallSegment>,
+    // Walls on only the given side of the cell, so the taxi can drive in
+    // through them but not out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub one_way_walls: Vec<WallSegment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<CellDescription>,
+    pub fixed_positions: Vec<FixedPositionDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terrain: Vec<TerrainDescription>,
+}
+
+impl World {
+    // Both text formats are read by the same parser, so either set of wall
+    // characters is accepted by each.
+    pub fn build(source: &str, format: Format, costs: Costs) -> Result<World, Error> {
+        let format = if format == Format::Auto {
+            Format::detect(source)
+        } else {
+            format
+        };
+
+        match format {
+            Format::Auto | Format::BoxDrawing | Format::Ascii => {
+                World::build_from_str(source, costs)
+            }
+
+            Format::Toml => {
+                let description = toml::from_str(source).map_err(|error| Error::Deserialize {
+                    source: String::from(source),
+                    message: error.to_string(),
+                })?;
+                build_from_description(&description, source, costs)
+            }
+
+            Format::Json => {
+                let description =
+                    serde_json::from_str(source).map_err(|error| Error::Deserialize {
+                        source: String::from(source),
+                        message: error.to_string(),
+                    })?;
+                build_from_description(&description, source, costs)
+            }
+        }
+    }
+
+    pub fn build_from_description(
+        description: &WorldDescription,
+        costs: Costs,
+    ) -> Result<World, Error> {
+        let source = toml::to_string(description).unwrap_or_default();
+        build_from_description(description, &source, costs)
+    }
+
+    pub fn write(&self, format: Format) -> String {
+        match format {
+            Format::Auto | Format::BoxDrawing => self.display(),
+            Format::Ascii => self.display_ascii(),
+            Format::Toml => toml::to_string(&self.to_description())
+                .expect("World descriptions can always be written as TOML"),
+            Format::Json => {
+                let mut result = serde_json::to_string_pretty(&self.to_description())
+                    .expect("World descriptions can always be written as JSON");
+                result.push('\n');
+                result
+            }
+        }
+    }
+
+    pub fn display_ascii(&self) -> String {
+        self.display()
+            .chars()
+            .map(|c| match c {
+                '─' => '-',
+                '│' => '|',
+                '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => '+',
+                _ => c,
+            })
+            .collect()
+    }
+
+    // Only the walls inside the world are listed, each once, as the east or
+    // south side of a cell.  A wall on one side alone is listed as a one-way
+    // wall of the cell it belongs to.
+    pub fn to_description(&self) -> WorldDescription {
+        let mut walls = Vec::new();
+        let mut one_way_walls = Vec::new();
+
+        for (y, row) in self.walls.iter().enumerate() {
+            for (x, wall) in row.iter().enumerate() {
+                if x + 1 < row.len() {
+                    push_wall_segment(
+                        &mut walls,
+                        &mut one_way_walls,
+                        (x, y, wall.east, Side::East),
+                        (x + 1, y, row[x + 1].west, Side::West),
+                    );
+                }
+
+                if y + 1 < self.walls.len() {
+                    push_wall_segment(
+                        &mut walls,
+                        &mut one_way_walls,
+                        (x, y, wall.south, Side::South),
+                        (x, y + 1, self.walls[y + 1][x].north, Side::North),
+                    );
+                }
+            }
+        }
+
+        let blocked = self
+            .blocked
+            .iter()
+            .map(|position| CellDescription {
+                x: position.x,
+                y: position.y,
+            })
+            .collect();
+
+        let fixed_positions = self
+            .fixed_positions
+            .iter()
+            .map(|fp| FixedPositionDescription {
+                id: fp.glyph,
+                x: fp.position.x,
+                y: fp.position.y,
+                name: if fp.name == fp.glyph.to_string() {
+                    None
+                } else {
+                    Some(fp.name.clone())
+                },
+            })
+            .collect();
+
+        let terrain = self
+            .terrain
+            .iter()
+            .map(|terrain| TerrainDescription {
+                x: terrain.position.x,
+                y: terrain.position.y,
+                movement: terrain.movement,
+            })
+            .collect();
+
+        WorldDescription {
+            width: self.width,
+            height: self.height,
+            walls,
+            one_way_walls,
+            blocked,
+            fixed_positions,
+            terrain,
+        }
+    }
+}
+
+// Each side is the cell, whether it has the wall, and which of its sides
+// the wall is on.
+fn push_wall_segment(
+    walls: &mut Vec<WallSegment>,
+    one_way_walls: &mut Vec<WallSegment>,
+    first: (usize, usize, bool, Side),
+    second: (usize, usize, bool, Side),
+) {
+    let segment = |(x, y, _, side): (usize, usize, bool, Side)| WallSegment {
+        x: x as i32,
+        y: y as i32,
+        side,
+    };
+
+    match (first.2, second.2) {
+        (true, true) => walls.push(segment(first)),
+        (true, false) => one_way_walls.push(segment(first)),
+        (false, true) => one_way_walls.push(segment(second)),
+        (false, false) => (),
+    }
+}
+
+fn build_from_description(
+    description: &WorldDescription,
+    source: &str,
+    costs: Costs,
+) -> Result<World, Error> {
+    let parse_error = |error| Error::Parse {
+        source: String::from(source),
+        location: None,
+        error,
+    };
+
+    let (width, height) = (description.width, description.height);
+
+    if width < 1 || height < 1 {
+        return Err(parse_error(ParseError::OutOfBounds {
+            x: width,
+            y: height,
+        }));
+    }
+
+    let in_bounds = |x: i32, y: i32| x >= 0 && x < width && y >= 0 && y < height;
+
+    let mut walls: Vec<Vec<Wall>> = Vec::with_capacity(height as usize);
+    for y in 0..height {
+        let row = (0..width)
+            .map(|x| Wall {
+                north: y == 0,
+                south: y == height - 1,
+                east: x == width - 1,
+                west: x == 0,
+            })
+            .collect();
+        walls.push(row);
+    }
+
+    for segment in &description.walls {
+        if !in_bounds(segment.x, segment.y) {
+            return Err(parse_error(ParseError::OutOfBounds {
+                x: segment.x,
+                y: segment.y,
+            }));
+        }
+
+        let (x, y) = (segment.x as usize, segment.y as usize);
+
+        match segment.side {
+            Side::North => {
+                walls[y][x].north = true;
+                if y > 0 {
+                    walls[y - 1][x].south = true;
+                }
+            }
+            Side::South => {
+                walls[y][x].south = true;
+                if y + 1 < height as usize {
+                    walls[y + 1][x].north = true;
+                }
+            }
+            Side::East => {
+                walls[y][x].east = true;
+                if x + 1 < width as usize {
+                    walls[y][x + 1].west = true;
+                }
+            }
+            Side::West => {
+                walls[y][x].west = true;
+                if x > 0 {
+                    walls[y][x - 1].east = true;
+                }
+            }
+        }
+    }
+
+    for segment in &description.one_way_walls {
+        if !in_bounds(segment.x, segment.y) {
+            return Err(parse_error(ParseError::OutOfBounds {
+                x: segment.x,
+                y: segment.y,
+            }));
+        }
+
+        let wall = &mut walls[segment.y as usize][segment.x as usize];
+
+        match segment.side {
+            Side::North => wall.north = true,
+            Side::South => wall.south = true,
+            Side::East => wall.east = true,
+            Side::West => wall.west = true,
+        }
+    }
+
+    let mut blocked = Vec::new();
+
+    for cell in &description.blocked {
+        if !in_bounds(cell.x, cell.y) {
+            return Err(parse_error(ParseError::OutOfBounds {
+                x: cell.x,
+                y: cell.y,
+            }));
+        }
+
+        let position = Position::new(cell.x, cell.y);
+        if !blocked.contains(&position) {
+            blocked.push(position);
+        }
+    }
+
+    blocked.sort_by_key(|position: &Position| (position.y, position.x));
+
+    let mut fixed_positions: Vec<FixedPosition> = Vec::new();
+
+    for fp in &description.fixed_positions {
+        // These would not survive being written out as text.
+        if fp.id == '.' || fp.id.is_whitespace() || "|-+─│#<>^".contains(fp.id) {
+            return Err(parse_error(ParseError::InvalidFixedPositionId {
+                id: fp.id,
+            }));
+        }
+
+        if !in_bounds(fp.x, fp.y) {
+            return Err(parse_error(ParseError::OutOfBounds { x: fp.x, y: fp.y }));
+        }
+
+        let position = Position::new(fp.x, fp.y);
+
+        if blocked.contains(&position) {
+            return Err(parse_error(ParseError::BlockedFixedPosition { id: fp.id }));
+        }
+
+        if fixed_positions
+            .iter()
+            .any(|other| other.glyph == fp.id || other.position == position)
+        {
+            return Err(parse_error(ParseError::DuplicateFixedPosition {
+                id: fp.id,
+            }));
+        }
+
+        let mut fixed_position = FixedPosition::new(fp.id, position);
+        if let Some(ref name) = fp.name {
+            fixed_position.name = name.clone();
+        }
+
+        fixed_positions.push(fixed_position);
+    }
+
+    for (index, fp) in fixed_positions.iter().enumerate() {
+        if name_clashes(&fixed_positions, index, &fp.name) {
+            return Err(parse_error(ParseError::DuplicateLocationName {
+                name: fp.name.clone(),
+            }));
+        }
+    }
+
+    // Kept in reading order, as build_from_str would find them.
+    fixed_positions.sort_by_key(|fp| (fp.position.y, fp.position.x));
+
+    let mut terrain: Vec<TerrainCost> = Vec::new();
+
+    for cell in &description.terrain {
+        if !in_bounds(cell.x, cell.y) {
+            return Err(parse_error(ParseError::OutOfBounds {
+                x: cell.x,
+                y: cell.y,
+            }));
+        }
+
+        let position = Position::new(cell.x, cell.y);
+
+        // Later entries for a cell replace earlier ones.
+        terrain.retain(|other| other.position != position);
+        terrain.push(TerrainCost {
+            position,
+            movement: cell.movement,
+        });
+    }
+
+    terrain.sort_by_key(|terrain| (terrain.position.y, terrain.position.x));
+
+    let mut world = World {
+        width,
+        height,
+        walls,
+        blocked,
+        fixed_positions,
+        terrain,
+        costs,
+        fixed_ids: Vec::new(),
+        affects: Vec::new(),
+        open_cells: Vec::new(),
+        open_positions: Vec::new(),
+        terrain_costs: Vec::new(),
+        terrain_levels: Vec::new(),
+    };
+    world.build_lookups();
+
+    check_reachable(world, source)
+}
+
+#[cfg(test)]
+mod test_format {
+
+    use super::*;
+
+    use crate::world::LocationId;
+
+    const BOX_SOURCE: &str = "\
+                              ┌───┬─────┐\n\
+                              │R .│. . G│\n\
+                              │   │     │\n\
+                              │. .│. . .│\n\
+                              │         │\n\
+                              │. . . . .│\n\
+                              │         │\n\
+                              │.│. .│. .│\n\
+                              │ │   │   │\n\
+                              │Y│. .│B .│\n\
+                              └─┴───┴───┘\n\
+                              ";
+
+    const ASCII_SOURCE: &str = "\
+                                +---+-----+\n\
+                                |R .|. . G|\n\
+                                |   |     |\n\
+                                |. .|. . .|\n\
+                                |         |\n\
+                                |. . . . .|\n\
+                                |         |\n\
+                                |.|. .|. .|\n\
+                                | |   |   |\n\
+                                |Y|. .|B .|\n\
+                                +-+---+---+\n\
+                                ";
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(Format::detect(BOX_SOURCE), Format::BoxDrawing);
+        assert_eq!(Format::detect(ASCII_SOURCE), Format::Ascii);
+        assert_eq!(Format::detect("width = 2"), Format::Toml);
+        assert_eq!(Format::detect("  { \"width\": 2 }"), Format::Json);
+    }
+
+    #[test]
+    fn ascii_matches_box_drawing() {
+        let box_world = World::build(BOX_SOURCE, Format::Auto, Costs::default()).unwrap();
+        let ascii_world = World::build(ASCII_SOURCE, Format::Auto, Costs::default()).unwrap();
+
+        assert_eq!(box_world, ascii_world);
+        assert_eq!(box_world.display_ascii(), ASCII_SOURCE);
+    }
+
+    #[test]
+    fn round_trips() {
+        let world = World::build_from_str(BOX_SOURCE, Costs::default()).unwrap();
+
+        for &format in &[
+            Format::BoxDrawing,
+            Format::Ascii,
+            Format::Toml,
+            Format::Json,
+        ] {
+            let written = world.write(format);
+            assert_eq!(Format::detect(&written), format);
+
+            let rebuilt = World::build(&written, Format::Auto, Costs::default()).unwrap();
+            assert_eq!(rebuilt, world, "{:?}:\n{}", format, written);
+        }
+    }
+
+    #[test]
+    fn round_trips_blocked_and_one_way() {
+        let source = "\
+                      ┌───────┐\n\
+                      │R . .<.│\n\
+                      │  ^ v  │\n\
+                      │# . . G│\n\
+                      └───────┘\n\
+                      ";
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+
+        let description = world.to_description();
+        assert_eq!(description.walls.len(), 0);
+        assert_eq!(description.one_way_walls.len(), 3);
+        assert_eq!(description.blocked, vec![CellDescription { x: 0, y: 1 }]);
+
+        for &format in &[Format::Ascii, Format::Toml, Format::Json] {
+            let written = world.write(format);
+
+            let rebuilt = World::build(&written, Format::Auto, Costs::default()).unwrap();
+            assert_eq!(rebuilt, world, "{:?}:\n{}", format, written);
+        }
+    }
+
+    #[test]
+    fn round_trips_terrain() {
+        let mut world = World::build_from_str(BOX_SOURCE, Costs::default()).unwrap();
+        world
+            .apply_terrain_layer(
+                ". . . . .\n\
+                 . . 2 2 .\n\
+                 . . . . .\n\
+                 . . . . .\n\
+                 . . . . .\n",
+            )
+            .unwrap();
+
+        for &format in &[Format::Toml, Format::Json] {
+            let written = world.write(format);
+
+            let rebuilt = World::build(&written, Format::Auto, Costs::default()).unwrap();
+            assert_eq!(rebuilt, world, "{:?}:\n{}", format, written);
+        }
+    }
+
+    #[test]
+    fn round_trips_location_names() {
+        let mut world = World::build_from_str(BOX_SOURCE, Costs::default()).unwrap();
+        world.name_location('R', "Red").unwrap();
+        world.name_location('B', "Blue").unwrap();
+
+        let description = world.to_description();
+        assert_eq!(
+            description.fixed_positions[0].name,
+            Some(String::from("Red"))
+        );
+        assert_eq!(description.fixed_positions[1].name, None);
+
+        for &format in &[Format::Toml, Format::Json] {
+            let written = world.write(format);
+
+            let rebuilt = World::build(&written, Format::Auto, Costs::default()).unwrap();
+            assert_eq!(rebuilt, world, "{:?}:\n{}", format, written);
+        }
+    }
+
+    #[test]
+    fn description_lists_interior_walls() {
+        let world = World::build_from_str(BOX_SOURCE, Costs::default()).unwrap();
+        let description = world.to_description();
+
+        assert_eq!(description.width, 5);
+        assert_eq!(description.height, 5);
+        assert_eq!(description.walls.len(), 6);
+        assert_eq!(
+            description.walls[0],
+            WallSegment {
+                x: 1,
+                y: 0,
+                side: Side::East
+            }
+        );
+        assert_eq!(
+            description.fixed_positions[3],
+            FixedPositionDescription {
+                id: 'B',
+                x: 3,
+                y: 4,
+                name: None,
+            }
+        );
+    }
+
+    #[test]
+    fn reads_toml_description() {
+        let source = r#"
+            width = 3
+            height = 2
+            walls = [
+                { x = 1, y = 0, side = "west" },
+                { x = 2, y = 1, side = "north" },
+            ]
+            fixed_positions = [
+                { id = "G", x = 2, y = 1 },
+                { id = "R", x = 0, y = 0 },
+            ]
+        "#;
+
+        let world = World::build(source, Format::Auto, Costs::default()).unwrap();
+
+        let expected = "\
+                        ┌─┬───┐\n\
+                        │R│. .│\n\
+                        │    ─┤\n\
+                        │. . G│\n\
+                        └─────┘\n\
+                        ";
+
+        assert_eq!(world.display(), expected);
+        assert_eq!(world.find_location("R").map(LocationId::index), Some(0));
+    }
+
+    #[test]
+    fn rejects_invalid_descriptions() {
+        let description = WorldDescription {
+            width: 2,
+            height: 2,
+            walls: vec![WallSegment {
+                x: 2,
+                y: 0,
+                side: Side::East,
+            }],
+            one_way_walls: vec![],
+            blocked: vec![],
+            fixed_positions: vec![
+                FixedPositionDescription {
+                    id: 'R',
+                    x: 0,
+                    y: 0,
+                    name: None,
+                },
+                FixedPositionDescription {
+                    id: 'G',
+                    x: 1,
+                    y: 1,
+                    name: None,
+                },
+            ],
+            terrain: vec![],
+        };
+
+        assert_matches!(
+            World::build_from_description(&description, Costs::default()),
+            Err(Error::Parse {
+                error: ParseError::OutOfBounds { x: 2, y: 0 },
+                ..
+            })
+        );
+
+        let mut description = description;
+        description.walls.clear();
+        description.fixed_positions[1].id = 'R';
+
+        assert_matches!(
+            World::build_from_description(&description, Costs::default()),
+            Err(Error::Parse {
+                error: ParseError::DuplicateFixedPosition { id: 'R' },
+                ..
+            })
+        );
+
+        assert_matches!(
+            World::build("width = \"wide\"", Format::Toml, Costs::default()),
+            Err(Error::Deserialize { .. })
+        );
+    }
+}
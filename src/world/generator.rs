@@ -0,0 +1,383 @@
+use std::fmt;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::position::Position;
+use crate::runner::Probe;
+use crate::state;
+use crate::state::State;
+
+use super::{Costs, FixedPosition, Wall, World};
+
+// Ids handed out to fixed positions, in order.
+const FIXED_POSITION_IDS: &str = "RGYBACDEFHIJKLMNOPQSTUVWXZ";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generator {
+    width: i32,
+    height: i32,
+    wall_density: f64,
+    num_fixed_positions: usize,
+}
+
+pub enum Error {
+    InvalidSize {
+        width: i32,
+        height: i32,
+    },
+    InvalidWallDensity {
+        wall_density: f64,
+    },
+    InvalidFixedPositionCount {
+        num_fixed_positions: usize,
+        maximum: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::InvalidSize { width, height } => write!(
+                f,
+                "Cannot generate a {}x{} world, both sides must be at least 1.",
+                width, height
+            ),
+            Error::InvalidWallDensity { wall_density } => {
+                write!(f, "Wall density {} must be between 0 and 1.", wall_density)
+            }
+            Error::InvalidFixedPositionCount {
+                num_fixed_positions,
+                maximum,
+            } => write!(
+                f,
+                "Cannot place {} fixed positions, there must be between 2 and {}.",
+                num_fixed_positions, maximum
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Generator {
+    pub fn new(
+        width: i32,
+        height: i32,
+        wall_density: f64,
+        num_fixed_positions: usize,
+    ) -> Result<Generator, Error> {
+        if width < 1 || height < 1 {
+            return Err(Error::InvalidSize { width, height });
+        }
+
+        if !(0.0..=1.0).contains(&wall_density) {
+            return Err(Error::InvalidWallDensity { wall_density });
+        }
+
+        let maximum = FIXED_POSITION_IDS
+            .chars()
+            .count()
+            .min((width * height) as usize);
+
+        if num_fixed_positions < 2 || num_fixed_positions > maximum {
+            return Err(Error::InvalidFixedPositionCount {
+                num_fixed_positions,
+                maximum,
+            });
+        }
+
+        Ok(Generator {
+            width,
+            height,
+            wall_density,
+            num_fixed_positions,
+        })
+    }
+
+    // Interior walls are added in a random order, skipping any that would cut
+    // part of the world off, until wall_density of the interior edges have
+    // walls.  So every cell, and every fixed position, stays reachable from
+    // every other.  Dense worlds may end up with fewer walls than asked for,
+    // once only a spanning tree of open edges is left.
+    pub fn generate<R: Rng>(&self, costs: Costs, rng: &mut R) -> World {
+        let mut walls = Vec::with_capacity(self.height as usize);
+        for y in 0..self.height {
+            let row = (0..self.width)
+                .map(|x| Wall {
+                    north: y == 0,
+                    south: y == self.height - 1,
+                    east: x == self.width - 1,
+                    west: x == 0,
+                })
+                .collect();
+            walls.push(row);
+        }
+
+        let mut world = World {
+            width: self.width,
+            height: self.height,
+            walls,
+            blocked: Vec::new(),
+            fixed_positions: Vec::new(),
+            terrain: Vec::new(),
+            costs,
+            fixed_ids: Vec::new(),
+            affects: Vec::new(),
+            open_cells: Vec::new(),
+            open_positions: Vec::new(),
+            terrain_costs: Vec::new(),
+            terrain_levels: Vec::new(),
+        };
+
+        // Each interior edge, as the cell west or north of it and whether it
+        // is the cell's east side.
+        let mut edges = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if x < self.width - 1 {
+                    edges.push((Position::new(x, y), true));
+                }
+                if y < self.height - 1 {
+                    edges.push((Position::new(x, y), false));
+                }
+            }
+        }
+
+        let target_walls = (self.wall_density * edges.len() as f64).round() as usize;
+
+        edges.shuffle(rng);
+
+        let mut num_walls = 0;
+        for (position, east) in edges {
+            if num_walls >= target_walls {
+                break;
+            }
+
+            set_wall(&mut world, position, east, true);
+
+            if is_connected(&world) {
+                num_walls += 1;
+            } else {
+                set_wall(&mut world, position, east, false);
+            }
+        }
+
+        let mut cells = Vec::with_capacity((self.width * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells.push(Position::new(x, y));
+            }
+        }
+
+        // Kept in reading order, as build_from_str would find them.
+        let mut positions: Vec<Position> = cells
+            .choose_multiple(rng, self.num_fixed_positions)
+            .cloned()
+            .collect();
+        positions.sort_by_key(|position| (position.y, position.x));
+
+        world.fixed_positions = FIXED_POSITION_IDS
+            .chars()
+            .zip(positions)
+            .map(|(id, position)| FixedPosition::new(id, position))
+            .collect();
+
+        world.build_lookups();
+        world
+    }
+}
+
+fn set_wall(world: &mut World, position: Position, east: bool, present: bool) {
+    let (x, y) = (position.x as usize, position.y as usize);
+
+    if east {
+        world.walls[y][x].east = present;
+        world.walls[y][x + 1].west = present;
+    } else {
+        world.walls[y][x].south = present;
+        world.walls[y + 1][x].north = present;
+    }
+}
+
+fn is_connected(world: &World) -> bool {
+    world
+        .distances_from(Position::new(0, 0))
+        .iter()
+        .all(Option::is_some)
+}
+
+// The fewest actions that deliver the passenger, counting the pick up and
+// drop off, or None if some part of the trip cannot be reached.
+pub fn optimal_steps(world: &World, state: State) -> Option<usize> {
+    let distance = |from: Position, to: Position| {
+        world.distances_from(from)[(to.y * world.width + to.x) as usize]
+    };
+
+    let destination = world.get_fixed_position(state.get_destination())?;
+
+    match state.get_passenger() {
+        Some(id) if id == state.get_destination() => Some(0),
+        Some(id) => {
+            let passenger = world.get_fixed_position(id)?;
+            Some(distance(state.get_taxi(), passenger)? + 1 + distance(passenger, destination)? + 1)
+        }
+        None => Some(distance(state.get_taxi(), destination)? + 1),
+    }
+}
+
+// Random probes for a world, each allowed exactly its optimal number of steps.
+pub fn generate_probes<R: Rng>(
+    world: &World,
+    num_probes: usize,
+    rng: &mut R,
+) -> Result<Vec<Probe>, state::Error> {
+    let mut probes = Vec::with_capacity(num_probes);
+
+    while probes.len() < num_probes {
+        let state = State::build_random(world, rng)?;
+
+        if let Some(steps) = optimal_steps(world, state) {
+            probes.push(Probe::new(state, steps));
+        }
+    }
+
+    Ok(probes)
+}
+
+#[cfg(test)]
+mod test_generator {
+
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64Mcg;
+
+    fn count_interior_walls(world: &World) -> usize {
+        let mut count = 0;
+
+        for (y, row) in world.walls.iter().enumerate() {
+            for (x, wall) in row.iter().enumerate() {
+                if wall.east && x < (world.width - 1) as usize {
+                    count += 1;
+                }
+                if wall.south && y < (world.height - 1) as usize {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        assert_matches!(Generator::new(0, 5, 0.2, 4), Err(Error::InvalidSize { .. }));
+        assert_matches!(
+            Generator::new(5, 5, 1.5, 4),
+            Err(Error::InvalidWallDensity { .. })
+        );
+        assert_matches!(
+            Generator::new(5, 5, 0.2, 1),
+            Err(Error::InvalidFixedPositionCount { .. })
+        );
+        assert_matches!(
+            Generator::new(2, 2, 0.2, 5),
+            Err(Error::InvalidFixedPositionCount { maximum: 4, .. })
+        );
+    }
+
+    #[test]
+    fn open_world_has_no_interior_walls() {
+        let generator = Generator::new(4, 3, 0.0, 3).unwrap();
+        let world = generator.generate(Costs::default(), &mut Pcg64Mcg::seed_from_u64(1));
+
+        assert_eq!(world.width, 4);
+        assert_eq!(world.height, 3);
+        assert_eq!(world.num_fixed_positions(), 3);
+        assert_eq!(count_interior_walls(&world), 0);
+    }
+
+    #[test]
+    fn dense_world_stays_connected() {
+        let generator = Generator::new(6, 6, 1.0, 4).unwrap();
+
+        for seed in 0..10 {
+            let world = generator.generate(Costs::default(), &mut Pcg64Mcg::seed_from_u64(seed));
+
+            assert!(is_connected(&world));
+
+            // All that is left open is a spanning tree of the 36 cells.
+            assert_eq!(count_interior_walls(&world), 60 - 35);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_world() {
+        let generator = Generator::new(5, 5, 0.3, 4).unwrap();
+
+        let world_a = generator.generate(Costs::default(), &mut Pcg64Mcg::seed_from_u64(7));
+        let world_b = generator.generate(Costs::default(), &mut Pcg64Mcg::seed_from_u64(7));
+
+        assert_eq!(world_a, world_b);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let generator = Generator::new(7, 4, 0.4, 5).unwrap();
+        let world = generator.generate(Costs::default(), &mut Pcg64Mcg::seed_from_u64(3));
+
+        let rebuilt = World::build_from_str(&world.display(), Costs::default()).unwrap();
+
+        assert_eq!(rebuilt, world);
+    }
+
+    #[test]
+    fn optimal_steps_standard_world() {
+        let source = "\
+                      ┌───┬─────┐\n\
+                      │R .│. . G│\n\
+                      │   │     │\n\
+                      │. .│. . .│\n\
+                      │         │\n\
+                      │. . . . .│\n\
+                      │         │\n\
+                      │.│. .│. .│\n\
+                      │ │   │   │\n\
+                      │Y│. .│B .│\n\
+                      └─┴───┴───┘\n\
+                      ";
+
+        let world = World::build_from_str(source, Costs::default()).unwrap();
+
+        let state = State::build_named(&world, (2, 2), Some("Y"), "R").unwrap();
+        assert_eq!(optimal_steps(&world, state), Some(10));
+
+        let state = State::build_named(&world, (0, 3), Some("B"), "G").unwrap();
+        assert_eq!(optimal_steps(&world, state), Some(13));
+
+        let state = State::build_named(&world, (0, 3), None, "G").unwrap();
+        assert_eq!(optimal_steps(&world, state), Some(8));
+    }
+
+    #[test]
+    fn probes_are_solvable() {
+        let generator = Generator::new(5, 5, 0.5, 4).unwrap();
+        let mut rng = Pcg64Mcg::seed_from_u64(11);
+        let world = generator.generate(Costs::default(), &mut rng);
+
+        let probes = generate_probes(&world, 6, &mut rng).unwrap();
+
+        assert_eq!(probes.len(), 6);
+        for probe in &probes {
+            assert_eq!(
+                optimal_steps(&world, probe.state),
+                Some(probe.maximum_steps)
+            );
+        }
+    }
+}
@@ -21,7 +21,7 @@ fn build_fails_unknown_passenger() {
 
     let costs = Costs::default();
     let w = World::build_from_str(source_world, costs).unwrap();
-    State::build(&w, (1, 3), Some('C'), 'B').unwrap();
+    State::build_named(&w, (1, 3), Some("C"), "B").unwrap();
 }
 
 #[test]
@@ -43,7 +43,7 @@ fn build_fails_unknown_destination() {
 
     let costs = Costs::default();
     let w = World::build_from_str(source_world, costs).unwrap();
-    State::build(&w, (1, 3), Some('Y'), 'Q').unwrap();
+    State::build_named(&w, (1, 3), Some("Y"), "Q").unwrap();
 }
 
 #[test]
@@ -65,7 +65,7 @@ fn build_fails_invalid_taxi() {
 
     let costs = Costs::default();
     let w = World::build_from_str(source_world, costs).unwrap();
-    State::build(&w, (1, 6), Some('R'), 'B').unwrap();
+    State::build_named(&w, (1, 6), Some("R"), "B").unwrap();
 }
 
 #[test]
@@ -86,7 +86,7 @@ fn output_matches_str_simple() {
 
     let costs = Costs::default();
     let w = World::build_from_str(&source_world, costs).unwrap();
-    let state = State::build(&w, (0, 1), Some('G'), 'R').unwrap();
+    let state = State::build_named(&w, (0, 1), Some("G"), "R").unwrap();
 
     let output = state.display(&w);
     assert_eq!(output, expected_str);
@@ -110,7 +110,7 @@ fn output_matches_str_passenger_in_taxi() {
 
     let costs = Costs::default();
     let w = World::build_from_str(&source_world, costs).unwrap();
-    let state = State::build(&w, (0, 1), None, 'R').unwrap();
+    let state = State::build_named(&w, (0, 1), None, "R").unwrap();
 
     let output = state.display(&w);
     assert_eq!(output, expected_str);
@@ -148,7 +148,7 @@ fn output_matches_str_complex() {
 
     let costs = Costs::default();
     let world = World::build_from_str(source_world, costs).unwrap();
-    let initial_state = State::build(&world, (1, 3), Some('R'), 'B').unwrap();
+    let initial_state = State::build_named(&world, (1, 3), Some("R"), "B").unwrap();
 
     let initial_str = initial_state.display(&world);
     assert_eq!(expected_initial_str, initial_str);
@@ -186,7 +186,7 @@ fn move_allowed_north() {
 
     let costs = Costs::default();
     let world = World::build_from_str(source_world, costs).unwrap();
-    let initial_state = State::build(&world, (1, 3), Some('R'), 'G').unwrap();
+    let initial_state = State::build_named(&world, (1, 3), Some("R"), "G").unwrap();
 
     let (_, state_north) = initial_state.apply_action(&world, Actions::North);
     assert_eq!(expected_north, state_north.display(&world));
@@ -225,7 +225,7 @@ fn move_top_north() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 0), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 0), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_north) = state.apply_action(&w, Actions::North);
@@ -268,7 +268,7 @@ fn move_wall_north() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 3), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 3), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_north) = state.apply_action(&w, Actions::North);
@@ -311,7 +311,7 @@ fn move_allowed_south() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (3, 1), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (3, 1), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_south) = state.apply_action(&w, Actions::South);
@@ -354,7 +354,7 @@ fn move_bottom_south() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (0, 4), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (0, 4), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_south) = state.apply_action(&w, Actions::South);
@@ -397,7 +397,7 @@ fn move_wall_south() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 2), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 2), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_south) = state.apply_action(&w, Actions::South);
@@ -440,7 +440,7 @@ fn move_allowed_east() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 2), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 2), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_east) = state.apply_action(&w, Actions::East);
@@ -483,7 +483,7 @@ fn move_right_east() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (3, 1), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (3, 1), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_east) = state.apply_action(&w, Actions::East);
@@ -526,7 +526,7 @@ fn move_wall_east() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 1), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 1), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_east) = state.apply_action(&w, Actions::East);
@@ -569,7 +569,7 @@ fn move_allowed_west() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 1), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 1), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_west) = state.apply_action(&w, Actions::West);
@@ -612,7 +612,7 @@ fn move_left_west() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (1, 2), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (1, 2), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_west) = state.apply_action(&w, Actions::West);
@@ -655,7 +655,7 @@ fn move_wall_west() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (3, 4), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (3, 4), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 let (_, state_west) = state.apply_action(&w, Actions::West);
@@ -696,7 +696,7 @@ fn reaches_destination() {
     let costs = Costs::default();
     match World::build_from_str(source_world, costs) {
         Err(msg) => panic!(msg),
-        Ok(w) => match State::build(&w, (2, 1), Some('R'), 'G') {
+        Ok(w) => match State::build_named(&w, (2, 1), Some("R"), "G") {
             Err(msg) => panic!(msg),
             Ok(state) => {
                 println!();
@@ -750,7 +750,7 @@ fn movement_reward() {
 
     let costs = Costs::default();
     let world = World::build_from_str(source_world, costs).unwrap();
-    let initial_state = State::build(&world, (1, 2), Some('R'), 'G').unwrap();
+    let initial_state = State::build_named(&world, (1, 2), Some("R"), "G").unwrap();
 
     assert_eq!(expected_initial_str, initial_state.display(&world));
 
@@ -791,7 +791,7 @@ fn correct_pickup_reward() {
 
     let costs = Costs::default();
     let world = World::build_from_str(source_world, costs).unwrap();
-    let initial_state = State::build(&world, (0, 0), Some('R'), 'G').unwrap();
+    let initial_state = State::build_named(&world, (0, 0), Some("R"), "G").unwrap();
 
     assert_eq!(expected_initial_str, initial_state.display(&world));
 
@@ -823,7 +823,7 @@ fn incorrect_pickup_reward() {
                                       └─────┘\n\
                                       ";
 
-    let off_passenger_state = State::build(&world, (1, 1), Some('R'), 'G').unwrap();
+    let off_passenger_state = State::build_named(&world, (1, 1), Some("R"), "G").unwrap();
 
     assert_eq!(
         expected_off_passenger_str,
@@ -844,7 +844,7 @@ fn incorrect_pickup_reward() {
                                       └─────┘\n\
                                       ";
 
-    let has_passenger_state = State::build(&world, (1, 1), None, 'G').unwrap();
+    let has_passenger_state = State::build_named(&world, (1, 1), None, "G").unwrap();
 
     assert_eq!(
         expected_has_passenger_str,
@@ -865,7 +865,7 @@ fn incorrect_pickup_reward() {
                                  └─────┘\n\
                                  ";
 
-    let wrong_fp_state = State::build(&world, (1, 2), Some('R'), 'G').unwrap();
+    let wrong_fp_state = State::build_named(&world, (1, 2), Some("R"), "G").unwrap();
 
     assert_eq!(expected_wrong_fp_str, wrong_fp_state.display(&world));
     assert!(
@@ -898,7 +898,7 @@ fn incorrect_dropoff_reward() {
                                      └─────┘\n\
                                      ";
 
-    let no_passenger_state = State::build(&world, (1, 1), Some('R'), 'G').unwrap();
+    let no_passenger_state = State::build_named(&world, (1, 1), Some("R"), "G").unwrap();
 
     assert_eq!(
         expected_no_passenger_str,
@@ -920,7 +920,7 @@ fn incorrect_dropoff_reward() {
                                              └─────┘\n\
                                              ";
 
-    let no_passenger_on_deststate = State::build(&world, (2, 0), Some('R'), 'G').unwrap();
+    let no_passenger_on_deststate = State::build_named(&world, (2, 0), Some("R"), "G").unwrap();
 
     assert_eq!(
         expected_no_passenger_on_dest_str,
@@ -946,7 +946,7 @@ fn incorrect_dropoff_reward() {
                                          └─────┘\n\
                                          ";
 
-    let passenger_off_fp_state = State::build(&world, (1, 1), None, 'G').unwrap();
+    let passenger_off_fp_state = State::build_named(&world, (1, 1), None, "G").unwrap();
 
     assert_eq!(
         expected_passenger_off_fp_str,
@@ -972,7 +972,7 @@ fn incorrect_dropoff_reward() {
                                            └─────┘\n\
                                            ";
 
-    let passenger_wrong_fp_state = State::build(&world, (1, 2), None, 'G').unwrap();
+    let passenger_wrong_fp_state = State::build_named(&world, (1, 2), None, "G").unwrap();
 
     assert_eq!(
         expected_passenger_wrong_fp_str,
@@ -1043,3 +1043,63 @@ fn state_iterator_walks_all() {
 
     assert_eq!(observed_states.len(), num_states);
 }
+
+#[test]
+fn state_iterator_skips_blocked() {
+    let source_world = "\
+                        ┌─────┐\n\
+                        │R # G│\n\
+                        │     │\n\
+                        │. . .│\n\
+                        └─────┘\n\
+                        ";
+
+    let costs = Costs::default();
+    let world = World::build_from_str(source_world, costs).unwrap();
+
+    // 5 open cells, 2 destations, 3 passenger states
+    let num_states = 5 * 2 * 3;
+
+    let mut observed_states = Vec::with_capacity(num_states);
+
+    for state in StateIterator::new(&world) {
+        assert!(!world.is_blocked(state.get_taxi()));
+        assert!(!observed_states.contains(&state));
+        observed_states.push(state);
+    }
+
+    assert_eq!(observed_states.len(), num_states);
+}
+
+#[test]
+fn build_random_avoids_blocked() {
+    let source_world = "\
+                        ┌───────┐\n\
+                        │R # # #│\n\
+                        │       │\n\
+                        │# # # G│\n\
+                        └───────┘\n\
+                        ";
+
+    let costs = Costs::default();
+    let world = World::build_from_str(source_world, costs);
+
+    // Every open cell is a fixed position, but they cannot reach each other.
+    assert!(world.is_err());
+
+    let source_world = "\
+                        ┌───────┐\n\
+                        │R . # #│\n\
+                        │       │\n\
+                        │# . . G│\n\
+                        └───────┘\n\
+                        ";
+
+    let world = World::build_from_str(source_world, costs).unwrap();
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..100 {
+        let state = State::build_random(&world, &mut rng).unwrap();
+        assert!(!world.is_blocked(state.get_taxi()));
+    }
+}
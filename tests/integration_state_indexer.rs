@@ -14,9 +14,9 @@ fn indices_unique() {
                         └─────┘\n\
                         ";
 
-    let possible_passengers = [Some('R'), Some('G'), Some('Y'), None];
+    let possible_passengers = [Some("R"), Some("G"), Some("Y"), None];
 
-    let possible_destinations = ['R', 'Y', 'G'];
+    let possible_destinations = ["R", "Y", "G"];
 
     let costs = Costs::default();
     let world = World::build_from_str(source_world, costs).unwrap();
@@ -29,7 +29,8 @@ fn indices_unique() {
         for passenger in &possible_passengers {
             for y in 0..world.height {
                 for x in 0..world.width {
-                    let state = State::build(&world, (x, y), *passenger, *destination).unwrap();
+                    let state =
+                        State::build_named(&world, (x, y), *passenger, destination).unwrap();
                     let state_index = state_indexer.get_index(&world, &state).unwrap();
 
                     assert!(state_index < visited_states.len());
@@ -60,3 +61,32 @@ fn indices_unique() {
         assert!(v);
     }
 }
+
+#[test]
+fn blocked_cells_skipped() {
+    let source_world = "\
+                        ┌─────┐\n\
+                        │R # G│\n\
+                        │     │\n\
+                        │. . .│\n\
+                        └─────┘\n\
+                        ";
+
+    let costs = Costs::default();
+    let world = World::build_from_str(source_world, costs).unwrap();
+
+    let state_indexer = StateIndexer::new(&world);
+
+    // 5 open cells, 3 passenger states, 2 destinations
+    assert_eq!(state_indexer.num_states(), 5 * 3 * 2);
+
+    let blocked_state = State::build_named(&world, (1, 0), Some("R"), "G");
+    assert!(blocked_state.is_err());
+
+    for state_index in 0..state_indexer.num_states() {
+        let state = state_indexer.get_state(&world, state_index).unwrap();
+
+        assert!(!world.is_blocked(state.get_taxi()));
+        assert_eq!(state_indexer.get_index(&world, &state), Some(state_index));
+    }
+}
@@ -13,7 +13,7 @@ fn build_fails_on_emptystring() {
 fn build_world() {
     let source = "\
                   ┌───┬─────┐\n\
-                  │. .│. . .│\n\
+                  │R .│. . G│\n\
                   │   │     │\n\
                   │. .│. . .│\n\
                   │         │\n\
@@ -39,7 +39,7 @@ fn build_world() {
 fn wall_move_validity() {
     let source = "\
                   ┌───┬─────┐\n\
-                  │. .│. . .│\n\
+                  │R .│. . G│\n\
                   │   │     │\n\
                   │. .│. . .│\n\
                   │         │\n\
@@ -96,7 +96,7 @@ fn wall_move_validity() {
 
 #[test]
 fn edge_action_validity() {
-    let source = " . . . \n . . . \n . . . \n";
+    let source = "       \n R . . \n       \n . . . \n       \n . . G \n       \n";
 
     let costs = Costs::default();
     match World::build_from_str(source, costs) {
@@ -147,12 +147,18 @@ fn read_fixed_position() {
         Ok(world) => {
             assert_eq!(world.num_fixed_positions(), 4);
 
-            assert_eq!(world.get_fixed_position('R'), Some(Position::new(0, 0)));
-            assert_eq!(world.get_fixed_position('G'), Some(Position::new(4, 0)));
-            assert_eq!(world.get_fixed_position('Y'), Some(Position::new(0, 4)));
-            assert_eq!(world.get_fixed_position('B'), Some(Position::new(3, 4)));
+            let position = |name| {
+                world
+                    .find_location(name)
+                    .and_then(|id| world.get_fixed_position(id))
+            };
 
-            assert_eq!(world.get_fixed_position('?'), None);
+            assert_eq!(position("R"), Some(Position::new(0, 0)));
+            assert_eq!(position("G"), Some(Position::new(4, 0)));
+            assert_eq!(position("Y"), Some(Position::new(0, 4)));
+            assert_eq!(position("B"), Some(Position::new(3, 4)));
+
+            assert_eq!(world.find_location("?"), None);
         }
     }
 }
@@ -176,19 +182,19 @@ fn fixed_position_indices() {
     let costs = Costs::default();
     let world = World::build_from_str(source, costs).unwrap();
 
-    let tests = ['R', 'G', 'B', 'Y'];
+    let tests = [("R", 0), ("G", 1), ("B", 3), ("Y", 2)];
 
-    for test in &tests {
-        println!("Testing '{}'", *test);
-        if let Some(index) = world.get_fixed_index(*test) {
-            assert_eq!(Some(*test), world.get_fixed_id_from_index(index));
-        } else {
-            panic!("Index is None");
-        }
+    for &(name, expected_index) in &tests {
+        println!("Testing '{}'", name);
+        let id = world.find_location(name).unwrap();
+
+        assert_eq!(Some(expected_index), world.get_fixed_index(id));
+        assert_eq!(Some(id), world.get_fixed_id_from_index(expected_index));
+        assert_eq!(Some(name), world.location_name(id));
     }
 
     println!("Testing '?'");
-    assert_eq!(None, world.get_fixed_index('?'));
+    assert_eq!(None, world.find_location("?"));
     assert_eq!(None, world.get_fixed_id_from_index(4));
     assert_eq!(None, world.get_fixed_id_from_index(12));
 }
@@ -235,7 +241,7 @@ fn pickup_dropoff_validity() {
 
     assert_eq!(
         world.determine_affect(Position::new(0, 0), Actions::PickUp),
-        ActionAffect::PickUp('R')
+        ActionAffect::PickUp(world.find_location("R").unwrap())
     );
 
     assert_eq!(
@@ -245,7 +251,7 @@ fn pickup_dropoff_validity() {
 
     assert_eq!(
         world.determine_affect(Position::new(3, 4), Actions::DropOff),
-        ActionAffect::DropOff('B')
+        ActionAffect::DropOff(world.find_location("B").unwrap())
     );
 
     assert_eq!(